@@ -0,0 +1,56 @@
+#![feature(test)]
+
+extern crate test;
+
+use lexer::interner::StringInterner;
+use std::path::Path;
+use test::Bencher;
+
+// all the `.rs` files under `$YARP_BENCH_DIR`, eg. a checkout of a large
+// crate, that yarp can lex; this crate's own sources if it isn't set
+fn corpus() -> Vec<u8> {
+    fn walk(path: &Path, out: &mut Vec<u8>) {
+        if path.is_dir() {
+            let mut entries: Vec<_> = std::fs::read_dir(path)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .collect();
+            entries.sort();
+            for entry in entries {
+                walk(&entry, out);
+            }
+        } else if path.extension().map_or(false, |ext| ext == "rs") {
+            let text = std::fs::read(path).unwrap();
+            let mut interner = StringInterner::empty();
+            if lexer::str_to_token_slice(&text, &mut interner).is_ok() {
+                out.extend_from_slice(&text);
+                out.push(b'\n');
+            }
+        }
+    }
+    let dir = std::env::var("YARP_BENCH_DIR")
+        .unwrap_or_else(|_| concat!(env!("CARGO_MANIFEST_DIR"), "/src").into());
+    let mut out = vec![];
+    walk(Path::new(&dir), &mut out);
+    out
+}
+
+#[bench]
+fn bench_layered(b: &mut Bencher) {
+    let input = corpus();
+    b.bytes = input.len() as u64;
+    b.iter(|| {
+        let mut interner = StringInterner::empty();
+        lexer::str_to_token_slice(&input, &mut interner).unwrap()
+    });
+}
+
+#[bench]
+fn bench_direct(b: &mut Bencher) {
+    let input = corpus();
+    b.bytes = input.len() as u64;
+    b.iter(|| {
+        let mut interner = StringInterner::empty();
+        lexer::direct::str_to_token_slice(&input, &mut interner).unwrap()
+    });
+}
@@ -0,0 +1,12 @@
+//! Inserts strings into a `StringInterner`, and checks it against a
+//! `HashMap`.
+//!
+//! `cargo +nightly fuzz run interner` from `lexer`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    lexer_fuzz::interner(data);
+});
@@ -0,0 +1,12 @@
+//! Lexes bytes fed in arbitrary chunks, and checks the tokens against those
+//! of the whole input at once and of the direct lexer.
+//!
+//! `cargo +nightly fuzz run lexer` from `lexer`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    lexer_fuzz::lexer(data);
+});
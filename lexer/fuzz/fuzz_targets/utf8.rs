@@ -0,0 +1,12 @@
+//! Decodes bytes fed in arbitrary chunks, and checks the chars against
+//! `std::str::from_utf8`.
+//!
+//! `cargo +nightly fuzz run utf8` from `lexer`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    lexer_fuzz::utf8(data);
+});
@@ -0,0 +1,26 @@
+//! Splits fuzz input into the bytes to lex and the chunks to feed them in.
+
+/// The first byte is how many chunk lengths follow it, the bytes after the
+/// lengths are the input. The chunks are one byte longer than the lengths,
+/// taken in turn, and with no lengths the input is a single chunk.
+pub fn split(data: &[u8]) -> (&[u8], Vec<&[u8]>) {
+    let n = match data.first() {
+        Some(&n) => (n as usize % 16).min(data.len() - 1),
+        None => return (data, vec![]),
+    };
+    let (lens, mut input) = data[1..].split_at(n);
+    let all = input;
+    let mut chunks = vec![];
+    for &len in lens.iter().cycle() {
+        if input.is_empty() {
+            break;
+        }
+        let (chunk, rest) = input.split_at((len as usize + 1).min(input.len()));
+        chunks.push(chunk);
+        input = rest;
+    }
+    if !input.is_empty() {
+        chunks.push(input);
+    }
+    (all, chunks)
+}
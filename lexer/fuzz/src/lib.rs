@@ -0,0 +1,119 @@
+//! The checks of the fuzz targets in `fuzz_targets`, which `tests/corpus.rs`
+//! also runs on the corpus, to replay it under Miri.
+
+use lexer::interner::StringInterner;
+use lexer::iter::{CharIter, PushLexer, TokenIter};
+use lexer::source::from_chunks;
+use lexer::{
+    char_generator_from_byte, direct, str_to_token_slice,
+    token_generator_from_char,
+};
+use std::collections::HashMap;
+
+mod chunks;
+
+/// Decodes bytes fed in arbitrary chunks, and checks the chars against
+/// `std::str::from_utf8`.
+pub fn utf8(data: &[u8]) {
+    let (input, chunks) = chunks::split(data);
+    let chars: Vec<_> =
+        CharIter::new(char_generator_from_byte(from_chunks(chunks))).collect();
+    let valid = match std::str::from_utf8(input) {
+        Ok(s) => s,
+        Err(err) => std::str::from_utf8(&input[..err.valid_up_to()]).unwrap(),
+    };
+    // the chars of the valid prefix, then an error if there is more
+    let n = valid.chars().count();
+    assert_eq!(chars.len(), n + (valid.len() < input.len()) as usize);
+    for (c, expected) in chars.iter().zip(valid.chars()) {
+        assert_eq!(*c, Ok(expected));
+    }
+    if valid.len() < input.len() {
+        assert!(chars[n].is_err());
+    }
+}
+
+/// Lexes bytes fed in arbitrary chunks, and checks the tokens against those
+/// of the whole input at once and of the direct lexer.
+pub fn lexer(data: &[u8]) {
+    let (input, chunks) = chunks::split(data);
+    let mut interner = StringInterner::empty();
+    let whole = str_to_token_slice(input, &mut interner);
+
+    let mut chunked_interner = StringInterner::empty();
+    let char_gen = char_generator_from_byte(from_chunks(chunks.clone()));
+    let chunked: Result<Vec<_>, _> = TokenIter::new(token_generator_from_char(
+        char_gen,
+        &mut chunked_interner,
+    ))
+    .collect();
+    assert_eq!(chunked, whole);
+
+    let mut pushed = vec![];
+    let mut push_interner = StringInterner::empty();
+    let mut push_lexer = PushLexer::new(&mut push_interner);
+    let mut res = Ok(());
+    for chunk in &chunks {
+        res = push_lexer.push(chunk, |ts| pushed.push(ts));
+        if res.is_err() {
+            break;
+        }
+    }
+    if res.is_ok() {
+        res = push_lexer.finish(|ts| pushed.push(ts));
+    }
+    assert_eq!(res.map(|()| pushed), whole);
+
+    // the direct lexer decodes UTF-8 lazily, and may see another error first
+    let mut direct_interner = StringInterner::empty();
+    let direct = direct::str_to_token_slice(input, &mut direct_interner);
+    if std::str::from_utf8(input).is_ok() {
+        assert_eq!(direct, whole);
+    } else {
+        assert!(direct.is_err() && whole.is_err());
+    }
+
+    if let Ok(tokens) = whole {
+        // only valid UTF-8 lexes, into tokens that cover it
+        assert!(std::str::from_utf8(input).is_ok());
+        let mut pos = 0;
+        for ts in &tokens {
+            assert_eq!(ts.sp.lo, pos);
+            assert!(ts.sp.hi > ts.sp.lo);
+            pos = ts.sp.hi;
+        }
+        assert_eq!(pos, input.len());
+        for key in 0..interner.len() {
+            assert_eq!(interner.get(key), direct_interner.get(key));
+        }
+    }
+}
+
+/// Inserts strings into a `StringInterner`, and checks it against a
+/// `HashMap`.
+pub fn interner(data: &[u8]) {
+    let mut interner = StringInterner::empty();
+    let mut model = HashMap::new();
+    let mut keys = vec![];
+    // `0xff` never occurs in UTF-8, so it separates the strings
+    for bytes in data.split(|&b| b == 0xff) {
+        let s = String::from_utf8_lossy(bytes);
+        let len = interner.len();
+        let key = interner.insert(&*s);
+        match model.get(&*s) {
+            // the same string gets the same key
+            Some(&old) => assert_eq!(key, old),
+            // a new string gets the next key
+            None => {
+                assert_eq!(key, len);
+                model.insert(s.to_string(), key);
+            }
+        }
+        assert_eq!(interner.len(), model.len());
+        keys.push((key, s));
+    }
+    // earlier strings stay put as later ones are inserted
+    for (key, s) in &keys {
+        assert_eq!(interner.get(*key), &**s);
+    }
+}
@@ -0,0 +1,55 @@
+//! Runs the checks of each fuzz target on a few seeds and on its corpus, as
+//! `cargo fuzz` leaves it in `corpus/<target>`. Run it under Miri to find
+//! undefined behavior on the inputs the fuzzer has found so far, with
+//! isolation off for it to read the corpus:
+//!
+//! ```text
+//! MIRIFLAGS=-Zmiri-disable-isolation cargo +nightly miri test
+//! ```
+//!
+//! from `lexer/fuzz`. Miri is slow, so fuzz with a small `-max_len` first.
+
+use std::fs;
+use std::path::Path;
+
+// cut into chunks of 1 and 3 bytes, and whole, valid and invalid UTF-8
+const SEEDS: &[&[u8]] = &[
+    b"",
+    b"\x02\x00\x02fn main() { let s = \"\xc3\xa9\"; }",
+    b"\x00'a: loop { /* /* */ */ r#\"x\"#; 1.5e3_f32 }",
+    b"\x01\x00a\xe2\x80",
+    b"\x00\xed\xa0\x80\xff\xc0\xaf",
+    b"\x01\x01a\xffb\xffa\xff\xff",
+];
+
+fn check(target: &str, run: fn(&[u8])) {
+    for seed in SEEDS {
+        run(seed);
+    }
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("corpus")
+        .join(target);
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for entry in entries {
+            let path = entry.unwrap().path();
+            let data = fs::read(&path).unwrap();
+            eprintln!("{}", path.display());
+            run(&data);
+        }
+    }
+}
+
+#[test]
+fn corpus_utf8() {
+    check("utf8", lexer_fuzz::utf8);
+}
+
+#[test]
+fn corpus_lexer() {
+    check("lexer", lexer_fuzz::lexer);
+}
+
+#[test]
+fn corpus_interner() {
+    check("interner", lexer_fuzz::interner);
+}
@@ -0,0 +1,413 @@
+//! Dumps the tokens of a file, or of stdin, one per line.
+//!
+//! The `human` format is for reading, `json` prints a JSON object per token
+//! and `rustc` mimics rustc's debug output of tokens, for diffing against
+//! it.
+
+use lexer::interner::StringInterner;
+use lexer::lines::LineMap;
+use lexer::token::{Lit, Token, TokenAndSpan};
+use lexer::{str_to_token_slice, IntKey};
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::process;
+
+const USAGE: &str = "\
+usage: yarp-lex [options] [FILE]
+
+Prints the tokens of FILE, or of stdin if FILE is missing or `-`.
+
+options:
+    --format FORMAT  human (the default), json or rustc
+    --trivia         include whitespace, comments and shebangs
+    --indices        show the interner index of each name
+    -h, --help       print this help";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Format {
+    Human,
+    Json,
+    Rustc,
+}
+
+struct Options {
+    format: Format,
+    trivia: bool,
+    indices: bool,
+    path: Option<String>,
+}
+
+fn parse_args<I: Iterator<Item = String>>(
+    mut args: I,
+) -> Result<Options, String> {
+    let mut options = Options {
+        format: Format::Human,
+        trivia: false,
+        indices: false,
+        path: None,
+    };
+    // whether a file, or `-` for stdin, was given
+    let mut input_given = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                options.format = match args.next().as_ref().map(|s| s.as_str())
+                {
+                    Some("human") => Format::Human,
+                    Some("json") => Format::Json,
+                    Some("rustc") => Format::Rustc,
+                    Some(other) => {
+                        return Err(format!("unknown format `{}`", other))
+                    }
+                    None => return Err("`--format` needs a value".to_string()),
+                }
+            }
+            "--trivia" => options.trivia = true,
+            "--indices" => options.indices = true,
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown option `{}`", arg))
+            }
+            _ if input_given => {
+                return Err("more than one file given".to_string())
+            }
+            "-" => input_given = true,
+            _ => {
+                input_given = true;
+                options.path = Some(arg);
+            }
+        }
+    }
+    Ok(options)
+}
+
+// the name of the token's variant, as in rustc's `TokenKind`
+fn kind_name(tok: &Token) -> &'static str {
+    match *tok {
+        Token::Eq => "Eq",
+        Token::Lt => "Lt",
+        Token::Le => "Le",
+        Token::EqEq => "EqEq",
+        Token::Ne => "Ne",
+        Token::Ge => "Ge",
+        Token::Gt => "Gt",
+        Token::AndAnd => "AndAnd",
+        Token::OrOr => "OrOr",
+        Token::Not => "Not",
+        Token::Tilde => "Tilde",
+        Token::BinOp(_) => "BinOp",
+        Token::BinOpEq(_) => "BinOpEq",
+        Token::At => "At",
+        Token::Dot => "Dot",
+        Token::DotDot => "DotDot",
+        Token::DotDotDot => "DotDotDot",
+        Token::DotDotEq => "DotDotEq",
+        Token::Comma => "Comma",
+        Token::Semi => "Semi",
+        Token::Colon => "Colon",
+        Token::ModSep => "ModSep",
+        Token::RArrow => "RArrow",
+        Token::LArrow => "LArrow",
+        Token::FatArrow => "FatArrow",
+        Token::Pound => "Pound",
+        Token::Dollar => "Dollar",
+        Token::Question => "Question",
+        Token::SingleQuote => "SingleQuote",
+        Token::OpenDelim(_) => "OpenDelim",
+        Token::CloseDelim(_) => "CloseDelim",
+        Token::Literal(..) => "Literal",
+        Token::Ident(..) => "Ident",
+        Token::Lifetime(_) => "Lifetime",
+        Token::DocComment(_) => "DocComment",
+        Token::Whitespace => "Whitespace",
+        Token::Comment => "Comment",
+        Token::Shebang(_) => "Shebang",
+        Token::Eof => "Eof",
+    }
+}
+
+fn lit_name(lit: Lit) -> (&'static str, IntKey) {
+    match lit {
+        Lit::Byte(n) => ("Byte", n),
+        Lit::Char(n) => ("Char", n),
+        Lit::Err(n) => ("Err", n),
+        Lit::Integer(n) => ("Integer", n),
+        Lit::Float(n) => ("Float", n),
+        Lit::Str_(n) => ("Str", n),
+        Lit::StrRaw(n, _) => ("StrRaw", n),
+        Lit::ByteStr(n) => ("ByteStr", n),
+        Lit::ByteStrRaw(n, _) => ("ByteStrRaw", n),
+    }
+}
+
+// the interned name a token carries
+fn name_key(tok: &Token) -> Option<IntKey> {
+    match *tok {
+        Token::Literal(lit, _) => Some(lit_name(lit).1),
+        Token::Ident(n, _)
+        | Token::Lifetime(n)
+        | Token::DocComment(n)
+        | Token::Shebang(n) => Some(n),
+        _ => None,
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                write!(out, "\\u{:04x}", c as u32).unwrap()
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// a name as rustc's `Symbol` debug output shows it, with its index if asked
+fn symbol(key: IntKey, interner: &StringInterner, indices: bool) -> String {
+    if indices {
+        format!("{:?}#{}", interner.get(key), key)
+    } else {
+        format!("{:?}", interner.get(key))
+    }
+}
+
+// the token in the shape of rustc's `TokenKind` debug output
+fn rustc_kind(tok: &Token, interner: &StringInterner, indices: bool) -> String {
+    let sym = |key| symbol(key, interner, indices);
+    match *tok {
+        Token::BinOp(op) => format!("BinOp({:?})", op),
+        Token::BinOpEq(op) => format!("BinOpEq({:?})", op),
+        Token::OpenDelim(delim) => format!("OpenDelim({:?})", delim),
+        Token::CloseDelim(delim) => format!("CloseDelim({:?})", delim),
+        Token::Literal(lit, suffix) => {
+            let (kind, key) = lit_name(lit);
+            let kind = match lit {
+                Lit::StrRaw(_, n) | Lit::ByteStrRaw(_, n) => {
+                    format!("{}({})", kind, n)
+                }
+                _ => kind.to_string(),
+            };
+            let suffix = match suffix {
+                Some(key) => format!("Some({})", sym(key)),
+                None => "None".to_string(),
+            };
+            format!(
+                "Literal(Lit {{ kind: {}, symbol: {}, suffix: {} }})",
+                kind,
+                sym(key),
+                suffix
+            )
+        }
+        Token::Ident(key, raw) => format!("Ident({}, {})", sym(key), raw),
+        Token::Lifetime(key) => format!("Lifetime({})", sym(key)),
+        Token::DocComment(key) => format!("DocComment({})", sym(key)),
+        Token::Shebang(key) => format!("Shebang({})", sym(key)),
+        ref tok => kind_name(tok).to_string(),
+    }
+}
+
+fn print_token<W: Write>(
+    out: &mut W,
+    ts: &TokenAndSpan,
+    input: &[u8],
+    lines: &LineMap,
+    path: &str,
+    interner: &StringInterner,
+    options: &Options,
+) -> io::Result<()> {
+    let text = String::from_utf8_lossy(&input[ts.sp.lo..ts.sp.hi]);
+    let (line, col) = lines.line_col(ts.sp.lo);
+    match options.format {
+        Format::Human => {
+            let key = match name_key(&ts.tok) {
+                Some(key) if options.indices => format!(" #{}", key),
+                _ => String::new(),
+            };
+            writeln!(
+                out,
+                "{:>4}:{:<3} {:>6}..{:<6} {:<11} {:?}{}",
+                line,
+                col,
+                ts.sp.lo,
+                ts.sp.hi,
+                kind_name(&ts.tok),
+                text,
+                key
+            )
+        }
+        Format::Json => {
+            let mut obj = format!(
+                "{{\"kind\":\"{}\",\"lo\":{},\"hi\":{},\"line\":{},\
+                 \"col\":{},\"text\":{}",
+                kind_name(&ts.tok),
+                ts.sp.lo,
+                ts.sp.hi,
+                line,
+                col,
+                json_string(&text)
+            );
+            if let Token::Literal(lit, _) = ts.tok {
+                write!(obj, ",\"lit\":\"{}\"", lit_name(lit).0).unwrap();
+            }
+            if let Some(key) = name_key(&ts.tok) {
+                write!(obj, ",\"name\":{}", json_string(interner.get(key)))
+                    .unwrap();
+                if options.indices {
+                    write!(obj, ",\"index\":{}", key).unwrap();
+                }
+            }
+            writeln!(out, "{}}}", obj)
+        }
+        Format::Rustc => {
+            let (end_line, end_col) = lines.line_col(ts.sp.hi);
+            writeln!(
+                out,
+                "Token {{ kind: {}, span: {}:{}:{}: {}:{} }}",
+                rustc_kind(&ts.tok, interner, options.indices),
+                path,
+                line,
+                col + 1,
+                end_line,
+                end_col + 1
+            )
+        }
+    }
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let mut input = vec![];
+    let path = match options.path {
+        Some(ref path) => {
+            input = std::fs::read(path)
+                .map_err(|err| format!("can't read {}: {}", path, err))?;
+            path.as_str()
+        }
+        None => {
+            io::stdin()
+                .read_to_end(&mut input)
+                .map_err(|err| format!("can't read stdin: {}", err))?;
+            "<stdin>"
+        }
+    };
+    let lines = LineMap::new(&input);
+    let mut interner = StringInterner::empty();
+    let tokens = str_to_token_slice(&input, &mut interner).map_err(|err| {
+        match err.span() {
+            Some(sp) => {
+                let (line, col) = lines.line_col(sp.lo);
+                format!("{}:{}:{}: {}", path, line, col + 1, err)
+            }
+            None => format!("{}: {}", path, err),
+        }
+    })?;
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    for ts in tokens
+        .iter()
+        .filter(|ts| options.trivia || !ts.tok.is_trivia())
+    {
+        print_token(&mut out, ts, &input, &lines, path, &interner, options)
+            .map_err(|err| format!("can't write: {}", err))?;
+    }
+    out.flush().map_err(|err| format!("can't write: {}", err))
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    let options = match parse_args(args.into_iter()) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(err) = run(&options) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dump(input: &str, args: &[&str]) -> String {
+        let args = args.iter().map(|arg| arg.to_string());
+        let options = parse_args(args).unwrap();
+        let lines = LineMap::new(input.as_bytes());
+        let mut interner = StringInterner::empty();
+        let tokens =
+            str_to_token_slice(input.as_bytes(), &mut interner).unwrap();
+        let mut out = vec![];
+        for ts in tokens
+            .iter()
+            .filter(|ts| options.trivia || !ts.tok.is_trivia())
+        {
+            print_token(
+                &mut out,
+                ts,
+                input.as_bytes(),
+                &lines,
+                "a.rs",
+                &interner,
+                &options,
+            )
+            .unwrap();
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_formats() {
+        let input = "fn\n  \"\\\"\"u8 // c";
+        assert_eq!(
+            dump(input, &[]),
+            "   1:0        0..2      Ident       \"fn\"\n   \
+             2:2        5..11     Literal     \"\\\"\\\\\\\"\\\"u8\"\n"
+        );
+        assert_eq!(
+            dump(input, &["--format", "json", "--indices", "--trivia"])
+                .lines()
+                .nth(2),
+            Some(
+                "{\"kind\":\"Literal\",\"lo\":5,\"hi\":11,\"line\":2,\
+                 \"col\":2,\"text\":\"\\\"\\\\\\\"\\\"u8\",\"lit\":\"Str\",\
+                 \"name\":\"\\\\\\\"\",\"index\":1}"
+            )
+        );
+        assert_eq!(
+            dump(input, &["--format", "rustc"]),
+            "Token { kind: Ident(\"fn\", false), span: a.rs:1:1: 1:3 }\n\
+             Token { kind: Literal(Lit { kind: Str, symbol: \"\\\\\\\"\", \
+             suffix: Some(\"u8\") }), span: a.rs:2:3: 2:9 }\n"
+        );
+    }
+
+    #[test]
+    fn test_args() {
+        let parse = |args: &[&str]| {
+            parse_args(args.iter().map(|arg| arg.to_string())).map(|o| o.path)
+        };
+        assert_eq!(parse(&["a.rs"]), Ok(Some("a.rs".to_string())));
+        assert_eq!(parse(&["-"]), Ok(None));
+        assert!(parse(&["--format"]).is_err());
+        assert!(parse(&["--format", "xml"]).is_err());
+        assert!(parse(&["a.rs", "b.rs"]).is_err());
+        assert!(parse(&["a.rs", "-"]).is_err());
+        assert!(parse(&["-", "a.rs"]).is_err());
+        assert!(parse(&["-", "-"]).is_err());
+    }
+}
@@ -0,0 +1,404 @@
+//! Doc comments as rustc sees them, mirrors rustc's
+//! `parse::lexer::comments`.
+//!
+//! rustc desugars `/// text` into `#[doc = r" text"]`, and `//! text` into
+//! `#![doc = r" text"]`, before macros see them. Block doc comments lose
+//! their leading and trailing blank lines, and the `*` column lined up in
+//! front of each line.
+
+use crate::interner::StringInterner;
+use crate::token::{DelimToken, Lit, Span, Token};
+use crate::tokenstream::{DelimSpan, TokenTree};
+use crate::{is_doc_comment, str_to_token_slice};
+
+pub use syntax::AttrStyle;
+
+pub fn doc_comment_style(comment: &str) -> AttrStyle {
+    if comment.starts_with("//!") || comment.starts_with("/*!") {
+        AttrStyle::Inner
+    } else {
+        AttrStyle::Outer
+    }
+}
+
+// drops blank lines at the start and at the end, and a first and last line
+// of only `*`s
+fn vertical_trim(lines: Vec<&str>) -> Vec<&str> {
+    let mut i = 0;
+    let mut j = lines.len();
+    if !lines.is_empty() && lines[0].chars().all(|c| c == '*') {
+        i += 1;
+    }
+    while i < j && lines[i].trim().is_empty() {
+        i += 1;
+    }
+    if j > i && lines[j - 1].chars().skip(1).all(|c| c == '*') {
+        j -= 1;
+    }
+    while j > i && lines[j - 1].trim().is_empty() {
+        j -= 1;
+    }
+    lines[i..j].to_vec()
+}
+
+// drops `[ \t]*\*` from the start of each line, if every line has it with
+// the `*` in the same column
+fn horizontal_trim(lines: Vec<&str>) -> Vec<&str> {
+    let mut col = None;
+    for line in &lines {
+        for (j, c) in line.char_indices() {
+            if col.map_or(false, |i| j > i) || !"* \t".contains(c) {
+                return lines;
+            }
+            if c == '*' {
+                match col {
+                    None => col = Some(j),
+                    Some(i) if i != j => return lines,
+                    Some(_) => {}
+                }
+                break;
+            }
+        }
+        if col.map_or(true, |i| i >= line.len()) {
+            return lines;
+        }
+    }
+    match col {
+        Some(i) => lines.iter().map(|line| &line[i + 1..]).collect(),
+        None => lines,
+    }
+}
+
+/// The text of the `#[doc]` attribute a doc comment desugars to, or `None`
+/// if `comment` is not a whole doc comment, eg. a plain comment or the tail
+/// of a block doc comment lexed from its middle on.
+pub fn strip_doc_comment_decoration(comment: &str) -> Option<String> {
+    if !is_doc_comment(comment) {
+        return None;
+    }
+    // `///!` is an outer doc comment, but loses its `!` as well
+    for prefix in &["///!", "///", "//!"] {
+        if comment.starts_with(prefix) {
+            return Some(comment[prefix.len()..].to_string());
+        }
+    }
+    if comment.len() < 5 || !comment.ends_with("*/") {
+        return None;
+    }
+    let lines = comment[3..comment.len() - 2].lines().collect();
+    Some(horizontal_trim(vertical_trim(lines)).join("\n"))
+}
+
+// the number of `#`s a raw string of `text` needs
+fn raw_str_hashes(text: &str) -> u16 {
+    let mut hashes = 0;
+    let mut count = 0;
+    for c in text.chars() {
+        count = match c {
+            '"' => 1,
+            '#' if count > 0 => count + 1,
+            _ => 0,
+        };
+        hashes = hashes.max(count);
+    }
+    hashes
+}
+
+fn push_doc_attr(
+    style: AttrStyle,
+    text: &str,
+    sp: Span,
+    interner: &mut StringInterner,
+    out: &mut Vec<TokenTree>,
+) {
+    out.push(TokenTree::Token(sp, Token::Pound));
+    if style == AttrStyle::Inner {
+        out.push(TokenTree::Token(sp, Token::Not));
+    }
+    let lit = Lit::StrRaw(interner.insert(text), raw_str_hashes(text));
+    let inner = vec![
+        TokenTree::Token(sp, Token::Ident(interner.insert("doc"), false)),
+        TokenTree::Token(sp, Token::Eq),
+        TokenTree::Token(sp, Token::Literal(lit, None)),
+    ];
+    let delim_sp = DelimSpan {
+        open: sp,
+        close: sp,
+    };
+    out.push(TokenTree::Delimited(delim_sp, DelimToken::Bracket, inner));
+}
+
+/// Replaces each doc comment with the `#[doc]` or `#![doc]` attribute it
+/// stands for, as rustc's `TokenStream` desugaring does: every token of the
+/// attribute has the comment's span, and the text is a raw string.
+pub fn desugar_doc_comments(
+    trees: &[TokenTree],
+    interner: &mut StringInterner,
+) -> Vec<TokenTree> {
+    let mut out = vec![];
+    for tree in trees {
+        match *tree {
+            TokenTree::Token(sp, Token::DocComment(name)) => {
+                let comment = interner.get(name).to_string();
+                match strip_doc_comment_decoration(&comment) {
+                    Some(text) => {
+                        let style = doc_comment_style(&comment);
+                        push_doc_attr(style, &text, sp, interner, &mut out);
+                    }
+                    None => out.push(tree.clone()),
+                }
+            }
+            TokenTree::Delimited(sp, delim, ref inner) => {
+                let inner = desugar_doc_comments(inner, interner);
+                out.push(TokenTree::Delimited(sp, delim, inner));
+            }
+            ref tree => out.push(tree.clone()),
+        }
+    }
+    out
+}
+
+// the style, text and span of the `#[doc = "..."]` attribute `trees` start
+// with, and how many trees it takes
+fn doc_attr(
+    trees: &[TokenTree],
+    interner: &StringInterner,
+) -> Option<(AttrStyle, String, Span, usize)> {
+    let lo = match trees.first()? {
+        TokenTree::Token(sp, Token::Pound) => *sp,
+        _ => return None,
+    };
+    let style = match trees.get(1)? {
+        TokenTree::Token(_, Token::Not) => AttrStyle::Inner,
+        _ => AttrStyle::Outer,
+    };
+    let n = if style == AttrStyle::Inner { 3 } else { 2 };
+    let (hi, inner) = match trees.get(n - 1)? {
+        TokenTree::Delimited(sp, DelimToken::Bracket, inner) => {
+            (sp.close, inner)
+        }
+        _ => return None,
+    };
+    let toks = inner
+        .iter()
+        .map(|tree| match *tree {
+            TokenTree::Token(_, tok) => Some(tok),
+            TokenTree::Delimited(..) => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let text = match toks.as_slice() {
+        [Token::Ident(doc, false), Token::Eq, Token::Literal(lit, None)]
+            if interner.get(*doc) == "doc" =>
+        {
+            match *lit {
+                Lit::StrRaw(text, _) => interner.get(text),
+                // escapes would need to be processed
+                Lit::Str_(text) if !interner.get(text).contains('\\') => {
+                    interner.get(text)
+                }
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    Some((style, text.to_string(), lo.to(hi), n))
+}
+
+// a doc comment that desugars to `text`, if there is one
+fn doc_comment(style: AttrStyle, text: &str) -> Option<String> {
+    let (line, block) = match style {
+        AttrStyle::Outer => ("///", "/**"),
+        AttrStyle::Inner => ("//!", "/*!"),
+    };
+    let candidates =
+        [format!("{}{}", line, text), format!("{}{}*/", block, text)];
+    candidates.iter().find_map(|comment| {
+        let mut interner = StringInterner::empty();
+        let tokens = str_to_token_slice(comment.as_bytes(), &mut interner);
+        match tokens.as_ref().map(|tokens| tokens.as_slice()) {
+            Ok([ts]) => match ts.tok {
+                Token::DocComment(_) => {}
+                _ => return None,
+            },
+            _ => return None,
+        }
+        if doc_comment_style(comment) == style
+            && strip_doc_comment_decoration(comment)
+                .map_or(false, |s| s == text)
+        {
+            Some(comment.clone())
+        } else {
+            None
+        }
+    })
+}
+
+/// Turns `#[doc = "..."]` and `#![doc = "..."]` attributes back into doc
+/// comments, for printing. An attribute is kept if no doc comment desugars
+/// to it, eg. one whose text has a newline and `*/`.
+pub fn resugar_doc_comments(
+    trees: &[TokenTree],
+    interner: &mut StringInterner,
+) -> Vec<TokenTree> {
+    let mut out = vec![];
+    let mut i = 0;
+    while i < trees.len() {
+        if let Some((style, text, sp, n)) = doc_attr(&trees[i..], interner) {
+            if let Some(comment) = doc_comment(style, &text) {
+                let name = interner.insert(comment.as_str());
+                out.push(TokenTree::Token(sp, Token::DocComment(name)));
+                i += n;
+                continue;
+            }
+        }
+        match trees[i] {
+            TokenTree::Delimited(sp, delim, ref inner) => {
+                let inner = resugar_doc_comments(inner, interner);
+                out.push(TokenTree::Delimited(sp, delim, inner));
+            }
+            ref tree => out.push(tree.clone()),
+        }
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::token_to_string;
+    use crate::tokenstream::build_token_trees;
+    use crate::{str_to_token_slice_from, LexState};
+
+    fn trees(input: &str, interner: &mut StringInterner) -> Vec<TokenTree> {
+        build_token_trees(
+            str_to_token_slice(input.as_bytes(), interner).unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn print(trees: &[TokenTree], interner: &StringInterner) -> String {
+        let mut out = vec![];
+        for tree in trees {
+            match *tree {
+                TokenTree::Token(_, tok) => {
+                    out.push(token_to_string(&tok, interner))
+                }
+                TokenTree::Delimited(_, delim, ref inner) => {
+                    out.push(token_to_string(
+                        &Token::OpenDelim(delim),
+                        interner,
+                    ));
+                    if !inner.is_empty() {
+                        out.push(print(inner, interner));
+                    }
+                    out.push(token_to_string(
+                        &Token::CloseDelim(delim),
+                        interner,
+                    ));
+                }
+            }
+        }
+        out.join(" ")
+    }
+
+    #[test]
+    fn test_strip() {
+        let strip = |comment| strip_doc_comment_decoration(comment).unwrap();
+        assert_eq!(strip("/// a"), " a");
+        assert_eq!(strip("//!a"), "a");
+        assert_eq!(strip("///! a"), " a");
+        assert_eq!(strip("/** a */"), " a ");
+        assert_eq!(strip("/**\n * a\n *  b\n */"), " a\n  b");
+        assert_eq!(strip("/*!\n\n  a\n b\n\n*/"), "  a\n b");
+        // the `*`s are not lined up
+        assert_eq!(strip("/**\n * a\n  * b\n*/"), " * a\n  * b");
+        // plain comments, and the tail of a block doc comment
+        let plain = ["// a", "//// a", "/* a */", "/**/", "/** a", " a */"];
+        for comment in &plain {
+            assert_eq!(strip_doc_comment_decoration(comment), None);
+        }
+    }
+
+    #[test]
+    fn test_desugar() {
+        let mut interner = StringInterner::empty();
+        let input = "//! a\nmod m { /** \"#b\n */ fn f() {} }";
+        let trees = trees(input, &mut interner);
+        let desugared = desugar_doc_comments(&trees, &mut interner);
+        assert_eq!(
+            print(&desugared, &interner),
+            "# ! [ doc = r\" a\" ] mod m { # [ doc = r##\" \"#b\"## ] \
+             fn f ( ) { } }"
+        );
+        assert_eq!(desugared[0].span(), Span::new(0, 5));
+        let resugared = resugar_doc_comments(&desugared, &mut interner);
+        assert_eq!(
+            print(&resugared, &interner),
+            "//! a mod m { /// \"#b fn f ( ) { } }"
+        );
+        assert_eq!(resugared[0].span(), Span::new(0, 5));
+    }
+
+    #[test]
+    fn test_desugar_tail() {
+        // the end of `/** a\n b */`, lexed from its second line on
+        let mut interner = StringInterner::empty();
+        let state = LexState::BlockComment {
+            depth: 1,
+            doc: true,
+        };
+        let (tokens, _) =
+            str_to_token_slice_from(b" b */ f", 6, state, &mut interner)
+                .unwrap();
+        let trees = build_token_trees(tokens).unwrap();
+        let desugared = desugar_doc_comments(&trees, &mut interner);
+        assert_eq!(desugared, trees);
+    }
+
+    #[test]
+    fn test_crlf() {
+        // rustc reads CRLFs as newlines
+        let mut interner = StringInterner::empty();
+        let input = "/// a\r\n/** b\r\n c */\r\nconst S: &str = \"d\r\ne\";";
+        let trees = trees(input, &mut interner);
+        let desugared = desugar_doc_comments(&trees, &mut interner);
+        assert_eq!(
+            print(&desugared, &interner),
+            "# [ doc = r\" a\" ] # [ doc = r\" b\n c \" ] \
+             const S : & str = \"d\ne\" ;"
+        );
+    }
+
+    #[test]
+    fn test_resugar() {
+        let mut interner = StringInterner::empty();
+        let trees = trees(
+            "#[doc = \"a\nbc\"] #[doc = r\"*/\n\"] #[doc = \"\\n\"] #[doc(a)]",
+            &mut interner,
+        );
+        let resugared = resugar_doc_comments(&trees, &mut interner);
+        assert_eq!(
+            print(&resugared, &interner),
+            "/**a\nbc*/ # [ doc = r\"*/\n\" ] # [ doc = \"\\n\" ] \
+             # [ doc ( a ) ]"
+        );
+    }
+
+    // a resugared attribute desugars back to itself
+    #[quickcheck]
+    fn check_resugar(text: String, inner: bool) -> bool {
+        let mut interner = StringInterner::empty();
+        let style = if inner {
+            AttrStyle::Inner
+        } else {
+            AttrStyle::Outer
+        };
+        let sp = Span::new(0, 1);
+        let mut attr = vec![];
+        push_doc_attr(style, &text, sp, &mut interner, &mut attr);
+        let resugared = resugar_doc_comments(&attr, &mut interner);
+        resugared == attr
+            || desugar_doc_comments(&resugared, &mut interner) == attr
+    }
+}
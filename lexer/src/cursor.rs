@@ -0,0 +1,344 @@
+//! A token cursor with arbitrary lookahead and backtracking, for the parser.
+//!
+//! Tokens are pulled from the generator on demand and buffered until the
+//! cursor, and every outstanding checkpoint, has moved past them, so memory
+//! is bounded by the lookahead used plus the distance back to the earliest
+//! checkpoint. Checkpoints may be released in any order, each of them once.
+//! When the generator runs out of bytes, the call returns
+//! `Lookahead::NeedMoreU8`; once its source has more, the same call can be
+//! made again.
+
+use crate::token::{Token, TokenAndSpan};
+use crate::{LexError, TokenGenerator, TokenResult};
+use std::collections::VecDeque;
+use std::ops::{Generator, GeneratorState};
+use std::pin::Pin;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Lookahead<T> {
+    Ready(T),
+    NeedMoreU8,
+}
+
+/// A position of the cursor to `rewind` to. It is released by `rewind` or
+/// `commit`, which take it by value.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Checkpoint {
+    // tells apart checkpoints taken at the same position
+    id: usize,
+    pos: usize,
+}
+
+pub struct TokenCursor<G> {
+    gen: G,
+    skip_trivia: bool,
+    // the buffered tokens, `buf[0]` being the `base`th token of the input
+    buf: VecDeque<TokenAndSpan>,
+    base: usize,
+    // index in the input of the next token
+    pos: usize,
+    // the ids and positions of the outstanding checkpoints, oldest first
+    checkpoints: Vec<(usize, usize)>,
+    next_id: usize,
+    eof: Option<TokenAndSpan>,
+    error: Option<LexError>,
+}
+
+impl<G: TokenGenerator + std::marker::Unpin> TokenCursor<G> {
+    /// A cursor over the tokens of `gen`, skipping whitespace and comments.
+    pub fn new(gen: G) -> TokenCursor<G> {
+        TokenCursor::with_trivia(gen, false)
+    }
+
+    /// A cursor over the tokens of `gen`, keeping the trivia if
+    /// `keep_trivia`.
+    pub fn with_trivia(gen: G, keep_trivia: bool) -> TokenCursor<G> {
+        TokenCursor {
+            gen,
+            skip_trivia: !keep_trivia,
+            buf: VecDeque::new(),
+            base: 0,
+            pos: 0,
+            checkpoints: vec![],
+            next_id: 0,
+            eof: None,
+            error: None,
+        }
+    }
+
+    /// The `n`th token after the cursor, the next one being the 0th. Past
+    /// the end of input it is the `Eof` token.
+    pub fn peek(
+        &mut self,
+        n: usize,
+    ) -> Result<Lookahead<TokenAndSpan>, LexError> {
+        let i = self.pos - self.base + n;
+        while self.buf.len() <= i {
+            if let Some(eof) = self.eof {
+                return Ok(Lookahead::Ready(eof));
+            }
+            if let Some(err) = self.error {
+                return Err(err);
+            }
+            match Pin::new(&mut self.gen).resume() {
+                GeneratorState::Yielded(TokenResult::Ok(ts)) => {
+                    if ts.tok == Token::Eof {
+                        self.eof = Some(ts);
+                    } else if !(self.skip_trivia && ts.tok.is_trivia()) {
+                        self.buf.push_back(ts);
+                    }
+                }
+                GeneratorState::Yielded(TokenResult::NeedMoreU8) => {
+                    return Ok(Lookahead::NeedMoreU8);
+                }
+                GeneratorState::Yielded(_) => {}
+                GeneratorState::Complete(err) => {
+                    self.error = Some(err);
+                }
+            }
+        }
+        Ok(Lookahead::Ready(self.buf[i]))
+    }
+
+    /// Moves past the next token and returns it. At the end of input the
+    /// cursor stays put and returns the `Eof` token.
+    pub fn bump(&mut self) -> Result<Lookahead<TokenAndSpan>, LexError> {
+        let ret = self.peek(0)?;
+        if let Lookahead::Ready(ts) = ret {
+            if ts.tok != Token::Eof {
+                self.pos += 1;
+                self.trim();
+            }
+        }
+        Ok(ret)
+    }
+
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        let checkpoint = Checkpoint {
+            id: self.next_id,
+            pos: self.pos,
+        };
+        self.next_id += 1;
+        self.checkpoints.push((checkpoint.id, checkpoint.pos));
+        checkpoint
+    }
+
+    /// Moves the cursor back to `checkpoint` and releases it. Other
+    /// checkpoints stay outstanding, even those taken after it.
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.pos = checkpoint.pos;
+        self.release(checkpoint);
+        self.trim();
+    }
+
+    /// Releases `checkpoint` without moving the cursor.
+    pub fn commit(&mut self, checkpoint: Checkpoint) {
+        self.release(checkpoint);
+        self.trim();
+    }
+
+    /// How many tokens are buffered.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn release(&mut self, checkpoint: Checkpoint) {
+        // a checkpoint can't be released twice, so it's missing only if it
+        // was taken on another cursor
+        let i = self
+            .checkpoints
+            .iter()
+            .position(|&(id, _)| id == checkpoint.id)
+            .expect("checkpoint of another cursor");
+        self.checkpoints.remove(i);
+    }
+
+    // drop the tokens nothing can go back to anymore
+    fn trim(&mut self) {
+        let keep = self
+            .checkpoints
+            .iter()
+            .map(|&(_, pos)| pos)
+            .fold(self.pos, usize::min);
+        while self.base < keep {
+            self.buf.pop_front();
+            self.base += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interner::StringInterner;
+    use crate::source::from_chunks;
+    use crate::{char_generator_from_byte, token_generator_from_char};
+
+    // `peek` or `bump`, waiting out the `NeedMoreU8`s
+    fn ready<F>(mut f: F) -> Result<Token, LexError>
+    where
+        F: FnMut() -> Result<Lookahead<TokenAndSpan>, LexError>,
+    {
+        loop {
+            if let Lookahead::Ready(ts) = f()? {
+                return Ok(ts.tok);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cursor() {
+        let input = "a < b > /* c */ ( d )";
+        let mut interner = StringInterner::empty();
+        let u8_gen = from_chunks(input.as_bytes().chunks(1));
+        let char_gen = char_generator_from_byte(u8_gen);
+        let mut cursor = TokenCursor::new(token_generator_from_char(
+            char_gen,
+            &mut interner,
+        ));
+        assert_eq!(cursor.peek(0), Ok(Lookahead::NeedMoreU8));
+        assert_eq!(ready(|| cursor.peek(3)), Ok(Token::Gt));
+        assert_eq!(cursor.buffered(), 4);
+
+        let cp = cursor.checkpoint();
+        for _ in 0..4 {
+            ready(|| cursor.bump()).unwrap();
+        }
+        assert_eq!(
+            ready(|| cursor.bump()),
+            Ok(Token::OpenDelim(crate::token::DelimToken::Paren))
+        );
+        // everything since the checkpoint is kept
+        assert_eq!(cursor.buffered(), 5);
+        cursor.rewind(cp);
+        assert!(match ready(|| cursor.bump()) {
+            Ok(Token::Ident(..)) => true,
+            _ => false,
+        });
+        assert_eq!(ready(|| cursor.peek(0)), Ok(Token::Lt));
+        // without checkpoints only the lookahead is kept
+        assert_eq!(cursor.buffered(), 4);
+
+        assert_eq!(ready(|| cursor.peek(10)), Ok(Token::Eof));
+        for _ in 0..6 {
+            ready(|| cursor.bump()).unwrap();
+        }
+        assert_eq!(ready(|| cursor.bump()), Ok(Token::Eof));
+        assert_eq!(cursor.buffered(), 0);
+    }
+
+    #[test]
+    fn test_error() {
+        let mut interner = StringInterner::empty();
+        let char_gen =
+            char_generator_from_byte(from_chunks(vec![&b"a \""[..]]));
+        let mut cursor = TokenCursor::new(token_generator_from_char(
+            char_gen,
+            &mut interner,
+        ));
+        assert!(ready(|| cursor.peek(1)).is_err());
+        assert!(ready(|| cursor.peek(0)).is_ok());
+        assert!(ready(|| cursor.peek(1)).is_err());
+    }
+
+    // an operation on the cursor, as generated by quickcheck
+    fn prop_ops(input: &str, ops: &[(u8, u8)]) -> bool {
+        let mut interner = StringInterner::empty();
+        let tokens: Vec<Token> =
+            match crate::str_to_token_slice(input.as_bytes(), &mut interner) {
+                Ok(tokens) => tokens
+                    .into_iter()
+                    .map(|ts| ts.tok)
+                    .filter(|tok| !tok.is_trivia())
+                    .collect(),
+                Err(_) => return true,
+            };
+        let token = |i: usize| tokens.get(i).cloned().unwrap_or(Token::Eof);
+        let mut interner = StringInterner::empty();
+        let u8_gen = from_chunks(input.as_bytes().chunks(3));
+        let char_gen = char_generator_from_byte(u8_gen);
+        let mut cursor = TokenCursor::new(token_generator_from_char(
+            char_gen,
+            &mut interner,
+        ));
+        // the model: position and checkpoints
+        let mut pos = 0;
+        let mut checkpoints: Vec<(Checkpoint, usize)> = vec![];
+        for &(op, arg) in ops {
+            match op % 4 {
+                0 => {
+                    let n = arg as usize % 8;
+                    if ready(|| cursor.peek(n)) != Ok(token(pos + n)) {
+                        return false;
+                    }
+                }
+                1 => {
+                    if ready(|| cursor.bump()) != Ok(token(pos)) {
+                        return false;
+                    }
+                    pos = (pos + 1).min(tokens.len());
+                }
+                2 => checkpoints.push((cursor.checkpoint(), pos)),
+                _ => {
+                    if checkpoints.is_empty() {
+                        continue;
+                    }
+                    let i = arg as usize % checkpoints.len();
+                    let (cp, cp_pos) = checkpoints.remove(i);
+                    if op % 8 == 3 {
+                        cursor.rewind(cp);
+                        pos = cp_pos;
+                    } else {
+                        cursor.commit(cp);
+                    }
+                }
+            }
+            let earliest =
+                checkpoints.iter().map(|&(_, p)| p).fold(pos, usize::min);
+            if cursor.buffered() > pos - earliest + 8 {
+                return false;
+            }
+        }
+        true
+    }
+
+    #[quickcheck]
+    fn check_ops(input: String, ops: Vec<(u8, u8)>) -> bool {
+        prop_ops(&input, &ops)
+    }
+
+    #[test]
+    fn test_ops() {
+        let ops = [(2, 0), (1, 0), (1, 0), (2, 0), (1, 0), (3, 1), (0, 5)];
+        assert!(prop_ops("fn f(x: u8) -> u8 { x }", &ops));
+    }
+
+    #[test]
+    fn test_out_of_order() {
+        let mut interner = StringInterner::empty();
+        let char_gen =
+            char_generator_from_byte(from_chunks(vec![&b"a b c d"[..]]));
+        let mut cursor = TokenCursor::new(token_generator_from_char(
+            char_gen,
+            &mut interner,
+        ));
+        let outer = cursor.checkpoint();
+        let same = cursor.checkpoint();
+        ready(|| cursor.bump()).unwrap();
+        let inner = cursor.checkpoint();
+        ready(|| cursor.bump()).unwrap();
+        // releasing the first of two checkpoints at one position keeps the
+        // other, and releasing an earlier one keeps a later one
+        cursor.commit(outer);
+        cursor.rewind(inner);
+        assert_eq!(cursor.buffered(), 2);
+        cursor.rewind(same);
+        assert!(match ready(|| cursor.peek(0)) {
+            Ok(Token::Ident(..)) => true,
+            _ => false,
+        });
+        ready(|| cursor.bump()).unwrap();
+        // with no checkpoints left only the lookahead is kept
+        assert_eq!(cursor.buffered(), 1);
+    }
+}
@@ -0,0 +1,275 @@
+//! Delegating from one generator to another, like python's `yield from`.
+//!
+//! A sub-lexer is a generator that lexes the rest of one token from a
+//! `SharedReader`, the char source it shares with its parent. It yields what
+//! the parent has to yield meanwhile (`NeedMoreU8`, `LineStart`), and returns
+//! the token or the error. The parent runs it to completion with
+//! `yield_from!`.
+
+use crate::token::{Span, Token};
+use crate::{CharGenerator, CharResult, LexError, LexState, TokenResult};
+use std::cell::RefCell;
+use std::ops::{Generator, GeneratorState};
+use std::pin::Pin;
+use std::rc::Rc;
+
+pub trait SubLexer =
+    Generator<Yield = TokenResult, Return = Result<Token, LexError>>;
+
+pub type SharedReader<T> = Rc<RefCell<CharReader<T>>>;
+
+pub enum Step {
+    Char(Option<char>),
+    // has to be yielded before the next char can be read
+    Yield(TokenResult),
+}
+
+/// Reads chars for the token generator, and keeps the text of the current
+/// token.
+pub struct CharReader<T> {
+    source: T,
+    // byte offset of the next char
+    pos: usize,
+    // byte offset where the current token starts
+    lo: usize,
+    // source text of the current token, ie. `source[lo..pos]`
+    text: String,
+    // chars we looked ahead at but didn't consume
+    pushback: Vec<char>,
+    eof: bool,
+    // a `\n` was read, and the `LineStart` after it has been reported
+    newline: bool,
+    /// The state reported by the next `LineStart`.
+    pub mode: LexState,
+}
+
+impl<T: CharGenerator + std::marker::Unpin> CharReader<T> {
+    /// `source` starts at byte offset `start` of the file.
+    pub fn new(source: T, start: usize) -> CharReader<T> {
+        CharReader {
+            source,
+            pos: start,
+            lo: start,
+            text: String::new(),
+            pushback: vec![],
+            eof: false,
+            newline: false,
+            mode: LexState::Normal,
+        }
+    }
+
+    pub fn shared(source: T, start: usize) -> SharedReader<T> {
+        Rc::new(RefCell::new(CharReader::new(source, start)))
+    }
+
+    /// Reads the next char, `None` at the end of input. Use `read_char!`
+    /// rather than calling this in a loop.
+    pub fn step(&mut self) -> Result<Step, LexError> {
+        if self.newline {
+            self.newline = false;
+            return Ok(Step::Char(Some('\n')));
+        }
+        if let Some(c) = self.pushback.pop() {
+            self.pos += c.len_utf8();
+            self.text.push(c);
+            return Ok(Step::Char(Some(c)));
+        }
+        if self.eof {
+            return Ok(Step::Char(None));
+        }
+        match Pin::new(&mut self.source).resume() {
+            GeneratorState::Yielded(CharResult::Ok(c)) => {
+                self.pos += c.len_utf8();
+                self.text.push(c);
+                if c == '\n' {
+                    self.newline = true;
+                    return Ok(Step::Yield(TokenResult::LineStart(
+                        self.pos, self.mode,
+                    )));
+                }
+                Ok(Step::Char(Some(c)))
+            }
+            GeneratorState::Yielded(CharResult::NeedMoreU8) => {
+                Ok(Step::Yield(TokenResult::NeedMoreU8))
+            }
+            GeneratorState::Yielded(CharResult::Eof) => {
+                self.eof = true;
+                Ok(Step::Char(None))
+            }
+            GeneratorState::Complete(err) => Err(LexError::Utf8(err)),
+        }
+    }
+
+    /// Gives back the char last read.
+    pub fn unread(&mut self, c: char) {
+        self.pos -= c.len_utf8();
+        self.text.pop();
+        self.pushback.push(c);
+    }
+
+    pub fn start_token(&mut self) {
+        self.lo = self.pos;
+        self.text.clear();
+    }
+
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub fn lo(&self) -> usize {
+        self.lo
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.lo, self.pos)
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Runs the generator `$gen` to completion, yielding what it yields.
+/// Evaluates to what it returns.
+#[macro_export]
+macro_rules! yield_from {
+    ($gen:expr) => {{
+        let mut gen = $gen;
+        loop {
+            let state =
+                ::std::ops::Generator::resume(::std::pin::Pin::new(&mut gen));
+            match state {
+                ::std::ops::GeneratorState::Yielded(y) => yield y,
+                ::std::ops::GeneratorState::Complete(ret) => break ret,
+            }
+        }
+    }};
+}
+
+/// Reads the next char of the `SharedReader` `$reader`, yielding whatever
+/// has to be yielded first. Evaluates to a `Result<Option<char>, LexError>`.
+#[macro_export]
+macro_rules! read_char {
+    ($reader:expr) => {
+        loop {
+            let step = $reader.borrow_mut().step();
+            match step {
+                Ok($crate::delegate::Step::Char(c)) => break Ok(c),
+                Ok($crate::delegate::Step::Yield(y)) => yield y,
+                Err(err) => break Err(err),
+            }
+        }
+    };
+}
+
+/// Consumes the next char of `$reader` if it is `$ch`. Evaluates to a
+/// `Result<bool, LexError>`.
+#[macro_export]
+macro_rules! eat_char {
+    ($reader:expr, $ch:expr) => {
+        match $crate::read_char!($reader) {
+            Ok(Some(c)) if c == $ch => Ok(true),
+            Ok(Some(c)) => {
+                $reader.borrow_mut().unread(c);
+                Ok(false)
+            }
+            Ok(None) => Ok(false),
+            Err(err) => Err(err),
+        }
+    };
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::char_generator_from_byte;
+    use crate::source::from_chunks;
+
+    pub(crate) type TestChars = Box<dyn CharGenerator + std::marker::Unpin>;
+
+    /// Runs the sub-lexer `make` builds on `input`, fed one byte at a time.
+    /// Returns the token, the text it didn't consume and how many times it
+    /// yielded.
+    pub(crate) fn run_sub_lexer<F, G>(
+        input: &'static str,
+        make: F,
+    ) -> (Result<Token, LexError>, &'static str, usize)
+    where
+        F: FnOnce(SharedReader<TestChars>) -> G,
+        G: SubLexer + std::marker::Unpin,
+    {
+        let chunks = input.as_bytes().chunks(1);
+        let char_gen: TestChars =
+            Box::new(char_generator_from_byte(from_chunks(chunks)));
+        let reader = CharReader::shared(char_gen, 0);
+        let mut sub = make(reader.clone());
+        let mut yields = 0;
+        let ret = loop {
+            match Pin::new(&mut sub).resume() {
+                GeneratorState::Yielded(_) => yields += 1,
+                GeneratorState::Complete(ret) => break ret,
+            }
+        };
+        let pos = reader.borrow().pos();
+        (ret, &input[pos..], yields)
+    }
+
+    fn lex_pair<T: CharGenerator + std::marker::Unpin>(
+        reader: SharedReader<T>,
+    ) -> impl SubLexer {
+        move || {
+            let first = read_char!(reader)?;
+            let second = eat_char!(reader, 'b')?;
+            if first == Some('a') && second {
+                Ok(Token::Whitespace)
+            } else {
+                Err(LexError::UnknownStartOfToken('?', reader.borrow().span()))
+            }
+        }
+    }
+
+    fn lex_two<T: CharGenerator + std::marker::Unpin>(
+        reader: SharedReader<T>,
+    ) -> impl SubLexer {
+        move || {
+            yield_from!(lex_pair(reader.clone()))?;
+            yield_from!(lex_pair(reader.clone()))?;
+            Ok(Token::Comment)
+        }
+    }
+
+    #[test]
+    fn test_yield_from() {
+        let (tok, rest, yields) = run_sub_lexer("abab c", lex_two);
+        assert_eq!(tok, Ok(Token::Comment));
+        assert_eq!(rest, " c");
+        // a `NeedMoreU8` before each byte read after the first chunk
+        assert_eq!(yields, 3);
+        let (tok, rest, _) = run_sub_lexer("abac", lex_two);
+        assert_eq!(
+            tok,
+            Err(LexError::UnknownStartOfToken('?', Span::new(0, 3)))
+        );
+        assert_eq!(rest, "c");
+    }
+
+    #[test]
+    fn test_line_start() {
+        let char_gen =
+            char_generator_from_byte(from_chunks(vec![&b"a\nb"[..]]));
+        let reader = CharReader::shared(char_gen, 0);
+        let mut steps = vec![];
+        loop {
+            let step = reader.borrow_mut().step().unwrap();
+            match step {
+                Step::Char(None) => break,
+                Step::Char(Some(c)) => steps.push(format!("{:?}", c)),
+                Step::Yield(TokenResult::LineStart(pos, _)) => {
+                    steps.push(format!("line {}", pos))
+                }
+                Step::Yield(_) => {}
+            }
+        }
+        assert_eq!(steps, vec!["'a'", "line 2", "'\\n'", "'b'"]);
+    }
+}
@@ -0,0 +1,597 @@
+//! A lexer scanning bytes directly, without the char and token generators.
+//!
+//! It makes the same tokens, and interns the same strings in the same order,
+//! as `crate::str_to_token_slice`. ASCII is handled byte by byte, and UTF-8
+//! is only decoded where a non-ASCII char may occur: in whitespace,
+//! identifiers, literals and comments. So on input that is not UTF-8 both
+//! fail, but this one may fail with the lex error of a token that ends before
+//! the invalid bytes are decoded.
+
+use crate::interner::StringInterner;
+use crate::token::{BinOpToken, DelimToken, Lit, Span, Token, TokenAndSpan};
+use crate::{
+    decode_utf8, find_bare_cr, intern_ident, is_dec_digit, is_doc_comment,
+    is_hex_digit, is_id_continue, is_id_start, is_whitespace, strip_crlf,
+    IntKey, LexError,
+};
+
+/// Same as `crate::str_to_token_slice`, only faster.
+pub fn str_to_token_slice(
+    input: &[u8],
+    interner: &mut StringInterner,
+) -> Result<Vec<TokenAndSpan>, LexError> {
+    let mut lexer = Lexer {
+        input,
+        pos: 0,
+        lo: 0,
+        interner,
+    };
+    let mut ret = Vec::new();
+    while lexer.pos < input.len() {
+        lexer.lo = lexer.pos;
+        let tok = lexer.token()?;
+        ret.push(TokenAndSpan {
+            tok,
+            sp: lexer.span(),
+        });
+    }
+    Ok(ret)
+}
+
+struct Lexer<'i, 'n> {
+    input: &'i [u8],
+    // byte offset of the next char
+    pos: usize,
+    // byte offset where the current token starts
+    lo: usize,
+    interner: &'n mut StringInterner,
+}
+
+impl<'i, 'n> Lexer<'i, 'n> {
+    fn span(&self) -> Span {
+        Span::new(self.lo, self.pos)
+    }
+
+    // source text of the current token
+    fn text(&self) -> &'i str {
+        // only complete, validated chars are ever consumed
+        unsafe { std::str::from_utf8_unchecked(&self.input[self.lo..self.pos]) }
+    }
+
+    // fail on a bare CR in the current token, see `find_bare_cr`
+    fn deny_bare_cr(&self, lf_after: bool) -> Result<(), LexError> {
+        match find_bare_cr(self.text(), lf_after) {
+            Some(i) => {
+                let lo = self.lo + i;
+                Err(LexError::BareCr(Span::new(lo, lo + 1)))
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn intern_text(&mut self) -> IntKey {
+        self.interner.insert(self.text())
+    }
+
+    // interns `text`, a part of the current token, see `strip_crlf`
+    fn intern_stripped(&mut self, text: &str, lf_after: bool) -> IntKey {
+        self.interner.insert(&*strip_crlf(text, lf_after))
+    }
+
+    // the next char and its length, without consuming it
+    fn peek(&self) -> Result<Option<(char, usize)>, LexError> {
+        match self.input.get(self.pos) {
+            None => Ok(None),
+            Some(&b) if b < 0x80 => Ok(Some((b as char, 1))),
+            Some(_) => match decode_utf8(&self.input[self.pos..]) {
+                Ok(c) => Ok(Some(c)),
+                Err(err) => Err(LexError::Utf8(err)),
+            },
+        }
+    }
+
+    fn peek_char(&self) -> Result<Option<char>, LexError> {
+        Ok(self.peek()?.map(|(c, _)| c))
+    }
+
+    fn bump(&mut self) -> Result<Option<char>, LexError> {
+        Ok(self.peek()?.map(|(c, len)| {
+            self.pos += len;
+            c
+        }))
+    }
+
+    // consume the next byte if it is the ASCII char `b`
+    fn eat(&mut self, b: u8) -> bool {
+        if self.input.get(self.pos) == Some(&b) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_while<F: Fn(char) -> bool>(
+        &mut self,
+        pred: F,
+    ) -> Result<(), LexError> {
+        while let Some((c, len)) = self.peek()? {
+            if !pred(c) {
+                break;
+            }
+            self.pos += len;
+        }
+        Ok(())
+    }
+
+    fn bin_op(&mut self, op: BinOpToken) -> Token {
+        if self.eat(b'=') {
+            Token::BinOpEq(op)
+        } else {
+            Token::BinOp(op)
+        }
+    }
+
+    fn ident(&mut self) -> Result<Token, LexError> {
+        self.eat_while(is_id_continue)?;
+        let text = self.text();
+        let name = intern_ident(self.interner, text);
+        Ok(Token::Ident(name, false))
+    }
+
+    // an optional literal suffix, eg. the `u8` in `1u8`
+    fn suffix(&mut self) -> Result<Option<IntKey>, LexError> {
+        match self.peek_char()? {
+            Some(c) if is_id_start(c) => {
+                let start = self.pos - self.lo;
+                self.eat_while(is_id_continue)?;
+                Ok(Some(self.interner.insert(&self.text()[start..])))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // the body of a char or byte literal, after the opening quote
+    fn quoted_char(&mut self, prefix_len: usize) -> Result<IntKey, LexError> {
+        match self.bump()? {
+            Some('\\') => match self.bump()? {
+                Some('u') => {
+                    if self.eat(b'{') {
+                        self.eat_while(|c| c != '}' && c != '\'' && c != '\n')?;
+                        self.eat(b'}');
+                    }
+                }
+                Some('x') => self.eat_while(|c| c.is_ascii_hexdigit())?,
+                Some(_) => {}
+                None => {
+                    return Err(LexError::UnterminatedCharLiteral(self.span()))
+                }
+            },
+            Some('\'') => {
+                return Err(LexError::EmptyCharLiteral(self.span()));
+            }
+            Some('\n') | Some('\r') | Some('\t') => {
+                return Err(LexError::CharMustBeEscaped(self.span()));
+            }
+            Some(_) => {}
+            None => return Err(LexError::UnterminatedCharLiteral(self.span())),
+        }
+        if !self.eat(b'\'') {
+            return Err(LexError::UnterminatedCharLiteral(self.span()));
+        }
+        let text = self.text();
+        Ok(self.interner.insert(&text[prefix_len..text.len() - 1]))
+    }
+
+    // the body of a string or byte string, after the opening quote
+    fn quoted_str(&mut self, prefix_len: usize) -> Result<IntKey, LexError> {
+        loop {
+            match self.bump()? {
+                Some('"') => break,
+                Some('\\') => {
+                    self.bump()?;
+                }
+                Some(_) => {}
+                None => {
+                    return Err(LexError::UnterminatedDoubleQuoteString(
+                        self.span(),
+                    ));
+                }
+            }
+        }
+        self.deny_bare_cr(true)?;
+        let text = self.text();
+        Ok(self.intern_stripped(&text[prefix_len..text.len() - 1], false))
+    }
+
+    // a raw string, after the `r` or `br` prefix
+    fn raw_str(&mut self) -> Result<(IntKey, u16), LexError> {
+        let mut hashes: u16 = 0;
+        while self.eat(b'#') {
+            hashes += 1;
+        }
+        if !self.eat(b'"') {
+            return Err(LexError::InvalidRawStringDelimiter(self.span()));
+        }
+        let start = self.pos - self.lo;
+        loop {
+            match self.bump()? {
+                Some('"') => {
+                    let mut closing: u16 = 0;
+                    while closing < hashes && self.eat(b'#') {
+                        closing += 1;
+                    }
+                    if closing == hashes {
+                        break;
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    return Err(LexError::UnterminatedRawString(self.span()))
+                }
+            }
+        }
+        self.deny_bare_cr(true)?;
+        let text = self.text();
+        let end = text.len() - 1 - hashes as usize;
+        Ok((self.intern_stripped(&text[start..end], false), hashes))
+    }
+
+    // the rest of a block comment, after the opening `/*`
+    fn block_comment(&mut self) -> Result<Token, LexError> {
+        let mut depth: usize = 1;
+        while depth > 0 {
+            match self.bump()? {
+                Some('/') => {
+                    if self.eat(b'*') {
+                        depth += 1;
+                    }
+                }
+                Some('*') => {
+                    if self.eat(b'/') {
+                        depth -= 1;
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    return Err(LexError::UnterminatedBlockComment(self.span()))
+                }
+            }
+        }
+        if is_doc_comment(self.text()) {
+            self.deny_bare_cr(true)?;
+            Ok(Token::DocComment(self.intern_stripped(self.text(), false)))
+        } else {
+            Ok(Token::Comment)
+        }
+    }
+
+    fn number(&mut self, c: char) -> Result<Token, LexError> {
+        let mut is_float = false;
+        let mut is_hex = false;
+        if c == '0' {
+            if self.eat(b'x') {
+                is_hex = true;
+            } else if !self.eat(b'o') {
+                self.eat(b'b');
+            }
+        }
+        if is_hex {
+            self.eat_while(is_hex_digit)?;
+        } else {
+            self.eat_while(is_dec_digit)?;
+        }
+        let is_decimal = !is_hex
+            && self.text().bytes().all(|b| b.is_ascii_digit() || b == b'_');
+        if is_decimal && self.eat(b'.') {
+            // `1..2` and `1.foo()` don't make `1.` a float
+            match self.peek_char()? {
+                Some(c) if c == '.' || is_id_start(c) => self.pos -= 1,
+                _ => {
+                    is_float = true;
+                    self.eat_while(is_dec_digit)?;
+                }
+            }
+        }
+        if is_decimal && (self.eat(b'e') || self.eat(b'E')) {
+            let exponent = self.pos - 1;
+            if !self.eat(b'+') {
+                self.eat(b'-');
+            }
+            match self.peek_char()? {
+                Some(c) if is_dec_digit(c) => {
+                    self.eat_while(is_dec_digit)?;
+                    is_float = true;
+                }
+                // not an exponent, leave `e` to the suffix
+                _ => self.pos = exponent,
+            }
+        }
+        let name = self.intern_text();
+        let lit = if is_float {
+            Lit::Float(name)
+        } else {
+            Lit::Integer(name)
+        };
+        Ok(Token::Literal(lit, self.suffix()?))
+    }
+
+    fn token(&mut self) -> Result<Token, LexError> {
+        let c = match self.bump()? {
+            Some(c) => c,
+            None => unreachable!("no token at the end of input"),
+        };
+        let tok = match c {
+            c if is_whitespace(c) => {
+                self.eat_while(is_whitespace)?;
+                Token::Whitespace
+            }
+            '/' => {
+                if self.eat(b'/') {
+                    self.eat_while(|c| c != '\n')?;
+                    if is_doc_comment(self.text()) {
+                        let lf_after = self.peek()?.is_some();
+                        self.deny_bare_cr(lf_after)?;
+                        Token::DocComment(
+                            self.intern_stripped(self.text(), lf_after),
+                        )
+                    } else {
+                        Token::Comment
+                    }
+                } else if self.eat(b'*') {
+                    self.block_comment()?
+                } else {
+                    self.bin_op(BinOpToken::Slash)
+                }
+            }
+            '=' => {
+                if self.eat(b'=') {
+                    Token::EqEq
+                } else if self.eat(b'>') {
+                    Token::FatArrow
+                } else {
+                    Token::Eq
+                }
+            }
+            '!' => {
+                if self.eat(b'=') {
+                    Token::Ne
+                } else {
+                    Token::Not
+                }
+            }
+            '<' => {
+                if self.eat(b'=') {
+                    Token::Le
+                } else if self.eat(b'<') {
+                    self.bin_op(BinOpToken::Shl)
+                } else if self.eat(b'-') {
+                    Token::LArrow
+                } else {
+                    Token::Lt
+                }
+            }
+            '>' => {
+                if self.eat(b'=') {
+                    Token::Ge
+                } else if self.eat(b'>') {
+                    self.bin_op(BinOpToken::Shr)
+                } else {
+                    Token::Gt
+                }
+            }
+            '&' => {
+                if self.eat(b'&') {
+                    Token::AndAnd
+                } else {
+                    self.bin_op(BinOpToken::And)
+                }
+            }
+            '|' => {
+                if self.eat(b'|') {
+                    Token::OrOr
+                } else {
+                    self.bin_op(BinOpToken::Or)
+                }
+            }
+            '-' => {
+                if self.eat(b'>') {
+                    Token::RArrow
+                } else {
+                    self.bin_op(BinOpToken::Minus)
+                }
+            }
+            '+' => self.bin_op(BinOpToken::Plus),
+            '*' => self.bin_op(BinOpToken::Star),
+            '%' => self.bin_op(BinOpToken::Percent),
+            '^' => self.bin_op(BinOpToken::Caret),
+            '.' => {
+                if !self.eat(b'.') {
+                    Token::Dot
+                } else if self.eat(b'.') {
+                    Token::DotDotDot
+                } else if self.eat(b'=') {
+                    Token::DotDotEq
+                } else {
+                    Token::DotDot
+                }
+            }
+            ':' => {
+                if self.eat(b':') {
+                    Token::ModSep
+                } else {
+                    Token::Colon
+                }
+            }
+            '#' => {
+                // `#!` at the very start of a file is a shebang, unless it is
+                // the beginning of an inner attribute `#![`
+                if self.lo == 0 && self.eat(b'!') {
+                    if self.peek_char()? != Some('[') {
+                        self.eat_while(|c| c != '\n')?;
+                        let lf_after = self.peek()?.is_some();
+                        Token::Shebang(
+                            self.intern_stripped(self.text(), lf_after),
+                        )
+                    } else {
+                        self.pos -= 1;
+                        Token::Pound
+                    }
+                } else {
+                    Token::Pound
+                }
+            }
+            '~' => Token::Tilde,
+            '@' => Token::At,
+            ',' => Token::Comma,
+            ';' => Token::Semi,
+            '$' => Token::Dollar,
+            '?' => Token::Question,
+            '(' => Token::OpenDelim(DelimToken::Paren),
+            ')' => Token::CloseDelim(DelimToken::Paren),
+            '[' => Token::OpenDelim(DelimToken::Bracket),
+            ']' => Token::CloseDelim(DelimToken::Bracket),
+            '{' => Token::OpenDelim(DelimToken::Brace),
+            '}' => Token::CloseDelim(DelimToken::Brace),
+            '\'' => {
+                // `'a` is a lifetime, `'a'` is a char literal
+                let c2 = match self.bump()? {
+                    Some(c2) => c2,
+                    None => {
+                        return Err(LexError::UnterminatedCharLiteral(
+                            self.span(),
+                        ));
+                    }
+                };
+                if is_id_start(c2) && self.peek_char()? != Some('\'') {
+                    self.eat_while(is_id_continue)?;
+                    let name = intern_ident(self.interner, self.text());
+                    Token::Lifetime(name)
+                } else {
+                    self.pos -= c2.len_utf8();
+                    let name = self.quoted_char(1)?;
+                    Token::Literal(Lit::Char(name), self.suffix()?)
+                }
+            }
+            '"' => {
+                let name = self.quoted_str(1)?;
+                Token::Literal(Lit::Str_(name), self.suffix()?)
+            }
+            'r' => {
+                if self.eat(b'#') {
+                    match self.peek_char()? {
+                        Some('"') | Some('#') => {
+                            self.pos -= 1;
+                            let (name, hashes) = self.raw_str()?;
+                            Token::Literal(
+                                Lit::StrRaw(name, hashes),
+                                self.suffix()?,
+                            )
+                        }
+                        Some(c) if is_id_start(c) => {
+                            self.eat_while(is_id_continue)?;
+                            let text = &self.text()[2..];
+                            let name = intern_ident(self.interner, text);
+                            Token::Ident(name, true)
+                        }
+                        _ => {
+                            self.pos -= 1;
+                            self.ident()?
+                        }
+                    }
+                } else if self.peek_char()? == Some('"') {
+                    let (name, hashes) = self.raw_str()?;
+                    Token::Literal(Lit::StrRaw(name, hashes), self.suffix()?)
+                } else {
+                    self.ident()?
+                }
+            }
+            'b' => {
+                if self.eat(b'\'') {
+                    let name = self.quoted_char(2)?;
+                    Token::Literal(Lit::Byte(name), self.suffix()?)
+                } else if self.eat(b'"') {
+                    let name = self.quoted_str(2)?;
+                    Token::Literal(Lit::ByteStr(name), self.suffix()?)
+                } else if self.eat(b'r') {
+                    match self.peek_char()? {
+                        Some('"') | Some('#') => {
+                            let (name, hashes) = self.raw_str()?;
+                            Token::Literal(
+                                Lit::ByteStrRaw(name, hashes),
+                                self.suffix()?,
+                            )
+                        }
+                        _ => self.ident()?,
+                    }
+                } else {
+                    self.ident()?
+                }
+            }
+            c if is_id_start(c) => self.ident()?,
+            c if c.is_ascii_digit() => self.number(c)?,
+            c => return Err(LexError::UnknownStartOfToken(c, self.span())),
+        };
+        Ok(tok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Utf8Error;
+
+    // the same tokens, with the same strings interned
+    fn both(input: &[u8]) -> bool {
+        let mut interner = StringInterner::empty();
+        let layered = crate::str_to_token_slice(input, &mut interner);
+        let mut direct_interner = StringInterner::empty();
+        layered == str_to_token_slice(input, &mut direct_interner)
+            && (0..interner.len())
+                .all(|i| interner.get(i) == direct_interner.get(i))
+    }
+
+    // maps arbitrary bytes onto chars that make up interesting rust tokens
+    fn rusty(bytes: &[u8]) -> String {
+        let alphabet: Vec<char> = "ab1_0xeE+-.\"'#r/*!\n\r=<>:[]\\u{}ü\u{2028}"
+            .chars()
+            .collect();
+        bytes
+            .iter()
+            .map(|b| alphabet[*b as usize % alphabet.len()])
+            .collect()
+    }
+
+    #[quickcheck]
+    fn check_same_tokens(input: String) -> bool {
+        both(input.as_bytes())
+    }
+
+    #[quickcheck]
+    fn check_same_tokens_rusty(bytes: Vec<u8>) -> bool {
+        both(rusty(&bytes).as_bytes())
+    }
+
+    #[test]
+    fn test_same_tokens() {
+        assert!(both(include_bytes!("lib.rs")));
+        assert!(both(
+            b"#!/bin/sh\n#![a] 1.5e+3f32 1e 1.. 1.a 0x1f 0b1_0 'a' 'a"
+        ));
+        assert!(both("br##\"ü\"#\"## r#a r# 'ü'".as_bytes()));
+        assert!(both(b"#!x\r\n/// a\r\n/** b\r\n*/ \"c\r\n\" r\"d\r\n\""));
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let mut interner = StringInterner::empty();
+        let mut lex = |input: &[u8]| str_to_token_slice(input, &mut interner);
+        let err = |err| Err(LexError::Utf8(err));
+        assert_eq!(lex(b"a \x80"), err(Utf8Error::InvalidLeading));
+        assert_eq!(lex(b"\"\xc3\""), err(Utf8Error::InvalidCont));
+        assert_eq!(lex(b"// \xe2\x80"), err(Utf8Error::UnexpectedEof));
+        // overlong `/`, and a surrogate
+        assert_eq!(lex(b"\xc0\xaf"), err(Utf8Error::InvalidScalarValue));
+        assert_eq!(lex(b"\xed\xa0\x80"), err(Utf8Error::InvalidScalarValue));
+    }
+}
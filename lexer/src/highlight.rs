@@ -0,0 +1,367 @@
+//! Syntax highlighting: a class for each token of the lossless token
+//! stream, and a file rendered to HTML or to ANSI colored text with them.
+
+use crate::interner::StringInterner;
+use crate::lint::{char_lint, LintKind};
+use crate::token::{DelimToken, Lit, Token, TokenAndSpan};
+use crate::{str_to_token_slice, LexError};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Class {
+    Keyword,
+    Ident,
+    Lifetime,
+    /// String, char and byte literals.
+    String,
+    Number,
+    Comment,
+    DocComment,
+    /// A macro name and its `!`, eg. `println!`.
+    Macro,
+    /// An attribute, from its `#` to its closing `]`.
+    Attribute,
+    Punct,
+}
+
+impl Class {
+    /// The CSS class of `to_html`.
+    pub fn css_class(self) -> &'static str {
+        match self {
+            Class::Keyword => "kw",
+            Class::Ident => "ident",
+            Class::Lifetime => "lifetime",
+            Class::String => "string",
+            Class::Number => "number",
+            Class::Comment => "comment",
+            Class::DocComment => "doccomment",
+            Class::Macro => "macro",
+            Class::Attribute => "attribute",
+            Class::Punct => "op",
+        }
+    }
+
+    /// The SGR parameters of `to_ansi`, `None` for the default color.
+    pub fn ansi_color(self) -> Option<&'static str> {
+        match self {
+            Class::Keyword => Some("1;35"),
+            Class::Lifetime => Some("33"),
+            Class::String => Some("32"),
+            Class::Number => Some("36"),
+            Class::Comment => Some("2"),
+            Class::DocComment => Some("2;32"),
+            Class::Macro => Some("34"),
+            Class::Attribute => Some("33"),
+            Class::Ident | Class::Punct => None,
+        }
+    }
+}
+
+// strict and reserved keywords of the 2018 edition
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const",
+    "continue", "crate", "do", "dyn", "else", "enum", "extern", "false",
+    "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro", "match",
+    "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self",
+    "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+fn token_class(tok: &Token, interner: &StringInterner) -> Option<Class> {
+    let class = match *tok {
+        Token::Ident(name, false) if KEYWORDS.contains(&interner.get(name)) => {
+            Class::Keyword
+        }
+        Token::Ident(..) => Class::Ident,
+        Token::Lifetime(_) => Class::Lifetime,
+        Token::Literal(Lit::Integer(_), _)
+        | Token::Literal(Lit::Float(_), _) => Class::Number,
+        Token::Literal(..) => Class::String,
+        Token::Comment | Token::Shebang(_) => Class::Comment,
+        Token::DocComment(_) => Class::DocComment,
+        Token::Whitespace | Token::Eof => return None,
+        _ => Class::Punct,
+    };
+    Some(class)
+}
+
+/// The class of each of `tokens`, `None` for whitespace.
+pub fn classify(
+    tokens: &[TokenAndSpan],
+    interner: &StringInterner,
+) -> Vec<Option<Class>> {
+    let mut classes: Vec<_> = tokens
+        .iter()
+        .map(|ts| token_class(&ts.tok, interner))
+        .collect();
+    // the index of the next token that isn't trivia
+    let next =
+        |i: usize| (i + 1..tokens.len()).find(|&j| !tokens[j].tok.is_trivia());
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i].tok {
+            Token::Pound => {
+                let mut open = next(i);
+                if open.map(|j| tokens[j].tok) == Some(Token::Not) {
+                    open = open.and_then(next);
+                }
+                let open = match open {
+                    Some(j)
+                        if tokens[j].tok
+                            == Token::OpenDelim(DelimToken::Bracket) =>
+                    {
+                        j
+                    }
+                    _ => {
+                        i += 1;
+                        continue;
+                    }
+                };
+                // up to the matching `]`, or the end of input
+                let mut depth = 0;
+                let mut end = open;
+                while end < tokens.len() {
+                    match tokens[end].tok {
+                        Token::OpenDelim(DelimToken::Bracket) => depth += 1,
+                        Token::CloseDelim(DelimToken::Bracket) => depth -= 1,
+                        _ => {}
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    end += 1;
+                }
+                let end = end.min(tokens.len() - 1);
+                for class in &mut classes[i..=end] {
+                    *class = Some(Class::Attribute);
+                }
+                i = end + 1;
+            }
+            Token::Ident(..) if i + 1 < tokens.len() => {
+                if tokens[i + 1].tok == Token::Not
+                    && classes[i] == Some(Class::Ident)
+                {
+                    classes[i] = Some(Class::Macro);
+                    classes[i + 1] = Some(Class::Macro);
+                    i += 1;
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    classes
+}
+
+// lexes `input` and calls `f` on each run of text of the same class
+fn render<F>(
+    input: &str,
+    interner: &mut StringInterner,
+    mut f: F,
+) -> Result<String, LexError>
+where
+    F: FnMut(&mut String, Option<Class>, &str),
+{
+    let tokens = str_to_token_slice(input.as_bytes(), interner)?;
+    let classes = classify(&tokens, interner);
+    let mut out = String::with_capacity(input.len() * 2);
+    let mut run: Option<(Option<Class>, usize, usize)> = None;
+    for (ts, &class) in tokens.iter().zip(&classes) {
+        run = match run {
+            Some((c, lo, _)) if c == class => Some((c, lo, ts.sp.hi)),
+            Some((c, lo, hi)) => {
+                f(&mut out, c, &input[lo..hi]);
+                Some((class, ts.sp.lo, ts.sp.hi))
+            }
+            None => Some((class, ts.sp.lo, ts.sp.hi)),
+        };
+    }
+    if let Some((c, lo, hi)) = run {
+        f(&mut out, c, &input[lo..hi]);
+    }
+    Ok(out)
+}
+
+fn escape_html(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// `input` as a `<pre class="rust">` element, with a `<span>` of the CSS
+/// class of `Class::css_class` around each highlighted run of text.
+pub fn to_html(
+    input: &str,
+    interner: &mut StringInterner,
+) -> Result<String, LexError> {
+    let body = render(input, interner, |out, class, text| match class {
+        Some(class) => {
+            out.push_str("<span class=\"");
+            out.push_str(class.css_class());
+            out.push_str("\">");
+            escape_html(out, text);
+            out.push_str("</span>");
+        }
+        None => escape_html(out, text),
+    })?;
+    Ok(format!("<pre class=\"rust\">{}</pre>\n", body))
+}
+
+// like `escape_html`, for a terminal: C0 controls but newline and tab, and
+// DEL, become their control pictures, eg. `␛` for ESC. C1 controls, some of
+// which terminals take for the start of an escape sequence, eg. U+009B for
+// `ESC [`, and the bidi controls, which reorder the text shown, become
+// escapes like `\u{9b}`.
+fn escape_controls(out: &mut String, text: &str) {
+    for c in text.chars() {
+        escape_control(out, c);
+    }
+}
+
+fn escape_control(out: &mut String, c: char) {
+    match c {
+        '\n' | '\t' => out.push(c),
+        '\x00'..='\x1f' => {
+            out.push(std::char::from_u32(0x2400 + c as u32).unwrap())
+        }
+        '\x7f' => out.push('\u{2421}'),
+        '\u{80}'..='\u{9f}' => out.push_str(&c.escape_unicode().to_string()),
+        c => match char_lint(c) {
+            Some(LintKind::BidiControl(_)) => {
+                out.push_str(&c.escape_unicode().to_string())
+            }
+            _ => out.push(c),
+        },
+    }
+}
+
+/// `input` with ANSI escape codes setting the color of each highlighted run
+/// of text. Control chars in `input` are shown as their Unicode control
+/// pictures or as `\u{..}` escapes rather than sent to the terminal.
+pub fn to_ansi(
+    input: &str,
+    interner: &mut StringInterner,
+) -> Result<String, LexError> {
+    render(input, interner, |out, class, text| {
+        match class.and_then(Class::ansi_color) {
+            Some(color) => {
+                out.push_str("\x1b[");
+                out.push_str(color);
+                out.push('m');
+                escape_controls(out, text);
+                out.push_str("\x1b[0m");
+            }
+            None => escape_controls(out, text),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classes(input: &str) -> Vec<(&str, Option<Class>)> {
+        let mut interner = StringInterner::empty();
+        let tokens =
+            str_to_token_slice(input.as_bytes(), &mut interner).unwrap();
+        let classes = classify(&tokens, &interner);
+        tokens
+            .iter()
+            .map(|ts| &input[ts.sp.lo..ts.sp.hi])
+            .zip(classes)
+            .filter(|&(_, class)| class.is_some())
+            .collect()
+    }
+
+    #[test]
+    fn test_classify() {
+        use self::Class::*;
+        assert_eq!(
+            classes("#![a(b)] fn f<'a>() { r#fn!(\"x\", 1.0) } // c"),
+            vec![
+                ("#", Some(Attribute)),
+                ("!", Some(Attribute)),
+                ("[", Some(Attribute)),
+                ("a", Some(Attribute)),
+                ("(", Some(Attribute)),
+                ("b", Some(Attribute)),
+                (")", Some(Attribute)),
+                ("]", Some(Attribute)),
+                ("fn", Some(Keyword)),
+                ("f", Some(Ident)),
+                ("<", Some(Punct)),
+                ("'a", Some(Lifetime)),
+                (">", Some(Punct)),
+                ("(", Some(Punct)),
+                (")", Some(Punct)),
+                ("{", Some(Punct)),
+                ("r#fn", Some(Macro)),
+                ("!", Some(Macro)),
+                ("(", Some(Punct)),
+                ("\"x\"", Some(String)),
+                (",", Some(Punct)),
+                ("1.0", Some(Number)),
+                (")", Some(Punct)),
+                ("}", Some(Punct)),
+                ("// c", Some(Comment)),
+            ]
+        );
+        // `!` after a keyword is not a macro, and `#` starts an attribute
+        // only when the next token but trivia is `[`
+        assert_eq!(classes("if !a")[1], ("!", Some(Punct)));
+        assert_eq!(classes("# [")[0], ("#", Some(Attribute)));
+        assert_eq!(classes("#a")[0], ("#", Some(Punct)));
+    }
+
+    #[test]
+    fn test_render() {
+        let mut interner = StringInterner::empty();
+        assert_eq!(
+            to_html("/// <a>\nlet s = \"&\";", &mut interner).unwrap(),
+            "<pre class=\"rust\"><span class=\"doccomment\">/// &lt;a&gt;\
+             </span>\n<span class=\"kw\">let</span> <span class=\"ident\">s\
+             </span> <span class=\"op\">=</span> <span class=\"string\">\
+             &quot;&amp;&quot;</span><span class=\"op\">;</span></pre>\n"
+        );
+        assert_eq!(
+            to_ansi("fn f() {}", &mut interner).unwrap(),
+            "\x1b[1;35mfn\x1b[0m f() {}"
+        );
+        assert_eq!(
+            to_ansi("\"\x1b[2J\x07\t\x7f\"", &mut interner).unwrap(),
+            "\x1b[32m\"\u{241b}[2J\u{2407}\t\u{2421}\"\x1b[0m"
+        );
+        assert_eq!(
+            to_ansi("// \u{9b}2J \u{85} \u{202e}é", &mut interner).unwrap(),
+            "\x1b[2m// \\u{9b}2J \\u{85} \\u{202e}é\x1b[0m"
+        );
+    }
+
+    // the output holds the input, with its controls escaped
+    #[quickcheck]
+    fn check_ansi_lossless(input: String) -> bool {
+        let mut interner = StringInterner::empty();
+        match to_ansi(&input, &mut interner) {
+            Ok(out) => {
+                let mut plain = String::new();
+                let mut chars = out.chars();
+                while let Some(c) = chars.next() {
+                    if c == '\x1b' {
+                        chars.by_ref().find(|&c| c == 'm');
+                    } else {
+                        plain.push(c);
+                    }
+                }
+                let mut escaped = String::new();
+                escape_controls(&mut escaped, &input);
+                plain == escaped
+            }
+            Err(_) => true,
+        }
+    }
+}
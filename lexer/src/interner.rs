@@ -1,3 +1,75 @@
+use crate::IntKey;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+pub struct StringInterner {
+    storage: HashMap<Box<str>, usize>,
+    index: Vec<*const str>,
+}
+
+impl StringInterner {
+    pub fn empty() -> StringInterner {
+        StringInterner {
+            storage: HashMap::new(),
+            index: vec![],
+        }
+    }
+    pub fn insert<Q: ?Sized>(&mut self, string: &Q) -> IntKey
+    where
+        Box<str>: std::borrow::Borrow<Q>,
+        Q: std::hash::Hash + Eq,
+        Q: AsRef<str>,
+    {
+        use std::collections::hash_map::*;
+        match self.storage.raw_entry_mut().from_key(string) {
+            RawEntryMut::Occupied(occ) => *occ.get() as IntKey,
+            RawEntryMut::Vacant(vac) => {
+                let ret = self.index.len();
+                let (k, _) = vac
+                    .insert(string.as_ref().to_string().into_boxed_str(), ret);
+                self.index.push(k as &str);
+                ret
+            }
+        }
+    }
+
+    pub fn get(&self, key: IntKey) -> &str {
+        unsafe { &*self.index[key as usize] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+}
+
+// SAFETY: `index` only points into the boxes owned by `storage`, which move
+// with the interner and are never mutated
+unsafe impl Send for StringInterner {}
+
+// where the lexer puts the names it reads: a borrowed interner, or one shared
+// with whoever owns the lexer
+pub trait Intern {
+    fn intern(&mut self, string: &str) -> IntKey;
+}
+
+impl Intern for StringInterner {
+    fn intern(&mut self, string: &str) -> IntKey {
+        self.insert(string)
+    }
+}
+
+impl<'a> Intern for &'a mut StringInterner {
+    fn intern(&mut self, string: &str) -> IntKey {
+        self.insert(string)
+    }
+}
+
+impl Intern for Arc<Mutex<StringInterner>> {
+    fn intern(&mut self, string: &str) -> IntKey {
+        self.lock().expect("interner poisoned").insert(string)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
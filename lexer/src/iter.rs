@@ -0,0 +1,279 @@
+//! Driving char and token generators without hand-written `resume` loops.
+//!
+//! `CharIter` and `TokenIter` are for generators whose input is complete, ie.
+//! their byte source ends with an empty chunk: `NeedMoreU8` then only means a
+//! chunk boundary, and resuming again goes on. `PushLexer` is for input that
+//! arrives in pieces, and lexes each piece as it is pushed.
+
+use crate::interner::{Intern, StringInterner};
+use crate::token::{Token, TokenAndSpan};
+use crate::{
+    char_generator_from_byte, token_generator_from_state, CharGenerator,
+    CharResult, LexError, LexState, TokenGenerator, TokenResult, Utf8Error,
+};
+use std::cell::Cell;
+use std::ops::{Generator, GeneratorState};
+use std::pin::Pin;
+use std::rc::Rc;
+
+/// The chars of a `CharGenerator`, up to its end of input or first error.
+pub struct CharIter<G> {
+    gen: G,
+    done: bool,
+}
+
+impl<G: CharGenerator + std::marker::Unpin> CharIter<G> {
+    pub fn new(gen: G) -> CharIter<G> {
+        CharIter { gen, done: false }
+    }
+}
+
+impl<G: CharGenerator + std::marker::Unpin> Iterator for CharIter<G> {
+    type Item = Result<char, Utf8Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match Pin::new(&mut self.gen).resume() {
+                GeneratorState::Yielded(CharResult::Ok(c)) => {
+                    return Some(Ok(c))
+                }
+                GeneratorState::Yielded(CharResult::NeedMoreU8) => {}
+                GeneratorState::Yielded(CharResult::Eof) => self.done = true,
+                GeneratorState::Complete(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// The tokens of a `TokenGenerator`, up to but excluding `Eof`, or up to its
+/// first error.
+pub struct TokenIter<G> {
+    gen: G,
+    done: bool,
+}
+
+impl<G: TokenGenerator + std::marker::Unpin> TokenIter<G> {
+    pub fn new(gen: G) -> TokenIter<G> {
+        TokenIter { gen, done: false }
+    }
+}
+
+impl<G: TokenGenerator + std::marker::Unpin> Iterator for TokenIter<G> {
+    type Item = Result<TokenAndSpan, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match Pin::new(&mut self.gen).resume() {
+                GeneratorState::Yielded(TokenResult::Ok(ts)) => {
+                    if ts.tok == Token::Eof {
+                        self.done = true;
+                    } else {
+                        return Some(Ok(ts));
+                    }
+                }
+                GeneratorState::Yielded(_) => {}
+                GeneratorState::Complete(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// A token generator over chunks handed to it one at a time, for drivers
+/// that read each chunk into a buffer of their own. The byte generator
+/// yields the chunk last given to `set_chunk`, as it is only resumed after
+/// the token generator yielded `NeedMoreU8`.
+pub(crate) struct InboxLexer<'a> {
+    // the chunk the byte generator yields when it is resumed next
+    inbox: Rc<Cell<&'static [u8]>>,
+    tokens:
+        Pin<Box<dyn Generator<Yield = TokenResult, Return = LexError> + 'a>>,
+}
+
+impl<'a> InboxLexer<'a> {
+    pub(crate) fn new<I: 'a + Intern>(interner: I) -> InboxLexer<'a> {
+        let inbox: Rc<Cell<&'static [u8]>> = Rc::new(Cell::new(&[]));
+        let chunk = inbox.clone();
+        let u8_gen = move || loop {
+            yield chunk.get();
+        };
+        let char_gen = char_generator_from_byte(u8_gen);
+        InboxLexer {
+            inbox,
+            tokens: Box::pin(token_generator_from_state(
+                char_gen,
+                interner,
+                0,
+                LexState::Normal,
+            )),
+        }
+    }
+
+    /// Hands `chunk` to the byte generator. An empty chunk ends the input.
+    ///
+    /// # Safety
+    ///
+    /// The char generator reads `chunk` until it yields `NeedMoreU8`, so
+    /// `chunk` must neither change nor be freed until then, nor before `self`
+    /// is dropped.
+    pub(crate) unsafe fn set_chunk(&self, chunk: &[u8]) {
+        let chunk = std::slice::from_raw_parts(chunk.as_ptr(), chunk.len());
+        self.inbox.set(chunk);
+    }
+
+    pub(crate) fn resume(&mut self) -> GeneratorState<TokenResult, LexError> {
+        self.tokens.as_mut().resume()
+    }
+}
+
+/// Lexes input pushed to it a chunk at a time. Each `push` hands over the
+/// tokens that chunk completed; a token still open at the end of the chunk
+/// is handed over by a later `push` or by `finish`.
+pub struct PushLexer<'a> {
+    lexer: InboxLexer<'a>,
+    // declared after `lexer`, so it is dropped after the generators
+    chunk: Vec<u8>,
+    result: Option<Result<(), LexError>>,
+}
+
+impl<'a> PushLexer<'a> {
+    pub fn new(interner: &'a mut StringInterner) -> PushLexer<'a> {
+        PushLexer {
+            lexer: InboxLexer::new(interner),
+            chunk: vec![],
+            result: None,
+        }
+    }
+
+    /// Lexes `chunk`, the next bytes of the input, calling `f` with each
+    /// token completed. Once an error was returned, it is returned again.
+    pub fn push<F>(&mut self, chunk: &[u8], f: F) -> Result<(), LexError>
+    where
+        F: FnMut(TokenAndSpan),
+    {
+        if let Some(result) = self.result {
+            return result;
+        }
+        // an empty chunk would mean the end of input
+        if chunk.is_empty() {
+            return Ok(());
+        }
+        self.chunk.clear();
+        self.chunk.extend_from_slice(chunk);
+        self.feed(f)
+    }
+
+    /// Ends the input, calling `f` with the tokens left. `Eof` is not
+    /// passed to `f`.
+    pub fn finish<F>(&mut self, f: F) -> Result<(), LexError>
+    where
+        F: FnMut(TokenAndSpan),
+    {
+        if let Some(result) = self.result {
+            return result;
+        }
+        self.chunk.clear();
+        let ret = self.feed(f);
+        self.result = Some(ret);
+        ret
+    }
+
+    fn feed<F>(&mut self, mut f: F) -> Result<(), LexError>
+    where
+        F: FnMut(TokenAndSpan),
+    {
+        // SAFETY: `feed` runs the generators until they yield `NeedMoreU8`
+        // or end, before `self.chunk` can change again, and `self.chunk` is
+        // dropped after them.
+        unsafe { self.lexer.set_chunk(&self.chunk) };
+        loop {
+            match self.lexer.resume() {
+                GeneratorState::Yielded(TokenResult::Ok(ts)) => {
+                    if ts.tok == Token::Eof {
+                        return Ok(());
+                    }
+                    f(ts);
+                }
+                GeneratorState::Yielded(TokenResult::NeedMoreU8) => {
+                    return Ok(());
+                }
+                GeneratorState::Yielded(_) => {}
+                GeneratorState::Complete(err) => {
+                    self.result = Some(Err(err));
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::from_chunks;
+    use crate::{str_to_token_slice, token_generator_from_char};
+
+    #[quickcheck]
+    fn check_push(input: String, size: usize) -> bool {
+        let bytes = input.as_bytes();
+        let mut interner = StringInterner::empty();
+        let expected = str_to_token_slice(bytes, &mut interner);
+        let mut interner = StringInterner::empty();
+        let mut lexer = PushLexer::new(&mut interner);
+        let mut tokens = vec![];
+        let mut ret = Ok(());
+        for chunk in bytes.chunks(size % 7 + 1) {
+            ret = ret.and(lexer.push(chunk, |ts| tokens.push(ts)));
+        }
+        ret = ret.and(lexer.finish(|ts| tokens.push(ts)));
+        expected == ret.map(|_| tokens)
+    }
+
+    #[quickcheck]
+    fn check_iter(input: String, size: usize) -> bool {
+        let bytes = input.as_bytes();
+        let chunks = || from_chunks(bytes.chunks(size % 7 + 1));
+        let chars: Result<String, _> =
+            CharIter::new(char_generator_from_byte(chunks())).collect();
+        let mut interner = StringInterner::empty();
+        let expected = str_to_token_slice(bytes, &mut interner);
+        let mut interner = StringInterner::empty();
+        let char_gen = char_generator_from_byte(chunks());
+        let tokens: Result<Vec<_>, _> =
+            TokenIter::new(token_generator_from_char(char_gen, &mut interner))
+                .collect();
+        chars.as_ref().map(|s| s.as_str()) == Ok(input.as_str())
+            && tokens == expected
+    }
+
+    #[test]
+    fn test_push_pending_token() {
+        let mut interner = StringInterner::empty();
+        let mut lexer = PushLexer::new(&mut interner);
+        let mut tokens = vec![];
+        lexer.push(b"ab", |ts| tokens.push(ts)).unwrap();
+        // `ab` may go on in the next chunk
+        assert!(tokens.is_empty());
+        lexer.push(b"c d", |ts| tokens.push(ts)).unwrap();
+        assert_eq!(tokens.len(), 2);
+        lexer.finish(|ts| tokens.push(ts)).unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[0].sp, crate::token::Span::new(0, 3));
+    }
+
+    #[test]
+    fn test_push_error() {
+        let mut interner = StringInterner::empty();
+        let mut lexer = PushLexer::new(&mut interner);
+        let err = lexer.push(b"a \"b", |_| {}).and(lexer.finish(|_| {}));
+        assert!(err.is_err());
+        assert_eq!(lexer.push(b"c", |_| panic!()), err);
+    }
+}
@@ -1,4 +1,4 @@
-#![feature(rustc_private, hash_raw_entry, never_type)]
+#![feature(hash_raw_entry, never_type)]
 #![feature(generators, generator_trait)]
 #![feature(trait_alias)]
 
@@ -8,45 +8,188 @@ extern crate quickcheck;
 #[macro_use(quickcheck)]
 extern crate quickcheck_macros;
 
-extern crate syntax;
-
-pub use syntax::parse::token::Token;
+pub mod comments;
+pub mod cursor;
+#[macro_use]
+pub mod delegate;
+pub mod direct;
+pub mod highlight;
+pub mod interner;
+pub mod iter;
+pub mod lines;
+pub mod lint;
+pub mod ops;
+#[cfg(feature = "proc-macro2")]
+pub mod proc_macro2_compat;
+pub mod relex;
+pub mod source;
+#[cfg(feature = "futures")]
+pub mod stream;
+pub mod token;
+pub mod tokenstream;
+pub mod transcode;
 pub mod utf8;
 
+pub use token::Token;
+
+use delegate::CharReader;
+use interner::{Intern, StringInterner};
+use ops::{
+    lex_and, lex_bin_op, lex_colon, lex_dot, lex_eq, lex_gt, lex_lt, lex_minus,
+    lex_not, lex_or,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::ops::{Generator, GeneratorState};
 use std::pin::Pin;
+use token::{BinOpToken, DelimToken, Lit, Span, TokenAndSpan};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+use unicode_xid::UnicodeXID;
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Utf8Error {
     InvalidLeading,
     InvalidCont,
     InvalidScalarValue,
+    // input ended in the middle of a multi-byte sequence
+    UnexpectedEof,
+    // reading the input failed, the error itself is kept by its source
+    Io(std::io::ErrorKind),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LexError {
+    Utf8(Utf8Error),
+    UnknownStartOfToken(char, Span),
+    UnterminatedBlockComment(Span),
+    UnterminatedDoubleQuoteString(Span),
+    UnterminatedRawString(Span),
+    // a raw string whose `#`s are not followed by `"`
+    InvalidRawStringDelimiter(Span),
+    UnterminatedCharLiteral(Span),
+    EmptyCharLiteral(Span),
+    // a literal newline, carriage return or tab in a char literal
+    CharMustBeEscaped(Span),
+    // a carriage return not followed by a newline, in a string or doc comment
+    BareCr(Span),
 }
 
+impl std::fmt::Display for Utf8Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Utf8Error::InvalidLeading => {
+                write!(f, "invalid UTF-8 leading byte")
+            }
+            Utf8Error::InvalidCont => {
+                write!(f, "invalid UTF-8 continuation byte")
+            }
+            Utf8Error::InvalidScalarValue => {
+                write!(f, "UTF-8 of a surrogate, an overlong one or too large")
+            }
+            Utf8Error::UnexpectedEof => {
+                write!(f, "input ends within a UTF-8 sequence")
+            }
+            Utf8Error::Io(kind) => {
+                write!(f, "can't read the input: {:?}", kind)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Utf8Error {}
+
+impl LexError {
+    /// Where the error is, unless it is in the UTF-8 or reading of the input.
+    pub fn span(&self) -> Option<Span> {
+        match *self {
+            LexError::Utf8(_) => None,
+            LexError::UnknownStartOfToken(_, sp)
+            | LexError::UnterminatedBlockComment(sp)
+            | LexError::UnterminatedDoubleQuoteString(sp)
+            | LexError::UnterminatedRawString(sp)
+            | LexError::InvalidRawStringDelimiter(sp)
+            | LexError::UnterminatedCharLiteral(sp)
+            | LexError::EmptyCharLiteral(sp)
+            | LexError::CharMustBeEscaped(sp)
+            | LexError::BareCr(sp) => Some(sp),
+        }
+    }
+}
+
+// the message, without the span, as rustc words it
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            LexError::Utf8(err) => write!(f, "{}", err),
+            LexError::UnknownStartOfToken(c, _) => {
+                write!(f, "unknown start of token: {}", c.escape_debug())
+            }
+            LexError::UnterminatedBlockComment(_) => {
+                write!(f, "unterminated block comment")
+            }
+            LexError::UnterminatedDoubleQuoteString(_) => {
+                write!(f, "unterminated double quote string")
+            }
+            LexError::UnterminatedRawString(_) => {
+                write!(f, "unterminated raw string")
+            }
+            LexError::InvalidRawStringDelimiter(_) => write!(
+                f,
+                "found invalid character; only `#` is allowed in raw string \
+                 delimitation"
+            ),
+            LexError::UnterminatedCharLiteral(_) => {
+                write!(f, "unterminated character literal")
+            }
+            LexError::EmptyCharLiteral(_) => {
+                write!(f, "empty character literal")
+            }
+            LexError::CharMustBeEscaped(_) => {
+                write!(f, "character constant must be escaped")
+            }
+            LexError::BareCr(_) => write!(f, "bare CR not allowed"),
+        }
+    }
+}
+
+impl std::error::Error for LexError {}
+
 pub enum CharResult {
     Ok(char),
     NeedMoreU8,
+    Eof,
 }
 
 pub enum TokenResult {
-    Ok(Token),
+    Ok(TokenAndSpan),
     NeedMoreU8,
     NeedMoreChar,
+    // a line starts at this byte offset, with the lexer in this state
+    LineStart(usize, LexState),
 }
 
-// an endless u8 generator
-pub trait U8Generator<'a> = Generator<Yield = &'a [u8], Return = !>;
+/// Where the token generator is at the start of a line. Only comments and
+/// strings can span lines, anywhere else it is `Normal`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LexState {
+    Normal,
+    BlockComment { depth: usize, doc: bool },
+    Str { byte: bool },
+    RawStr { byte: bool, hashes: u16 },
+}
+
+// an endless u8 generator, yields an empty slice once the input is exhausted,
+// unless reading the input fails
+pub trait U8Generator<'a> =
+    Generator<Yield = &'a [u8], Return = std::io::ErrorKind>;
 // an endless char generator, unless meets invalid utf8, or don't have enought u8 to decode utf8 char
 pub trait CharGenerator = Generator<Yield = CharResult, Return = Utf8Error>;
 // and endless Token generator, unless unlerlying CharGenerator didn't, and don't see enough char to decide on a whole token(eg. '=' vs '==')
-pub trait TokenGenerator = Generator<Yield = TokenResult, Return = Utf8Error>;
+pub trait TokenGenerator = Generator<Yield = TokenResult, Return = LexError>;
 
-pub type IntKey = usize;
-/*
-pub struct StringInterner {
-    storage: HashMap<Box<str>, usize>,
-    index: Vec<*const str>,
-}
-*/
+pub use syntax::IntKey;
 
 pub fn char_generator_from_byte<
     'b,
@@ -62,21 +205,19 @@ where
         let mut input: &[u8] = &[];
 
         macro_rules! replenish {
-            () => {{
+            ($on_eof:expr) => {{
                 {
-                    loop {
-                        match Pin::new(&mut source).resume() {
-                            GeneratorState::Yielded(buf) => {
-                                input = buf;
-                                len = buf.len();
-                                if len == 0 {
-                                    yield CharResult::NeedMoreU8;
-                                    continue;
-                                }
-                                pos = 0;
-                                break;
+                    match Pin::new(&mut source).resume() {
+                        GeneratorState::Yielded(buf) => {
+                            if buf.len() == 0 {
+                                $on_eof;
                             }
-                            _ => panic!(),
+                            input = buf;
+                            len = buf.len();
+                            pos = 0;
+                        }
+                        GeneratorState::Complete(kind) => {
+                            return Utf8Error::Io(kind)
                         }
                     }
                 }
@@ -84,81 +225,137 @@ where
         }
 
         macro_rules! next_u8 {
-            () => {
+            ($on_eof:expr) => {
                 if pos < len {
                     let p1 = pos;
                     pos += 1;
-                    input[p1] as u32
+                    input[p1]
                 } else {
                     yield CharResult::NeedMoreU8;
-                    replenish!();
+                    replenish!($on_eof);
                     pos = 1;
-                    input[0] as u32
-                }
-            };
-        }
-
-        macro_rules! next_u8_cont {
-            () => {{
-                let c = next_u8!();
-                let mask = 0b1100_0000;
-                if c & mask != 0b1000_0000 {
-                    return Utf8Error::InvalidCont;
+                    input[0]
                 }
-                c & !mask
-            }};
-        }
-
-        macro_rules! ok_char {
-            ($u_32:expr) => {
-                unsafe { CharResult::Ok(std::char::from_u32_unchecked($u_32)) }
             };
         }
 
-        replenish!();
+        replenish!(loop {
+            yield CharResult::Eof;
+        });
         pos = 0;
 
         loop {
-            let c1 = next_u8!();
-            if c1 < 0b1000_0000u32 {
-                yield ok_char!(c1);
+            let b0 = next_u8!(loop {
+                yield CharResult::Eof;
+            });
+            if b0 < 0x80 {
+                yield CharResult::Ok(b0 as char);
                 continue;
             }
-            if c1 < 0b1100_0000u32 {
-                return Utf8Error::InvalidLeading;
+            let (n, mut c) = match utf8_lead(b0) {
+                Ok(lead) => lead,
+                Err(err) => return err,
+            };
+            for _ in 1..n {
+                let b = next_u8!(return Utf8Error::UnexpectedEof);
+                c = match utf8_cont(c, b) {
+                    Ok(c) => c,
+                    Err(err) => return err,
+                };
             }
-            let c2 = next_u8_cont!();
-            if c1 < 0b1110_0000u32 {
-                yield ok_char!((c1 & 0x1F) << 6 | c2);
-                continue;
+            match utf8_char(c, n) {
+                Ok(c) => yield CharResult::Ok(c),
+                Err(err) => return err,
             }
-            let c3 = next_u8_cont!();
-            if c1 < 0b1111_0000u32 {
-                yield ok_char!((c1 & 0xF) << 12 | c2 << 6 | c3);
-                continue;
-            }
-            let c4 = next_u8_cont!();
-            yield ok_char!((c1 & 0x7) << 18 | c2 << 12 | c3 << 6 | c4);
         }
     };
 }
 
+// the length of the char whose UTF-8 starts with `b0`, and the bits of it
+// `b0` holds
+fn utf8_lead(b0: u8) -> Result<(usize, u32), Utf8Error> {
+    let b0 = u32::from(b0);
+    match b0 {
+        0x00..=0x7F => Ok((1, b0)),
+        0xC0..=0xDF => Ok((2, b0 & 0x1F)),
+        0xE0..=0xEF => Ok((3, b0 & 0x0F)),
+        0xF0..=0xF7 => Ok((4, b0 & 0x07)),
+        _ => Err(Utf8Error::InvalidLeading),
+    }
+}
+
+// `c` followed by the bits of the continuation byte `b`
+fn utf8_cont(c: u32, b: u8) -> Result<u32, Utf8Error> {
+    if b & 0xC0 != 0x80 {
+        return Err(Utf8Error::InvalidCont);
+    }
+    Ok(c << 6 | u32::from(b & 0x3F))
+}
+
+// the char `c`, decoded from `len` bytes
+fn utf8_char(c: u32, len: usize) -> Result<char, Utf8Error> {
+    // the smallest char that needs `len` bytes
+    let min = [0, 0, 0x80, 0x800, 0x10000][len];
+    match std::char::from_u32(c) {
+        Some(ch) if c >= min => Ok(ch),
+        _ => Err(Utf8Error::InvalidScalarValue),
+    }
+}
+
+// decodes the char `bytes` starts with, returns it and its length. The char
+// generator decodes the same way, a byte at a time.
+fn decode_utf8(bytes: &[u8]) -> Result<(char, usize), Utf8Error> {
+    let (len, mut c) = utf8_lead(bytes[0])?;
+    for i in 1..len {
+        match bytes.get(i) {
+            Some(&b) => c = utf8_cont(c, b)?,
+            None => return Err(Utf8Error::UnexpectedEof),
+        }
+    }
+    Ok((utf8_char(c, len)?, len))
+}
+
 pub fn str_to_char_slice(input: &[u8]) -> Result<Vec<char>, Utf8Error> {
-    let u8_gen = || {
-        yield input;
-        panic!();
-    };
-    let mut char_gen = char_generator_from_byte(u8_gen);
-    let mut ret = Vec::new();
+    let char_gen = char_generator_from_byte(source::from_slice(input));
+    iter::CharIter::new(char_gen).collect()
+}
+
+pub fn str_to_token_slice(
+    input: &[u8],
+    interner: &mut StringInterner,
+) -> Result<Vec<TokenAndSpan>, LexError> {
+    let char_gen = char_generator_from_byte(source::from_slice(input));
+    iter::TokenIter::new(token_generator_from_char(char_gen, interner))
+        .collect()
+}
+
+/// Lexes `input`, the rest of a file from byte offset `start` on, with the
+/// lexer resumed in `state`. Returns the tokens, the first of which may be
+/// the tail of a comment or string, and the state at each line start.
+pub fn str_to_token_slice_from(
+    input: &[u8],
+    start: usize,
+    state: LexState,
+    interner: &mut StringInterner,
+) -> Result<(Vec<TokenAndSpan>, Vec<(usize, LexState)>), LexError> {
+    let char_gen = char_generator_from_byte(source::from_slice(input));
+    let mut token_gen =
+        token_generator_from_state(char_gen, interner, start, state);
+    let mut tokens = Vec::new();
+    let mut lines = Vec::new();
     loop {
-        match Pin::new(&mut char_gen).resume() {
+        match Pin::new(&mut token_gen).resume() {
             GeneratorState::Yielded(res) => match res {
-                CharResult::Ok(ch) => {
-                    ret.push(ch);
+                TokenResult::Ok(ts) => {
+                    if ts.tok == Token::Eof {
+                        return Ok((tokens, lines));
+                    }
+                    tokens.push(ts);
                 }
-                CharResult::NeedMoreU8 => {
-                    return Ok(ret);
+                TokenResult::LineStart(pos, state) => {
+                    lines.push((pos, state));
                 }
+                TokenResult::NeedMoreU8 | TokenResult::NeedMoreChar => {}
             },
             GeneratorState::Complete(err) => {
                 return Err(err);
@@ -167,6 +364,661 @@ pub fn str_to_char_slice(input: &[u8]) -> Result<Vec<char>, Utf8Error> {
     }
 }
 
+// Pattern_White_Space, as used by rustc
+fn is_whitespace(c: char) -> bool {
+    match c {
+        ' ' | '\n' | '\t' | '\u{000B}' | '\u{000C}' | '\r' | '\u{0085}'
+        | '\u{200E}' | '\u{200F}' | '\u{2028}' | '\u{2029}' => true,
+        _ => false,
+    }
+}
+
+fn is_id_start(c: char) -> bool {
+    c.is_ascii_alphabetic()
+        || c == '_'
+        || (c > '\x7f' && UnicodeXID::is_xid_start(c))
+}
+
+fn is_id_continue(c: char) -> bool {
+    c.is_ascii_alphanumeric()
+        || c == '_'
+        || (c > '\x7f' && UnicodeXID::is_xid_continue(c))
+}
+
+// interns an identifier or lifetime in NFC, as rustc does
+fn intern_ident<I: Intern>(interner: &mut I, name: &str) -> IntKey {
+    if name.is_ascii() || is_nfc(name) {
+        interner.intern(name)
+    } else {
+        interner.intern(name.nfc().collect::<String>().as_str())
+    }
+}
+
+// the offset in `text` of its first carriage return not followed by a
+// newline, `lf_after` telling whether `text` itself is followed by one
+fn find_bare_cr(text: &str, lf_after: bool) -> Option<usize> {
+    let bytes = text.as_bytes();
+    (0..bytes.len()).find(|&i| {
+        bytes[i] == b'\r'
+            && match bytes.get(i + 1) {
+                Some(&b) => b != b'\n',
+                None => !lf_after,
+            }
+    })
+}
+
+// `text` without the carriage return of each CRLF in it, as rustc
+// normalizes them to newlines, `lf_after` telling whether `text` itself is
+// followed by a newline
+fn strip_crlf(text: &str, lf_after: bool) -> Cow<'_, str> {
+    if !text.contains('\r') {
+        return Cow::Borrowed(text);
+    }
+    let mut chars = text.chars().peekable();
+    let mut ret = String::with_capacity(text.len());
+    while let Some(c) = chars.next() {
+        let is_crlf = c == '\r'
+            && match chars.peek() {
+                Some(&c) => c == '\n',
+                None => lf_after,
+            };
+        if !is_crlf {
+            ret.push(c);
+        }
+    }
+    Cow::Owned(ret)
+}
+
+fn is_dec_digit(c: char) -> bool {
+    c.is_ascii_digit() || c == '_'
+}
+
+fn is_hex_digit(c: char) -> bool {
+    c.is_ascii_hexdigit() || c == '_'
+}
+
+fn is_doc_comment(s: &str) -> bool {
+    if s.starts_with("//") {
+        (s.starts_with("///") && !s.starts_with("////")) || s.starts_with("//!")
+    } else {
+        (s.starts_with("/**") && !s.starts_with("/***") && s != "/**/")
+            || s.starts_with("/*!")
+    }
+}
+
+pub fn token_generator_from_char<
+    'a,
+    T: 'a + CharGenerator + std::marker::Unpin,
+>(
+    source: T,
+    interner: &'a mut StringInterner,
+) -> impl TokenGenerator + 'a {
+    token_generator_from_char_at(source, interner, 0)
+}
+
+// like `token_generator_from_char`, but `source` starts at byte offset
+// `start` of the file, which must be a token boundary
+pub fn token_generator_from_char_at<
+    'a,
+    T: 'a + CharGenerator + std::marker::Unpin,
+>(
+    source: T,
+    interner: &'a mut StringInterner,
+    start: usize,
+) -> impl TokenGenerator + 'a {
+    token_generator_from_state(source, interner, start, LexState::Normal)
+}
+
+// like `token_generator_from_char_at`, but `start` may be inside a comment or
+// string, as described by `state`. Yields `LineStart` after each `\n`.
+pub fn token_generator_from_state<
+    'a,
+    T: 'a + CharGenerator + std::marker::Unpin,
+    I: 'a + Intern,
+>(
+    source: T,
+    mut interner: I,
+    start: usize,
+    state: LexState,
+) -> impl TokenGenerator + 'a {
+    return move || {
+        let reader = CharReader::shared(source, start);
+
+        macro_rules! next_char {
+            () => {
+                match read_char!(reader) {
+                    Ok(c) => c,
+                    Err(err) => return err,
+                }
+            };
+        }
+
+        // give back the char last returned by `next_char!`
+        macro_rules! unread {
+            ($c:expr) => {
+                reader.borrow_mut().unread($c)
+            };
+        }
+
+        // source text of the current token
+        macro_rules! text {
+            () => {
+                reader.borrow().text()
+            };
+        }
+
+        // the state reported by the next `LineStart`
+        macro_rules! set_mode {
+            ($mode:expr) => {
+                reader.borrow_mut().mode = $mode
+            };
+        }
+
+        // finish the current token with a sub-lexer
+        macro_rules! delegate {
+            ($sub_lexer:expr) => {
+                match yield_from!($sub_lexer) {
+                    Ok(tok) => tok,
+                    Err(err) => return err,
+                }
+            };
+        }
+
+        macro_rules! peek {
+            () => {
+                match next_char!() {
+                    Some(c) => {
+                        unread!(c);
+                        Some(c)
+                    }
+                    None => None,
+                }
+            };
+        }
+
+        // consume the next char if it is `$ch`
+        macro_rules! eat {
+            ($ch:expr) => {
+                match next_char!() {
+                    Some(c) if c == $ch => true,
+                    Some(c) => {
+                        unread!(c);
+                        false
+                    }
+                    None => false,
+                }
+            };
+        }
+
+        macro_rules! eat_while {
+            ($pred:expr) => {
+                loop {
+                    match next_char!() {
+                        Some(c) if $pred(c) => {}
+                        Some(c) => {
+                            unread!(c);
+                            break;
+                        }
+                        None => break,
+                    }
+                }
+            };
+        }
+
+        macro_rules! span {
+            () => {
+                reader.borrow().span()
+            };
+        }
+
+        // fail on a bare CR in the current token, see `find_bare_cr`
+        macro_rules! deny_bare_cr {
+            ($lf_after:expr) => {
+                let lf_after: bool = $lf_after;
+                if let Some(i) = find_bare_cr(text!(), lf_after) {
+                    let lo = reader.borrow().lo() + i;
+                    return LexError::BareCr(Span::new(lo, lo + 1));
+                }
+            };
+        }
+
+        macro_rules! ident {
+            () => {{
+                eat_while!(is_id_continue);
+                Token::Ident(intern_ident(&mut interner, text!()), false)
+            }};
+        }
+
+        // an optional literal suffix, eg. the `u8` in `1u8`
+        macro_rules! suffix {
+            () => {
+                match peek!() {
+                    Some(c) if is_id_start(c) => {
+                        let start = text!().len();
+                        eat_while!(is_id_continue);
+                        Some(interner.intern(&text!()[start..]))
+                    }
+                    _ => None,
+                }
+            };
+        }
+
+        // the body of a char or byte literal, after the opening quote
+        macro_rules! quoted_char {
+            ($prefix_len:expr) => {{
+                match next_char!() {
+                    Some('\\') => match next_char!() {
+                        Some('u') => {
+                            if eat!('{') {
+                                eat_while!(|c| c != '}'
+                                    && c != '\''
+                                    && c != '\n');
+                                eat!('}');
+                            }
+                        }
+                        Some('x') => {
+                            eat_while!(|c: char| c.is_ascii_hexdigit());
+                        }
+                        Some(_) => {}
+                        None => {
+                            return LexError::UnterminatedCharLiteral(span!());
+                        }
+                    },
+                    Some('\'') => {
+                        return LexError::EmptyCharLiteral(span!());
+                    }
+                    Some('\n') | Some('\r') | Some('\t') => {
+                        return LexError::CharMustBeEscaped(span!());
+                    }
+                    Some(_) => {}
+                    None => {
+                        return LexError::UnterminatedCharLiteral(span!());
+                    }
+                }
+                if !eat!('\'') {
+                    return LexError::UnterminatedCharLiteral(span!());
+                }
+                interner.intern(&text!()[$prefix_len..text!().len() - 1])
+            }};
+        }
+
+        // the body of a string or byte string, after the opening quote
+        macro_rules! quoted_str {
+            ($prefix_len:expr, $byte:expr) => {{
+                set_mode!(LexState::Str { byte: $byte });
+                loop {
+                    match next_char!() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            next_char!();
+                        }
+                        Some(_) => {}
+                        None => {
+                            return LexError::UnterminatedDoubleQuoteString(
+                                span!(),
+                            );
+                        }
+                    }
+                }
+                deny_bare_cr!(true);
+                set_mode!(LexState::Normal);
+                let end = text!().len() - 1;
+                interner.intern(&strip_crlf(&text!()[$prefix_len..end], false))
+            }};
+        }
+
+        // the rest of a raw string, whose content starts at `text[$start..]`
+        macro_rules! raw_str_rest {
+            ($byte:expr, $hashes:expr, $start:expr) => {{
+                let hashes: u16 = $hashes;
+                let start: usize = $start;
+                set_mode!(LexState::RawStr {
+                    byte: $byte,
+                    hashes,
+                });
+                loop {
+                    match next_char!() {
+                        Some('"') => {
+                            let mut closing: u16 = 0;
+                            while closing < hashes && eat!('#') {
+                                closing += 1;
+                            }
+                            if closing == hashes {
+                                break;
+                            }
+                        }
+                        Some(_) => {}
+                        None => {
+                            return LexError::UnterminatedRawString(span!());
+                        }
+                    }
+                }
+                deny_bare_cr!(true);
+                set_mode!(LexState::Normal);
+                let end = text!().len() - 1 - hashes as usize;
+                (
+                    interner.intern(&strip_crlf(&text!()[start..end], false)),
+                    hashes,
+                )
+            }};
+        }
+
+        // a raw string, after the `r` or `br` prefix
+        macro_rules! raw_str {
+            ($byte:expr) => {{
+                let mut hashes: u16 = 0;
+                while eat!('#') {
+                    hashes += 1;
+                }
+                if !eat!('"') {
+                    return LexError::InvalidRawStringDelimiter(span!());
+                }
+                raw_str_rest!($byte, hashes, text!().len())
+            }};
+        }
+
+        // the rest of a block comment nested `$depth` deep
+        macro_rules! block_comment {
+            ($depth:expr, $doc:expr) => {{
+                let mut depth: usize = $depth;
+                while depth > 0 {
+                    // `$doc` looks at the text read so far
+                    let doc: bool = $doc;
+                    set_mode!(LexState::BlockComment { depth, doc });
+                    match next_char!() {
+                        Some('/') => {
+                            if eat!('*') {
+                                depth += 1;
+                            }
+                        }
+                        Some('*') => {
+                            if eat!('/') {
+                                depth -= 1;
+                            }
+                        }
+                        Some(_) => {}
+                        None => {
+                            return LexError::UnterminatedBlockComment(span!());
+                        }
+                    }
+                }
+                set_mode!(LexState::Normal);
+                if $doc {
+                    deny_bare_cr!(true);
+                    Token::DocComment(
+                        interner.intern(&strip_crlf(text!(), false)),
+                    )
+                } else {
+                    Token::Comment
+                }
+            }};
+        }
+
+        // finish the token `state` says `start` is in the middle of
+        let resumed = match state {
+            LexState::Normal => None,
+            LexState::BlockComment { depth, doc } => {
+                Some(block_comment!(depth, doc))
+            }
+            LexState::Str { byte } => {
+                let name = quoted_str!(0, byte);
+                let lit = if byte {
+                    Lit::ByteStr(name)
+                } else {
+                    Lit::Str_(name)
+                };
+                Some(Token::Literal(lit, suffix!()))
+            }
+            LexState::RawStr { byte, hashes } => {
+                let (name, hashes) = raw_str_rest!(byte, hashes, 0);
+                let lit = if byte {
+                    Lit::ByteStrRaw(name, hashes)
+                } else {
+                    Lit::StrRaw(name, hashes)
+                };
+                Some(Token::Literal(lit, suffix!()))
+            }
+        };
+        if let Some(tok) = resumed {
+            let sp = span!();
+            yield TokenResult::Ok(TokenAndSpan { tok, sp });
+        }
+
+        loop {
+            reader.borrow_mut().start_token();
+            let c = match next_char!() {
+                Some(c) => c,
+                None => break,
+            };
+            let tok = match c {
+                c if is_whitespace(c) => {
+                    eat_while!(is_whitespace);
+                    Token::Whitespace
+                }
+                '/' => {
+                    if eat!('/') {
+                        eat_while!(|c| c != '\n');
+                        if is_doc_comment(text!()) {
+                            let lf_after = peek!() == Some('\n');
+                            deny_bare_cr!(lf_after);
+                            Token::DocComment(
+                                interner.intern(&strip_crlf(text!(), lf_after)),
+                            )
+                        } else {
+                            Token::Comment
+                        }
+                    } else if eat!('*') {
+                        block_comment!(1, is_doc_comment(text!()))
+                    } else {
+                        delegate!(lex_bin_op(reader.clone(), BinOpToken::Slash))
+                    }
+                }
+                '=' => delegate!(lex_eq(reader.clone())),
+                '!' => delegate!(lex_not(reader.clone())),
+                '<' => delegate!(lex_lt(reader.clone())),
+                '>' => delegate!(lex_gt(reader.clone())),
+                '&' => delegate!(lex_and(reader.clone())),
+                '|' => delegate!(lex_or(reader.clone())),
+                '-' => delegate!(lex_minus(reader.clone())),
+                '+' => delegate!(lex_bin_op(reader.clone(), BinOpToken::Plus)),
+                '*' => delegate!(lex_bin_op(reader.clone(), BinOpToken::Star)),
+                '%' => {
+                    delegate!(lex_bin_op(reader.clone(), BinOpToken::Percent))
+                }
+                '^' => {
+                    delegate!(lex_bin_op(reader.clone(), BinOpToken::Caret))
+                }
+                '.' => delegate!(lex_dot(reader.clone())),
+                ':' => delegate!(lex_colon(reader.clone())),
+                '#' => {
+                    // `#!` at the very start of a file is a shebang, unless
+                    // it is the beginning of an inner attribute `#![`
+                    if reader.borrow().lo() == 0 && eat!('!') {
+                        if peek!() != Some('[') {
+                            eat_while!(|c| c != '\n');
+                            let lf_after = peek!() == Some('\n');
+                            Token::Shebang(
+                                interner.intern(&strip_crlf(text!(), lf_after)),
+                            )
+                        } else {
+                            unread!('!');
+                            Token::Pound
+                        }
+                    } else {
+                        Token::Pound
+                    }
+                }
+                '~' => Token::Tilde,
+                '@' => Token::At,
+                ',' => Token::Comma,
+                ';' => Token::Semi,
+                '$' => Token::Dollar,
+                '?' => Token::Question,
+                '(' => Token::OpenDelim(DelimToken::Paren),
+                ')' => Token::CloseDelim(DelimToken::Paren),
+                '[' => Token::OpenDelim(DelimToken::Bracket),
+                ']' => Token::CloseDelim(DelimToken::Bracket),
+                '{' => Token::OpenDelim(DelimToken::Brace),
+                '}' => Token::CloseDelim(DelimToken::Brace),
+                '\'' => {
+                    // `'a` is a lifetime, `'a'` is a char literal
+                    let c2 = match next_char!() {
+                        Some(c2) => c2,
+                        None => {
+                            return LexError::UnterminatedCharLiteral(span!());
+                        }
+                    };
+                    if is_id_start(c2) && peek!() != Some('\'') {
+                        eat_while!(is_id_continue);
+                        Token::Lifetime(intern_ident(&mut interner, text!()))
+                    } else {
+                        unread!(c2);
+                        let name = quoted_char!(1);
+                        Token::Literal(Lit::Char(name), suffix!())
+                    }
+                }
+                '"' => {
+                    let name = quoted_str!(1, false);
+                    Token::Literal(Lit::Str_(name), suffix!())
+                }
+                'r' => {
+                    if eat!('#') {
+                        match peek!() {
+                            Some('"') | Some('#') => {
+                                unread!('#');
+                                let (name, hashes) = raw_str!(false);
+                                Token::Literal(
+                                    Lit::StrRaw(name, hashes),
+                                    suffix!(),
+                                )
+                            }
+                            Some(c) if is_id_start(c) => {
+                                eat_while!(is_id_continue);
+                                Token::Ident(
+                                    intern_ident(&mut interner, &text!()[2..]),
+                                    true,
+                                )
+                            }
+                            _ => {
+                                unread!('#');
+                                ident!()
+                            }
+                        }
+                    } else if peek!() == Some('"') {
+                        let (name, hashes) = raw_str!(false);
+                        Token::Literal(Lit::StrRaw(name, hashes), suffix!())
+                    } else {
+                        ident!()
+                    }
+                }
+                'b' => {
+                    if eat!('\'') {
+                        let name = quoted_char!(2);
+                        Token::Literal(Lit::Byte(name), suffix!())
+                    } else if eat!('"') {
+                        let name = quoted_str!(2, true);
+                        Token::Literal(Lit::ByteStr(name), suffix!())
+                    } else if eat!('r') {
+                        match peek!() {
+                            Some('"') | Some('#') => {
+                                let (name, hashes) = raw_str!(true);
+                                Token::Literal(
+                                    Lit::ByteStrRaw(name, hashes),
+                                    suffix!(),
+                                )
+                            }
+                            _ => ident!(),
+                        }
+                    } else {
+                        ident!()
+                    }
+                }
+                c if is_id_start(c) => ident!(),
+                c if c.is_ascii_digit() => {
+                    let mut is_float = false;
+                    let mut is_hex = false;
+                    if c == '0' {
+                        if eat!('x') {
+                            is_hex = true;
+                        } else if !eat!('o') {
+                            eat!('b');
+                        }
+                    }
+                    if is_hex {
+                        eat_while!(is_hex_digit);
+                    } else {
+                        eat_while!(is_dec_digit);
+                    }
+                    let is_decimal = !is_hex
+                        && text!()
+                            .chars()
+                            .all(|c| c.is_ascii_digit() || c == '_');
+                    if is_decimal && eat!('.') {
+                        // `1..2` and `1.foo()` don't make `1.` a float
+                        match peek!() {
+                            Some(c) if c == '.' || is_id_start(c) => {
+                                unread!('.');
+                            }
+                            _ => {
+                                is_float = true;
+                                eat_while!(is_dec_digit);
+                            }
+                        }
+                    }
+                    if is_decimal {
+                        match peek!() {
+                            Some(e) if e == 'e' || e == 'E' => {
+                                next_char!();
+                                let sign = if eat!('+') {
+                                    Some('+')
+                                } else if eat!('-') {
+                                    Some('-')
+                                } else {
+                                    None
+                                };
+                                match peek!() {
+                                    Some(c) if is_dec_digit(c) => {
+                                        eat_while!(is_dec_digit);
+                                        is_float = true;
+                                    }
+                                    // not an exponent, leave `e` to the suffix
+                                    _ => {
+                                        if let Some(sign) = sign {
+                                            unread!(sign);
+                                        }
+                                        unread!(e);
+                                    }
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    let name = interner.intern(text!());
+                    let lit = if is_float {
+                        Lit::Float(name)
+                    } else {
+                        Lit::Integer(name)
+                    };
+                    Token::Literal(lit, suffix!())
+                }
+                c => {
+                    return LexError::UnknownStartOfToken(c, span!());
+                }
+            };
+            let sp = span!();
+            yield TokenResult::Ok(TokenAndSpan { tok, sp });
+        }
+
+        let sp = span!();
+        loop {
+            yield TokenResult::Ok(TokenAndSpan {
+                tok: Token::Eof,
+                sp,
+            });
+        }
+    };
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -186,124 +1038,341 @@ mod test {
         let res = prop1(input.as_str());
         return res;
     }
-}
 
-pub fn token_generator_from_char<T: CharGenerator + std::marker::Unpin>(
-    mut source: T,
-) -> impl TokenGenerator {
-    return move || {
-        let handle_eq = handle_eq_impl(Pin::new(&mut source));
-//        let handle_plus = handle_plus_impl(Pin::new(&mut source));
+    #[test]
+    fn test_invalid_utf8() {
+        let err = |input: &[u8]| str_to_char_slice(input).err();
+        assert_eq!(err(b"a\x80"), Some(Utf8Error::InvalidLeading));
+        assert_eq!(err(b"\xf8\x80\x80\x80"), Some(Utf8Error::InvalidLeading));
+        assert_eq!(err(b"\xc3a"), Some(Utf8Error::InvalidCont));
+        assert_eq!(err(b"\xe2\x80"), Some(Utf8Error::UnexpectedEof));
+        // overlong `/`, a surrogate, and a char past U+10FFFF
+        assert_eq!(err(b"\xc0\xaf"), Some(Utf8Error::InvalidScalarValue));
+        assert_eq!(err(b"\xed\xa0\x80"), Some(Utf8Error::InvalidScalarValue));
+        assert_eq!(
+            err(b"\xf4\x90\x80\x80"),
+            Some(Utf8Error::InvalidScalarValue)
+        );
+    }
 
-        macro_rules! next_char {
-            () => {
-                loop {
-                    match Pin::new(&mut source).resume() {
-                        GeneratorState::Yielded(cr) => {
-                            match cr {
-                                CharResult::Ok(c) => break c,
-                                CharResult::NeedMoreU8 => yield TokenResult::NeedMoreU8,
-                            }
-                        },
-                        GeneratorState::Complete(err) => {
-                            return err;
-                        }
-                    }
-                }
+    // the chars are those `std::str::from_utf8` finds valid
+    #[quickcheck]
+    fn check_from_utf8(bytes: Vec<u8>) -> bool {
+        let chars: Vec<_> = iter::CharIter::new(char_generator_from_byte(
+            source::from_slice(&bytes),
+        ))
+        .collect();
+        let (valid, invalid) = match std::str::from_utf8(&bytes) {
+            Ok(s) => (s, false),
+            Err(err) => {
+                let valid = &bytes[..err.valid_up_to()];
+                (std::str::from_utf8(valid).unwrap(), true)
+            }
+        };
+        let mut expected: Vec<_> = valid.chars().map(Ok).collect();
+        // invalid UTF-8 must end in an error, not just stop
+        if invalid {
+            match chars.last() {
+                Some(&Err(err)) => expected.push(Err(err)),
+                _ => return false,
             }
         }
+        chars == expected
+    }
 
-        macro_rules! my_co_await {
-            ($func:ident($($exp:expr),*)) => {
-                match $func(Pin::new(&mut source, $($expr),*)) {
-                    GeneratorState::Yielded(tr) => yield tr,
-                    GeneratorState::Complete(err) => return err,
-                }
+    fn lex(input: &str) -> Vec<(Token, String)> {
+        let mut interner = StringInterner::empty();
+        let tokens = str_to_token_slice(input.as_bytes(), &mut interner)
+            .unwrap_or_else(|err| panic!("{:?}", err));
+        tokens
+            .into_iter()
+            .filter(|ts| ts.tok != Token::Whitespace)
+            .map(|ts| (ts.tok, input[ts.sp.lo..ts.sp.hi].to_string()))
+            .collect()
+    }
+
+    fn lex_text(input: &str) -> Vec<String> {
+        lex(input).into_iter().map(|(_, text)| text).collect()
+    }
+
+    #[test]
+    fn test_operators() {
+        assert_eq!(
+            lex_text("a>>=b..=c...d..e::f->g=>h<-i&&j||k!=l<<m"),
+            vec![
+                "a", ">>=", "b", "..=", "c", "...", "d", "..", "e", "::", "f",
+                "->", "g", "=>", "h", "<-", "i", "&&", "j", "||", "k", "!=",
+                "l", "<<", "m"
+            ]
+        );
+        let toks: Vec<Token> = lex("+= - * %= ^ ! ~ @ , ; $ ?")
+            .into_iter()
+            .map(|(tok, _)| tok)
+            .collect();
+        assert_eq!(
+            toks,
+            vec![
+                Token::BinOpEq(BinOpToken::Plus),
+                Token::BinOp(BinOpToken::Minus),
+                Token::BinOp(BinOpToken::Star),
+                Token::BinOpEq(BinOpToken::Percent),
+                Token::BinOp(BinOpToken::Caret),
+                Token::Not,
+                Token::Tilde,
+                Token::At,
+                Token::Comma,
+                Token::Semi,
+                Token::Dollar,
+                Token::Question,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(
+            lex_text("1..2 1.0 1. 1.foo 1e10 2.5E-3f64 0x1F_u8 0b101 1e"),
+            vec![
+                "1",
+                "..",
+                "2",
+                "1.0",
+                "1.",
+                "1",
+                ".",
+                "foo",
+                "1e10",
+                "2.5E-3f64",
+                "0x1F_u8",
+                "0b101",
+                "1e"
+            ]
+        );
+        assert_eq!(
+            lex_text(
+                r####"'a' '\'' '\u{1F600}' b'\x7f' "a\"b" r#"a"b"# br"c""####
+            ),
+            vec![
+                r"'a'",
+                r"'\''",
+                r"'\u{1F600}'",
+                r"b'\x7f'",
+                r#""a\"b""#,
+                r###"r#"a"b"#"###,
+                r#"br"c""#
+            ]
+        );
+        let mut interner = StringInterner::empty();
+        let tokens =
+            str_to_token_slice(br##"r#"x"# 1.5f32"##, &mut interner).unwrap();
+        match tokens[0].tok {
+            Token::Literal(Lit::StrRaw(name, 1), None) => {
+                assert_eq!(interner.get(name), "x")
             }
+            tok => panic!("{:?}", tok),
         }
-
-        loop {
-            let c = next_char!();
-            match c {
-                '=' => {
-                    //my_co_await(lex_eq());
-        return Utf8Error::InvalidLeading;
-                },
-                _ => {
-        return Utf8Error::InvalidLeading;
-                }
+        match tokens[2].tok {
+            Token::Literal(Lit::Float(name), Some(suffix)) => {
+                assert_eq!(interner.get(name), "1.5");
+                assert_eq!(interner.get(suffix), "f32");
             }
+            tok => panic!("{:?}", tok),
         }
+    }
 
-        return Utf8Error::InvalidLeading;
-    };
-}
+    #[test]
+    fn test_idents_and_lifetimes() {
+        let toks = lex("r#match 'a 'static br b 'c' _x ünicode");
+        let kinds: Vec<String> = toks
+            .iter()
+            .map(|(tok, _)| match tok {
+                Token::Ident(_, false) => "ident".to_string(),
+                Token::Ident(_, true) => "raw".to_string(),
+                Token::Lifetime(_) => "lifetime".to_string(),
+                Token::Literal(..) => "literal".to_string(),
+                tok => format!("{:?}", tok),
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "raw", "lifetime", "lifetime", "ident", "ident", "literal",
+                "ident", "ident"
+            ]
+        );
+    }
 
-fn handle_eq_impl<'a, T: CharGenerator + std::marker::Unpin>(
-    source: Pin<&'a mut T> 
-) -> impl TokenGenerator + 'a {
-    return || {
-        source.resume();
-        yield TokenResult::NeedMoreU8;
-        return Utf8Error::InvalidLeading;
-    };
-}
+    #[test]
+    fn test_comments() {
+        let toks = lex("#!/bin/sh\n// a\n/// b\n//// c\n//! d\n/* /* */ */ /** e */ /*! f */ /**/ x");
+        let kinds: Vec<&str> = toks
+            .iter()
+            .map(|(tok, _)| match tok {
+                Token::Shebang(_) => "shebang",
+                Token::Comment => "comment",
+                Token::DocComment(_) => "doc",
+                Token::Ident(..) => "ident",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "shebang", "comment", "doc", "comment", "doc", "comment",
+                "doc", "doc", "comment", "ident"
+            ]
+        );
+        assert_eq!(lex_text("#![x]")[0], "#");
+    }
 
-fn handle_plus_impl<'a, T: CharGenerator + std::marker::Unpin>(
-    source: Pin<&'a mut CharGenerator>
-) -> impl TokenGenerator + 'a {
-    return || {
-        let source = source;
-        source.resume();
-        yield TokenResult::NeedMoreU8;
-        return Utf8Error::InvalidLeading;
-    };
-}
-/*
-impl Future for CharStream {
-    type Output = char;
-    fn poll(self: Pin<&mut Self>, lw: &std::task::LocalWaker) -> Poll<char> {
-        yield Poll::Ready('a');
-        yield Poll::Pending;
-        return ();
-    }
-}
-
-impl StringInterner {
-    pub fn empty() -> StringInterner {
-        StringInterner {
-            storage: HashMap::new(),
-            index: vec![],
-        }
-    }
-    pub fn insert<Q: ?Sized>(&mut self, string: &Q) -> IntKey
-    where
-        Box<str>: std::borrow::Borrow<Q>,
-        Q: std::hash::Hash + Eq,
-        Q: AsRef<str>,
-    {
-        use std::collections::hash_map::*;
-        match self.storage.raw_entry_mut().from_key(string) {
-            RawEntryMut::Occupied(occ) => *occ.get() as IntKey,
-            RawEntryMut::Vacant(vac) => {
-                let ret = self.index.len();
-                let (k, _) = vac
-                    .insert(string.as_ref().to_string().into_boxed_str(), ret);
-                self.index.push(k as &str);
-                ret
+    #[test]
+    fn test_errors() {
+        let mut interner = StringInterner::empty();
+        let mut err = |s: &str| {
+            str_to_token_slice(s.as_bytes(), &mut interner)
+                .err()
+                .unwrap()
+        };
+        assert_eq!(
+            err("a /* b"),
+            LexError::UnterminatedBlockComment(Span::new(2, 6))
+        );
+        assert_eq!(
+            err("\"abc"),
+            LexError::UnterminatedDoubleQuoteString(Span::new(0, 4))
+        );
+        assert_eq!(err("''"), LexError::EmptyCharLiteral(Span::new(0, 2)));
+        assert_eq!(err("'\n'"), LexError::CharMustBeEscaped(Span::new(0, 2)));
+        assert_eq!(err("\"a\rb\""), LexError::BareCr(Span::new(2, 3)));
+        assert_eq!(err("r#\"\r\"#"), LexError::BareCr(Span::new(3, 4)));
+        assert_eq!(err("/** \r */"), LexError::BareCr(Span::new(4, 5)));
+        assert_eq!(err("/// a\r"), LexError::BareCr(Span::new(5, 6)));
+        // CRLF, and CRs outside of strings and doc comments, are fine
+        let mut interner = StringInterner::empty();
+        let input = b"/// a\r\n\"b\r\n\" // \r\r";
+        assert!(str_to_token_slice(input, &mut interner).is_ok());
+        assert_eq!(
+            err("a \\"),
+            LexError::UnknownStartOfToken('\\', Span::new(2, 3))
+        );
+        let zwsp = err("\u{200B}");
+        assert_eq!(zwsp.to_string(), "unknown start of token: \\u{200b}");
+        assert_eq!(zwsp.span(), Some(Span::new(0, 3)));
+        let utf8 = LexError::Utf8(Utf8Error::InvalidCont);
+        assert_eq!(utf8.to_string(), "invalid UTF-8 continuation byte");
+        assert_eq!(utf8.span(), None);
+    }
+
+    // tokens cover the input without gaps, so the stream is lossless
+    #[quickcheck]
+    fn check_lossless(input: String) -> bool {
+        let mut interner = StringInterner::empty();
+        match str_to_token_slice(input.as_bytes(), &mut interner) {
+            Ok(tokens) => {
+                let mut pos = 0;
+                for ts in tokens.iter() {
+                    if ts.sp.lo != pos || ts.sp.hi <= ts.sp.lo {
+                        return false;
+                    }
+                    pos = ts.sp.hi;
+                }
+                pos == input.len()
+            }
+            Err(_) => true,
+        }
+    }
+
+    #[test]
+    fn test_line_states() {
+        let input = "a /* x\n/* y\n*/ */ \"s\nt\" br#\"u\nv\"#\nb";
+        let mut interner = StringInterner::empty();
+        let (_, lines) = str_to_token_slice_from(
+            input.as_bytes(),
+            0,
+            LexState::Normal,
+            &mut interner,
+        )
+        .unwrap();
+        let states: Vec<LexState> =
+            lines.iter().map(|(_, state)| *state).collect();
+        assert_eq!(
+            states,
+            vec![
+                LexState::BlockComment {
+                    depth: 1,
+                    doc: false
+                },
+                LexState::BlockComment {
+                    depth: 2,
+                    doc: false
+                },
+                LexState::Str { byte: false },
+                LexState::RawStr {
+                    byte: true,
+                    hashes: 1
+                },
+                LexState::Normal,
+            ]
+        );
+        let (pos, state) = lines[3];
+        let (tokens, _) = str_to_token_slice_from(
+            &input.as_bytes()[pos..],
+            pos,
+            state,
+            &mut interner,
+        )
+        .unwrap();
+        match tokens[0].tok {
+            Token::Literal(Lit::ByteStrRaw(name, 1), None) => {
+                assert_eq!(interner.get(name), "v")
             }
+            tok => panic!("{:?}", tok),
         }
+        assert_eq!(tokens[0].sp, Span::new(pos, pos + 3));
     }
 
-    pub fn get(&self, key: IntKey) -> &str {
-        unsafe { &*self.index[key as usize] }
+    // resuming at a line start gives the tail of the token the line starts
+    // in, followed by the same tokens as lexing the whole input
+    fn prop_resume(input: &str) -> bool {
+        let mut interner = StringInterner::empty();
+        let (full, lines) = match str_to_token_slice_from(
+            input.as_bytes(),
+            0,
+            LexState::Normal,
+            &mut interner,
+        ) {
+            Ok(res) => res,
+            Err(_) => return true,
+        };
+        lines.iter().all(|&(pos, state)| {
+            let (tokens, _) = str_to_token_slice_from(
+                &input.as_bytes()[pos..],
+                pos,
+                state,
+                &mut interner,
+            )
+            .unwrap();
+            match full.iter().position(|ts| ts.sp.hi > pos) {
+                Some(i) if full[i].sp.lo == pos => tokens[..] == full[i..],
+                Some(i) => {
+                    tokens[0].sp == Span::new(pos, full[i].sp.hi)
+                        && tokens[1..] == full[i + 1..]
+                }
+                None => tokens.is_empty(),
+            }
+        })
     }
 
-    pub fn len(&self) -> usize {
-        self.index.len()
+    #[quickcheck]
+    fn check_resume(bytes: Vec<u8>) -> bool {
+        let alphabet: Vec<char> = "a1 #r/*\n\"'\\b".chars().collect();
+        let input: String = bytes
+            .iter()
+            .map(|b| alphabet[*b as usize % alphabet.len()])
+            .collect();
+        prop_resume(&input)
     }
 }
-*/
 
 /*
 /// we need to internal number to make complex double's size fitts into a token
@@ -0,0 +1,153 @@
+//! Line and column numbers of byte offsets, and the line endings of a file.
+//!
+//! A line ends at `\n`, and `\r\n` is one line ending: the `\r` before a `\n`
+//! is never part of a line's span, and its offset is at the line's end
+//! column. A `\r` on its own does not end a line.
+
+use crate::token::Span;
+
+/// How many lines of a file end in each way.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct LineEndings {
+    pub lf: usize,
+    pub crlf: usize,
+    /// `\r`s not followed by `\n`, which end no line.
+    pub bare_cr: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LineEndingStyle {
+    /// The file has a single line.
+    None,
+    Lf,
+    CrLf,
+    Mixed,
+}
+
+impl LineEndings {
+    pub fn count(input: &[u8]) -> LineEndings {
+        let mut endings = LineEndings::default();
+        let mut cr = false;
+        for &b in input {
+            match b {
+                b'\n' if cr => endings.crlf += 1,
+                b'\n' => endings.lf += 1,
+                _ if cr => endings.bare_cr += 1,
+                _ => {}
+            }
+            cr = b == b'\r';
+        }
+        if cr {
+            endings.bare_cr += 1;
+        }
+        endings
+    }
+
+    pub fn style(&self) -> LineEndingStyle {
+        match (self.lf, self.crlf) {
+            (0, 0) => LineEndingStyle::None,
+            (_, 0) => LineEndingStyle::Lf,
+            (0, _) => LineEndingStyle::CrLf,
+            _ => LineEndingStyle::Mixed,
+        }
+    }
+}
+
+/// Where the lines of a file start.
+pub struct LineMap<'a> {
+    input: &'a [u8],
+    starts: Vec<usize>,
+}
+
+impl<'a> LineMap<'a> {
+    pub fn new(input: &'a [u8]) -> LineMap<'a> {
+        let mut starts = vec![0];
+        starts.extend(
+            input
+                .iter()
+                .enumerate()
+                .filter(|&(_, &b)| b == b'\n')
+                .map(|(i, _)| i + 1),
+        );
+        LineMap { input, starts }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// The line `pos` is on, from 0.
+    pub fn line(&self, pos: usize) -> usize {
+        match self.starts.binary_search(&pos) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        }
+    }
+
+    /// The span of `line`, from 0, without its line ending.
+    pub fn line_span(&self, line: usize) -> Span {
+        let lo = self.starts[line];
+        let hi = match self.starts.get(line + 1) {
+            Some(&next) if next >= 2 && self.input[next - 2] == b'\r' => {
+                next - 2
+            }
+            Some(&next) => next - 1,
+            None => self.input.len(),
+        };
+        Span::new(lo, hi)
+    }
+
+    /// The line, from 1, and the column in chars, from 0, of `pos`, as
+    /// `proc_macro::LineColumn` has them.
+    pub fn line_col(&self, pos: usize) -> (usize, usize) {
+        let line = self.line(pos);
+        let sp = self.line_span(line);
+        let col = self.input[sp.lo..pos.min(sp.hi)]
+            .iter()
+            .filter(|&&b| b & 0xc0 != 0x80)
+            .count();
+        (line + 1, col)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_map() {
+        let input = "ab\r\nü\rc\nd";
+        let map = LineMap::new(input.as_bytes());
+        assert_eq!(map.line_count(), 3);
+        assert_eq!(map.line_span(0), Span::new(0, 2));
+        assert_eq!(map.line_span(1), Span::new(4, 8));
+        assert_eq!(map.line_span(2), Span::new(9, 10));
+        assert_eq!(map.line_col(2), (1, 2));
+        // the `\r` of `\r\n` is at the end of the line
+        assert_eq!(map.line_col(3), (1, 2));
+        assert_eq!(map.line_col(4), (2, 0));
+        assert_eq!(map.line_col(7), (2, 2));
+        assert_eq!(map.line_col(10), (3, 1));
+        let endings = LineEndings::count(input.as_bytes());
+        assert_eq!(
+            endings,
+            LineEndings {
+                lf: 1,
+                crlf: 1,
+                bare_cr: 1
+            }
+        );
+        assert_eq!(endings.style(), LineEndingStyle::Mixed);
+    }
+
+    #[quickcheck]
+    fn check_line_endings(input: String) -> bool {
+        let endings = LineEndings::count(input.as_bytes());
+        let crlf = input.matches("\r\n").count();
+        endings.crlf == crlf
+            && endings.lf == input.matches('\n').count() - crlf
+            && endings.bare_cr == input.matches('\r').count() - crlf
+            && LineMap::new(input.as_bytes()).line_count()
+                == endings.lf + endings.crlf + 1
+    }
+}
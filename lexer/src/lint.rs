@@ -0,0 +1,512 @@
+//! Lints on source text that lexes fine but may not read the way it runs,
+//! eg. text direction overrides hiding code inside a comment (CVE-2021-42574,
+//! "Trojan Source").
+//!
+//! `lint_chars` wraps a char generator and reports what it sees as the chars
+//! go through, so it works wherever a `CharGenerator` does. `lint_idents`
+//! looks at the identifiers and lifetimes of a whole file once it is lexed,
+//! after rustc's `confusable_idents` and `mixed_script_confusables` lints,
+//! with the file standing in for rustc's crate.
+
+use crate::interner::StringInterner;
+use crate::iter::TokenIter;
+use crate::token::{Span, Token, TokenAndSpan};
+use crate::{
+    char_generator_from_byte, source, token_generator_from_char, CharGenerator,
+    CharResult, IntKey, LexError,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::{Generator, GeneratorState};
+use std::pin::Pin;
+use std::rc::Rc;
+use unicode_security::mixed_script::AugmentedScriptSet;
+use unicode_security::{
+    is_potential_mixed_script_confusable_char, skeleton, GeneralSecurityProfile,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LintKind {
+    /// A char changing the direction text is displayed in, U+200E, U+200F,
+    /// U+202A to U+202E and U+2066 to U+2069.
+    BidiControl(char),
+    /// A char displayed as nothing at all, eg. a zero width space.
+    Invisible(char),
+    /// An identifier looking like an earlier, different one, at the span.
+    /// Both have the same UTS #39 skeleton and one is not ASCII.
+    ConfusableIdent(IntKey, Span),
+    /// The identifier where a script is first used, when every char of the
+    /// script in all identifiers is confusable with another script's, eg.
+    /// Cyrillic `а` and `у` but no other Cyrillic. The char is one of those.
+    MixedScriptConfusables(IntKey, char),
+    /// An identifier not written in NFC. Its interned name is in NFC.
+    NonNfcIdent(IntKey),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub sp: Span,
+    pub level: Level,
+}
+
+impl Lint {
+    pub fn message(&self, interner: &StringInterner) -> String {
+        match self.kind {
+            LintKind::BidiControl(c) => format!(
+                "unicode codepoint changing visible direction of text: \
+                 `\\u{{{:x}}}`",
+                c as u32
+            ),
+            LintKind::Invisible(c) => {
+                format!("invisible unicode codepoint: `\\u{{{:x}}}`", c as u32)
+            }
+            LintKind::ConfusableIdent(name, _) => format!(
+                "identifier `{}` is confusable with an earlier one",
+                interner.get(name)
+            ),
+            LintKind::MixedScriptConfusables(name, c) => format!(
+                "the usage of script `{}` consists solely of mixed script \
+                 confusables, eg. `{}` in identifier `{}`",
+                AugmentedScriptSet::for_char(c),
+                c,
+                interner.get(name)
+            ),
+            LintKind::NonNfcIdent(name) => format!(
+                "identifier `{}` is not in normalization form C",
+                interner.get(name)
+            ),
+        }
+    }
+}
+
+/// The level of each lint.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LintConfig {
+    pub bidi_control: Level,
+    pub invisible: Level,
+    pub confusable_idents: Level,
+    pub mixed_script_confusables: Level,
+    pub non_nfc_idents: Level,
+}
+
+impl Default for LintConfig {
+    fn default() -> LintConfig {
+        LintConfig {
+            bidi_control: Level::Deny,
+            invisible: Level::Warn,
+            confusable_idents: Level::Warn,
+            mixed_script_confusables: Level::Warn,
+            non_nfc_idents: Level::Warn,
+        }
+    }
+}
+
+impl LintConfig {
+    pub fn level(&self, kind: LintKind) -> Level {
+        match kind {
+            LintKind::BidiControl(_) => self.bidi_control,
+            LintKind::Invisible(_) => self.invisible,
+            LintKind::ConfusableIdent(..) => self.confusable_idents,
+            LintKind::MixedScriptConfusables(..) => {
+                self.mixed_script_confusables
+            }
+            LintKind::NonNfcIdent(_) => self.non_nfc_idents,
+        }
+    }
+}
+
+/// Where `lint_chars` reports to.
+pub type Lints = Rc<RefCell<Vec<Lint>>>;
+
+pub fn char_lint(c: char) -> Option<LintKind> {
+    match c {
+        '\u{200E}'
+        | '\u{200F}'
+        | '\u{202A}'..='\u{202E}'
+        | '\u{2066}'..='\u{2069}' => Some(LintKind::BidiControl(c)),
+        '\u{00AD}'
+        | '\u{180E}'
+        | '\u{200B}'..='\u{200D}'
+        | '\u{2060}'..='\u{2064}'
+        | '\u{FEFF}' => Some(LintKind::Invisible(c)),
+        _ => None,
+    }
+}
+
+/// Passes on the chars of `gen`, adding a lint to `lints` for each char
+/// `char_lint` flags and `config` doesn't allow. `gen` starts at byte offset
+/// `start` of the file, as for `token_generator_from_char_at`.
+pub fn lint_chars<G: CharGenerator + std::marker::Unpin>(
+    mut gen: G,
+    start: usize,
+    config: LintConfig,
+    lints: Lints,
+) -> impl CharGenerator + std::marker::Unpin {
+    move || {
+        let mut pos = start;
+        loop {
+            match Pin::new(&mut gen).resume() {
+                GeneratorState::Yielded(CharResult::Ok(c)) => {
+                    let hi = pos + c.len_utf8();
+                    if let Some(kind) = char_lint(c) {
+                        let level = config.level(kind);
+                        if level != Level::Allow {
+                            let sp = Span::new(pos, hi);
+                            lints.borrow_mut().push(Lint { kind, sp, level });
+                        }
+                    }
+                    pos = hi;
+                    yield CharResult::Ok(c);
+                }
+                GeneratorState::Yielded(res) => yield res,
+                GeneratorState::Complete(err) => return err,
+            }
+        }
+    }
+}
+
+// how the identifiers use a script, for `mixed_script_confusables`
+enum ScriptUsage {
+    // only with chars confusable with another script's, first of them `c` in
+    // the identifier `name` at `sp`
+    Suspicious(char, IntKey, Span),
+    Verified,
+}
+
+/// The lints on the identifiers and lifetimes among `tokens`, the tokens of
+/// `input`.
+pub fn lint_idents(
+    input: &[u8],
+    tokens: &[TokenAndSpan],
+    config: LintConfig,
+    interner: &StringInterner,
+) -> Vec<Lint> {
+    let mut lints = vec![];
+    let mut push = |kind, sp| {
+        let level = config.level(kind);
+        if level != Level::Allow {
+            lints.push(Lint { kind, sp, level });
+        }
+    };
+    // the first identifier seen for each skeleton, and whether it is ASCII
+    let mut skeletons: HashMap<String, (IntKey, Span, bool)> = HashMap::new();
+    let mut scripts: HashMap<AugmentedScriptSet, ScriptUsage> = HashMap::new();
+    // ASCII identifiers are taken to be Latin, without looking at them
+    scripts.insert(AugmentedScriptSet::for_char('a'), ScriptUsage::Verified);
+    for ts in tokens {
+        let (name, raw) = match ts.tok {
+            Token::Ident(name, raw) => (name, raw),
+            // named with a leading `'`, so only like other lifetimes
+            Token::Lifetime(name) => (name, false),
+            _ => continue,
+        };
+        let text = interner.get(name);
+        let is_ascii = text.is_ascii();
+        if !is_ascii {
+            let lo = if raw { ts.sp.lo + 2 } else { ts.sp.lo };
+            // the lexer interns the NFC of the source text
+            if &input[lo..ts.sp.hi] != text.as_bytes() {
+                push(LintKind::NonNfcIdent(name), ts.sp);
+            }
+        }
+        for c in text.chars() {
+            if c.is_ascii() || !c.identifier_allowed() {
+                continue;
+            }
+            let is_confusable = is_potential_mixed_script_confusable_char(c);
+            let usage = scripts
+                .entry(AugmentedScriptSet::for_char(c))
+                .or_insert(if is_confusable {
+                    ScriptUsage::Suspicious(c, name, ts.sp)
+                } else {
+                    ScriptUsage::Verified
+                });
+            if !is_confusable {
+                *usage = ScriptUsage::Verified;
+            }
+        }
+        let skel: String = skeleton(text).collect();
+        match skeletons.get_mut(&skel) {
+            Some(first) => {
+                let (first_name, first_sp, first_is_ascii) = *first;
+                if first_name != name && !(first_is_ascii && is_ascii) {
+                    push(LintKind::ConfusableIdent(name, first_sp), ts.sp);
+                }
+                // as rustc does, so a later ASCII one is checked against it
+                if first_is_ascii && !is_ascii {
+                    *first = (name, ts.sp, false);
+                }
+            }
+            None => {
+                skeletons.insert(skel, (name, ts.sp, is_ascii));
+            }
+        }
+    }
+    let verified: Vec<AugmentedScriptSet> = scripts
+        .iter()
+        .filter_map(|(&set, usage)| match usage {
+            ScriptUsage::Verified => Some(set),
+            ScriptUsage::Suspicious(..) => None,
+        })
+        .collect();
+    let mut suspicious: Vec<(Span, char, IntKey)> = vec![];
+    for (&set, usage) in scripts.iter() {
+        let (c, name, sp) = match *usage {
+            ScriptUsage::Suspicious(c, name, sp) => (c, name, sp),
+            ScriptUsage::Verified => continue,
+        };
+        // a set sharing a script with a verified one, eg. Han and Japanese,
+        // is in use with chars that are not confusables
+        let overlaps = |other: &AugmentedScriptSet| {
+            let mut both = *other;
+            both.intersect_with(set);
+            !other.is_all() && !both.is_empty() && !both.is_all()
+        };
+        if !set.is_all() && !verified.iter().any(overlaps) {
+            suspicious.push((sp, c, name));
+        }
+    }
+    suspicious.sort_by_key(|&(sp, c, _)| (sp.lo, c));
+    for (sp, c, name) in suspicious {
+        push(LintKind::MixedScriptConfusables(name, c), sp);
+    }
+    lints.sort_by_key(|lint| lint.sp.lo);
+    lints
+}
+
+/// Lexes `input` as `str_to_token_slice` does, also returning its lints.
+pub fn str_to_token_slice_linted(
+    input: &[u8],
+    config: LintConfig,
+    interner: &mut StringInterner,
+) -> Result<(Vec<TokenAndSpan>, Vec<Lint>), LexError> {
+    let lints = Lints::default();
+    let char_gen = char_generator_from_byte(source::from_slice(input));
+    let char_gen = lint_chars(char_gen, 0, config, lints.clone());
+    let tokens: Vec<_> =
+        TokenIter::new(token_generator_from_char(char_gen, interner))
+            .collect::<Result<_, _>>()?;
+    let mut lints = lints.borrow().clone();
+    lints.extend(lint_idents(input, &tokens, config, interner));
+    lints.sort_by_key(|lint| lint.sp.lo);
+    Ok((tokens, lints))
+}
+
+/// Whether any of `lints` is denied.
+pub fn is_denied(lints: &[Lint]) -> bool {
+    lints.iter().any(|lint| lint.level == Level::Deny)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{str_to_token_slice, token_generator_from_char_at};
+
+    #[test]
+    fn test_trojan_source() {
+        let input = "/* \u{202E} } \u{2066} */ let s = \"a\u{200B}\";";
+        let mut interner = StringInterner::empty();
+        let (_, lints) = str_to_token_slice_linted(
+            input.as_bytes(),
+            LintConfig::default(),
+            &mut interner,
+        )
+        .unwrap();
+        let found: Vec<_> = lints
+            .iter()
+            .map(|lint| (lint.kind, &input[lint.sp.lo..lint.sp.hi], lint.level))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (LintKind::BidiControl('\u{202E}'), "\u{202E}", Level::Deny),
+                (LintKind::BidiControl('\u{2066}'), "\u{2066}", Level::Deny),
+                (LintKind::Invisible('\u{200B}'), "\u{200B}", Level::Warn),
+            ]
+        );
+        assert!(is_denied(&lints));
+
+        let config = LintConfig {
+            bidi_control: Level::Warn,
+            invisible: Level::Allow,
+            ..LintConfig::default()
+        };
+        let (_, lints) =
+            str_to_token_slice_linted(input.as_bytes(), config, &mut interner)
+                .unwrap();
+        assert_eq!(lints.len(), 2);
+        assert!(!is_denied(&lints));
+    }
+
+    #[test]
+    fn test_marks() {
+        // a Cyrillic `а`, then a right-to-left mark and an invisible times
+        let found = ident_lints("а \"\u{200F}\u{2062}\"");
+        // sorted by span, not char lints first
+        let texts: Vec<_> = found.iter().map(|&(_, text)| text).collect();
+        assert_eq!(texts, ["а", "\u{200F}", "\u{2062}"]);
+        assert_eq!(found[1].0, LintKind::BidiControl('\u{200F}'));
+        assert_eq!(found[2].0, LintKind::Invisible('\u{2062}'));
+    }
+
+    #[test]
+    fn test_lint_chars_from() {
+        // the rest of a file from byte 100 on
+        let input = "x \"\u{200B}\"";
+        let lints = Lints::default();
+        let char_gen =
+            char_generator_from_byte(source::from_slice(input.as_bytes()));
+        let char_gen =
+            lint_chars(char_gen, 100, LintConfig::default(), lints.clone());
+        let mut interner = StringInterner::empty();
+        let tokens: Vec<_> = TokenIter::new(token_generator_from_char_at(
+            char_gen,
+            &mut interner,
+            100,
+        ))
+        .collect::<Result<_, _>>()
+        .unwrap();
+        assert_eq!(tokens[0].sp, Span::new(100, 101));
+        let lints = lints.borrow();
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].kind, LintKind::Invisible('\u{200B}'));
+        assert_eq!(lints[0].sp, Span::new(103, 106));
+    }
+
+    #[test]
+    fn test_idents() {
+        // Latin `a`, Cyrillic `а`, `é` in NFD and NFC, then Latin `p` with
+        // Cyrillic `у`
+        let input = "a а e\u{301} \u{e9} pу";
+        let mut interner = StringInterner::empty();
+        let (tokens, lints) = str_to_token_slice_linted(
+            input.as_bytes(),
+            LintConfig::default(),
+            &mut interner,
+        )
+        .unwrap();
+        let name = |i: usize| match tokens[i].tok {
+            Token::Ident(name, _) => name,
+            _ => panic!(),
+        };
+        assert_eq!(name(4), name(6));
+        assert_eq!(interner.get(name(4)), "\u{e9}");
+        let found: Vec<_> =
+            lints.iter().map(|lint| (lint.kind, lint.sp)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    LintKind::ConfusableIdent(name(2), tokens[0].sp),
+                    tokens[2].sp
+                ),
+                (LintKind::MixedScriptConfusables(name(2), 'а'), tokens[2].sp),
+                (LintKind::NonNfcIdent(name(4)), tokens[4].sp),
+            ]
+        );
+        assert!(!is_denied(&lints));
+    }
+
+    fn ident_lints(input: &str) -> Vec<(LintKind, &str)> {
+        let mut interner = StringInterner::empty();
+        let (_, lints) = str_to_token_slice_linted(
+            input.as_bytes(),
+            LintConfig::default(),
+            &mut interner,
+        )
+        .unwrap();
+        lints
+            .iter()
+            .map(|lint| (lint.kind, &input[lint.sp.lo..lint.sp.hi]))
+            .collect()
+    }
+
+    #[test]
+    fn test_confusable_order() {
+        // small roman numeral fifty, then Latin `l`, and the other way round
+        let found = ident_lints("\u{217C} l");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1, "l");
+        let found = ident_lints("l \u{217C}");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1, "\u{217C}");
+        // two ASCII identifiers are never confusable, though `m` and `rn`
+        // have the same skeleton
+        assert_eq!(ident_lints("m rn"), vec![]);
+    }
+
+    #[test]
+    fn test_lifetimes() {
+        // Latin `'a` then Cyrillic `'а`, which is not like the identifier `а`
+        let found = ident_lints("'a 'а а");
+        let texts: Vec<_> = found.iter().map(|&(_, text)| text).collect();
+        assert_eq!(texts, ["'а", "'а"]);
+        match (found[0].0, found[1].0) {
+            (
+                LintKind::ConfusableIdent(..),
+                LintKind::MixedScriptConfusables(_, 'а'),
+            ) => {}
+            _ => panic!("{:?}", found),
+        }
+        // interned in NFC
+        let mut interner = StringInterner::empty();
+        let tokens = str_to_token_slice(b"'e\xcc\x81", &mut interner).unwrap();
+        match tokens[0].tok {
+            Token::Lifetime(name) => assert_eq!(interner.get(name), "'\u{e9}"),
+            ref tok => panic!("{:?}", tok),
+        }
+        assert_eq!(ident_lints("'e\u{301}")[0].0, LintKind::NonNfcIdent(0));
+    }
+
+    #[test]
+    fn test_mixed_script_confusables() {
+        // Cyrillic only used with chars confusable with Latin ones
+        let found = ident_lints("x аb у");
+        assert_eq!(found.len(), 1);
+        match found[0] {
+            (LintKind::MixedScriptConfusables(_, 'а'), "аb") => {}
+            lint => panic!("{:?}", lint),
+        }
+        // `д` is not confusable, so Cyrillic is in real use, and mixing it
+        // with Latin is fine
+        assert_eq!(ident_lints("дом pу"), vec![]);
+        // all but `λ` are confusables, which is enough for Greek
+        assert_eq!(ident_lints("αβ ο λ"), vec![]);
+    }
+
+    #[quickcheck]
+    fn check_lint_chars(input: String) -> bool {
+        let mut interner = StringInterner::empty();
+        let expected = str_to_token_slice(input.as_bytes(), &mut interner);
+        let mut interner = StringInterner::empty();
+        let linted = str_to_token_slice_linted(
+            input.as_bytes(),
+            LintConfig::default(),
+            &mut interner,
+        );
+        let expected_lints = input
+            .char_indices()
+            .filter(|&(_, c)| char_lint(c).is_some())
+            .map(|(i, c)| Span::new(i, i + c.len_utf8()));
+        match (expected, linted) {
+            (Ok(expected), Ok((tokens, lints))) => {
+                let char_lints = lints.iter().filter(|lint| match lint.kind {
+                    LintKind::BidiControl(_) | LintKind::Invisible(_) => true,
+                    _ => false,
+                });
+                tokens == expected
+                    && char_lints.map(|lint| lint.sp).eq(expected_lints)
+            }
+            (Err(expected), Err(err)) => expected == err,
+            _ => false,
+        }
+    }
+}
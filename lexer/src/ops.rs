@@ -0,0 +1,188 @@
+//! Sub-lexers for operators, one per family of operators sharing a first
+//! char. Each one is started after that char was read, and lexes the rest
+//! of the operator.
+
+use crate::delegate::{SharedReader, SubLexer};
+use crate::token::{BinOpToken, Token};
+use crate::CharGenerator;
+
+/// `op` or `op=`, after the chars of `op`. The `/` of comments is handled by
+/// the caller.
+pub fn lex_bin_op<T: CharGenerator + std::marker::Unpin>(
+    reader: SharedReader<T>,
+    op: BinOpToken,
+) -> impl SubLexer {
+    move || {
+        if eat_char!(reader, '=')? {
+            Ok(Token::BinOpEq(op))
+        } else {
+            Ok(Token::BinOp(op))
+        }
+    }
+}
+
+/// `=`, `==` and `=>`.
+pub fn lex_eq<T: CharGenerator + std::marker::Unpin>(
+    reader: SharedReader<T>,
+) -> impl SubLexer {
+    move || {
+        if eat_char!(reader, '=')? {
+            Ok(Token::EqEq)
+        } else if eat_char!(reader, '>')? {
+            Ok(Token::FatArrow)
+        } else {
+            Ok(Token::Eq)
+        }
+    }
+}
+
+/// `!` and `!=`.
+pub fn lex_not<T: CharGenerator + std::marker::Unpin>(
+    reader: SharedReader<T>,
+) -> impl SubLexer {
+    move || {
+        if eat_char!(reader, '=')? {
+            Ok(Token::Ne)
+        } else {
+            Ok(Token::Not)
+        }
+    }
+}
+
+/// `<`, `<=`, `<<`, `<<=` and `<-`.
+pub fn lex_lt<T: CharGenerator + std::marker::Unpin>(
+    reader: SharedReader<T>,
+) -> impl SubLexer {
+    move || {
+        if eat_char!(reader, '=')? {
+            Ok(Token::Le)
+        } else if eat_char!(reader, '<')? {
+            yield_from!(lex_bin_op(reader.clone(), BinOpToken::Shl))
+        } else if eat_char!(reader, '-')? {
+            Ok(Token::LArrow)
+        } else {
+            Ok(Token::Lt)
+        }
+    }
+}
+
+/// `>`, `>=`, `>>` and `>>=`.
+pub fn lex_gt<T: CharGenerator + std::marker::Unpin>(
+    reader: SharedReader<T>,
+) -> impl SubLexer {
+    move || {
+        if eat_char!(reader, '=')? {
+            Ok(Token::Ge)
+        } else if eat_char!(reader, '>')? {
+            yield_from!(lex_bin_op(reader.clone(), BinOpToken::Shr))
+        } else {
+            Ok(Token::Gt)
+        }
+    }
+}
+
+/// `&`, `&&` and `&=`.
+pub fn lex_and<T: CharGenerator + std::marker::Unpin>(
+    reader: SharedReader<T>,
+) -> impl SubLexer {
+    move || {
+        if eat_char!(reader, '&')? {
+            Ok(Token::AndAnd)
+        } else {
+            yield_from!(lex_bin_op(reader.clone(), BinOpToken::And))
+        }
+    }
+}
+
+/// `|`, `||` and `|=`.
+pub fn lex_or<T: CharGenerator + std::marker::Unpin>(
+    reader: SharedReader<T>,
+) -> impl SubLexer {
+    move || {
+        if eat_char!(reader, '|')? {
+            Ok(Token::OrOr)
+        } else {
+            yield_from!(lex_bin_op(reader.clone(), BinOpToken::Or))
+        }
+    }
+}
+
+/// `-`, `-=` and `->`.
+pub fn lex_minus<T: CharGenerator + std::marker::Unpin>(
+    reader: SharedReader<T>,
+) -> impl SubLexer {
+    move || {
+        if eat_char!(reader, '>')? {
+            Ok(Token::RArrow)
+        } else {
+            yield_from!(lex_bin_op(reader.clone(), BinOpToken::Minus))
+        }
+    }
+}
+
+/// `.`, `..`, `...` and `..=`.
+pub fn lex_dot<T: CharGenerator + std::marker::Unpin>(
+    reader: SharedReader<T>,
+) -> impl SubLexer {
+    move || {
+        if !eat_char!(reader, '.')? {
+            Ok(Token::Dot)
+        } else if eat_char!(reader, '.')? {
+            Ok(Token::DotDotDot)
+        } else if eat_char!(reader, '=')? {
+            Ok(Token::DotDotEq)
+        } else {
+            Ok(Token::DotDot)
+        }
+    }
+}
+
+/// `:` and `::`.
+pub fn lex_colon<T: CharGenerator + std::marker::Unpin>(
+    reader: SharedReader<T>,
+) -> impl SubLexer {
+    move || {
+        if eat_char!(reader, ':')? {
+            Ok(Token::ModSep)
+        } else {
+            Ok(Token::Colon)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delegate::tests::run_sub_lexer;
+    use crate::delegate::SharedReader;
+
+    // lexes `input` after its first char, returns the token and the rest
+    fn op<F, G>(input: &'static str, make: F) -> (Token, &'static str)
+    where
+        F: FnOnce(SharedReader<crate::delegate::tests::TestChars>) -> G,
+        G: SubLexer + std::marker::Unpin,
+    {
+        let (tok, rest, _) = run_sub_lexer(&input[1..], make);
+        (tok.unwrap(), rest)
+    }
+
+    #[test]
+    fn test_ops() {
+        assert_eq!(op("=>x", lex_eq), (Token::FatArrow, "x"));
+        assert_eq!(op("= =", lex_eq), (Token::Eq, " ="));
+        assert_eq!(op("!==", lex_not), (Token::Ne, "="));
+        assert_eq!(op("<<=1", lex_lt), (Token::BinOpEq(BinOpToken::Shl), "1"));
+        assert_eq!(op("<-", lex_lt), (Token::LArrow, ""));
+        assert_eq!(op(">>>", lex_gt), (Token::BinOp(BinOpToken::Shr), ">"));
+        assert_eq!(op("&&=", lex_and), (Token::AndAnd, "="));
+        assert_eq!(op("|=", lex_or), (Token::BinOpEq(BinOpToken::Or), ""));
+        assert_eq!(op("->", lex_minus), (Token::RArrow, ""));
+        assert_eq!(op("..=", lex_dot), (Token::DotDotEq, ""));
+        assert_eq!(op("....", lex_dot), (Token::DotDotDot, "."));
+        assert_eq!(op(":::", lex_colon), (Token::ModSep, ":"));
+        assert_eq!(
+            op("*=", |r| lex_bin_op(r, BinOpToken::Star)),
+            (Token::BinOpEq(BinOpToken::Star), "")
+        );
+    }
+}
@@ -0,0 +1,503 @@
+//! Conversions between yarp token trees and `proc_macro2::TokenStream`.
+//!
+//! `proc_macro2` spans can't be built from byte offsets, and don't expose
+//! them either, so both directions take a closure mapping one kind of span to
+//! the other. `call_site` and `dummy` are the trivial mappings.
+
+use crate::comments::{
+    doc_comment_style, strip_doc_comment_decoration, AttrStyle,
+};
+use crate::interner::StringInterner;
+use crate::str_to_token_slice;
+use crate::token::{
+    token_to_string, BinOpToken, DelimToken, Span, Token, DUMMY_SP,
+};
+use crate::tokenstream::{
+    build_token_trees, DelimSpan, TokenTree, TokenTreeError,
+};
+use crate::LexError;
+use proc_macro2 as pm2;
+use std::iter::FromIterator;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Error {
+    Lex(LexError),
+    TokenTree(TokenTreeError),
+    /// A token the lexer accepts but `proc_macro2` rejects, eg. the raw
+    /// identifier `r#self`, the literal `1e` or the tail of a doc comment.
+    InvalidToken(Span),
+    /// A delimiter token outside `TokenTree::Delimited`. `proc_macro2` only
+    /// has delimiters as the two ends of a `Group`.
+    StrayDelimiter(Span),
+}
+
+impl From<LexError> for Error {
+    fn from(err: LexError) -> Error {
+        Error::Lex(err)
+    }
+}
+
+impl From<TokenTreeError> for Error {
+    fn from(err: TokenTreeError) -> Error {
+        Error::TokenTree(err)
+    }
+}
+
+pub fn call_site(_: Span) -> pm2::Span {
+    pm2::Span::call_site()
+}
+
+pub fn dummy(_: pm2::Span) -> Span {
+    DUMMY_SP
+}
+
+fn delimiter_to_pm2(delim: DelimToken) -> pm2::Delimiter {
+    match delim {
+        DelimToken::Paren => pm2::Delimiter::Parenthesis,
+        DelimToken::Bracket => pm2::Delimiter::Bracket,
+        DelimToken::Brace => pm2::Delimiter::Brace,
+        DelimToken::NoDelim => pm2::Delimiter::None,
+    }
+}
+
+fn delimiter_from_pm2(delim: pm2::Delimiter) -> DelimToken {
+    match delim {
+        pm2::Delimiter::Parenthesis => DelimToken::Paren,
+        pm2::Delimiter::Bracket => DelimToken::Bracket,
+        pm2::Delimiter::Brace => DelimToken::Brace,
+        pm2::Delimiter::None => DelimToken::NoDelim,
+    }
+}
+
+/// Lexes `input` straight into a `proc_macro2::TokenStream`, with every span
+/// set to `Span::call_site()`.
+pub fn lex_to_token_stream(input: &str) -> Result<pm2::TokenStream, Error> {
+    let mut interner = StringInterner::empty();
+    let tokens = str_to_token_slice(input.as_bytes(), &mut interner)?;
+    let trees = build_token_trees(tokens)?;
+    to_token_stream(&trees, &interner, &mut call_site)
+}
+
+/// Converts token trees into a `proc_macro2::TokenStream`. Multi-char
+/// operators become `Joint` puncts, lifetimes a `'` followed by an ident,
+/// and doc comments `#[doc = "..."]` attributes, as rustc does. Fails on
+/// tokens `proc_macro2` has no representation for.
+pub fn to_token_stream<F>(
+    trees: &[TokenTree],
+    interner: &StringInterner,
+    span_map: &mut F,
+) -> Result<pm2::TokenStream, Error>
+where
+    F: FnMut(Span) -> pm2::Span,
+{
+    let mut out: Vec<pm2::TokenTree> = vec![];
+    for tree in trees.iter() {
+        match *tree {
+            TokenTree::Delimited(sp, delim, ref inner) => {
+                let stream = to_token_stream(inner, interner, span_map)?;
+                let mut group =
+                    pm2::Group::new(delimiter_to_pm2(delim), stream);
+                group.set_span(span_map(sp.entire()));
+                out.push(group.into());
+            }
+            TokenTree::Token(sp, tok) => {
+                push_token(tok, sp, span_map(sp), interner, &mut out)?;
+            }
+        }
+    }
+    Ok(pm2::TokenStream::from_iter(out))
+}
+
+fn push_token(
+    tok: Token,
+    sp: Span,
+    span: pm2::Span,
+    interner: &StringInterner,
+    out: &mut Vec<pm2::TokenTree>,
+) -> Result<(), Error> {
+    match tok {
+        Token::Ident(..) | Token::Lifetime(_) | Token::Literal(..) => {
+            // proc_macro2 only builds arbitrary literals by parsing them, and
+            // its `Ident` constructors panic on names it rejects, so all three
+            // are parsed and checked to come back as the same kind of token
+            let text = token_to_string(&tok, interner);
+            let trees: Vec<pm2::TokenTree> =
+                match text.parse::<pm2::TokenStream>() {
+                    Ok(stream) => stream.into_iter().collect(),
+                    Err(_) => return Err(Error::InvalidToken(sp)),
+                };
+            let is_same = match (tok, &trees[..]) {
+                (Token::Ident(..), [pm2::TokenTree::Ident(_)]) => true,
+                (
+                    Token::Lifetime(_),
+                    [pm2::TokenTree::Punct(_), pm2::TokenTree::Ident(_)],
+                ) => true,
+                (Token::Literal(..), [pm2::TokenTree::Literal(_)]) => true,
+                _ => false,
+            };
+            if !is_same {
+                return Err(Error::InvalidToken(sp));
+            }
+            for mut tt in trees {
+                tt.set_span(span);
+                out.push(tt);
+            }
+        }
+        Token::DocComment(name) => {
+            let comment = interner.get(name);
+            let body = strip_doc_comment_decoration(comment)
+                .ok_or(Error::InvalidToken(sp))?;
+            push_doc_comment(comment, &body, span, out);
+        }
+        Token::OpenDelim(_) | Token::CloseDelim(_) => {
+            return Err(Error::StrayDelimiter(sp));
+        }
+        Token::Whitespace | Token::Comment | Token::Shebang(_) | Token::Eof => {
+        }
+        _ => {
+            let text = token_to_string(&tok, interner);
+            let mut chars = text.chars().peekable();
+            while let Some(c) = chars.next() {
+                let spacing = if chars.peek().is_some() {
+                    pm2::Spacing::Joint
+                } else {
+                    pm2::Spacing::Alone
+                };
+                let mut punct = pm2::Punct::new(c, spacing);
+                punct.set_span(span);
+                out.push(punct.into());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn push_doc_comment(
+    comment: &str,
+    body: &str,
+    span: pm2::Span,
+    out: &mut Vec<pm2::TokenTree>,
+) {
+    let is_inner = doc_comment_style(comment) == AttrStyle::Inner;
+    let spacing = if is_inner {
+        pm2::Spacing::Joint
+    } else {
+        pm2::Spacing::Alone
+    };
+    let mut pound = pm2::Punct::new('#', spacing);
+    pound.set_span(span);
+    out.push(pound.into());
+    if is_inner {
+        let mut not = pm2::Punct::new('!', pm2::Spacing::Alone);
+        not.set_span(span);
+        out.push(not.into());
+    }
+    let mut eq = pm2::Punct::new('=', pm2::Spacing::Alone);
+    eq.set_span(span);
+    let mut lit = pm2::Literal::string(body);
+    lit.set_span(span);
+    let attr: Vec<pm2::TokenTree> =
+        vec![pm2::Ident::new("doc", span).into(), eq.into(), lit.into()];
+    let mut group = pm2::Group::new(
+        pm2::Delimiter::Bracket,
+        pm2::TokenStream::from_iter(attr),
+    );
+    group.set_span(span);
+    out.push(group.into());
+}
+
+/// Converts a `proc_macro2::TokenStream` into token trees. Runs of `Joint`
+/// puncts are glued back into yarp's multi-char operators as rustc's
+/// `Token::glue` does, each keeping the spans of the puncts it was made of.
+pub fn from_token_stream<F>(
+    stream: pm2::TokenStream,
+    interner: &mut StringInterner,
+    span_map: &mut F,
+) -> Result<Vec<TokenTree>, Error>
+where
+    F: FnMut(pm2::Span) -> Span,
+{
+    let mut out: Vec<TokenTree> = vec![];
+    let mut trees = stream.into_iter().peekable();
+    while let Some(tt) = trees.next() {
+        match tt {
+            pm2::TokenTree::Group(group) => {
+                let inner =
+                    from_token_stream(group.stream(), interner, span_map)?;
+                let sp = DelimSpan {
+                    open: span_map(group.span_open()),
+                    close: span_map(group.span_close()),
+                };
+                out.push(TokenTree::Delimited(
+                    sp,
+                    delimiter_from_pm2(group.delimiter()),
+                    inner,
+                ));
+            }
+            pm2::TokenTree::Ident(ident) => {
+                let name = ident.to_string();
+                let tok = if name.starts_with("r#") {
+                    Token::Ident(interner.insert(&name[2..]), true)
+                } else {
+                    Token::Ident(interner.insert(name.as_str()), false)
+                };
+                out.push(TokenTree::Token(span_map(ident.span()), tok));
+            }
+            pm2::TokenTree::Punct(punct) => {
+                let mut sp = span_map(punct.span());
+                let mut spacing = punct.spacing();
+                if punct.as_char() == '\'' && spacing == pm2::Spacing::Joint {
+                    if let Some(pm2::TokenTree::Ident(ident)) = trees.peek() {
+                        let name = format!("'{}", ident);
+                        let sp = sp.to(span_map(ident.span()));
+                        let tok = Token::Lifetime(interner.insert(&*name));
+                        out.push(TokenTree::Token(sp, tok));
+                        trees.next();
+                        continue;
+                    }
+                }
+                let mut tok = Token::from_punct(punct.as_char())
+                    .ok_or(Error::InvalidToken(sp))?;
+                // glue only what makes an operator, so a joint `/` `/` stays
+                // two slashes instead of lexing as a comment
+                while spacing == pm2::Spacing::Joint {
+                    let next = match trees.peek() {
+                        Some(pm2::TokenTree::Punct(next)) => next.clone(),
+                        _ => break,
+                    };
+                    let glued = Token::from_punct(next.as_char())
+                        .and_then(|joint| tok.glue(&joint));
+                    tok = match glued {
+                        Some(glued) => glued,
+                        None => break,
+                    };
+                    trees.next();
+                    sp = sp.to(span_map(next.span()));
+                    spacing = next.spacing();
+                }
+                out.push(TokenTree::Token(sp, tok));
+            }
+            pm2::TokenTree::Literal(lit) => {
+                let mut sp = span_map(lit.span());
+                let text = lit.to_string();
+                // proc_macro2 hands over a negative literal, eg. `-1i32`, as
+                // a `-` punct and the literal, both spanning the whole
+                if let Some(TokenTree::Token(minus_sp, Token::BinOp(op))) =
+                    out.last_mut()
+                {
+                    if *op == BinOpToken::Minus
+                        && *minus_sp == sp
+                        && sp.len() == text.len() + 1
+                    {
+                        *minus_sp = Span::new(sp.lo, sp.lo + 1);
+                        sp = Span::new(sp.lo + 1, sp.hi);
+                    }
+                }
+                // or the literal itself is `-1i32`, and lexes as `-` and
+                // `1i32`, which get their part of the span if it covers the
+                // text
+                let has_offsets = sp.len() == text.len();
+                for ts in str_to_token_slice(text.as_bytes(), interner)? {
+                    if ts.tok.is_trivia() {
+                        continue;
+                    }
+                    let sub = if has_offsets {
+                        Span::new(sp.lo + ts.sp.lo, sp.lo + ts.sp.hi)
+                    } else {
+                        sp
+                    };
+                    out.push(TokenTree::Token(sub, ts.tok));
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn erase_spans(trees: &[TokenTree]) -> Vec<TokenTree> {
+        trees
+            .iter()
+            .map(|tree| match *tree {
+                TokenTree::Token(_, tok) => TokenTree::Token(DUMMY_SP, tok),
+                TokenTree::Delimited(_, delim, ref inner) => {
+                    TokenTree::Delimited(
+                        DelimSpan {
+                            open: DUMMY_SP,
+                            close: DUMMY_SP,
+                        },
+                        delim,
+                        erase_spans(inner),
+                    )
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = r#"fn f<'a>(x: &'a [u8]) -> Vec<Vec<u8>> {
+            let mut y = x.len() >> 1;
+            y <<= 2; y += 1.5e3f64 as usize;
+            r#type::<()>(b'x', "a\"b", r"c", 'd', ..=y, &&x);
+        }"#;
+        let mut interner = StringInterner::empty();
+        let tokens =
+            str_to_token_slice(input.as_bytes(), &mut interner).unwrap();
+        let trees = build_token_trees(tokens).unwrap();
+        let stream =
+            to_token_stream(&trees, &interner, &mut call_site).unwrap();
+        let back = from_token_stream(stream.clone(), &mut interner, &mut dummy)
+            .unwrap();
+        assert_eq!(erase_spans(&trees), back);
+        let again = to_token_stream(&back, &interner, &mut call_site).unwrap();
+        assert_eq!(stream.to_string(), again.to_string());
+    }
+
+    #[test]
+    fn test_doc_comment() {
+        let stream =
+            lex_to_token_stream("//! inner\n/// outer\nfn f() {}").unwrap();
+        let expected: pm2::TokenStream =
+            "#![doc = \" inner\"] #[doc = \" outer\"] fn f() {}"
+                .parse()
+                .unwrap();
+        assert_eq!(stream.to_string(), expected.to_string());
+        // block doc comments lose their decoration
+        let stream = lex_to_token_stream("/**\n * a\n */").unwrap();
+        assert_eq!(stream.to_string(), "# [doc = \" a\"]");
+    }
+
+    #[test]
+    fn test_spans() {
+        let mut interner = StringInterner::empty();
+        let stream: pm2::TokenStream = "a >>= b".parse().unwrap();
+        // number the puncts' spans by the order they are mapped
+        let mut n = 0;
+        let trees = from_token_stream(stream, &mut interner, &mut |_| {
+            n += 1;
+            Span::new(n, n + 1)
+        })
+        .unwrap();
+        assert_eq!(trees[1].span(), Span::new(2, 5));
+    }
+
+    #[test]
+    fn test_invalid_token() {
+        let check = |input: &str, lo: usize, hi: usize| {
+            assert_eq!(
+                lex_to_token_stream(input).unwrap_err(),
+                Error::InvalidToken(Span::new(lo, hi)),
+                "{}",
+                input
+            );
+        };
+        check("'\\u{zzz}'", 0, 9);
+        check("r#self", 0, 6);
+        check("(r#crate)", 1, 8);
+        check("r#super", 0, 7);
+        check("r#_", 0, 3);
+        check("r#Self", 0, 6);
+        // the lexer never makes such a lifetime, but trees may come from
+        // elsewhere
+        let mut interner = StringInterner::empty();
+        let sp = Span::new(1, 3);
+        let trees =
+            [TokenTree::Token(sp, Token::Lifetime(interner.insert("'1")))];
+        assert_eq!(
+            to_token_stream(&trees, &interner, &mut call_site).unwrap_err(),
+            Error::InvalidToken(sp)
+        );
+        // proc_macro2 takes the `e` of `1e` for a suffix, unlike rustc
+        let stream = lex_to_token_stream("x + 1e").unwrap();
+        match stream.into_iter().nth(2) {
+            Some(pm2::TokenTree::Literal(lit)) => {
+                assert_eq!(lit.to_string(), "1e")
+            }
+            tt => panic!("expected a literal, found {:?}", tt),
+        }
+    }
+
+    #[test]
+    fn test_glue() {
+        let joint = |c| pm2::Punct::new(c, pm2::Spacing::Joint).into();
+        let alone = |c| pm2::Punct::new(c, pm2::Spacing::Alone).into();
+        let slash = Token::BinOp(BinOpToken::Slash);
+        let star = Token::BinOp(BinOpToken::Star);
+        let check = |puncts: Vec<pm2::TokenTree>, expected: &[Token]| {
+            let mut interner = StringInterner::empty();
+            let stream = pm2::TokenStream::from_iter(puncts);
+            let trees =
+                from_token_stream(stream, &mut interner, &mut dummy).unwrap();
+            let expected: Vec<TokenTree> = expected
+                .iter()
+                .map(|&tok| TokenTree::Token(DUMMY_SP, tok))
+                .collect();
+            assert_eq!(trees, expected);
+        };
+        check(vec![joint('/'), alone('/')], &[slash, slash]);
+        check(vec![joint('/'), alone('*')], &[slash, star]);
+        check(
+            vec![joint('/'), joint('*'), alone('=')],
+            &[slash, Token::BinOpEq(BinOpToken::Star)],
+        );
+        check(
+            vec![joint('&'), joint('&'), alone('=')],
+            &[Token::AndAnd, Token::Eq],
+        );
+        check(
+            vec![joint('\''), alone('=')],
+            &[Token::SingleQuote, Token::Eq],
+        );
+    }
+
+    #[test]
+    fn test_stray_delimiter() {
+        let interner = StringInterner::empty();
+        let sp = Span::new(0, 1);
+        let trees = [TokenTree::Token(sp, Token::OpenDelim(DelimToken::Paren))];
+        assert_eq!(
+            to_token_stream(&trees, &interner, &mut call_site).unwrap_err(),
+            Error::StrayDelimiter(sp)
+        );
+    }
+
+    #[test]
+    fn test_doc_comment_tail() {
+        let mut interner = StringInterner::empty();
+        let sp = Span::new(6, 11);
+        let tail = Token::DocComment(interner.insert(" b */"));
+        let trees = [TokenTree::Token(sp, tail)];
+        assert_eq!(
+            to_token_stream(&trees, &interner, &mut call_site).unwrap_err(),
+            Error::InvalidToken(sp)
+        );
+    }
+
+    #[test]
+    fn test_negative_literal() {
+        let mut interner = StringInterner::empty();
+        let lit = pm2::Literal::i32_suffixed(-1);
+        let stream = pm2::TokenStream::from(pm2::TokenTree::from(lit));
+        let trees = from_token_stream(stream, &mut interner, &mut |_| {
+            Span::new(10, 15)
+        })
+        .unwrap();
+        let spans: Vec<Span> = trees.iter().map(TokenTree::span).collect();
+        assert_eq!(spans, vec![Span::new(10, 11), Span::new(11, 15)]);
+        assert_eq!(
+            trees[0],
+            TokenTree::Token(spans[0], Token::BinOp(BinOpToken::Minus))
+        );
+        // without room for sub-spans both share the span
+        let lit = pm2::Literal::i32_suffixed(-1);
+        let stream = pm2::TokenStream::from(pm2::TokenTree::from(lit));
+        let trees =
+            from_token_stream(stream, &mut interner, &mut dummy).unwrap();
+        assert_eq!(trees.len(), 2);
+        assert!(trees.iter().all(|tree| tree.span() == DUMMY_SP));
+    }
+}
@@ -0,0 +1,191 @@
+//! Incremental relexing after a text edit.
+//!
+//! The lexer only looks ahead a couple of chars past the end of a token, and
+//! carries no state from one token to the next but where it is: a shebang
+//! is only a shebang at offset 0. So lexing can restart at any token
+//! boundary far enough before the edit, and can stop as soon as a new token
+//! after the edit is an old token shifted: from there on the text, and so
+//! the tokens, are the old ones shifted. The token has to be compared, not
+//! only where it starts, as the edit may have moved `#!` to offset 0.
+
+use crate::interner::StringInterner;
+use crate::source::from_slice;
+use crate::token::{Span, Token, TokenAndSpan};
+use crate::{
+    char_generator_from_byte, token_generator_from_char_at, LexError,
+    TokenResult,
+};
+use std::ops::{Generator, GeneratorState};
+use std::pin::Pin;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Error {
+    Lex(LexError),
+    /// The edited range is not within the previous text.
+    InvalidRange(Span),
+}
+
+impl From<LexError> for Error {
+    fn from(err: LexError) -> Error {
+        Error::Lex(err)
+    }
+}
+
+// the lexer decides where a token ends by peeking at most 2 chars past it,
+// eg. `1..` or `r#!`
+const MAX_LOOKAHEAD_BYTES: usize = 2 * 4;
+
+/// Returns the tokens of `source`, the text obtained by replacing `range` of
+/// the previous text with `new_text`. `tokens` are the complete tokens of the
+/// previous text, as returned by `str_to_token_slice`, lexed with the same
+/// `interner`.
+pub fn relex(
+    tokens: &[TokenAndSpan],
+    range: Span,
+    new_text: &str,
+    source: &str,
+    interner: &mut StringInterner,
+) -> Result<Vec<TokenAndSpan>, Error> {
+    let old_len = tokens.last().map_or(0, |ts| ts.sp.hi);
+    if range.lo > range.hi || range.hi > old_len {
+        return Err(Error::InvalidRange(range));
+    }
+    debug_assert_eq!(old_len - range.len() + new_text.len(), source.len());
+
+    // tokens that neither overlap nor peeked into the edited range are kept
+    let keep = tokens
+        .iter()
+        .take_while(|ts| ts.sp.hi + MAX_LOOKAHEAD_BYTES <= range.lo)
+        .count();
+    let restart = if keep == 0 { 0 } else { tokens[keep - 1].sp.hi };
+    let mut ret: Vec<TokenAndSpan> = tokens[..keep].to_vec();
+
+    // old tokens after the edit, and where they are in the new text
+    let edit_end = range.lo + new_text.len();
+    let tail = {
+        let first = tokens
+            .iter()
+            .position(|ts| ts.sp.lo >= range.hi)
+            .unwrap_or(tokens.len());
+        &tokens[first..]
+    };
+    let shift = |sp: Span| {
+        Span::new(sp.lo - range.hi + edit_end, sp.hi - range.hi + edit_end)
+    };
+
+    let input = &source.as_bytes()[restart..];
+    let char_gen = char_generator_from_byte(from_slice(input));
+    let mut token_gen =
+        token_generator_from_char_at(char_gen, interner, restart);
+    loop {
+        match Pin::new(&mut token_gen).resume() {
+            GeneratorState::Yielded(TokenResult::Ok(ts)) => {
+                if ts.tok == Token::Eof {
+                    return Ok(ret);
+                }
+                if ts.sp.lo >= edit_end {
+                    let synced = tail.binary_search_by_key(&ts.sp.lo, |old| {
+                        shift(old.sp).lo
+                    });
+                    let synced = synced.ok().filter(|&i| {
+                        tail[i].tok == ts.tok && shift(tail[i].sp) == ts.sp
+                    });
+                    if let Some(i) = synced {
+                        ret.extend(tail[i..].iter().map(|old| TokenAndSpan {
+                            tok: old.tok,
+                            sp: shift(old.sp),
+                        }));
+                        return Ok(ret);
+                    }
+                }
+                ret.push(ts);
+            }
+            GeneratorState::Yielded(_) => {}
+            GeneratorState::Complete(err) => {
+                return Err(Error::Lex(err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::str_to_token_slice;
+
+    // maps arbitrary bytes onto chars that make up interesting rust tokens
+    fn rusty(bytes: &[u8]) -> String {
+        let alphabet: Vec<char> =
+            "ab1_ .\"'#!r/*\n=<>-:{}eü\\".chars().collect();
+        bytes
+            .iter()
+            .map(|b| alphabet[*b as usize % alphabet.len()])
+            .collect()
+    }
+
+    fn prop_relex(old: &str, lo: usize, len: usize, new_text: &str) -> bool {
+        let mut interner = StringInterner::empty();
+        let tokens = match str_to_token_slice(old.as_bytes(), &mut interner) {
+            Ok(tokens) => tokens,
+            Err(_) => return true,
+        };
+        let chars: Vec<char> = old.chars().collect();
+        let lo = lo % (chars.len() + 1);
+        let hi = lo + len % (chars.len() - lo + 1);
+        let byte_lo: usize = chars[..lo].iter().map(|c| c.len_utf8()).sum();
+        let byte_hi: usize = chars[..hi].iter().map(|c| c.len_utf8()).sum();
+        let source =
+            format!("{}{}{}", &old[..byte_lo], new_text, &old[byte_hi..]);
+        let incremental = relex(
+            &tokens,
+            Span::new(byte_lo, byte_hi),
+            new_text,
+            &source,
+            &mut interner,
+        );
+        let full = str_to_token_slice(source.as_bytes(), &mut interner);
+        incremental == full.map_err(Error::Lex)
+    }
+
+    #[quickcheck]
+    fn check_relex(old: Vec<u8>, lo: usize, len: usize, new: Vec<u8>) -> bool {
+        prop_relex(&rusty(&old), lo, len, &rusty(&new))
+    }
+
+    #[test]
+    fn test_relex() {
+        assert!(prop_relex("let a = 1;", 4, 1, "abc"));
+        assert!(prop_relex("a /* b */ c", 2, 0, "\""));
+        assert!(prop_relex("1.. b", 1, 2, "."));
+        assert!(prop_relex("r #a", 1, 1, ""));
+        assert!(prop_relex("\"a\" b c d", 0, 1, ""));
+        // the `#` moves to offset 0 and starts a shebang
+        assert!(prop_relex("a#!x", 0, 1, ""));
+    }
+
+    #[test]
+    fn test_invalid_range() {
+        let mut interner = StringInterner::empty();
+        let tokens = str_to_token_slice(b"ab", &mut interner).unwrap();
+        let range = Span::new(1, 3);
+        assert_eq!(
+            relex(&tokens, range, "", "a", &mut interner),
+            Err(Error::InvalidRange(range))
+        );
+    }
+
+    #[test]
+    fn test_resync() {
+        let old = "fn a() { b; }\nfn c() { d; }";
+        let mut interner = StringInterner::empty();
+        let tokens = str_to_token_slice(old.as_bytes(), &mut interner).unwrap();
+        let source = old.replacen("b", "bb", 1);
+        let new =
+            relex(&tokens, Span::new(9, 10), "bb", &source, &mut interner)
+                .unwrap();
+        assert_eq!(new.len(), tokens.len());
+        assert_eq!(new[8].sp, Span::new(9, 11));
+        assert_eq!(new[9].sp, Span::new(11, 12));
+        assert_eq!(new.last().unwrap().sp.hi, source.len());
+    }
+}
@@ -0,0 +1,273 @@
+//! Ready-made `U8Generator`s.
+//!
+//! A `U8Generator<'a>` yields chunks borrowed for `'a`. Sources that read
+//! their input keep the chunk they yielded last in a `ReadBuffer` owned by
+//! the caller, and free it once they yield the next one, so they take as
+//! much memory as one chunk whatever the size of the input. That makes them
+//! unsafe: a chunk must not be read once its source is resumed again, as
+//! `char_generator_from_byte` and `transcode` never do.
+//!
+//! A read error ends the input with `Utf8Error::Io`, the `ReadBuffer` keeps
+//! the error itself.
+
+use crate::U8Generator;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufRead, Read};
+use std::path::Path;
+
+pub const DEFAULT_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Holds the chunk last read by `from_read`, `from_buf_read` and
+/// `from_file`, or yielded by `transcode::transcode`.
+pub struct ReadBuffer {
+    chunk: RefCell<Box<[u8]>>,
+    error: RefCell<Option<io::Error>>,
+}
+
+impl ReadBuffer {
+    pub fn new() -> ReadBuffer {
+        ReadBuffer {
+            chunk: RefCell::new(Box::new([])),
+            error: RefCell::new(None),
+        }
+    }
+
+    /// The error that ended the input early, whose kind the lexer returned
+    /// as `Utf8Error::Io`.
+    pub fn take_error(&self) -> Option<io::Error> {
+        self.error.borrow_mut().take()
+    }
+
+    // frees the chunk allocated before, see the module doc
+    pub(crate) fn alloc(&self, chunk: Box<[u8]>) -> &[u8] {
+        let mut slot = self.chunk.borrow_mut();
+        *slot = chunk;
+        let ptr: *const [u8] = &**slot;
+        // the boxed slice never moves, and is only dropped by the next
+        // `alloc` or with `self`
+        unsafe { &*ptr }
+    }
+
+    // keeps `err`, and returns its kind for the source to end with
+    pub(crate) fn set_error(&self, err: io::Error) -> io::ErrorKind {
+        let kind = err.kind();
+        *self.error.borrow_mut() = Some(err);
+        kind
+    }
+}
+
+impl Default for ReadBuffer {
+    fn default() -> ReadBuffer {
+        ReadBuffer::new()
+    }
+}
+
+pub fn from_slice<'a>(
+    input: &'a [u8],
+) -> impl U8Generator<'a> + std::marker::Unpin + 'a {
+    move || {
+        if !input.is_empty() {
+            yield input;
+        }
+        loop {
+            yield &[];
+        }
+    }
+}
+
+/// Yields the non-empty chunks of `chunks`, then end-of-input.
+pub fn from_chunks<'a, I>(
+    chunks: I,
+) -> impl U8Generator<'a> + std::marker::Unpin + 'a
+where
+    I: IntoIterator<Item = &'a [u8]>,
+    I::IntoIter: 'a,
+{
+    let chunks = chunks.into_iter();
+    move || {
+        for chunk in chunks {
+            if !chunk.is_empty() {
+                yield chunk;
+            }
+        }
+        loop {
+            yield &[];
+        }
+    }
+}
+
+/// Yields what each `read` of at most `chunk_size` bytes returns.
+///
+/// # Safety
+///
+/// A chunk yielded is freed once the generator is resumed again, or once
+/// another source using `buffer` yields, so it must not be read after that.
+pub unsafe fn from_read<'a, R: Read + 'a>(
+    mut reader: R,
+    chunk_size: usize,
+    buffer: &'a ReadBuffer,
+) -> impl U8Generator<'a> + std::marker::Unpin + 'a {
+    assert!(chunk_size > 0);
+    move || {
+        let mut chunk = vec![0; chunk_size];
+        loop {
+            match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => yield buffer.alloc(chunk[..n].into()),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return buffer.set_error(err),
+            }
+        }
+        loop {
+            yield &[];
+        }
+    }
+}
+
+/// Yields the contents of `reader`'s buffer each time it is filled.
+///
+/// # Safety
+///
+/// As for `from_read`.
+pub unsafe fn from_buf_read<'a, R: BufRead + 'a>(
+    mut reader: R,
+    buffer: &'a ReadBuffer,
+) -> impl U8Generator<'a> + std::marker::Unpin + 'a {
+    move || {
+        loop {
+            let chunk = match reader.fill_buf() {
+                Ok(buf) if buf.is_empty() => break,
+                Ok(buf) => buffer.alloc(buf.into()),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {
+                    continue;
+                }
+                Err(err) => return buffer.set_error(err),
+            };
+            reader.consume(chunk.len());
+            yield chunk;
+        }
+        loop {
+            yield &[];
+        }
+    }
+}
+
+/// # Safety
+///
+/// As for `from_read`.
+pub unsafe fn from_file<'a, P: AsRef<Path>>(
+    path: P,
+    chunk_size: usize,
+    buffer: &'a ReadBuffer,
+) -> io::Result<impl U8Generator<'a> + std::marker::Unpin + 'a> {
+    Ok(from_read(File::open(path)?, chunk_size, buffer))
+}
+
+/// Maps the file at `path` into memory, to be lexed with `from_slice`.
+///
+/// The file must not be modified while it is mapped.
+#[cfg(feature = "memmap")]
+pub fn map_file<P: AsRef<Path>>(path: P) -> io::Result<memmap::Mmap> {
+    let file = File::open(path)?;
+    unsafe { memmap::Mmap::map(&file) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interner::StringInterner;
+    use crate::token::TokenAndSpan;
+    use crate::{
+        char_generator_from_byte, str_to_token_slice,
+        token_generator_from_char, LexError, Token, TokenResult, Utf8Error,
+    };
+    use std::ops::{Generator, GeneratorState};
+    use std::pin::Pin;
+
+    fn lex<'a, T>(source: T) -> Result<Vec<TokenAndSpan>, LexError>
+    where
+        T: U8Generator<'a> + std::marker::Unpin + 'a,
+    {
+        let mut interner = StringInterner::empty();
+        let char_gen = char_generator_from_byte(source);
+        let mut token_gen = token_generator_from_char(char_gen, &mut interner);
+        let mut ret = vec![];
+        loop {
+            match Pin::new(&mut token_gen).resume() {
+                GeneratorState::Yielded(TokenResult::Ok(ts)) => {
+                    if ts.tok == Token::Eof {
+                        return Ok(ret);
+                    }
+                    ret.push(ts);
+                }
+                GeneratorState::Yielded(_) => {}
+                GeneratorState::Complete(err) => return Err(err),
+            }
+        }
+    }
+
+    // reads at most 3 bytes at a time, whatever the buffer size
+    struct Dribble<'b>(&'b [u8]);
+
+    impl<'b> Read for Dribble<'b> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(3);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[quickcheck]
+    fn check_sources(input: String, size: usize) -> bool {
+        let bytes = input.as_bytes();
+        let size = size % 5 + 1;
+        let mut interner = StringInterner::empty();
+        let expected = str_to_token_slice(bytes, &mut interner);
+        let buffer = ReadBuffer::new();
+        let reader = io::BufReader::with_capacity(size, bytes);
+        lex(from_slice(bytes)) == expected
+            && lex(from_chunks(bytes.chunks(size))) == expected
+            && lex(unsafe { from_read(Dribble(bytes), size, &buffer) })
+                == expected
+            && lex(unsafe { from_buf_read(reader, &buffer) }) == expected
+            && buffer.take_error().is_none()
+            && buffer.chunk.borrow().len() <= size
+    }
+
+    #[test]
+    fn test_read_error() {
+        struct Failing;
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::Other, "oops"))
+            }
+        }
+        let buffer = ReadBuffer::new();
+        let input = b"a b".chain(Failing);
+        assert_eq!(
+            lex(unsafe { from_read(input, 16, &buffer) }),
+            Err(LexError::Utf8(Utf8Error::Io(io::ErrorKind::Other)))
+        );
+        assert_eq!(buffer.take_error().unwrap().kind(), io::ErrorKind::Other);
+    }
+
+    #[test]
+    fn test_file() {
+        let path = std::env::temp_dir()
+            .join(format!("yarp-source-{}.rs", std::process::id()));
+        std::fs::write(&path, "fn main() { println!(\"ü\"); }").unwrap();
+        let buffer = ReadBuffer::new();
+        let tokens =
+            lex(unsafe { from_file(&path, 4, &buffer) }.unwrap()).unwrap();
+        #[cfg(feature = "memmap")]
+        {
+            let map = map_file(&path).unwrap();
+            assert_eq!(lex(from_slice(&map)).unwrap(), tokens);
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(tokens.len(), 16);
+        assert_eq!(tokens.last().unwrap().sp.hi, 29);
+    }
+}
@@ -0,0 +1,215 @@
+//! The byte -> char -> token pipeline as a `futures::Stream`, fed from an
+//! `AsyncRead`.
+//!
+//! Whenever the token generator yields `NeedMoreU8` the stream polls the
+//! reader, so a reader returning `Poll::Pending` makes the stream pending
+//! too, without blocking the thread.
+//!
+//! The stream interns names into a `StringInterner` shared through an
+//! `Arc<Mutex<_>>`, locked once per name, so it can be polled on any thread
+//! while the names of the tokens it handed out are looked up elsewhere.
+
+use crate::interner::StringInterner;
+use crate::iter::InboxLexer;
+use crate::source::DEFAULT_CHUNK_SIZE;
+use crate::token::{Token, TokenAndSpan};
+use crate::{LexError, TokenResult};
+use futures::io::AsyncRead;
+use futures::stream::Stream;
+use futures::task::{Context, Poll};
+use std::ops::GeneratorState;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Lex(LexError),
+}
+
+/// A stream of tokens lexed from `R`. Ends after the last token of the input
+/// or after the first error.
+pub struct AsyncTokenStream<R> {
+    reader: R,
+    lexer: InboxLexer<'static>,
+    // declared after `lexer`, so it is dropped after the generators
+    buf: Box<[u8]>,
+    need_more_u8: bool,
+    done: bool,
+}
+
+// SAFETY: the generators share their input chunk and char reader through
+// `Rc`s, but every clone of them lives inside `lexer`, so they all move to
+// another thread together. The chunk is in `buf`, which moves with them.
+unsafe impl<R: Send> Send for AsyncTokenStream<R> {}
+
+impl<R: AsyncRead + Unpin> AsyncTokenStream<R> {
+    pub fn new(
+        reader: R,
+        interner: Arc<Mutex<StringInterner>>,
+    ) -> AsyncTokenStream<R> {
+        AsyncTokenStream::with_capacity(DEFAULT_CHUNK_SIZE, reader, interner)
+    }
+
+    /// Reads the input in chunks of at most `capacity` bytes.
+    pub fn with_capacity(
+        capacity: usize,
+        reader: R,
+        interner: Arc<Mutex<StringInterner>>,
+    ) -> AsyncTokenStream<R> {
+        assert!(capacity > 0);
+        AsyncTokenStream {
+            reader,
+            lexer: InboxLexer::new(interner),
+            buf: vec![0; capacity].into_boxed_slice(),
+            // the char generator pulls its first chunk before yielding
+            need_more_u8: true,
+            done: false,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for AsyncTokenStream<R> {
+    type Item = Result<TokenAndSpan, Error>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        loop {
+            if this.need_more_u8 {
+                match Pin::new(&mut this.reader).poll_read(cx, &mut this.buf) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Err(err)) => {
+                        this.done = true;
+                        return Poll::Ready(Some(Err(Error::Io(err))));
+                    }
+                    Poll::Ready(Ok(n)) => {
+                        // SAFETY: `buf` is only overwritten once the
+                        // generators yield `NeedMoreU8` again, is never
+                        // reallocated, and is dropped after them. An empty
+                        // chunk tells the char generator it's EOF.
+                        unsafe { this.lexer.set_chunk(&this.buf[..n]) };
+                        this.need_more_u8 = false;
+                    }
+                }
+            }
+            match this.lexer.resume() {
+                GeneratorState::Yielded(TokenResult::Ok(ts)) => {
+                    if ts.tok == Token::Eof {
+                        this.done = true;
+                        return Poll::Ready(None);
+                    }
+                    return Poll::Ready(Some(Ok(ts)));
+                }
+                GeneratorState::Yielded(TokenResult::NeedMoreU8) => {
+                    this.need_more_u8 = true;
+                }
+                GeneratorState::Yielded(_) => {}
+                GeneratorState::Complete(err) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(Error::Lex(err))));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::str_to_token_slice;
+    use futures::executor::block_on;
+    use futures::stream::StreamExt;
+
+    // hands out one byte per read, and is pending before each of them
+    struct Trickle<'b> {
+        input: &'b [u8],
+        ready: bool,
+    }
+
+    impl<'b> AsyncRead for Trickle<'b> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            if !self.ready {
+                self.ready = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            self.ready = false;
+            if self.input.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            buf[0] = self.input[0];
+            self.input = &self.input[1..];
+            Poll::Ready(Ok(1))
+        }
+    }
+
+    fn lex_async<R: AsyncRead + Unpin>(
+        reader: R,
+        capacity: usize,
+    ) -> Result<Vec<TokenAndSpan>, Error> {
+        let interner = Arc::new(Mutex::new(StringInterner::empty()));
+        let stream =
+            AsyncTokenStream::with_capacity(capacity, reader, interner);
+        block_on(stream.collect::<Vec<_>>()).into_iter().collect()
+    }
+
+    #[test]
+    fn test_trickle() {
+        let input = "fn ünïcödé() -> u8 { /* ∀ */ 'a' as u8 }";
+        let tokens = lex_async(
+            Trickle {
+                input: input.as_bytes(),
+                ready: false,
+            },
+            16,
+        )
+        .unwrap();
+        let mut interner = StringInterner::empty();
+        let expected =
+            str_to_token_slice(input.as_bytes(), &mut interner).unwrap();
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_send() {
+        let input = "fn ünïcödé() {}";
+        let interner = Arc::new(Mutex::new(StringInterner::empty()));
+        let reader = futures::io::Cursor::new(input.as_bytes().to_vec());
+        let stream = AsyncTokenStream::new(reader, interner.clone());
+        let tokens =
+            std::thread::spawn(move || block_on(stream.collect::<Vec<_>>()))
+                .join()
+                .unwrap();
+        let interner = interner.lock().unwrap();
+        let names: Vec<&str> = tokens
+            .iter()
+            .filter_map(|ts| match ts.as_ref().unwrap().tok {
+                Token::Ident(name, false) => Some(interner.get(name)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, ["fn", "ünïcödé"]);
+    }
+
+    #[quickcheck]
+    fn check_chunked(input: String, capacity: usize) -> bool {
+        let reader = futures::io::Cursor::new(input.as_bytes());
+        let mut interner = StringInterner::empty();
+        let expected = str_to_token_slice(input.as_bytes(), &mut interner);
+        match lex_async(reader, capacity % 7 + 1) {
+            Ok(tokens) => expected == Ok(tokens),
+            Err(Error::Lex(err)) => expected == Err(err),
+            Err(Error::Io(_)) => false,
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Token data types, modelled after rustc's `syntax::parse::token`.
+//!
+//! The types are those of the `syntax` crate, this adds what needs the
+//! `StringInterner` their names are keys into.
+
+pub use syntax::token::*;
+
+use crate::interner::StringInterner;
+use crate::IntKey;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TokenAndSpan {
+    pub tok: Token,
+    pub sp: Span,
+}
+
+impl TokenAndSpan {
+    /// Glues `self` and `next` into one token if they are adjacent, see
+    /// `Token::glue`. A `'` and an identifier are glued into a lifetime.
+    pub fn glue(
+        &self,
+        next: &TokenAndSpan,
+        interner: &mut StringInterner,
+    ) -> Option<TokenAndSpan> {
+        if self.sp.hi != next.sp.lo {
+            return None;
+        }
+        let tok = match (self.tok, next.tok) {
+            (Token::SingleQuote, Token::Ident(name, false)) => {
+                let name = format!("'{}", interner.get(name));
+                Token::Lifetime(interner.insert(name.as_str()))
+            }
+            (tok, next) => tok.glue(&next)?,
+        };
+        Some(TokenAndSpan {
+            tok,
+            sp: self.sp.to(next.sp),
+        })
+    }
+
+    /// Splits `first` off the start of `self`, see `Token::split`, with the
+    /// span of each part. A lifetime is split into a `'` and an identifier.
+    pub fn split(
+        &self,
+        first: &Token,
+        interner: &mut StringInterner,
+    ) -> Option<(TokenAndSpan, TokenAndSpan)> {
+        let (len, rest) = match (*first, self.tok) {
+            (Token::SingleQuote, Token::Lifetime(name)) => {
+                let name = interner.get(name)[1..].to_string();
+                (1, Token::Ident(interner.insert(name.as_str()), false))
+            }
+            (first, tok) => {
+                let rest = tok.split(&first)?;
+                (token_to_string(&first, interner).len(), rest)
+            }
+        };
+        let mid = self.sp.lo + len;
+        Some((
+            TokenAndSpan {
+                tok: *first,
+                sp: Span::new(self.sp.lo, mid),
+            },
+            TokenAndSpan {
+                tok: rest,
+                sp: Span::new(mid, self.sp.hi),
+            },
+        ))
+    }
+}
+
+pub fn literal_to_string(
+    lit: Lit,
+    suffix: Option<IntKey>,
+    interner: &StringInterner,
+) -> String {
+    let mut out = match lit {
+        Lit::Byte(n) => format!("b'{}'", interner.get(n)),
+        Lit::Char(n) => format!("'{}'", interner.get(n)),
+        Lit::Float(n) | Lit::Integer(n) | Lit::Err(n) => {
+            interner.get(n).to_string()
+        }
+        Lit::Str_(n) => format!("\"{}\"", interner.get(n)),
+        Lit::StrRaw(n, hashes) => format!(
+            "r{delim}\"{string}\"{delim}",
+            delim = "#".repeat(hashes as usize),
+            string = interner.get(n)
+        ),
+        Lit::ByteStr(n) => format!("b\"{}\"", interner.get(n)),
+        Lit::ByteStrRaw(n, hashes) => format!(
+            "br{delim}\"{string}\"{delim}",
+            delim = "#".repeat(hashes as usize),
+            string = interner.get(n)
+        ),
+    };
+    if let Some(suffix) = suffix {
+        out.push_str(interner.get(suffix));
+    }
+    out
+}
+
+/// Prints a token the way it would appear in source, mirrors rustc's
+/// `pprust::token_to_string`. Trivia carry no text and print as a single
+/// space or as an empty string.
+pub fn token_to_string(tok: &Token, interner: &StringInterner) -> String {
+    match *tok {
+        Token::Eq => "=".to_string(),
+        Token::Lt => "<".to_string(),
+        Token::Le => "<=".to_string(),
+        Token::EqEq => "==".to_string(),
+        Token::Ne => "!=".to_string(),
+        Token::Ge => ">=".to_string(),
+        Token::Gt => ">".to_string(),
+        Token::Not => "!".to_string(),
+        Token::Tilde => "~".to_string(),
+        Token::OrOr => "||".to_string(),
+        Token::AndAnd => "&&".to_string(),
+        Token::BinOp(op) => binop_to_string(op).to_string(),
+        Token::BinOpEq(op) => format!("{}=", binop_to_string(op)),
+
+        /* Structural symbols */
+        Token::At => "@".to_string(),
+        Token::Dot => ".".to_string(),
+        Token::DotDot => "..".to_string(),
+        Token::DotDotDot => "...".to_string(),
+        Token::DotDotEq => "..=".to_string(),
+        Token::Comma => ",".to_string(),
+        Token::Semi => ";".to_string(),
+        Token::Colon => ":".to_string(),
+        Token::ModSep => "::".to_string(),
+        Token::RArrow => "->".to_string(),
+        Token::LArrow => "<-".to_string(),
+        Token::FatArrow => "=>".to_string(),
+        Token::OpenDelim(DelimToken::Paren) => "(".to_string(),
+        Token::CloseDelim(DelimToken::Paren) => ")".to_string(),
+        Token::OpenDelim(DelimToken::Bracket) => "[".to_string(),
+        Token::CloseDelim(DelimToken::Bracket) => "]".to_string(),
+        Token::OpenDelim(DelimToken::Brace) => "{".to_string(),
+        Token::CloseDelim(DelimToken::Brace) => "}".to_string(),
+        Token::OpenDelim(DelimToken::NoDelim)
+        | Token::CloseDelim(DelimToken::NoDelim) => " ".to_string(),
+        Token::Pound => "#".to_string(),
+        Token::Dollar => "$".to_string(),
+        Token::Question => "?".to_string(),
+        Token::SingleQuote => "'".to_string(),
+
+        /* Literals */
+        Token::Literal(lit, suffix) => literal_to_string(lit, suffix, interner),
+
+        /* Name components */
+        Token::Ident(s, false) => interner.get(s).to_string(),
+        Token::Ident(s, true) => format!("r#{}", interner.get(s)),
+        Token::Lifetime(s) => interner.get(s).to_string(),
+
+        /* Other */
+        Token::DocComment(s) => interner.get(s).to_string(),
+        Token::Eof => "<eof>".to_string(),
+        Token::Whitespace => " ".to_string(),
+        Token::Comment => "/* */".to_string(),
+        Token::Shebang(s) => interner.get(s).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::str_to_token_slice;
+
+    // lexes `input`, which must be a single token
+    fn lex(input: &str, interner: &mut StringInterner) -> TokenAndSpan {
+        let tokens = str_to_token_slice(input.as_bytes(), interner).unwrap();
+        assert_eq!(tokens.len(), 1);
+        tokens[0]
+    }
+
+    #[test]
+    fn test_split() {
+        let mut interner = StringInterner::empty();
+        let shr_eq = lex(">>=", &mut interner);
+        let (gt, ge) = shr_eq.split(&Token::Gt, &mut interner).unwrap();
+        assert_eq!((gt.tok, gt.sp), (Token::Gt, Span::new(0, 1)));
+        assert_eq!((ge.tok, ge.sp), (Token::Ge, Span::new(1, 3)));
+        assert_eq!(gt.glue(&ge, &mut interner), Some(shr_eq));
+        let and_and = lex("&&", &mut interner);
+        let (and, rest) = and_and
+            .split(&Token::BinOp(BinOpToken::And), &mut interner)
+            .unwrap();
+        assert_eq!(rest.tok, Token::BinOp(BinOpToken::And));
+        assert_eq!(rest.sp, Span::new(1, 2));
+        assert_eq!(and.glue(&rest, &mut interner), Some(and_and));
+        assert_eq!(Token::DotDotEq.split(&Token::DotDot), Some(Token::Eq));
+        assert_eq!(Token::DotDotEq.split(&Token::Dot), None);
+        assert_eq!(Token::Gt.split(&Token::Gt), None);
+        assert_eq!(Token::Lt.glue(&Token::LArrow), None);
+    }
+
+    #[test]
+    fn test_lifetime() {
+        let mut interner = StringInterner::empty();
+        let lifetime = lex("'static", &mut interner);
+        let (quote, ident) =
+            lifetime.split(&Token::SingleQuote, &mut interner).unwrap();
+        assert_eq!(quote.sp, Span::new(0, 1));
+        assert_eq!(ident.tok, lex("static", &mut interner).tok);
+        assert_eq!(ident.sp, Span::new(1, 7));
+        assert_eq!(quote.glue(&ident, &mut interner), Some(lifetime));
+        let far = TokenAndSpan {
+            tok: ident.tok,
+            sp: Span::new(2, 8),
+        };
+        assert_eq!(quote.glue(&far, &mut interner), None);
+    }
+}
@@ -0,0 +1,123 @@
+//! Token trees, modelled after rustc's `syntax::tokenstream`.
+
+pub use syntax::tokenstream::{DelimSpan, TokenTree};
+
+use crate::token::{DelimToken, Span, Token, TokenAndSpan};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TokenTreeError {
+    UnexpectedCloseDelim(DelimToken, Span),
+    UnclosedDelim(DelimToken, Span),
+    MismatchedCloseDelim {
+        open: (DelimToken, Span),
+        close: (DelimToken, Span),
+    },
+}
+
+/// Groups lexed tokens into token trees. Trivia is dropped and everything
+/// after `Eof` is ignored.
+pub fn build_token_trees<I>(tokens: I) -> Result<Vec<TokenTree>, TokenTreeError>
+where
+    I: IntoIterator<Item = TokenAndSpan>,
+{
+    // the enclosing open delimiters, with the trees collected before them
+    let mut stack: Vec<(DelimToken, Span, Vec<TokenTree>)> = vec![];
+    let mut trees: Vec<TokenTree> = vec![];
+    for TokenAndSpan { tok, sp } in tokens {
+        match tok {
+            Token::Eof => break,
+            Token::OpenDelim(delim) => {
+                stack.push((delim, sp, std::mem::replace(&mut trees, vec![])));
+            }
+            Token::CloseDelim(delim) => match stack.pop() {
+                Some((open, open_sp, outer)) => {
+                    if open != delim {
+                        return Err(TokenTreeError::MismatchedCloseDelim {
+                            open: (open, open_sp),
+                            close: (delim, sp),
+                        });
+                    }
+                    let inner = std::mem::replace(&mut trees, outer);
+                    trees.push(TokenTree::Delimited(
+                        DelimSpan {
+                            open: open_sp,
+                            close: sp,
+                        },
+                        delim,
+                        inner,
+                    ));
+                }
+                None => {
+                    return Err(TokenTreeError::UnexpectedCloseDelim(
+                        delim, sp,
+                    ));
+                }
+            },
+            tok if tok.is_trivia() => {}
+            tok => trees.push(TokenTree::Token(sp, tok)),
+        }
+    }
+    match stack.pop() {
+        Some((delim, sp, _)) => Err(TokenTreeError::UnclosedDelim(delim, sp)),
+        None => Ok(trees),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interner::StringInterner;
+    use crate::str_to_token_slice;
+
+    fn trees(input: &str) -> Result<Vec<TokenTree>, TokenTreeError> {
+        let mut interner = StringInterner::empty();
+        build_token_trees(
+            str_to_token_slice(input.as_bytes(), &mut interner).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_build_token_trees() {
+        let tts = trees("f(a, [1]) { }").unwrap();
+        assert_eq!(tts.len(), 3);
+        match tts[1] {
+            TokenTree::Delimited(sp, DelimToken::Paren, ref inner) => {
+                assert_eq!(sp.entire(), Span::new(1, 9));
+                assert_eq!(inner.len(), 3);
+                assert_eq!(inner[2].span(), Span::new(5, 8));
+            }
+            ref tt => panic!("{:?}", tt),
+        }
+        match tts[2] {
+            TokenTree::Delimited(_, DelimToken::Brace, ref inner) => {
+                assert!(inner.is_empty())
+            }
+            ref tt => panic!("{:?}", tt),
+        }
+    }
+
+    #[test]
+    fn test_unbalanced() {
+        assert_eq!(
+            trees("a)"),
+            Err(TokenTreeError::UnexpectedCloseDelim(
+                DelimToken::Paren,
+                Span::new(1, 2)
+            ))
+        );
+        assert_eq!(
+            trees("{ ("),
+            Err(TokenTreeError::UnclosedDelim(
+                DelimToken::Paren,
+                Span::new(2, 3)
+            ))
+        );
+        assert_eq!(
+            trees("(]"),
+            Err(TokenTreeError::MismatchedCloseDelim {
+                open: (DelimToken::Paren, Span::new(0, 1)),
+                close: (DelimToken::Bracket, Span::new(1, 2)),
+            })
+        );
+    }
+}
@@ -0,0 +1,405 @@
+//! A source transcoding UTF-16 or Latin-1 input into the UTF-8 the lexer
+//! reads, with a map from offsets in the UTF-8 back to the original input.
+
+use crate::source::ReadBuffer;
+use crate::token::Span;
+use crate::U8Generator;
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::ops::{Generator, GeneratorState};
+use std::pin::Pin;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    /// The encoding the byte order mark `input` starts with stands for, and
+    /// the length of the mark.
+    pub fn from_bom(input: &[u8]) -> Option<(Encoding, usize)> {
+        if input.starts_with(b"\xef\xbb\xbf") {
+            Some((Encoding::Utf8, 3))
+        } else if input.starts_with(b"\xff\xfe") {
+            Some((Encoding::Utf16Le, 2))
+        } else if input.starts_with(b"\xfe\xff") {
+            Some((Encoding::Utf16Be, 2))
+        } else {
+            None
+        }
+    }
+}
+
+// chars `len` bytes long in UTF-8 and `orig_len` in the input, from `lo` in
+// the UTF-8 and `orig_lo` in the input on, up to the next run
+#[derive(Clone, Copy, Debug)]
+struct Run {
+    lo: usize,
+    orig_lo: usize,
+    len: usize,
+    orig_len: usize,
+}
+
+/// Maps offsets in the output of `transcode` to offsets in its input.
+#[derive(Default)]
+pub struct OffsetMap {
+    runs: RefCell<Vec<Run>>,
+    // the offsets up to which the input was transcoded
+    end: Cell<(usize, usize)>,
+}
+
+impl OffsetMap {
+    pub fn new() -> OffsetMap {
+        OffsetMap::default()
+    }
+
+    /// The offset in the input of `pos`, an offset in the output.
+    pub fn original(&self, pos: usize) -> usize {
+        let runs = self.runs.borrow();
+        let i = match runs.binary_search_by_key(&pos, |run| run.lo) {
+            Ok(i) => i,
+            Err(0) => return pos,
+            Err(i) => i - 1,
+        };
+        let run = runs[i];
+        let chars = (pos - run.lo) / run.len;
+        let rest = (pos - run.lo) % run.len;
+        run.orig_lo + chars * run.orig_len + rest.min(run.orig_len)
+    }
+
+    pub fn original_span(&self, sp: Span) -> Span {
+        Span::new(self.original(sp.lo), self.original(sp.hi))
+    }
+
+    // skips `len` bytes of the input, eg. a byte order mark
+    fn skip(&self, len: usize) {
+        let (lo, orig_lo) = self.end.get();
+        self.end.set((lo, orig_lo + len));
+    }
+
+    // `count` chars transcoded from `orig_len` to `len` bytes
+    fn advance(&self, len: usize, orig_len: usize, count: usize) {
+        let (lo, orig_lo) = self.end.get();
+        let mut runs = self.runs.borrow_mut();
+        let same = match runs.last() {
+            Some(run) => {
+                run.len == len
+                    && run.orig_len == orig_len
+                    && run.orig_lo + (lo - run.lo) / len * orig_len == orig_lo
+            }
+            None => false,
+        };
+        if !same {
+            runs.push(Run {
+                lo,
+                orig_lo,
+                len,
+                orig_len,
+            });
+        }
+        self.end.set((lo + len * count, orig_lo + orig_len * count));
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// transcodes the complete chars at the start of `input` into `out`, returns
+// how many bytes of `input` that took
+fn decode(
+    encoding: Encoding,
+    input: &[u8],
+    eof: bool,
+    out: &mut String,
+    map: &OffsetMap,
+) -> io::Result<usize> {
+    let unit = |i: usize| {
+        let (a, b) = (input[i] as u16, input[i + 1] as u16);
+        match encoding {
+            Encoding::Utf16Le => a | b << 8,
+            _ => a << 8 | b,
+        }
+    };
+    let mut pos = 0;
+    match encoding {
+        Encoding::Utf8 => unreachable!("UTF-8 needs no transcoding"),
+        Encoding::Latin1 => {
+            for &b in input {
+                let c = b as char;
+                out.push(c);
+                map.advance(c.len_utf8(), 1, 1);
+            }
+            pos = input.len();
+        }
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            while pos + 2 <= input.len() {
+                let (c, orig_len) = match unit(pos) {
+                    u @ 0xd800..=0xdbff => {
+                        if pos + 4 > input.len() {
+                            break;
+                        }
+                        match unit(pos + 2) {
+                            v @ 0xdc00..=0xdfff => {
+                                let c = 0x10000
+                                    + ((u as u32 - 0xd800) << 10)
+                                    + (v as u32 - 0xdc00);
+                                (std::char::from_u32(c).unwrap(), 4)
+                            }
+                            _ => {
+                                return Err(invalid_data("unpaired surrogate"))
+                            }
+                        }
+                    }
+                    0xdc00..=0xdfff => {
+                        return Err(invalid_data("unpaired surrogate"))
+                    }
+                    u => (std::char::from_u32(u as u32).unwrap(), 2),
+                };
+                out.push(c);
+                map.advance(c.len_utf8(), orig_len, 1);
+                pos += orig_len;
+            }
+            if eof && pos < input.len() {
+                return Err(invalid_data("incomplete UTF-16 code unit"));
+            }
+        }
+    }
+    Ok(pos)
+}
+
+/// Yields `input` transcoded to UTF-8, leaving out the byte order mark of
+/// its encoding. `encoding` is the one of `input`, or `None` to tell it from
+/// the byte order mark, UTF-8 if there is none. Latin-1 input has no byte
+/// order mark, so it has to be given.
+///
+/// Invalid UTF-16 ends the input with `Utf8Error::Io` of kind `InvalidData`,
+/// after the chars before it, and with the error in `buffer`. UTF-8 is passed
+/// on as it is, for the lexer to validate.
+///
+/// # Safety
+///
+/// As for `source::from_read`: a chunk yielded is freed once the generator
+/// is resumed again. `input` may be a source using `buffer` too.
+pub unsafe fn transcode<'a, T>(
+    mut input: T,
+    encoding: Option<Encoding>,
+    buffer: &'a ReadBuffer,
+    map: &'a OffsetMap,
+) -> impl U8Generator<'a> + std::marker::Unpin + 'a
+where
+    T: U8Generator<'a> + std::marker::Unpin + 'a,
+{
+    move || {
+        macro_rules! next_chunk {
+            () => {
+                match Pin::new(&mut input).resume() {
+                    GeneratorState::Yielded(chunk) => chunk,
+                    GeneratorState::Complete(kind) => return kind,
+                }
+            };
+        }
+
+        // the input read but not transcoded yet
+        let mut pending: Vec<u8> = vec![];
+        let mut eof = false;
+        let mut bom = None;
+        if encoding != Some(Encoding::Latin1) {
+            // a byte order mark is 3 bytes at most
+            while pending.len() < 3 && !eof {
+                let chunk = next_chunk!();
+                eof = chunk.is_empty();
+                pending.extend_from_slice(chunk);
+            }
+            bom = Encoding::from_bom(&pending);
+        }
+        let encoding = match (encoding, bom) {
+            (Some(enc), _) => enc,
+            (None, Some((enc, _))) => enc,
+            (None, None) => Encoding::Utf8,
+        };
+        match bom {
+            Some((enc, len)) if enc == encoding => {
+                map.skip(len);
+                pending.drain(..len);
+            }
+            _ => {}
+        }
+
+        if encoding == Encoding::Utf8 {
+            if !pending.is_empty() {
+                map.advance(1, 1, pending.len());
+                yield buffer.alloc(pending.into());
+            }
+            while !eof {
+                let chunk = next_chunk!();
+                eof = chunk.is_empty();
+                map.advance(1, 1, chunk.len());
+                yield chunk;
+            }
+        } else {
+            let mut out = String::new();
+            loop {
+                let failed =
+                    match decode(encoding, &pending, eof, &mut out, map) {
+                        Ok(used) => {
+                            pending.drain(..used);
+                            None
+                        }
+                        Err(err) => Some(err),
+                    };
+                if !out.is_empty() {
+                    let chunk = std::mem::replace(&mut out, String::new());
+                    yield buffer.alloc(chunk.into_bytes().into());
+                }
+                if let Some(err) = failed {
+                    return buffer.set_error(err);
+                }
+                if eof {
+                    break;
+                }
+                let chunk = next_chunk!();
+                eof = chunk.is_empty();
+                pending.extend_from_slice(chunk);
+            }
+        }
+        loop {
+            yield &[];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interner::StringInterner;
+    use crate::iter::TokenIter;
+    use crate::source::from_chunks;
+    use crate::token::TokenAndSpan;
+    use crate::{
+        char_generator_from_byte, str_to_token_slice,
+        token_generator_from_char, LexError, Utf8Error,
+    };
+
+    fn encode(input: &str, encoding: Encoding, bom: bool) -> Vec<u8> {
+        let mut out = vec![];
+        match encoding {
+            Encoding::Utf8 => {
+                if bom {
+                    out.extend_from_slice(b"\xef\xbb\xbf");
+                }
+                out.extend_from_slice(input.as_bytes());
+            }
+            Encoding::Latin1 => out.extend(input.chars().map(|c| c as u8)),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let units = if bom { Some(0xfeff) } else { None };
+                for u in units.into_iter().chain(input.encode_utf16()) {
+                    let (hi, lo) = ((u >> 8) as u8, u as u8);
+                    if encoding == Encoding::Utf16Le {
+                        out.extend_from_slice(&[lo, hi]);
+                    } else {
+                        out.extend_from_slice(&[hi, lo]);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    // lexes `bytes` fed `size` bytes at a time, with the spans mapped back,
+    // up to the error that ended it, if any
+    fn lex(
+        bytes: &[u8],
+        encoding: Option<Encoding>,
+        size: usize,
+    ) -> (Vec<TokenAndSpan>, Option<LexError>) {
+        let buffer = ReadBuffer::new();
+        let map = OffsetMap::new();
+        let chunks = from_chunks(bytes.chunks(size));
+        let source = unsafe { transcode(chunks, encoding, &buffer, &map) };
+        let mut interner = StringInterner::empty();
+        let char_gen = char_generator_from_byte(source);
+        let mut tokens = vec![];
+        for ts in
+            TokenIter::new(token_generator_from_char(char_gen, &mut interner))
+        {
+            match ts {
+                Ok(ts) => tokens.push(TokenAndSpan {
+                    tok: ts.tok,
+                    sp: map.original_span(ts.sp),
+                }),
+                Err(err) => return (tokens, Some(err)),
+            }
+        }
+        (tokens, None)
+    }
+
+    #[quickcheck]
+    fn check_transcode(input: String, size: usize, bom: bool) -> bool {
+        let mut interner = StringInterner::empty();
+        let expected = match str_to_token_slice(input.as_bytes(), &mut interner)
+        {
+            Ok(tokens) => tokens,
+            Err(_) => return true,
+        };
+        let size = size % 5 + 1;
+        let same = |encoding: Encoding, detect: bool| {
+            let bytes = encode(&input, encoding, bom);
+            let given = if detect { None } else { Some(encoding) };
+            let (tokens, err) = lex(&bytes, given, size);
+            // the spans of the input in `encoding`, including its BOM
+            let offset =
+                |pos: usize| encode(&input[..pos], encoding, bom).len();
+            let mapped = expected.iter().map(|ts| TokenAndSpan {
+                tok: ts.tok,
+                sp: Span::new(offset(ts.sp.lo), offset(ts.sp.hi)),
+            });
+            err.is_none()
+                && tokens
+                    .iter()
+                    .map(|ts| ts.tok)
+                    .eq(mapped.clone().map(|ts| ts.tok))
+                && tokens.iter().map(|ts| ts.sp).eq(mapped.map(|ts| ts.sp))
+        };
+        same(Encoding::Utf8, true)
+            && same(Encoding::Utf16Le, bom)
+            && same(Encoding::Utf16Be, bom)
+    }
+
+    #[test]
+    fn test_latin1() {
+        let bytes = b"\xff \"\xe9\" a";
+        let (tokens, err) = lex(bytes, Some(Encoding::Latin1), 2);
+        assert!(err.is_none());
+        let spans: Vec<_> = tokens.iter().map(|ts| ts.sp).collect();
+        assert_eq!(
+            spans,
+            vec![
+                Span::new(0, 1),
+                Span::new(1, 2),
+                Span::new(2, 5),
+                Span::new(5, 6),
+                Span::new(6, 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_utf16() {
+        let mut bytes = encode("a b", Encoding::Utf16Le, true);
+        // a lone low surrogate
+        bytes.extend_from_slice(&[0x00, 0xdc]);
+        let invalid =
+            Some(LexError::Utf8(Utf8Error::Io(io::ErrorKind::InvalidData)));
+        let (tokens, err) = lex(&bytes, None, 3);
+        // `b` is cut short by the error, the tokens before it are whole
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[1].sp, Span::new(4, 6));
+        assert_eq!(err, invalid);
+        let (_, err) = lex(&bytes[..bytes.len() - 1], None, 3);
+        assert_eq!(err, invalid);
+    }
+}
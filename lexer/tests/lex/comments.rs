@@ -0,0 +1,11 @@
+#!/usr/bin/env rustx
+//! Inner doc.
+/*! Inner block doc. */
+
+/// Outer doc.
+/** Outer block doc. */
+// plain comment
+//// not a doc comment
+/* nested /* block */ comment */
+/*** not a doc comment either */
+fn f() {} /**/
@@ -0,0 +1,11 @@
+/**
+ * A block doc comment
+ * over several lines.
+ */
+struct S;
+
+impl S {
+    /*! inner /* nested */ doc */
+    /** "quotes" and \backslashes\ */
+    fn f(&self) -> &'static str { "" }
+}
@@ -0,0 +1,6 @@
+fn main() {
+    let r#match = self::Self;
+    let café = über_größe;
+    let _x1 = crate::a::b;
+    'outer: loop { break 'outer; }
+}
@@ -0,0 +1,9 @@
+const A: u8 = b'a';
+const B: char = '\u{1F600}';
+const C: &str = "escaped \"quote\" \n";
+const D: &str = r#"raw "string""#;
+const E: &[u8] = b"bytes\x00";
+const F: &[u8] = br##"raw # bytes"##;
+const G: f64 = 1.5e-3_f64 + 2. + 0x_ff_u32 as f64 + 0o17 as f64;
+const H: u128 = 0b1010_1010u128;
+const I: i32 = 1.max(2);
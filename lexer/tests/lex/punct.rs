@@ -0,0 +1,6 @@
+fn ops<'a, T: ?Sized>(x: &'a mut T) -> impl Fn(i32) -> i32 + 'a {
+    let y = a <<= b >>= c && d || !e ^ f & g | h;
+    y += 1; y -= 1; y *= 2; y /= 2; y %= 3; y ^= y; y &= y; y |= y;
+    match y { 0..=9 => (), 10...19 => {}, _ if y != 0 => [y; 2], }
+    x.0 == x.1 <= x.2 >= x.3 <- x @ $ # ~ ::a .. ...;
+}
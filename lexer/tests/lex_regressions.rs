@@ -0,0 +1,421 @@
+//! Lexes each `.rs` file under `tests/lex` and compares its tokens with
+//! those of `proc_macro2`'s lexer, which follows rustc's, on the same input.
+//!
+//! Both sides are dumped as the atoms of a `proc_macro2::TokenStream`, a line
+//! per ident, punct, literal and delimiter, so yarp's multi-char operators
+//! are split into puncts and its doc comments desugared into `#[doc = ".."]`.
+//! Fixtures must have balanced delimiters, as a token stream does.
+//!
+//! The reference dump of each fixture is kept in the `.tokens` file of the
+//! same name, so a change of `proc_macro2` shows up in review. Run with
+//! `YARP_BLESS=1` to write the dumps of new or changed fixtures. Both yarp
+//! lexers, the generator one and `lexer::direct`, must make the same tokens.
+
+use lexer::direct;
+use lexer::interner::StringInterner;
+use lexer::lines::LineMap;
+use lexer::token::{DelimToken, Lit, Token, TokenAndSpan};
+use lexer::{str_to_token_slice, LexError};
+use proc_macro2::{Delimiter, Literal, Spacing, TokenStream, TokenTree};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// lines of context shown around a divergence
+const CONTEXT: usize = 3;
+
+type Lexer =
+    fn(&[u8], &mut StringInterner) -> Result<Vec<TokenAndSpan>, LexError>;
+
+fn lit(lit: Lit, interner: &StringInterner) -> String {
+    match lit {
+        Lit::Byte(n) => format!("Byte({:?})", interner.get(n)),
+        Lit::Char(n) => format!("Char({:?})", interner.get(n)),
+        Lit::Err(n) => format!("Err({:?})", interner.get(n)),
+        Lit::Integer(n) => format!("Integer({:?})", interner.get(n)),
+        Lit::Float(n) => format!("Float({:?})", interner.get(n)),
+        Lit::Str_(n) => format!("Str({:?})", interner.get(n)),
+        Lit::StrRaw(n, hashes) => {
+            format!("StrRaw({:?}, {})", interner.get(n), hashes)
+        }
+        Lit::ByteStr(n) => format!("ByteStr({:?})", interner.get(n)),
+        Lit::ByteStrRaw(n, hashes) => {
+            format!("ByteStrRaw({:?}, {})", interner.get(n), hashes)
+        }
+    }
+}
+
+// the token with its interned strings in place of their keys, which the
+// order of interning would change
+fn describe(tok: &Token, interner: &StringInterner) -> String {
+    match *tok {
+        Token::Ident(name, raw) => {
+            format!("Ident({:?}, {})", interner.get(name), raw)
+        }
+        Token::Lifetime(name) => format!("Lifetime({:?})", interner.get(name)),
+        Token::Literal(l, Some(suffix)) => {
+            format!("Literal({}, {:?})", lit(l, interner), interner.get(suffix))
+        }
+        Token::Literal(l, None) => format!("Literal({})", lit(l, interner)),
+        Token::DocComment(s) => format!("DocComment({:?})", interner.get(s)),
+        Token::Shebang(s) => format!("Shebang({:?})", interner.get(s)),
+        ref tok => format!("{:?}", tok),
+    }
+}
+
+/// A line per token, `line:col lo..hi token text`, and the error if lexing
+/// fails, to compare the two lexers of yarp with.
+fn dump(input: &str, lex: Lexer) -> String {
+    let mut interner = StringInterner::empty();
+    let map = LineMap::new(input.as_bytes());
+    match lex(input.as_bytes(), &mut interner) {
+        Ok(tokens) => tokens
+            .iter()
+            .map(|ts| {
+                let (line, col) = map.line_col(ts.sp.lo);
+                format!(
+                    "{}:{} {}..{} {} {:?}\n",
+                    line,
+                    col,
+                    ts.sp.lo,
+                    ts.sp.hi,
+                    describe(&ts.tok, &interner),
+                    &input[ts.sp.lo..ts.sp.hi]
+                )
+            })
+            .collect(),
+        Err(err) => format!("error: {:?}\n", err),
+    }
+}
+
+// the chars `proc_macro2` lexes as puncts
+const PUNCT_CHARS: &str = "~!@#$%^&*-=+|;:,<.>/?'";
+
+fn open_char(delim: DelimToken) -> char {
+    match delim {
+        DelimToken::Paren => '(',
+        DelimToken::Bracket => '[',
+        DelimToken::Brace => '{',
+        DelimToken::NoDelim => ' ',
+    }
+}
+
+fn close_char(delim: DelimToken) -> char {
+    match delim {
+        DelimToken::Paren => ')',
+        DelimToken::Bracket => ']',
+        DelimToken::Brace => '}',
+        DelimToken::NoDelim => ' ',
+    }
+}
+
+fn delim_token(delim: Delimiter) -> DelimToken {
+    match delim {
+        Delimiter::Parenthesis => DelimToken::Paren,
+        Delimiter::Bracket => DelimToken::Bracket,
+        Delimiter::Brace => DelimToken::Brace,
+        Delimiter::None => DelimToken::NoDelim,
+    }
+}
+
+fn spacing(joint: bool) -> &'static str {
+    if joint {
+        "joint"
+    } else {
+        "alone"
+    }
+}
+
+fn walk(stream: TokenStream, out: &mut String) {
+    for tt in stream {
+        let start = tt.span().start();
+        let pos = format!("{}:{}", start.line, start.column);
+        match tt {
+            TokenTree::Group(group) => {
+                let delim = delim_token(group.delimiter());
+                let end = group.span_close().start();
+                out.push_str(&format!("{} open {}\n", pos, open_char(delim)));
+                walk(group.stream(), out);
+                out.push_str(&format!(
+                    "{}:{} close {}\n",
+                    end.line,
+                    end.column,
+                    close_char(delim)
+                ));
+            }
+            TokenTree::Ident(ident) => {
+                out.push_str(&format!("{} ident {}\n", pos, ident))
+            }
+            TokenTree::Punct(punct) => out.push_str(&format!(
+                "{} punct {} {}\n",
+                pos,
+                punct.as_char(),
+                spacing(punct.spacing() == Spacing::Joint)
+            )),
+            TokenTree::Literal(lit) => {
+                out.push_str(&format!("{} literal {}\n", pos, lit))
+            }
+        }
+    }
+}
+
+/// The atoms of `input` as `proc_macro2` lexes it, or `error` if it fails.
+fn reference(input: &str) -> String {
+    // rustc strips a shebang before lexing, `proc_macro2` does not, so blank
+    // it out and keep the positions after it
+    let mut input = input.to_string();
+    if input.starts_with("#!") && !input[2..].trim_start().starts_with('[') {
+        let end = input.find('\n').unwrap_or(input.len());
+        input.replace_range(..end, &" ".repeat(end));
+    }
+    match input.parse::<TokenStream>() {
+        Ok(stream) => {
+            let mut out = String::new();
+            walk(stream, &mut out);
+            out
+        }
+        Err(_) => "error\n".to_string(),
+    }
+}
+
+// a literal as written in the source, put back together from its parts
+fn lit_source(
+    lit: Lit,
+    suffix: Option<&str>,
+    interner: &StringInterner,
+) -> String {
+    let hashes = |n: u16| "#".repeat(n as usize);
+    let mut text = match lit {
+        Lit::Byte(n) => format!("b'{}'", interner.get(n)),
+        Lit::Char(n) => format!("'{}'", interner.get(n)),
+        Lit::Err(n) | Lit::Integer(n) | Lit::Float(n) => {
+            interner.get(n).to_string()
+        }
+        Lit::Str_(n) => format!("\"{}\"", interner.get(n)),
+        Lit::StrRaw(n, h) => {
+            format!("r{}\"{}\"{}", hashes(h), interner.get(n), hashes(h))
+        }
+        Lit::ByteStr(n) => format!("b\"{}\"", interner.get(n)),
+        Lit::ByteStrRaw(n, h) => {
+            format!("br{}\"{}\"{}", hashes(h), interner.get(n), hashes(h))
+        }
+    };
+    text.push_str(suffix.unwrap_or(""));
+    text
+}
+
+/// The atoms of the tokens yarp lexes from `input`, as `reference` dumps
+/// them, or `error` if lexing fails.
+fn atoms(input: &str, lex: Lexer) -> String {
+    let mut interner = StringInterner::empty();
+    let tokens = match lex(input.as_bytes(), &mut interner) {
+        Ok(tokens) => tokens,
+        Err(_) => return "error\n".to_string(),
+    };
+    let map = LineMap::new(input.as_bytes());
+    let pos = |pos: usize| {
+        let (line, col) = map.line_col(pos);
+        format!("{}:{}", line, col)
+    };
+    // a punct is joint if a punct, but not a comment, follows it right away
+    let joint = |hi: usize| {
+        let rest = &input[hi..];
+        !rest.starts_with("//")
+            && !rest.starts_with("/*")
+            && rest
+                .chars()
+                .next()
+                .map_or(false, |c| PUNCT_CHARS.contains(c))
+    };
+    let mut out = String::new();
+    for ts in tokens.iter().filter(|ts| !ts.tok.is_trivia()) {
+        let (lo, hi) = (ts.sp.lo, ts.sp.hi);
+        match ts.tok {
+            Token::Ident(name, raw) => {
+                let raw = if raw { "r#" } else { "" };
+                out.push_str(&format!(
+                    "{} ident {}{}\n",
+                    pos(lo),
+                    raw,
+                    interner.get(name)
+                ));
+            }
+            Token::Lifetime(name) => {
+                out.push_str(&format!("{} punct ' joint\n", pos(lo)));
+                out.push_str(&format!(
+                    "{} ident {}\n",
+                    pos(lo + 1),
+                    &interner.get(name)[1..]
+                ));
+            }
+            Token::Literal(lit, suffix) => out.push_str(&format!(
+                "{} literal {}\n",
+                pos(lo),
+                lit_source(lit, suffix.map(|n| interner.get(n)), &interner)
+            )),
+            Token::OpenDelim(delim) => out.push_str(&format!(
+                "{} open {}\n",
+                pos(lo),
+                open_char(delim)
+            )),
+            Token::CloseDelim(delim) => out.push_str(&format!(
+                "{} close {}\n",
+                pos(lo),
+                close_char(delim)
+            )),
+            Token::DocComment(text) => {
+                let text = interner.get(text);
+                let inner = text.starts_with("//!") || text.starts_with("/*!");
+                let body = if text.starts_with("//") {
+                    &text[3..]
+                } else {
+                    &text[3..text.len() - 2]
+                };
+                let at = pos(lo);
+                out.push_str(&format!("{} punct # alone\n", at));
+                if inner {
+                    out.push_str(&format!("{} punct ! alone\n", at));
+                }
+                out.push_str(&format!("{} open [\n", at));
+                out.push_str(&format!("{} ident doc\n", at));
+                out.push_str(&format!("{} punct = alone\n", at));
+                out.push_str(&format!(
+                    "{} literal {}\n",
+                    at,
+                    Literal::string(body)
+                ));
+                out.push_str(&format!("{} close ]\n", pos(hi - 1)));
+            }
+            _ => {
+                // an operator, a punct per char
+                for (i, c) in input[lo..hi].char_indices() {
+                    let joint = i + 1 < hi - lo || joint(hi);
+                    out.push_str(&format!(
+                        "{} punct {} {}\n",
+                        pos(lo + i),
+                        c,
+                        spacing(joint)
+                    ));
+                }
+            }
+        }
+    }
+    out
+}
+
+// the first line where `actual` and `expected` differ, with the lines around
+// it, or `None` if they are the same
+fn divergence(expected: &str, actual: &str) -> Option<String> {
+    let expected: Vec<_> = expected.lines().collect();
+    let actual: Vec<_> = actual.lines().collect();
+    let first = (0..expected.len().max(actual.len()))
+        .find(|&i| expected.get(i) != actual.get(i))?;
+    let lo = first.saturating_sub(CONTEXT);
+    let mut ret = format!("first divergence at line {}:\n", first + 1);
+    for (name, lines) in &[("expected", &expected), ("actual", &actual)] {
+        ret.push_str(&format!("  {}:\n", name));
+        let hi = (first + CONTEXT + 1).min(lines.len());
+        for (i, line) in lines.iter().enumerate().take(hi).skip(lo) {
+            let mark = if i == first { '>' } else { ' ' };
+            ret.push_str(&format!("   {} {:>4} | {}\n", mark, i + 1, line));
+        }
+        if first >= lines.len() {
+            ret.push_str("   >      | <end of dump>\n");
+        }
+    }
+    Some(ret)
+}
+
+// the `.rs` files under `dir` and its subdirectories
+fn fixtures_in(dir: &Path, paths: &mut Vec<PathBuf>) {
+    let entries = fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("{}: {}", dir.display(), err));
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            fixtures_in(&path, paths);
+        } else if path.extension().map_or(false, |ext| ext == "rs") {
+            paths.push(path);
+        }
+    }
+}
+
+fn fixtures() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/lex");
+    let mut paths = Vec::new();
+    fixtures_in(&dir, &mut paths);
+    paths.sort();
+    paths
+}
+
+// checks the fixture at `path`, returns what is wrong with it
+fn check(path: &Path, bless: bool) -> Option<String> {
+    let input = fs::read_to_string(path).unwrap();
+    let generator = dump(&input, str_to_token_slice);
+    let direct = dump(&input, direct::str_to_token_slice);
+    if let Some(diff) = divergence(&generator, &direct) {
+        return Some(format!(
+            "{}: the direct lexer differs, {}",
+            path.display(),
+            diff
+        ));
+    }
+    let expected = reference(&input);
+    let snapshot = path.with_extension("tokens");
+    if bless {
+        fs::write(&snapshot, &expected).unwrap();
+    } else {
+        match fs::read_to_string(&snapshot) {
+            Ok(ref snapshot) if *snapshot == expected => {}
+            Ok(_) => {
+                return Some(format!(
+                    "{}: proc_macro2 lexes it differently from the \
+                     snapshot, run with YARP_BLESS=1 to update it",
+                    path.display()
+                ))
+            }
+            Err(_) => {
+                return Some(format!(
+                    "{}: no snapshot, run with YARP_BLESS=1 to write it",
+                    path.display()
+                ))
+            }
+        }
+    }
+    let actual = atoms(&input, str_to_token_slice);
+    divergence(&expected, &actual)
+        .map(|diff| format!("{}: {}", path.display(), diff))
+}
+
+#[test]
+fn lex_regressions() {
+    let bless = env::var_os("YARP_BLESS").map_or(false, |v| v != "0");
+    let fixtures = fixtures();
+    assert!(!fixtures.is_empty(), "no fixtures in tests/lex");
+    let failures: Vec<_> = fixtures
+        .iter()
+        .filter_map(|path| check(path, bless))
+        .collect();
+    if !failures.is_empty() {
+        panic!(
+            "{} of {} fixtures failed\n\n{}",
+            failures.len(),
+            fixtures.len(),
+            failures.join("\n")
+        );
+    }
+}
+
+#[test]
+fn test_divergence() {
+    assert_eq!(divergence("a\nb\n", "a\nb\n"), None);
+    assert_eq!(
+        divergence("a\nb\nc\n", "a\nx\nc\n").unwrap(),
+        "first divergence at line 2:\n  expected:\n        1 | a\n   >    2 | b\n\
+         \x20       3 | c\n  actual:\n        1 | a\n   >    2 | x\n\
+         \x20       3 | c\n"
+    );
+    assert!(divergence("a\n", "a\nb\n")
+        .unwrap()
+        .contains("   >      | <end of dump>\n"));
+}
@@ -0,0 +1,1115 @@
+//! The abstract syntax tree, modelled after rustc's `syntax::ast`.
+//!
+//! Names are interned in the lexer's `StringInterner`, and every node that
+//! rustc gives a `NodeId` has one here, assigned in the order the parser
+//! makes them.
+
+use std::fmt;
+use syntax::token::{self, DelimToken, Span};
+use syntax::tokenstream::TokenTree;
+pub use syntax::AttrStyle;
+use syntax::IntKey;
+
+/// `P<T>` is rustc's owned smart pointer, a plain `Box` here.
+pub type P<T> = Box<T>;
+
+/// Boxes `value`, as rustc's `P(value)`.
+#[allow(non_snake_case)]
+pub fn P<T>(value: T) -> P<T> {
+    Box::new(value)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub u32);
+
+pub const CRATE_NODE_ID: NodeId = NodeId(0);
+
+/// The id of nodes the parser did not make.
+pub const DUMMY_NODE_ID: NodeId = NodeId(!0);
+
+impl fmt::Debug for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NodeId({})", self.0)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+pub fn respan<T>(span: Span, node: T) -> Spanned<T> {
+    Spanned { node, span }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Ident {
+    pub name: IntKey,
+    pub span: Span,
+}
+
+impl Ident {
+    pub fn new(name: IntKey, span: Span) -> Ident {
+        Ident { name, span }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Label {
+    pub ident: Ident,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Lifetime {
+    pub id: NodeId,
+    /// The name has its `'`.
+    pub ident: Ident,
+}
+
+/// A path like `std::vec::Vec<T>`, possibly global as in `::std::f`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Path {
+    pub span: Span,
+    pub segments: Vec<PathSegment>,
+}
+
+impl Path {
+    /// A path of one segment, without generic args.
+    pub fn from_ident(ident: Ident) -> Path {
+        Path {
+            span: ident.span,
+            segments: vec![PathSegment::from_ident(ident)],
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct PathSegment {
+    /// `{{root}}` for the segment a leading `::` stands for.
+    pub ident: Ident,
+    pub id: NodeId,
+    pub args: Option<P<GenericArgs>>,
+}
+
+impl PathSegment {
+    pub fn from_ident(ident: Ident) -> PathSegment {
+        PathSegment {
+            ident,
+            id: DUMMY_NODE_ID,
+            args: None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum GenericArgs {
+    /// `<'a, A, B, C = D>`
+    AngleBracketed(AngleBracketedArgs),
+    /// `(A, B) -> C`, as in `Fn(A, B) -> C`
+    Parenthesized(ParenthesizedArgs),
+}
+
+impl GenericArgs {
+    pub fn span(&self) -> Span {
+        match *self {
+            GenericArgs::AngleBracketed(ref args) => args.span,
+            GenericArgs::Parenthesized(ref args) => args.span,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum GenericArg {
+    Lifetime(Lifetime),
+    Type(P<Ty>),
+    Const(AnonConst),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct AngleBracketedArgs {
+    pub span: Span,
+    pub args: Vec<GenericArg>,
+    /// `Item = T` and `Item: Bound`, after the args.
+    pub constraints: Vec<AssocTyConstraint>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ParenthesizedArgs {
+    pub span: Span,
+    pub inputs: Vec<P<Ty>>,
+    pub output: FunctionRetTy,
+}
+
+/// `Item = T` or `Item: Bound` in `Iterator<Item = T>`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AssocTyConstraint {
+    pub id: NodeId,
+    pub ident: Ident,
+    pub kind: AssocTyConstraintKind,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum AssocTyConstraintKind {
+    Equality { ty: P<Ty> },
+    Bound { bounds: GenericBounds },
+}
+
+/// The `<T as Trait>` of `<T as Trait>::Assoc`. `position` is the number of
+/// segments of the path that belong to the trait, 0 for `<T>::Assoc`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct QSelf {
+    pub ty: P<Ty>,
+    pub path_span: Span,
+    pub position: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TraitBoundModifier {
+    None,
+    /// `?Trait`
+    Maybe,
+    /// `~const Trait`
+    MaybeConst,
+    /// `~const ?Trait`
+    MaybeConstMaybe,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum GenericBound {
+    Trait(PolyTraitRef, TraitBoundModifier),
+    Outlives(Lifetime),
+}
+
+impl GenericBound {
+    pub fn span(&self) -> Span {
+        match *self {
+            GenericBound::Trait(ref t, _) => t.span,
+            GenericBound::Outlives(ref l) => l.ident.span,
+        }
+    }
+}
+
+pub type GenericBounds = Vec<GenericBound>;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum GenericParamKind {
+    Lifetime,
+    Type {
+        default: Option<P<Ty>>,
+    },
+    Const {
+        ty: P<Ty>,
+        default: Option<AnonConst>,
+    },
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct GenericParam {
+    pub id: NodeId,
+    pub ident: Ident,
+    pub attrs: Vec<Attribute>,
+    pub bounds: GenericBounds,
+    pub kind: GenericParamKind,
+    pub span: Span,
+}
+
+/// The generic params and where-clause of an item.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Generics {
+    pub params: Vec<GenericParam>,
+    pub where_clause: WhereClause,
+    pub span: Span,
+}
+
+impl Generics {
+    /// No params and no where-clause, at `span`.
+    pub fn empty(span: Span) -> Generics {
+        Generics {
+            params: vec![],
+            where_clause: WhereClause {
+                predicates: vec![],
+                span,
+            },
+            span,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct WhereClause {
+    pub predicates: Vec<WherePredicate>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum WherePredicate {
+    /// `for<'a> T: Bound + 'a`
+    BoundPredicate(WhereBoundPredicate),
+    /// `'a: 'b + 'c`
+    RegionPredicate(WhereRegionPredicate),
+    /// `T = U`
+    EqPredicate(WhereEqPredicate),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct WhereBoundPredicate {
+    pub span: Span,
+    pub bound_generic_params: Vec<GenericParam>,
+    pub bounded_ty: P<Ty>,
+    pub bounds: GenericBounds,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct WhereRegionPredicate {
+    pub span: Span,
+    pub lifetime: Lifetime,
+    pub bounds: GenericBounds,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct WhereEqPredicate {
+    pub id: NodeId,
+    pub span: Span,
+    pub lhs_ty: P<Ty>,
+    pub rhs_ty: P<Ty>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Crate {
+    pub module: Mod,
+    pub attrs: Vec<Attribute>,
+    pub span: Span,
+}
+
+/// An attribute, or a doc comment desugared into one.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Attribute {
+    pub style: AttrStyle,
+    pub path: Path,
+    /// What follows the path, eg. `= "text"` or `(a, b)`.
+    pub tokens: Vec<TokenTree>,
+    pub is_sugared_doc: bool,
+    pub span: Span,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MacDelimiter {
+    Parenthesis,
+    Bracket,
+    Brace,
+}
+
+impl MacDelimiter {
+    pub fn from_token(delim: DelimToken) -> Option<MacDelimiter> {
+        match delim {
+            DelimToken::Paren => Some(MacDelimiter::Parenthesis),
+            DelimToken::Bracket => Some(MacDelimiter::Bracket),
+            DelimToken::Brace => Some(MacDelimiter::Brace),
+            DelimToken::NoDelim => None,
+        }
+    }
+}
+
+/// A macro invocation, `path!(tts)`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Mac {
+    pub path: Path,
+    pub delim: MacDelimiter,
+    pub tts: Vec<TokenTree>,
+    pub span: Span,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MacStmtStyle {
+    /// `foo!(...);` or `foo![...];`
+    Semicolon,
+    /// `foo! { ... }`
+    Braces,
+    /// `foo!(...)` as the trailing expression of a block
+    NoBraces,
+}
+
+/// The body of `macro_rules! name { ... }`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MacroDef {
+    pub tokens: Vec<TokenTree>,
+    /// `macro_rules!`, as opposed to `macro`.
+    pub legacy: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Mutability {
+    Mutable,
+    Immutable,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum BinOpKind {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    And,
+    Or,
+    BitXor,
+    BitAnd,
+    BitOr,
+    Shl,
+    Shr,
+    Eq,
+    Lt,
+    Le,
+    Ne,
+    Ge,
+    Gt,
+}
+
+impl BinOpKind {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            BinOpKind::Add => "+",
+            BinOpKind::Sub => "-",
+            BinOpKind::Mul => "*",
+            BinOpKind::Div => "/",
+            BinOpKind::Rem => "%",
+            BinOpKind::And => "&&",
+            BinOpKind::Or => "||",
+            BinOpKind::BitXor => "^",
+            BinOpKind::BitAnd => "&",
+            BinOpKind::BitOr => "|",
+            BinOpKind::Shl => "<<",
+            BinOpKind::Shr => ">>",
+            BinOpKind::Eq => "==",
+            BinOpKind::Lt => "<",
+            BinOpKind::Le => "<=",
+            BinOpKind::Ne => "!=",
+            BinOpKind::Ge => ">=",
+            BinOpKind::Gt => ">",
+        }
+    }
+
+    /// `&&` and `||`, which may not evaluate their right operand.
+    pub fn lazy(self) -> bool {
+        match self {
+            BinOpKind::And | BinOpKind::Or => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_comparison(self) -> bool {
+        match self {
+            BinOpKind::Eq
+            | BinOpKind::Lt
+            | BinOpKind::Le
+            | BinOpKind::Ne
+            | BinOpKind::Ge
+            | BinOpKind::Gt => true,
+            _ => false,
+        }
+    }
+}
+
+pub type BinOp = Spanned<BinOpKind>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum UnOp {
+    /// `*`
+    Deref,
+    /// `!`
+    Not,
+    /// `-`
+    Neg,
+}
+
+impl UnOp {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            UnOp::Deref => "*",
+            UnOp::Not => "!",
+            UnOp::Neg => "-",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LitKind {
+    /// `true` or `false`.
+    Bool(bool),
+    /// Any other literal, as the lexer made it: the text is not unescaped or
+    /// converted to a number.
+    Token(token::Lit, Option<IntKey>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Lit {
+    pub kind: LitKind,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub id: NodeId,
+    pub rules: BlockCheckMode,
+    pub span: Span,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum BlockCheckMode {
+    Default,
+    Unsafe,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Stmt {
+    pub id: NodeId,
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+impl Stmt {
+    /// The statement followed by a `;`.
+    pub fn add_trailing_semicolon(mut self) -> Stmt {
+        self.kind = match self.kind {
+            StmtKind::Expr(expr) => StmtKind::Semi(expr),
+            StmtKind::Mac(mac) => {
+                let (mac, _, attrs) = *mac;
+                StmtKind::Mac(P((mac, MacStmtStyle::Semicolon, attrs)))
+            }
+            kind => kind,
+        };
+        self
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum StmtKind {
+    /// `let pat: ty = init;`
+    Local(P<Local>),
+    Item(P<Item>),
+    /// An expression without a trailing `;`: the value of its block, or one
+    /// that needs no `;`, like `if` or `loop`.
+    Expr(P<Expr>),
+    /// An expression with a trailing `;`.
+    Semi(P<Expr>),
+    Mac(P<(Mac, MacStmtStyle, Vec<Attribute>)>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Local {
+    pub id: NodeId,
+    pub pat: P<Pat>,
+    pub ty: Option<P<Ty>>,
+    pub init: Option<P<Expr>>,
+    pub span: Span,
+    pub attrs: Vec<Attribute>,
+}
+
+/// A `match` arm, `pat if guard => body`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Arm {
+    pub attrs: Vec<Attribute>,
+    pub pat: P<Pat>,
+    pub guard: Option<P<Expr>>,
+    pub body: P<Expr>,
+    pub span: Span,
+    pub id: NodeId,
+}
+
+/// A field of a struct expression, `ident: expr` or just `ident`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Field {
+    pub ident: Ident,
+    pub expr: P<Expr>,
+    pub span: Span,
+    pub is_shorthand: bool,
+    pub attrs: Vec<Attribute>,
+    pub id: NodeId,
+}
+
+/// An expression evaluated at compile time, like an array length.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AnonConst {
+    pub id: NodeId,
+    pub value: P<Expr>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Expr {
+    pub id: NodeId,
+    pub kind: ExprKind,
+    pub span: Span,
+    pub attrs: Vec<Attribute>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RangeLimits {
+    /// `..`
+    HalfOpen,
+    /// `..=`
+    Closed,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CaptureBy {
+    /// `move |x| ...`
+    Value,
+    Ref,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Movability {
+    /// `static || ...`, a generator that may not move
+    Static,
+    Movable,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum IsAsync {
+    Async,
+    NotAsync,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ExprKind {
+    /// `box expr`
+    Box(P<Expr>),
+    /// `[a, b, c]`
+    Array(Vec<P<Expr>>),
+    /// `f(a, b)`
+    Call(P<Expr>, Vec<P<Expr>>),
+    /// `x.f::<T>(a, b)`, the receiver `x` is the first of the args.
+    MethodCall(PathSegment, Vec<P<Expr>>),
+    /// `(a, b)`
+    Tup(Vec<P<Expr>>),
+    Binary(BinOp, P<Expr>, P<Expr>),
+    Unary(UnOp, P<Expr>),
+    Lit(Lit),
+    /// `expr as ty`
+    Cast(P<Expr>, P<Ty>),
+    /// `expr: ty`
+    Type(P<Expr>, P<Ty>),
+    /// `let pat = expr`, in the condition of an `if` or `while`.
+    Let(P<Pat>, P<Expr>),
+    /// `if cond { ... } else ...`, the else is a block or another `if`.
+    If(P<Expr>, P<Block>, Option<P<Expr>>),
+    While(P<Expr>, P<Block>, Option<Label>),
+    /// `for pat in expr { ... }`
+    ForLoop(P<Pat>, P<Expr>, P<Block>, Option<Label>),
+    Loop(P<Block>, Option<Label>),
+    Match(P<Expr>, Vec<Arm>),
+    /// `move |a, b| body`, the span is that of the `|...|`.
+    Closure(CaptureBy, IsAsync, Movability, P<FnDecl>, P<Expr>, Span),
+    /// `'label: { ... }`
+    Block(P<Block>, Option<Label>),
+    /// `async move { ... }`
+    Async(CaptureBy, P<Block>),
+    /// `expr.await`
+    Await(P<Expr>),
+    /// `try { ... }`
+    TryBlock(P<Block>),
+    /// `a = b`
+    Assign(P<Expr>, P<Expr>),
+    /// `a += b`
+    AssignOp(BinOp, P<Expr>, P<Expr>),
+    /// `expr.field`, or `expr.0`
+    Field(P<Expr>, Ident),
+    /// `expr[index]`
+    Index(P<Expr>, P<Expr>),
+    /// `a..b`, `a..`, `..b`, `..` and `a..=b`
+    Range(Option<P<Expr>>, Option<P<Expr>>, RangeLimits),
+    /// `a::b`, `<T as Trait>::f`
+    Path(Option<QSelf>, Path),
+    /// `&expr` or `&mut expr`
+    AddrOf(Mutability, P<Expr>),
+    Break(Option<Label>, Option<P<Expr>>),
+    Continue(Option<Label>),
+    Ret(Option<P<Expr>>),
+    Mac(Mac),
+    /// `Path { a: x, b, ..base }`
+    Struct(Path, Vec<Field>, Option<P<Expr>>),
+    /// `[expr; N]`
+    Repeat(P<Expr>, AnonConst),
+    /// `(expr)`
+    Paren(P<Expr>),
+    /// `expr?`
+    Try(P<Expr>),
+    Yield(Option<P<Expr>>),
+    /// What the parser leaves in place of an expression it recovered from.
+    Err,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RangeSyntax {
+    /// `...`
+    DotDotDot,
+    /// `..=`
+    DotDotEq,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RangeEnd {
+    Included(RangeSyntax),
+    Excluded,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum BindingMode {
+    ByRef(Mutability),
+    ByValue(Mutability),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Pat {
+    pub id: NodeId,
+    pub kind: PatKind,
+    pub span: Span,
+}
+
+/// A field of a struct pattern, `ident: pat` or `ref mut ident`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct FieldPat {
+    pub ident: Ident,
+    pub pat: P<Pat>,
+    pub is_shorthand: bool,
+    pub attrs: Vec<Attribute>,
+    pub id: NodeId,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum PatKind {
+    /// `_`
+    Wild,
+    /// `ref mut name @ sub`
+    Ident(BindingMode, Ident, Option<P<Pat>>),
+    /// `Path { a, b: pat, .. }`, true if it has the `..`
+    Struct(Path, Vec<FieldPat>, bool),
+    /// `Path(a, .., b)`
+    TupleStruct(Path, Vec<P<Pat>>),
+    /// `a | b | c`
+    Or(Vec<P<Pat>>),
+    /// `a::B`, `<T as Trait>::C`
+    Path(Option<QSelf>, Path),
+    /// `(a, .., b)`
+    Tuple(Vec<P<Pat>>),
+    /// `box pat`
+    Box(P<Pat>),
+    /// `&pat` or `&mut pat`
+    Ref(P<Pat>, Mutability),
+    /// A literal, a path or a negated literal, as an expression.
+    Lit(P<Expr>),
+    /// `a..=b`, `a..b`, `a..` and `..=b`
+    Range(Option<P<Expr>>, Option<P<Expr>>, Spanned<RangeEnd>),
+    /// `[a, .., b]`
+    Slice(Vec<P<Pat>>),
+    /// The `..` of tuple and slice patterns.
+    Rest,
+    /// `(pat)`
+    Paren(P<Pat>),
+    Mac(Mac),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct MutTy {
+    pub ty: P<Ty>,
+    pub mutbl: Mutability,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct BareFnTy {
+    pub unsafety: Unsafety,
+    pub ext: Extern,
+    /// The `for<'a>` before the `fn`.
+    pub generic_params: Vec<GenericParam>,
+    pub decl: P<FnDecl>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Ty {
+    pub id: NodeId,
+    pub kind: TyKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TraitObjectSyntax {
+    Dyn,
+    None,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum TyKind {
+    /// `[T]`
+    Slice(P<Ty>),
+    /// `[T; N]`
+    Array(P<Ty>, AnonConst),
+    /// `*const T` or `*mut T`
+    Ptr(MutTy),
+    /// `&'a mut T`
+    Rptr(Option<Lifetime>, MutTy),
+    /// `for<'a> unsafe extern "C" fn(&'a T) -> U`
+    BareFn(P<BareFnTy>),
+    /// `!`
+    Never,
+    /// `(A, B)`, and `()`
+    Tup(Vec<P<Ty>>),
+    /// `a::B<C>`, `<T as Trait>::Assoc`
+    Path(Option<QSelf>, Path),
+    /// `dyn Trait + 'a`, or the same without `dyn`
+    TraitObject(GenericBounds, TraitObjectSyntax),
+    /// `impl Trait + 'a`
+    ImplTrait(NodeId, GenericBounds),
+    /// `(T)`
+    Paren(P<Ty>),
+    /// `typeof(expr)`, reserved
+    Typeof(AnonConst),
+    /// `_`
+    Infer,
+    /// The type of a `self` param without one.
+    ImplicitSelf,
+    Mac(Mac),
+    /// The `...` of a variadic foreign function.
+    CVarArgs,
+    Err,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Param {
+    pub attrs: Vec<Attribute>,
+    pub ty: P<Ty>,
+    pub pat: P<Pat>,
+    pub id: NodeId,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct FnDecl {
+    pub inputs: Vec<Param>,
+    pub output: FunctionRetTy,
+    /// The last param is a `...`.
+    pub c_variadic: bool,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum FunctionRetTy {
+    /// No `-> T`, the span is where it would be.
+    Default(Span),
+    Ty(P<Ty>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Unsafety {
+    Unsafe,
+    Normal,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Constness {
+    Const,
+    NotConst,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Defaultness {
+    Default,
+    Final,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ImplPolarity {
+    Positive,
+    /// `impl !Trait for T`
+    Negative,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum IsAuto {
+    Yes,
+    No,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Extern {
+    None,
+    /// `extern`
+    Implicit,
+    /// `extern "abi"`
+    Explicit(Lit),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct FnHeader {
+    pub unsafety: Unsafety,
+    pub asyncness: IsAsync,
+    pub constness: Constness,
+    pub ext: Extern,
+}
+
+impl Default for FnHeader {
+    fn default() -> FnHeader {
+        FnHeader {
+            unsafety: Unsafety::Normal,
+            asyncness: IsAsync::NotAsync,
+            constness: Constness::NotConst,
+            ext: Extern::None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct FnSig {
+    pub header: FnHeader,
+    pub decl: P<FnDecl>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CrateSugar {
+    /// `pub(crate)`
+    PubCrate,
+    /// `crate`
+    JustCrate,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum VisibilityKind {
+    Public,
+    Crate(CrateSugar),
+    /// `pub(self)`, `pub(super)` and `pub(in path)`
+    Restricted {
+        path: P<Path>,
+        id: NodeId,
+    },
+    Inherited,
+}
+
+pub type Visibility = Spanned<VisibilityKind>;
+
+/// A field of a struct, union or enum variant, without an ident in a tuple
+/// struct.
+#[derive(Clone, PartialEq, Debug)]
+pub struct StructField {
+    pub span: Span,
+    pub ident: Option<Ident>,
+    pub vis: Visibility,
+    pub id: NodeId,
+    pub ty: P<Ty>,
+    pub attrs: Vec<Attribute>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum VariantData {
+    /// `{ a: A, b: B }`
+    Struct(Vec<StructField>),
+    /// `(A, B)`
+    Tuple(Vec<StructField>, NodeId),
+    Unit(NodeId),
+}
+
+impl VariantData {
+    pub fn fields(&self) -> &[StructField] {
+        match *self {
+            VariantData::Struct(ref fields)
+            | VariantData::Tuple(ref fields, _) => fields,
+            VariantData::Unit(_) => &[],
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Variant {
+    pub ident: Ident,
+    pub attrs: Vec<Attribute>,
+    pub id: NodeId,
+    pub data: VariantData,
+    /// `= expr`
+    pub disr_expr: Option<AnonConst>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct EnumDef {
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum UseTreeKind {
+    /// `use prefix` or `use prefix as rename`
+    Simple(Option<Ident>),
+    /// `use prefix::{...}`
+    Nested(Vec<UseTree>),
+    /// `use prefix::*`
+    Glob,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct UseTree {
+    pub prefix: Path,
+    pub kind: UseTreeKind,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TraitRef {
+    pub path: Path,
+    pub ref_id: NodeId,
+}
+
+/// `for<'a> Trait<'a>`
+#[derive(Clone, PartialEq, Debug)]
+pub struct PolyTraitRef {
+    pub bound_generic_params: Vec<GenericParam>,
+    pub trait_ref: TraitRef,
+    pub span: Span,
+}
+
+/// The items of a module, those of a file or of `mod m { ... }`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Mod {
+    /// The span of the items, inside the braces.
+    pub inner: Span,
+    pub items: Vec<P<Item>>,
+    /// `mod m { ... }`, as opposed to `mod m;`
+    pub inline: bool,
+}
+
+/// `extern "abi" { ... }`
+#[derive(Clone, PartialEq, Debug)]
+pub struct ForeignMod {
+    pub abi: Option<Lit>,
+    pub items: Vec<ForeignItem>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ForeignItem {
+    pub ident: Ident,
+    pub attrs: Vec<Attribute>,
+    pub kind: ForeignItemKind,
+    pub id: NodeId,
+    pub span: Span,
+    pub vis: Visibility,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ForeignItemKind {
+    Fn(P<FnDecl>, Generics),
+    Static(P<Ty>, Mutability),
+    /// `type T;`
+    Ty,
+    Macro(Mac),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TraitItem {
+    pub id: NodeId,
+    pub ident: Ident,
+    pub attrs: Vec<Attribute>,
+    pub generics: Generics,
+    pub kind: TraitItemKind,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum TraitItemKind {
+    Const(P<Ty>, Option<P<Expr>>),
+    /// A method, with a body if it has a default one.
+    Method(FnSig, Option<P<Block>>),
+    /// `type T: Bounds = Default;`
+    Type(GenericBounds, Option<P<Ty>>),
+    Macro(Mac),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ImplItem {
+    pub id: NodeId,
+    pub ident: Ident,
+    pub vis: Visibility,
+    pub defaultness: Defaultness,
+    pub attrs: Vec<Attribute>,
+    pub generics: Generics,
+    pub kind: ImplItemKind,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ImplItemKind {
+    Const(P<Ty>, P<Expr>),
+    Method(FnSig, P<Block>),
+    TyAlias(P<Ty>),
+    Macro(Mac),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Item {
+    pub attrs: Vec<Attribute>,
+    pub id: NodeId,
+    pub span: Span,
+    pub vis: Visibility,
+    /// The name of the item, an empty name for items without one, like
+    /// `impl` blocks.
+    pub ident: Ident,
+    pub kind: ItemKind,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ItemKind {
+    /// `extern crate orig as ident;`
+    ExternCrate(Option<IntKey>),
+    Use(P<UseTree>),
+    Static(P<Ty>, Mutability, P<Expr>),
+    Const(P<Ty>, P<Expr>),
+    Fn(FnSig, Generics, P<Block>),
+    /// `mod m { ... }` or `mod m;`
+    Mod(Mod),
+    ForeignMod(ForeignMod),
+    /// `type T = U;`
+    TyAlias(P<Ty>, Generics),
+    Enum(EnumDef, Generics),
+    Struct(VariantData, Generics),
+    Union(VariantData, Generics),
+    /// `auto unsafe trait T: Bounds { ... }`
+    Trait(IsAuto, Unsafety, Generics, GenericBounds, Vec<TraitItem>),
+    /// `trait T = Bounds;`
+    TraitAlias(Generics, GenericBounds),
+    /// `unsafe default impl<T> !Trait for Ty { ... }`
+    Impl(
+        Unsafety,
+        ImplPolarity,
+        Defaultness,
+        Generics,
+        Option<TraitRef>,
+        P<Ty>,
+        Vec<ImplItem>,
+    ),
+    /// A macro invocation in item position.
+    Mac(Mac),
+    /// `macro_rules! name { ... }`
+    MacroDef(MacroDef),
+}
+
+impl ItemKind {
+    /// What the item is, for messages.
+    pub fn descriptive_variant(&self) -> &'static str {
+        match *self {
+            ItemKind::ExternCrate(..) => "extern crate",
+            ItemKind::Use(..) => "use",
+            ItemKind::Static(..) => "static item",
+            ItemKind::Const(..) => "constant item",
+            ItemKind::Fn(..) => "function",
+            ItemKind::Mod(..) => "module",
+            ItemKind::ForeignMod(..) => "foreign module",
+            ItemKind::TyAlias(..) => "type alias",
+            ItemKind::Enum(..) => "enum",
+            ItemKind::Struct(..) => "struct",
+            ItemKind::Union(..) => "union",
+            ItemKind::Trait(..) => "trait",
+            ItemKind::TraitAlias(..) => "trait alias",
+            ItemKind::Impl(..) => "impl",
+            ItemKind::Mac(..) => "item macro invocation",
+            ItemKind::MacroDef(..) => "macro definition",
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use super::{PResult, Parser, PathStyle};
+use crate::ast::{AttrStyle, Attribute, Ident, Path};
+use lexer::comments::{desugar_doc_comments, doc_comment_style};
+use lexer::token::{DelimToken, Span, Token};
+use lexer::tokenstream::TokenTree;
+use lexer::IntKey;
+
+impl<'a> Parser<'a> {
+    /// `#[attr]`s and outer doc comments.
+    pub fn parse_outer_attributes(&mut self) -> PResult<Vec<Attribute>> {
+        let mut attrs = vec![];
+        loop {
+            match self.token.tok {
+                Token::Pound => {
+                    if self.look_ahead(1, |t| *t == Token::Not) {
+                        let msg = "an inner attribute is not permitted in \
+                                   this context";
+                        return self.fatal(self.token.sp, msg.to_string());
+                    }
+                    attrs.push(self.parse_attribute(AttrStyle::Outer)?);
+                }
+                Token::DocComment(name) => {
+                    let attr = self.doc_comment_attr(name, self.token.sp);
+                    if attr.style != AttrStyle::Outer {
+                        let msg = "expected outer doc comment";
+                        return self.fatal(self.token.sp, msg.to_string());
+                    }
+                    attrs.push(attr);
+                    self.bump();
+                }
+                _ => return Ok(attrs),
+            }
+        }
+    }
+
+    /// `#![attr]`s and inner doc comments, as at the start of a block.
+    pub fn parse_inner_attributes(&mut self) -> PResult<Vec<Attribute>> {
+        let mut attrs = vec![];
+        loop {
+            match self.token.tok {
+                Token::Pound if self.look_ahead(1, |t| *t == Token::Not) => {
+                    attrs.push(self.parse_attribute(AttrStyle::Inner)?);
+                }
+                Token::DocComment(name) => {
+                    let attr = self.doc_comment_attr(name, self.token.sp);
+                    if attr.style != AttrStyle::Inner {
+                        return Ok(attrs);
+                    }
+                    attrs.push(attr);
+                    self.bump();
+                }
+                _ => return Ok(attrs),
+            }
+        }
+    }
+
+    fn parse_attribute(&mut self, style: AttrStyle) -> PResult<Attribute> {
+        let lo = self.token.sp;
+        self.expect(&Token::Pound)?;
+        if style == AttrStyle::Inner {
+            self.expect(&Token::Not)?;
+        }
+        self.expect(&Token::OpenDelim(DelimToken::Bracket))?;
+        let path = self.parse_path(PathStyle::Mod)?;
+        let tokens = self.parse_tts_to_close()?;
+        self.expect(&Token::CloseDelim(DelimToken::Bracket))?;
+        Ok(Attribute {
+            style,
+            path,
+            tokens,
+            is_sugared_doc: false,
+            span: lo.to(self.prev_span),
+        })
+    }
+
+    // the `#[doc = "..."]` a doc comment stands for, as the lexer desugars
+    // it
+    fn doc_comment_attr(&mut self, name: IntKey, sp: Span) -> Attribute {
+        let comment = TokenTree::Token(sp, Token::DocComment(name));
+        let style = doc_comment_style(self.interner.get(name));
+        let tokens = match desugar_doc_comments(&[comment], self.interner).pop()
+        {
+            Some(TokenTree::Delimited(_, DelimToken::Bracket, mut inner)) => {
+                inner.remove(0);
+                inner
+            }
+            tree => unreachable!("not a doc attribute: {:?}", tree),
+        };
+        let doc = Ident::new(self.interner.insert("doc"), sp);
+        Attribute {
+            style,
+            path: Path::from_ident(doc),
+            tokens,
+            is_sugared_doc: true,
+            span: sp,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lexer::interner::StringInterner;
+    use lexer::token::Lit;
+
+    #[test]
+    fn test_attributes() {
+        let mut interner = StringInterner::empty();
+        let src = "#![a] //! b\n#[c(d)] /// e\nx";
+        let mut p = Parser::from_source_str(src, &mut interner).unwrap();
+        let inner = p.parse_inner_attributes().unwrap();
+        let outer = p.parse_outer_attributes().unwrap();
+        assert!(p.check_ident());
+        assert_eq!(inner.len(), 2);
+        assert_eq!(outer.len(), 2);
+        assert_eq!(outer[0].span, Span::new(12, 19));
+        assert_eq!(outer[0].tokens.len(), 1);
+        assert!(!outer[0].is_sugared_doc);
+        assert!(inner[1].is_sugared_doc);
+        assert_eq!(inner[1].style, AttrStyle::Inner);
+        let lit = match outer[1].tokens[..] {
+            [TokenTree::Token(_, Token::Eq), TokenTree::Token(_, lit)] => lit,
+            ref tokens => panic!("{:?}", tokens),
+        };
+        match lit {
+            Token::Literal(Lit::StrRaw(text, 0), None) => {
+                assert_eq!(interner.get(text), " e")
+            }
+            lit => panic!("{:?}", lit),
+        }
+    }
+
+    #[test]
+    fn test_misplaced_attributes() {
+        let mut interner = StringInterner::empty();
+        let mut p = Parser::from_source_str("#![a]", &mut interner).unwrap();
+        assert!(p.parse_outer_attributes().is_err());
+        let mut p = Parser::from_source_str("/// a\nx", &mut interner).unwrap();
+        assert_eq!(p.parse_inner_attributes().unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,20 @@
+//! Which expressions end a statement by themselves, mirrors rustc's
+//! `parse::classify`.
+
+use crate::ast;
+
+/// Whether `e`, as a statement, needs a `;` to be followed by another one.
+/// Those that don't, like `if` and `match`, end the statement where they
+/// end, so `if x {} - 1` is two statements.
+pub fn expr_requires_semi_to_be_stmt(e: &ast::Expr) -> bool {
+    match e.kind {
+        ast::ExprKind::If(..)
+        | ast::ExprKind::Match(..)
+        | ast::ExprKind::Block(..)
+        | ast::ExprKind::While(..)
+        | ast::ExprKind::Loop(..)
+        | ast::ExprKind::ForLoop(..)
+        | ast::ExprKind::TryBlock(..) => false,
+        _ => true,
+    }
+}
@@ -0,0 +1,1815 @@
+//! Expressions, parsed by precedence climbing over the binary operators of
+//! `AssocOp`, mirrors rustc's `parse::parser::expr` and
+//! `util::parser`.
+
+use super::{
+    expr_requires_semi_to_be_stmt, PResult, Parser, PathStyle, Restrictions,
+    TokenType,
+};
+use crate::ast::{
+    self, Arm, Attribute, BinOpKind, BlockCheckMode, CaptureBy, Expr, ExprKind,
+    Field, FnDecl, FunctionRetTy, Ident, IsAsync, Label, Lit, LitKind, Mac,
+    Movability, Param, RangeLimits, TyKind, UnOp, P,
+};
+use lexer::interner::StringInterner;
+use lexer::token::{self, BinOpToken, DelimToken, Span, Token};
+use lexer::IntKey;
+
+/// An operator that takes a left and a right operand.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AssocOp {
+    /// `+`
+    Add,
+    /// `-`
+    Subtract,
+    /// `*`
+    Multiply,
+    /// `/`
+    Divide,
+    /// `%`
+    Modulus,
+    /// `&&`
+    LAnd,
+    /// `||`
+    LOr,
+    /// `^`
+    BitXor,
+    /// `&`
+    BitAnd,
+    /// `|`
+    BitOr,
+    /// `<<`
+    ShiftLeft,
+    /// `>>`
+    ShiftRight,
+    /// `==`
+    Equal,
+    /// `<`
+    Less,
+    /// `<=`
+    LessEqual,
+    /// `!=`
+    NotEqual,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterEqual,
+    /// `=`
+    Assign,
+    /// `+=`, `-=` and the like
+    AssignOp(BinOpToken),
+    /// `as`
+    As,
+    /// `..`
+    DotDot,
+    /// `..=`
+    DotDotEq,
+    /// `:`, type ascription
+    Colon,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Fixity {
+    /// `a + b + c` is `(a + b) + c`
+    Left,
+    /// `a = b = c` is `a = (b = c)`
+    Right,
+    /// `a..b..c` is an error
+    None,
+}
+
+impl AssocOp {
+    pub fn from_token(
+        tok: &Token,
+        interner: &StringInterner,
+    ) -> Option<AssocOp> {
+        let op = match *tok {
+            Token::BinOpEq(k) => AssocOp::AssignOp(k),
+            Token::Lt => AssocOp::Less,
+            Token::Le => AssocOp::LessEqual,
+            Token::Ge => AssocOp::GreaterEqual,
+            Token::Gt => AssocOp::Greater,
+            Token::EqEq => AssocOp::Equal,
+            Token::Ne => AssocOp::NotEqual,
+            Token::AndAnd => AssocOp::LAnd,
+            Token::OrOr => AssocOp::LOr,
+            Token::Eq => AssocOp::Assign,
+            Token::BinOp(BinOpToken::Star) => AssocOp::Multiply,
+            Token::BinOp(BinOpToken::Slash) => AssocOp::Divide,
+            Token::BinOp(BinOpToken::Percent) => AssocOp::Modulus,
+            Token::BinOp(BinOpToken::Plus) => AssocOp::Add,
+            Token::BinOp(BinOpToken::Minus) => AssocOp::Subtract,
+            Token::BinOp(BinOpToken::Shl) => AssocOp::ShiftLeft,
+            Token::BinOp(BinOpToken::Shr) => AssocOp::ShiftRight,
+            Token::BinOp(BinOpToken::And) => AssocOp::BitAnd,
+            Token::BinOp(BinOpToken::Caret) => AssocOp::BitXor,
+            Token::BinOp(BinOpToken::Or) => AssocOp::BitOr,
+            Token::DotDot => AssocOp::DotDot,
+            Token::DotDotEq => AssocOp::DotDotEq,
+            Token::Colon => AssocOp::Colon,
+            Token::Ident(name, false) if interner.get(name) == "as" => {
+                AssocOp::As
+            }
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    /// How tightly the operator binds, higher binds tighter.
+    pub fn precedence(self) -> usize {
+        match self {
+            AssocOp::As | AssocOp::Colon => 14,
+            AssocOp::Multiply | AssocOp::Divide | AssocOp::Modulus => 13,
+            AssocOp::Add | AssocOp::Subtract => 12,
+            AssocOp::ShiftLeft | AssocOp::ShiftRight => 11,
+            AssocOp::BitAnd => 10,
+            AssocOp::BitXor => 9,
+            AssocOp::BitOr => 8,
+            AssocOp::Less
+            | AssocOp::Greater
+            | AssocOp::LessEqual
+            | AssocOp::GreaterEqual
+            | AssocOp::Equal
+            | AssocOp::NotEqual => 7,
+            AssocOp::LAnd => 6,
+            AssocOp::LOr => 5,
+            AssocOp::DotDot | AssocOp::DotDotEq => 4,
+            AssocOp::Assign | AssocOp::AssignOp(_) => 2,
+        }
+    }
+
+    pub fn fixity(self) -> Fixity {
+        match self {
+            AssocOp::Assign | AssocOp::AssignOp(_) => Fixity::Right,
+            AssocOp::DotDot | AssocOp::DotDotEq => Fixity::None,
+            _ => Fixity::Left,
+        }
+    }
+
+    pub fn is_comparison(self) -> bool {
+        match self {
+            AssocOp::Less
+            | AssocOp::Greater
+            | AssocOp::LessEqual
+            | AssocOp::GreaterEqual
+            | AssocOp::Equal
+            | AssocOp::NotEqual => true,
+            _ => false,
+        }
+    }
+
+    /// The `BinOpKind` of the operator, if it is one.
+    pub fn to_ast_binop(self) -> Option<BinOpKind> {
+        let op = match self {
+            AssocOp::Less => BinOpKind::Lt,
+            AssocOp::Greater => BinOpKind::Gt,
+            AssocOp::LessEqual => BinOpKind::Le,
+            AssocOp::GreaterEqual => BinOpKind::Ge,
+            AssocOp::Equal => BinOpKind::Eq,
+            AssocOp::NotEqual => BinOpKind::Ne,
+            AssocOp::Multiply => BinOpKind::Mul,
+            AssocOp::Divide => BinOpKind::Div,
+            AssocOp::Modulus => BinOpKind::Rem,
+            AssocOp::Add => BinOpKind::Add,
+            AssocOp::Subtract => BinOpKind::Sub,
+            AssocOp::ShiftLeft => BinOpKind::Shl,
+            AssocOp::ShiftRight => BinOpKind::Shr,
+            AssocOp::BitAnd => BinOpKind::BitAnd,
+            AssocOp::BitXor => BinOpKind::BitXor,
+            AssocOp::BitOr => BinOpKind::BitOr,
+            AssocOp::LAnd => BinOpKind::And,
+            AssocOp::LOr => BinOpKind::Or,
+            _ => return None,
+        };
+        Some(op)
+    }
+}
+
+fn binop_token_to_ast(op: BinOpToken) -> BinOpKind {
+    match op {
+        BinOpToken::Plus => BinOpKind::Add,
+        BinOpToken::Minus => BinOpKind::Sub,
+        BinOpToken::Star => BinOpKind::Mul,
+        BinOpToken::Slash => BinOpKind::Div,
+        BinOpToken::Percent => BinOpKind::Rem,
+        BinOpToken::Caret => BinOpKind::BitXor,
+        BinOpToken::And => BinOpKind::BitAnd,
+        BinOpToken::Or => BinOpKind::BitOr,
+        BinOpToken::Shl => BinOpKind::Shl,
+        BinOpToken::Shr => BinOpKind::Shr,
+    }
+}
+
+/// The left operand of the expression being parsed, so far.
+pub(super) enum LhsExpr {
+    Unparsed,
+    AfterAttributes(Vec<Attribute>),
+    Parsed(P<Expr>),
+}
+
+/// The keywords that may start an expression.
+const EXPR_KEYWORDS: &[&str] = &[
+    "async", "do", "box", "break", "continue", "false", "for", "if", "let",
+    "loop", "match", "move", "return", "true", "unsafe", "while", "yield",
+    "static", "try", "self", "Self", "super", "crate",
+];
+
+impl<'a> Parser<'a> {
+    pub fn token_can_begin_expr(&self, tok: &Token) -> bool {
+        match *tok {
+            Token::Ident(name, raw) => {
+                let name = self.interner.get(name);
+                raw || !super::is_reserved(name)
+                    || EXPR_KEYWORDS.contains(&name)
+            }
+            Token::OpenDelim(_)
+            | Token::Literal(..)
+            | Token::Not
+            | Token::BinOp(BinOpToken::Minus)
+            | Token::BinOp(BinOpToken::Star)
+            | Token::BinOp(BinOpToken::Or)
+            | Token::OrOr
+            | Token::BinOp(BinOpToken::And)
+            | Token::AndAnd
+            | Token::DotDot
+            | Token::DotDotDot
+            | Token::DotDotEq
+            | Token::Lt
+            | Token::BinOp(BinOpToken::Shl)
+            | Token::ModSep
+            | Token::Lifetime(_)
+            | Token::Pound => true,
+            _ => false,
+        }
+    }
+
+    pub fn parse_expr(&mut self) -> PResult<P<Expr>> {
+        self.parse_expr_res(Restrictions::NONE, None)
+    }
+
+    pub(super) fn parse_expr_res(
+        &mut self,
+        r: Restrictions,
+        attrs: Option<Vec<Attribute>>,
+    ) -> PResult<P<Expr>> {
+        self.with_res(r, |this| {
+            let lhs = match attrs {
+                Some(attrs) => LhsExpr::AfterAttributes(attrs),
+                None => LhsExpr::Unparsed,
+            };
+            this.parse_assoc_expr_with(0, lhs)
+        })
+    }
+
+    /// The condition of an `if` or `while`, or the scrutinee of a `match`,
+    /// where `{` starts the block rather than a struct literal.
+    fn parse_cond_expr(&mut self) -> PResult<P<Expr>> {
+        self.parse_expr_res(Restrictions::NO_STRUCT_LITERAL, None)
+    }
+
+    /// Whether `e` is a whole statement, as the restrictions see it.
+    fn expr_is_complete(&self, e: &Expr) -> bool {
+        self.restrictions.contains(Restrictions::STMT_EXPR)
+            && !expr_requires_semi_to_be_stmt(e)
+    }
+
+    /// Parses operators binding at least as tight as `min_prec`, with `lhs`
+    /// as the left operand.
+    pub(super) fn parse_assoc_expr_with(
+        &mut self,
+        min_prec: usize,
+        lhs: LhsExpr,
+    ) -> PResult<P<Expr>> {
+        let mut lhs = match lhs {
+            LhsExpr::Parsed(expr) => expr,
+            LhsExpr::AfterAttributes(attrs) => {
+                if self.is_range_separator() {
+                    return self.parse_prefix_range_expr(Some(attrs));
+                }
+                self.parse_prefix_expr(Some(attrs))?
+            }
+            LhsExpr::Unparsed => {
+                if self.is_range_separator() {
+                    return self.parse_prefix_range_expr(None);
+                }
+                self.parse_prefix_expr(None)?
+            }
+        };
+        if self.expr_is_complete(&lhs) {
+            // `{ 1 } - 1` is a block statement and `-1`
+            return Ok(lhs);
+        }
+        self.expected_tokens.push(TokenType::Operator);
+        while let Some(op) = AssocOp::from_token(&self.token.tok, self.interner)
+        {
+            let prec = op.precedence();
+            if prec < min_prec {
+                break;
+            }
+            let lhs_span = lhs.span;
+            let op_span = self.token.sp;
+            if op.is_comparison() {
+                self.check_no_chained_comparison(&lhs)?;
+            }
+            self.bump();
+            match op {
+                AssocOp::As | AssocOp::Colon => {
+                    let rhs = self.parse_ty_no_plus()?;
+                    let span = lhs_span.to(rhs.span);
+                    let kind = if op == AssocOp::As {
+                        ExprKind::Cast(lhs, rhs)
+                    } else {
+                        ExprKind::Type(lhs, rhs)
+                    };
+                    lhs = self.mk_expr(span, kind, vec![]);
+                    continue;
+                }
+                AssocOp::DotDot | AssocOp::DotDotEq => {
+                    // ranges are not associative, and may have no end
+                    let rhs = if self.is_at_start_of_range_notation_rhs() {
+                        let lhs = LhsExpr::Unparsed;
+                        Some(self.parse_assoc_expr_with(prec + 1, lhs)?)
+                    } else {
+                        None
+                    };
+                    let hi = rhs.as_ref().map_or(op_span, |rhs| rhs.span);
+                    let limits = self.range_limits(op, op_span, &rhs)?;
+                    let kind = ExprKind::Range(Some(lhs), rhs, limits);
+                    lhs = self.mk_expr(lhs_span.to(hi), kind, vec![]);
+                    break;
+                }
+                _ => {}
+            }
+            let rhs_prec = match op.fixity() {
+                Fixity::Right => prec,
+                Fixity::Left | Fixity::None => prec + 1,
+            };
+            let r = self.restrictions.without(Restrictions::STMT_EXPR);
+            let rhs = self.with_res(r, |this| {
+                this.parse_assoc_expr_with(rhs_prec, LhsExpr::Unparsed)
+            })?;
+            let span = lhs_span.to(rhs.span);
+            let kind = match op {
+                AssocOp::Assign => ExprKind::Assign(lhs, rhs),
+                AssocOp::AssignOp(k) => {
+                    let op = ast::respan(op_span, binop_token_to_ast(k));
+                    ExprKind::AssignOp(op, lhs, rhs)
+                }
+                _ => {
+                    let op = op.to_ast_binop().expect("a binary operator");
+                    ExprKind::Binary(ast::respan(op_span, op), lhs, rhs)
+                }
+            };
+            lhs = self.mk_expr(span, kind, vec![]);
+        }
+        Ok(lhs)
+    }
+
+    // `a < b < c` needs parentheses
+    fn check_no_chained_comparison(&mut self, lhs: &Expr) -> PResult<()> {
+        match lhs.kind {
+            ExprKind::Binary(op, ..) if op.node.is_comparison() => {
+                let msg = "comparison operators cannot be chained";
+                self.fatal(op.span.to(self.token.sp), msg.to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn is_range_separator(&self) -> bool {
+        self.token.tok == Token::DotDot || self.token.tok == Token::DotDotEq
+    }
+
+    fn is_at_start_of_range_notation_rhs(&self) -> bool {
+        if !self.token_can_begin_expr(&self.token.tok) {
+            return false;
+        }
+        // `for x in a.. {}`
+        self.token.tok != Token::OpenDelim(DelimToken::Brace)
+            || !self.restrictions.contains(Restrictions::NO_STRUCT_LITERAL)
+    }
+
+    fn range_limits(
+        &self,
+        op: AssocOp,
+        op_span: Span,
+        end: &Option<P<Expr>>,
+    ) -> PResult<RangeLimits> {
+        if op == AssocOp::DotDot {
+            Ok(RangeLimits::HalfOpen)
+        } else if end.is_none() {
+            let msg = "inclusive range with no end".to_string();
+            self.fatal(op_span, msg)
+        } else {
+            Ok(RangeLimits::Closed)
+        }
+    }
+
+    // `..b`, `..=b` or `..`
+    fn parse_prefix_range_expr(
+        &mut self,
+        attrs: Option<Vec<Attribute>>,
+    ) -> PResult<P<Expr>> {
+        let attrs = self.parse_or_use_outer_attributes(attrs)?;
+        let lo = self.token.sp;
+        let op = if self.token.tok == Token::DotDot {
+            AssocOp::DotDot
+        } else {
+            AssocOp::DotDotEq
+        };
+        self.bump();
+        let end = if self.is_at_start_of_range_notation_rhs() {
+            let prec = op.precedence() + 1;
+            Some(self.parse_assoc_expr_with(prec, LhsExpr::Unparsed)?)
+        } else {
+            None
+        };
+        let hi = end.as_ref().map_or(lo, |end| end.span);
+        let limits = self.range_limits(op, lo, &end)?;
+        Ok(self.mk_expr(lo.to(hi), ExprKind::Range(None, end, limits), attrs))
+    }
+
+    fn parse_or_use_outer_attributes(
+        &mut self,
+        attrs: Option<Vec<Attribute>>,
+    ) -> PResult<Vec<Attribute>> {
+        match attrs {
+            Some(attrs) => Ok(attrs),
+            None => self.parse_outer_attributes(),
+        }
+    }
+
+    /// A unary expression: `!e`, `-e`, `*e`, `&e`, `&mut e` and `box e`.
+    fn parse_prefix_expr(
+        &mut self,
+        attrs: Option<Vec<Attribute>>,
+    ) -> PResult<P<Expr>> {
+        let attrs = self.parse_or_use_outer_attributes(attrs)?;
+        let lo = self.token.sp;
+        let kind = match self.token.tok {
+            Token::Not => {
+                self.bump();
+                ExprKind::Unary(UnOp::Not, self.parse_prefix_expr(None)?)
+            }
+            Token::BinOp(BinOpToken::Minus) => {
+                self.bump();
+                ExprKind::Unary(UnOp::Neg, self.parse_prefix_expr(None)?)
+            }
+            Token::BinOp(BinOpToken::Star) => {
+                self.bump();
+                ExprKind::Unary(UnOp::Deref, self.parse_prefix_expr(None)?)
+            }
+            Token::BinOp(BinOpToken::And) | Token::AndAnd => {
+                // `&&e` is `& &e`
+                self.expect_and()?;
+                let m = self.parse_mutability();
+                ExprKind::AddrOf(m, self.parse_prefix_expr(None)?)
+            }
+            _ if self.is_keyword("box") => {
+                self.bump();
+                ExprKind::Box(self.parse_prefix_expr(None)?)
+            }
+            _ => return self.parse_dot_or_call_expr(attrs),
+        };
+        Ok(self.mk_expr(lo.to(self.prev_span), kind, attrs))
+    }
+
+    fn parse_dot_or_call_expr(
+        &mut self,
+        attrs: Vec<Attribute>,
+    ) -> PResult<P<Expr>> {
+        let b = self.parse_bottom_expr()?;
+        self.parse_dot_or_call_expr_with(b, attrs)
+    }
+
+    /// The postfix operators applied to `e`: `?`, `.f`, `.f()`, `.await`,
+    /// calls and indexing. `attrs` go before those of `e`.
+    pub(super) fn parse_dot_or_call_expr_with(
+        &mut self,
+        e: P<Expr>,
+        mut attrs: Vec<Attribute>,
+    ) -> PResult<P<Expr>> {
+        let mut e = self.parse_dot_or_call_expr_with_(e)?;
+        if !attrs.is_empty() {
+            attrs.append(&mut e.attrs);
+            e.attrs = attrs;
+        }
+        Ok(e)
+    }
+
+    fn parse_dot_or_call_expr_with_(
+        &mut self,
+        e0: P<Expr>,
+    ) -> PResult<P<Expr>> {
+        let lo = e0.span;
+        let mut e = e0;
+        loop {
+            if self.eat(&Token::Question) {
+                e = self.mk_expr(
+                    lo.to(self.prev_span),
+                    ExprKind::Try(e),
+                    vec![],
+                );
+                continue;
+            }
+            if self.eat(&Token::Dot) {
+                e = self.parse_dot_suffix(e, lo)?;
+                continue;
+            }
+            // `match x {}.f()` is fine, but `{}(1)` is two statements
+            if self.expr_is_complete(&e) {
+                return Ok(e);
+            }
+            if self.check(&Token::OpenDelim(DelimToken::Paren)) {
+                let args = self.parse_paren_expr_seq()?;
+                let span = lo.to(self.prev_span);
+                e = self.mk_expr(span, ExprKind::Call(e, args), vec![]);
+            } else if self.eat(&Token::OpenDelim(DelimToken::Bracket)) {
+                let idx = self.parse_expr()?;
+                self.expect(&Token::CloseDelim(DelimToken::Bracket))?;
+                let span = lo.to(self.prev_span);
+                e = self.mk_expr(span, ExprKind::Index(e, idx), vec![]);
+            } else {
+                return Ok(e);
+            }
+        }
+    }
+
+    // what follows the `.` of `e.`
+    fn parse_dot_suffix(&mut self, e: P<Expr>, lo: Span) -> PResult<P<Expr>> {
+        match self.token.tok {
+            Token::Literal(token::Lit::Integer(name), None) => {
+                // `t.0`
+                let ident = Ident::new(name, self.token.sp);
+                self.bump();
+                return Ok(self.mk_expr(
+                    lo.to(ident.span),
+                    ExprKind::Field(e, ident),
+                    vec![],
+                ));
+            }
+            Token::Literal(token::Lit::Float(name), None) => {
+                // `t.0.1`, lexed as `t`, `.` and `0.1`
+                return self.parse_tuple_field_pair(e, lo, name);
+            }
+            Token::Literal(..) => {
+                let msg = format!(
+                    "unexpected token: {}, expected a field name",
+                    self.token_descr()
+                );
+                return self.fatal(self.token.sp, msg);
+            }
+            _ => {}
+        }
+        if self.eat_keyword("await") {
+            let span = lo.to(self.prev_span);
+            return Ok(self.mk_expr(span, ExprKind::Await(e), vec![]));
+        }
+        let ident = self.parse_ident()?;
+        let mut segment = ast::PathSegment {
+            ident,
+            id: self.next_node_id(),
+            args: None,
+        };
+        if self.eat(&Token::ModSep) {
+            // `x.f::<T>()`
+            let args_lo = self.token.sp;
+            if !self.eat_lt() {
+                return self.unexpected();
+            }
+            let (args, constraints) = self.parse_generic_args()?;
+            self.expect_gt()?;
+            segment.args = Some(P(ast::GenericArgs::AngleBracketed(
+                ast::AngleBracketedArgs {
+                    span: args_lo.to(self.prev_span),
+                    args,
+                    constraints,
+                },
+            )));
+        }
+        if self.check(&Token::OpenDelim(DelimToken::Paren)) {
+            let mut args = self.parse_paren_expr_seq()?;
+            args.insert(0, e);
+            let span = lo.to(self.prev_span);
+            Ok(self.mk_expr(span, ExprKind::MethodCall(segment, args), vec![]))
+        } else if segment.args.is_some() {
+            let msg = "field expressions may not have generic arguments";
+            self.fatal(self.prev_span, msg.to_string())
+        } else {
+            let span = lo.to(self.prev_span);
+            Ok(self.mk_expr(span, ExprKind::Field(e, ident), vec![]))
+        }
+    }
+
+    fn parse_tuple_field_pair(
+        &mut self,
+        e: P<Expr>,
+        lo: Span,
+        name: IntKey,
+    ) -> PResult<P<Expr>> {
+        let sp = self.token.sp;
+        let text = self.interner.get(name).to_string();
+        let mut parts = text.split('.');
+        let (a, b) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(a), Some(b), None)
+                if !a.is_empty()
+                    && !b.is_empty()
+                    && a.bytes().all(|c| c.is_ascii_digit())
+                    && b.bytes().all(|c| c.is_ascii_digit()) =>
+            {
+                (a, b)
+            }
+            _ => {
+                let msg = format!(
+                    "unexpected token: {}, expected a field name",
+                    self.token_descr()
+                );
+                return self.fatal(sp, msg);
+            }
+        };
+        self.bump();
+        let a_sp = Span::new(sp.lo, sp.lo + a.len());
+        let b_sp = Span::new(sp.hi - b.len(), sp.hi);
+        let a = Ident::new(self.interner.insert(a), a_sp);
+        let b = Ident::new(self.interner.insert(b), b_sp);
+        let e = self.mk_expr(lo.to(a_sp), ExprKind::Field(e, a), vec![]);
+        Ok(self.mk_expr(lo.to(b_sp), ExprKind::Field(e, b), vec![]))
+    }
+
+    // `(a, b)`, as the args of a call
+    fn parse_paren_expr_seq(&mut self) -> PResult<Vec<P<Expr>>> {
+        self.expect(&Token::OpenDelim(DelimToken::Paren))?;
+        let close = Token::CloseDelim(DelimToken::Paren);
+        self.parse_seq_to_end(&close, |p| p.parse_expr())
+    }
+
+    /// An expression without operators, or one that starts with a keyword.
+    fn parse_bottom_expr(&mut self) -> PResult<P<Expr>> {
+        let lo = self.token.sp;
+        let mut attrs = vec![];
+        let tok = self.token.tok;
+        let kind = match tok {
+            Token::OpenDelim(DelimToken::Paren) => {
+                // `(e)`, `(e,)`, `()` or `(a, b)`
+                self.bump();
+                attrs.extend(self.parse_inner_attributes()?);
+                let mut es = vec![];
+                let mut trailing_comma = false;
+                while !self.check(&Token::CloseDelim(DelimToken::Paren)) {
+                    es.push(self.parse_expr()?);
+                    trailing_comma = self.eat(&Token::Comma);
+                    if !trailing_comma {
+                        break;
+                    }
+                }
+                self.expect(&Token::CloseDelim(DelimToken::Paren))?;
+                if es.len() == 1 && !trailing_comma {
+                    ExprKind::Paren(es.pop().unwrap())
+                } else {
+                    ExprKind::Tup(es)
+                }
+            }
+            Token::OpenDelim(DelimToken::Brace) => {
+                return self.parse_block_expr(
+                    None,
+                    lo,
+                    BlockCheckMode::Default,
+                    attrs,
+                );
+            }
+            Token::BinOp(BinOpToken::Or) | Token::OrOr => {
+                return self.parse_closure_expr(attrs);
+            }
+            Token::OpenDelim(DelimToken::Bracket) => {
+                // `[a, b]` or `[e; n]`
+                self.bump();
+                attrs.extend(self.parse_inner_attributes()?);
+                let close = Token::CloseDelim(DelimToken::Bracket);
+                if self.eat(&close) {
+                    ExprKind::Array(vec![])
+                } else {
+                    let first = self.parse_expr()?;
+                    if self.eat(&Token::Semi) {
+                        let count = ast::AnonConst {
+                            value: self.parse_expr()?,
+                            id: self.next_node_id(),
+                        };
+                        self.expect(&close)?;
+                        ExprKind::Repeat(first, count)
+                    } else if self.eat(&Token::Comma) {
+                        let mut es = vec![first];
+                        es.extend(
+                            self.parse_seq_to_end(&close, |p| p.parse_expr())?,
+                        );
+                        ExprKind::Array(es)
+                    } else {
+                        self.expect(&close)?;
+                        ExprKind::Array(vec![first])
+                    }
+                }
+            }
+            Token::Literal(..) => ExprKind::Lit(self.parse_lit()?),
+            Token::Lifetime(name) => {
+                // `'a: loop {}`
+                let label = Label {
+                    ident: Ident::new(name, self.token.sp),
+                };
+                self.bump();
+                self.expect(&Token::Colon)?;
+                return self.parse_labeled_expr(label, lo, attrs);
+            }
+            _ if self.is_keyword("true") || self.is_keyword("false") => {
+                ExprKind::Lit(self.parse_lit()?)
+            }
+            _ if self.is_keyword("move")
+                || self.is_keyword("static")
+                || self.is_keyword("async") && !self.is_async_block() =>
+            {
+                return self.parse_closure_expr(attrs);
+            }
+            _ if self.eat_keyword("if") => {
+                return self.parse_if_expr(attrs);
+            }
+            _ if self.eat_keyword("for") => {
+                return self.parse_for_expr(None, lo, attrs);
+            }
+            _ if self.eat_keyword("while") => {
+                return self.parse_while_expr(None, lo, attrs);
+            }
+            _ if self.eat_keyword("loop") => {
+                return self.parse_loop_expr(None, lo, attrs);
+            }
+            _ if self.eat_keyword("match") => {
+                return self.parse_match_expr(attrs);
+            }
+            _ if self.eat_keyword("unsafe") => {
+                return self.parse_block_expr(
+                    None,
+                    lo,
+                    BlockCheckMode::Unsafe,
+                    attrs,
+                );
+            }
+            _ if self.is_keyword("try")
+                && self.look_ahead(1, |t| {
+                    *t == Token::OpenDelim(DelimToken::Brace)
+                }) =>
+            {
+                self.bump();
+                let block = self.parse_block_with_attrs(&mut attrs)?;
+                ExprKind::TryBlock(block)
+            }
+            _ if self.eat_keyword("async") => {
+                // `async {}` or `async move {}`
+                let capture = if self.eat_keyword("move") {
+                    CaptureBy::Value
+                } else {
+                    CaptureBy::Ref
+                };
+                let block = self.parse_block_with_attrs(&mut attrs)?;
+                ExprKind::Async(capture, block)
+            }
+            _ if self.eat_keyword("return") => {
+                ExprKind::Ret(self.parse_opt_expr_operand()?)
+            }
+            _ if self.eat_keyword("yield") => {
+                ExprKind::Yield(self.parse_opt_expr_operand()?)
+            }
+            _ if self.eat_keyword("break") => {
+                let label = self.parse_opt_label();
+                // `if break {}` breaks without a value
+                let value = if self.token_can_begin_expr(&self.token.tok)
+                    && !(self.token.tok == Token::OpenDelim(DelimToken::Brace)
+                        && self
+                            .restrictions
+                            .contains(Restrictions::NO_STRUCT_LITERAL))
+                {
+                    Some(self.parse_expr()?)
+                } else {
+                    None
+                };
+                ExprKind::Break(label, value)
+            }
+            _ if self.eat_keyword("continue") => {
+                ExprKind::Continue(self.parse_opt_label())
+            }
+            _ if self.eat_keyword("let") => {
+                return self.parse_let_expr(lo, attrs);
+            }
+            Token::Lt | Token::BinOp(BinOpToken::Shl) => {
+                // `<T as Trait>::f` or `<T>::f`
+                self.eat_lt();
+                let (qself, path) = self.parse_qpath(PathStyle::Expr)?;
+                ExprKind::Path(Some(qself), path)
+            }
+            _ if self.check_path() => {
+                let path = self.parse_path(PathStyle::Expr)?;
+                return self.parse_path_start_expr(lo, path, attrs);
+            }
+            _ => {
+                let msg = format!(
+                    "expected expression, found {}",
+                    self.token_descr()
+                );
+                return self.fatal(self.token.sp, msg);
+            }
+        };
+        Ok(self.mk_expr(lo.to(self.prev_span), kind, attrs))
+    }
+
+    /// What follows a path at the start of an expression: a macro call, a
+    /// struct literal, or nothing.
+    pub(super) fn parse_path_start_expr(
+        &mut self,
+        lo: Span,
+        path: ast::Path,
+        attrs: Vec<Attribute>,
+    ) -> PResult<P<Expr>> {
+        if self.eat(&Token::Not) {
+            let (delim, tts) = self.parse_delim_tts()?;
+            let span = lo.to(self.prev_span);
+            let mac = Mac {
+                path,
+                delim,
+                tts,
+                span,
+            };
+            return Ok(self.mk_expr(span, ExprKind::Mac(mac), attrs));
+        }
+        if self.check(&Token::OpenDelim(DelimToken::Brace))
+            && !self.restrictions.contains(Restrictions::NO_STRUCT_LITERAL)
+        {
+            return self.parse_struct_expr(lo, path, attrs);
+        }
+        let span = lo.to(self.prev_span);
+        Ok(self.mk_expr(span, ExprKind::Path(None, path), attrs))
+    }
+
+    fn is_async_block(&self) -> bool {
+        self.is_keyword("async")
+            && (self
+                .look_ahead(1, |t| *t == Token::OpenDelim(DelimToken::Brace))
+                || self.look_ahead(1, |t| self.token_is_keyword(t, "move"))
+                    && self.look_ahead(2, |t| {
+                        *t == Token::OpenDelim(DelimToken::Brace)
+                    }))
+    }
+
+    fn parse_opt_label(&mut self) -> Option<Label> {
+        match self.token.tok {
+            Token::Lifetime(name) => {
+                let ident = Ident::new(name, self.token.sp);
+                self.bump();
+                Some(Label { ident })
+            }
+            _ => None,
+        }
+    }
+
+    // the operand of `return` or `yield`, if there is one
+    fn parse_opt_expr_operand(&mut self) -> PResult<Option<P<Expr>>> {
+        if self.token_can_begin_expr(&self.token.tok) {
+            Ok(Some(self.parse_expr()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn parse_lit(&mut self) -> PResult<Lit> {
+        let kind = match self.token.tok {
+            Token::Literal(lit, suffix) => LitKind::Token(lit, suffix),
+            _ if self.is_keyword("true") => LitKind::Bool(true),
+            _ if self.is_keyword("false") => LitKind::Bool(false),
+            _ => {
+                let msg =
+                    format!("expected literal, found {}", self.token_descr());
+                return self.fatal(self.token.sp, msg);
+            }
+        };
+        let span = self.token.sp;
+        self.bump();
+        Ok(Lit { kind, span })
+    }
+
+    /// A literal, possibly negated, as in patterns and const args.
+    pub fn parse_literal_maybe_minus(&mut self) -> PResult<P<Expr>> {
+        let lo = self.token.sp;
+        let minus = self.eat(&Token::BinOp(BinOpToken::Minus));
+        let lit = self.parse_lit()?;
+        let expr = self.mk_expr(lit.span, ExprKind::Lit(lit), vec![]);
+        if minus {
+            let kind = ExprKind::Unary(UnOp::Neg, expr);
+            Ok(self.mk_expr(lo.to(self.prev_span), kind, vec![]))
+        } else {
+            Ok(expr)
+        }
+    }
+
+    pub fn check_const_arg(&mut self) -> bool {
+        self.expected_tokens.push(TokenType::Const);
+        match self.token.tok {
+            Token::OpenDelim(DelimToken::Brace)
+            | Token::Literal(..)
+            | Token::BinOp(BinOpToken::Minus) => true,
+            _ => self.is_keyword("true") || self.is_keyword("false"),
+        }
+    }
+
+    /// A const generic arg: a block, or a literal.
+    pub fn parse_const_arg(&mut self) -> PResult<P<Expr>> {
+        if self.token.tok == Token::OpenDelim(DelimToken::Brace) {
+            let lo = self.token.sp;
+            self.parse_block_expr(None, lo, BlockCheckMode::Default, vec![])
+        } else {
+            self.parse_literal_maybe_minus()
+        }
+    }
+
+    // `loop`, `while`, `for` or a block, after `'label:`
+    fn parse_labeled_expr(
+        &mut self,
+        label: Label,
+        lo: Span,
+        attrs: Vec<Attribute>,
+    ) -> PResult<P<Expr>> {
+        if self.eat_keyword("while") {
+            return self.parse_while_expr(Some(label), lo, attrs);
+        }
+        if self.eat_keyword("for") {
+            return self.parse_for_expr(Some(label), lo, attrs);
+        }
+        if self.eat_keyword("loop") {
+            return self.parse_loop_expr(Some(label), lo, attrs);
+        }
+        if self.check(&Token::OpenDelim(DelimToken::Brace)) {
+            let rules = BlockCheckMode::Default;
+            return self.parse_block_expr(Some(label), lo, rules, attrs);
+        }
+        let msg = "expected `while`, `for`, `loop` or `{` after a label";
+        self.fatal(self.token.sp, msg.to_string())
+    }
+
+    /// `{ ... }` as an expression, or `unsafe { ... }` after the `unsafe`.
+    pub fn parse_block_expr(
+        &mut self,
+        label: Option<Label>,
+        lo: Span,
+        rules: BlockCheckMode,
+        mut attrs: Vec<Attribute>,
+    ) -> PResult<P<Expr>> {
+        self.expect(&Token::OpenDelim(DelimToken::Brace))?;
+        attrs.extend(self.parse_inner_attributes()?);
+        let block = self.parse_block_tail(lo, rules)?;
+        let span = lo.to(block.span);
+        Ok(self.mk_expr(span, ExprKind::Block(block, label), attrs))
+    }
+
+    /// A block, adding its inner attributes to `attrs`.
+    pub fn parse_block_with_attrs(
+        &mut self,
+        attrs: &mut Vec<Attribute>,
+    ) -> PResult<P<ast::Block>> {
+        let lo = self.token.sp;
+        self.expect(&Token::OpenDelim(DelimToken::Brace))?;
+        attrs.extend(self.parse_inner_attributes()?);
+        self.parse_block_tail(lo, BlockCheckMode::Default)
+    }
+
+    // `|a, b: T| -> R body`, `move |x| x` and the like
+    fn parse_closure_expr(
+        &mut self,
+        attrs: Vec<Attribute>,
+    ) -> PResult<P<Expr>> {
+        let lo = self.token.sp;
+        let movability = if self.eat_keyword("static") {
+            Movability::Static
+        } else {
+            Movability::Movable
+        };
+        let asyncness = if self.eat_keyword("async") {
+            IsAsync::Async
+        } else {
+            IsAsync::NotAsync
+        };
+        let capture = if self.eat_keyword("move") {
+            CaptureBy::Value
+        } else {
+            CaptureBy::Ref
+        };
+        let decl_lo = self.token.sp;
+        let decl = self.parse_fn_block_decl()?;
+        let decl_span = decl_lo.to(self.prev_span);
+        let body = match decl.output {
+            FunctionRetTy::Default(_) => {
+                let r = self.restrictions.without(Restrictions::STMT_EXPR);
+                self.parse_expr_res(r, None)?
+            }
+            FunctionRetTy::Ty(_) => {
+                // with a return type the body must be a block
+                let body_lo = self.token.sp;
+                let rules = BlockCheckMode::Default;
+                self.parse_block_expr(None, body_lo, rules, vec![])?
+            }
+        };
+        let span = lo.to(body.span);
+        let kind = ExprKind::Closure(
+            capture, asyncness, movability, decl, body, decl_span,
+        );
+        Ok(self.mk_expr(span, kind, attrs))
+    }
+
+    // `|a, b| -> R` or `||`
+    fn parse_fn_block_decl(&mut self) -> PResult<P<FnDecl>> {
+        let inputs = if self.eat(&Token::OrOr) {
+            vec![]
+        } else {
+            self.expect(&Token::BinOp(BinOpToken::Or))?;
+            let close = Token::BinOp(BinOpToken::Or);
+            self.parse_seq_to_end(&close, |p| p.parse_fn_block_param())?
+        };
+        let output = self.parse_ret_ty(true)?;
+        Ok(P(FnDecl {
+            inputs,
+            output,
+            c_variadic: false,
+        }))
+    }
+
+    fn parse_fn_block_param(&mut self) -> PResult<Param> {
+        let lo = self.token.sp;
+        let attrs = self.parse_outer_attributes()?;
+        let pat = self.parse_pat()?;
+        let ty = if self.eat(&Token::Colon) {
+            self.parse_ty()?
+        } else {
+            self.mk_ty(self.prev_span, TyKind::Infer)
+        };
+        Ok(Param {
+            attrs,
+            ty,
+            pat,
+            id: self.next_node_id(),
+            span: lo.to(self.prev_span),
+        })
+    }
+
+    // `if cond { ... } else ...`, after the `if`
+    fn parse_if_expr(&mut self, attrs: Vec<Attribute>) -> PResult<P<Expr>> {
+        let lo = self.prev_span;
+        let cond = self.parse_cond_expr()?;
+        let thn = self.parse_block()?;
+        let els = if self.eat_keyword("else") {
+            Some(self.parse_else_expr()?)
+        } else {
+            None
+        };
+        let span = lo.to(self.prev_span);
+        Ok(self.mk_expr(span, ExprKind::If(cond, thn, els), attrs))
+    }
+
+    fn parse_else_expr(&mut self) -> PResult<P<Expr>> {
+        if self.eat_keyword("if") {
+            self.parse_if_expr(vec![])
+        } else {
+            let lo = self.token.sp;
+            let blk = self.parse_block()?;
+            let span = lo.to(blk.span);
+            Ok(self.mk_expr(span, ExprKind::Block(blk, None), vec![]))
+        }
+    }
+
+    // `let pat = e`, after the `let`
+    fn parse_let_expr(
+        &mut self,
+        lo: Span,
+        attrs: Vec<Attribute>,
+    ) -> PResult<P<Expr>> {
+        let pat = self.parse_top_pat()?;
+        self.expect(&Token::Eq)?;
+        // `let a = b && c` is `(let a = b) && c`
+        let prec = AssocOp::LAnd.precedence() + 1;
+        let expr = self.with_res(Restrictions::NO_STRUCT_LITERAL, |this| {
+            this.parse_assoc_expr_with(prec, LhsExpr::Unparsed)
+        })?;
+        let span = lo.to(expr.span);
+        Ok(self.mk_expr(span, ExprKind::Let(pat, expr), attrs))
+    }
+
+    // `for pat in e { ... }`, after the `for`
+    fn parse_for_expr(
+        &mut self,
+        label: Option<Label>,
+        lo: Span,
+        mut attrs: Vec<Attribute>,
+    ) -> PResult<P<Expr>> {
+        let pat = self.parse_top_pat()?;
+        self.expect_keyword("in")?;
+        let expr = self.parse_cond_expr()?;
+        let body = self.parse_block_with_attrs(&mut attrs)?;
+        let span = lo.to(self.prev_span);
+        let kind = ExprKind::ForLoop(pat, expr, body, label);
+        Ok(self.mk_expr(span, kind, attrs))
+    }
+
+    // `while cond { ... }`, after the `while`
+    fn parse_while_expr(
+        &mut self,
+        label: Option<Label>,
+        lo: Span,
+        mut attrs: Vec<Attribute>,
+    ) -> PResult<P<Expr>> {
+        let cond = self.parse_cond_expr()?;
+        let body = self.parse_block_with_attrs(&mut attrs)?;
+        let span = lo.to(self.prev_span);
+        Ok(self.mk_expr(span, ExprKind::While(cond, body, label), attrs))
+    }
+
+    // `loop { ... }`, after the `loop`
+    fn parse_loop_expr(
+        &mut self,
+        label: Option<Label>,
+        lo: Span,
+        mut attrs: Vec<Attribute>,
+    ) -> PResult<P<Expr>> {
+        let body = self.parse_block_with_attrs(&mut attrs)?;
+        let span = lo.to(self.prev_span);
+        Ok(self.mk_expr(span, ExprKind::Loop(body, label), attrs))
+    }
+
+    // `match e { arms }`, after the `match`
+    fn parse_match_expr(
+        &mut self,
+        mut attrs: Vec<Attribute>,
+    ) -> PResult<P<Expr>> {
+        let lo = self.prev_span;
+        let discr = self.parse_cond_expr()?;
+        self.expect(&Token::OpenDelim(DelimToken::Brace))?;
+        attrs.extend(self.parse_inner_attributes()?);
+        let mut arms = vec![];
+        while !self.eat(&Token::CloseDelim(DelimToken::Brace)) {
+            arms.push(self.parse_arm()?);
+        }
+        let span = lo.to(self.prev_span);
+        Ok(self.mk_expr(span, ExprKind::Match(discr, arms), attrs))
+    }
+
+    fn parse_arm(&mut self) -> PResult<Arm> {
+        let attrs = self.parse_outer_attributes()?;
+        let lo = self.token.sp;
+        let pat = self.parse_top_pat()?;
+        let guard = if self.eat_keyword("if") {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        self.expect(&Token::FatArrow)?;
+        let body = self.parse_expr_res(Restrictions::STMT_EXPR, None)?;
+        // a block-like body needs no comma
+        let close = Token::CloseDelim(DelimToken::Brace);
+        if expr_requires_semi_to_be_stmt(&body) && self.token.tok != close {
+            if !self.eat(&Token::Comma) {
+                self.check(&close);
+                return self.unexpected();
+            }
+        } else {
+            self.eat(&Token::Comma);
+        }
+        Ok(Arm {
+            attrs,
+            pat,
+            guard,
+            body,
+            span: lo.to(self.prev_span),
+            id: self.next_node_id(),
+        })
+    }
+
+    // `Path { a: x, b, ..base }`, at the `{`
+    fn parse_struct_expr(
+        &mut self,
+        lo: Span,
+        path: ast::Path,
+        mut attrs: Vec<Attribute>,
+    ) -> PResult<P<Expr>> {
+        self.bump();
+        attrs.extend(self.parse_inner_attributes()?);
+        let close = Token::CloseDelim(DelimToken::Brace);
+        let mut fields = vec![];
+        let mut base = None;
+        while !self.check(&close) {
+            if self.eat(&Token::DotDot) {
+                base = Some(self.parse_expr()?);
+                break;
+            }
+            fields.push(self.parse_field()?);
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+        }
+        self.expect(&close)?;
+        let span = lo.to(self.prev_span);
+        Ok(self.mk_expr(span, ExprKind::Struct(path, fields, base), attrs))
+    }
+
+    // `name: e`, `0: e` or `name`
+    fn parse_field(&mut self) -> PResult<Field> {
+        let attrs = self.parse_outer_attributes()?;
+        let lo = self.token.sp;
+        let (ident, expr, is_shorthand) =
+            if self.look_ahead(1, |t| *t == Token::Colon) {
+                let ident = match self.token.tok {
+                    Token::Literal(token::Lit::Integer(name), None) => {
+                        self.bump();
+                        Ident::new(name, self.prev_span)
+                    }
+                    _ => self.parse_ident()?,
+                };
+                self.bump();
+                (ident, self.parse_expr()?, false)
+            } else {
+                let ident = self.parse_ident()?;
+                let path = ast::Path::from_ident(ident);
+                let kind = ExprKind::Path(None, path);
+                (ident, self.mk_expr(ident.span, kind, vec![]), true)
+            };
+        Ok(Field {
+            ident,
+            expr,
+            span: lo.to(self.prev_span),
+            is_shorthand,
+            attrs,
+            id: self.next_node_id(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{check_err, parse_with};
+    use super::super::{parse_expr_from_source_str, ParseError};
+    use super::*;
+    use crate::ast::{Block, GenericArgs, PatKind, Stmt, StmtKind};
+    use lexer::token::literal_to_string;
+    use quickcheck::TestResult;
+
+    // prints `e` as an s-expression, with types, patterns and literals as
+    // they are in `src`
+    fn sexp(e: &Expr, src: &str, interner: &StringInterner) -> String {
+        let text = |sp: Span| src[sp.lo..sp.hi].to_string();
+        let s = |e: &Expr| sexp(e, src, interner);
+        let opt =
+            |e: &Option<P<Expr>>| e.as_ref().map_or("_".to_string(), |e| s(e));
+        let list = |es: &[P<Expr>]| {
+            es.iter().map(|e| s(e)).collect::<Vec<_>>().join(" ")
+        };
+        let label = |l: &Option<Label>| {
+            l.map_or(String::new(), |l| {
+                format!("{}: ", interner.get(l.ident.name))
+            })
+        };
+        match e.kind {
+            ExprKind::Box(ref e) => format!("(box {})", s(e)),
+            ExprKind::Array(ref es) => format!("[{}]", list(es)),
+            ExprKind::Call(ref f, ref args) => {
+                format!("(call {} {})", s(f), list(args)).replace(" )", ")")
+            }
+            ExprKind::MethodCall(ref seg, ref args) => {
+                let generics = match seg.args {
+                    Some(ref args) => text(args.span()),
+                    None => String::new(),
+                };
+                format!(
+                    "(.{}{}() {})",
+                    interner.get(seg.ident.name),
+                    generics,
+                    list(args)
+                )
+            }
+            ExprKind::Tup(ref es) => format!("(tuple {})", list(es)),
+            ExprKind::Binary(op, ref a, ref b) => {
+                format!("({} {} {})", op.node.to_string(), s(a), s(b))
+            }
+            ExprKind::Unary(op, ref e) => {
+                format!("({} {})", op.to_string(), s(e))
+            }
+            ExprKind::Lit(lit) => match lit.kind {
+                LitKind::Bool(b) => b.to_string(),
+                LitKind::Token(lit, suffix) => {
+                    literal_to_string(lit, suffix, interner)
+                }
+            },
+            ExprKind::Cast(ref e, ref ty) => {
+                format!("(as {} {})", s(e), text(ty.span))
+            }
+            ExprKind::Type(ref e, ref ty) => {
+                format!("(: {} {})", s(e), text(ty.span))
+            }
+            ExprKind::Let(ref pat, ref e) => {
+                format!("(let {} {})", text(pat.span), s(e))
+            }
+            ExprKind::If(ref cond, ref thn, ref els) => format!(
+                "(if {} {} {})",
+                s(cond),
+                block(thn, src, interner),
+                opt(els)
+            ),
+            ExprKind::While(ref cond, ref body, l) => format!(
+                "({}while {} {})",
+                label(&l),
+                s(cond),
+                block(body, src, interner)
+            ),
+            ExprKind::ForLoop(ref pat, ref e, ref body, l) => format!(
+                "({}for {} {} {})",
+                label(&l),
+                text(pat.span),
+                s(e),
+                block(body, src, interner)
+            ),
+            ExprKind::Loop(ref body, l) => {
+                format!("({}loop {})", label(&l), block(body, src, interner))
+            }
+            ExprKind::Match(ref e, ref arms) => {
+                let arms: Vec<_> = arms
+                    .iter()
+                    .map(|arm| {
+                        let guard = match arm.guard {
+                            Some(ref g) => format!(" if {}", s(g)),
+                            None => String::new(),
+                        };
+                        format!(
+                            "({}{} {})",
+                            text(arm.pat.span),
+                            guard,
+                            s(&arm.body)
+                        )
+                    })
+                    .collect();
+                format!("(match {} {})", s(e), arms.join(" "))
+            }
+            ExprKind::Closure(
+                capture,
+                asyncness,
+                movability,
+                ref decl,
+                ref body,
+                sp,
+            ) => {
+                let mut kw = String::new();
+                if movability == Movability::Static {
+                    kw.push_str("static ");
+                }
+                if asyncness == IsAsync::Async {
+                    kw.push_str("async ");
+                }
+                if capture == CaptureBy::Value {
+                    kw.push_str("move ");
+                }
+                let params: Vec<_> =
+                    decl.inputs.iter().map(|p| text(p.span)).collect();
+                assert!(text(sp).starts_with('|'));
+                format!("({}closure ({}) {})", kw, params.join(", "), s(body))
+            }
+            ExprKind::Block(ref blk, l) => {
+                format!("{}{}", label(&l), block(blk, src, interner))
+            }
+            ExprKind::Async(capture, ref blk) => format!(
+                "(async{} {})",
+                if capture == CaptureBy::Value {
+                    " move"
+                } else {
+                    ""
+                },
+                block(blk, src, interner)
+            ),
+            ExprKind::Await(ref e) => format!("(await {})", s(e)),
+            ExprKind::TryBlock(ref blk) => {
+                format!("(try {})", block(blk, src, interner))
+            }
+            ExprKind::Assign(ref a, ref b) => format!("(= {} {})", s(a), s(b)),
+            ExprKind::AssignOp(op, ref a, ref b) => {
+                format!("({}= {} {})", op.node.to_string(), s(a), s(b))
+            }
+            ExprKind::Field(ref e, ident) => {
+                format!("(. {} {})", s(e), interner.get(ident.name))
+            }
+            ExprKind::Index(ref e, ref i) => {
+                format!("(index {} {})", s(e), s(i))
+            }
+            ExprKind::Range(ref a, ref b, limits) => format!(
+                "({} {} {})",
+                if limits == RangeLimits::Closed {
+                    "..="
+                } else {
+                    ".."
+                },
+                opt(a),
+                opt(b)
+            ),
+            ExprKind::Path(_, ref path) => text(path.span),
+            ExprKind::AddrOf(m, ref e) => format!(
+                "(&{} {})",
+                if m == ast::Mutability::Mutable {
+                    "mut"
+                } else {
+                    ""
+                },
+                s(e)
+            ),
+            ExprKind::Break(l, ref e) => {
+                format!("(break {}{})", label(&l), opt(e))
+            }
+            ExprKind::Continue(l) => format!("(continue {})", label(&l)),
+            ExprKind::Ret(ref e) => format!("(return {})", opt(e)),
+            ExprKind::Mac(ref mac) => format!("(mac {})", text(mac.span)),
+            ExprKind::Struct(ref path, ref fields, ref base) => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|f| {
+                        let name = interner.get(f.ident.name);
+                        if f.is_shorthand {
+                            name.to_string()
+                        } else {
+                            format!("({} {})", name, s(&f.expr))
+                        }
+                    })
+                    .collect();
+                format!(
+                    "(struct {} {} ..{})",
+                    text(path.span),
+                    fields.join(" "),
+                    opt(base)
+                )
+            }
+            ExprKind::Repeat(ref e, ref n) => {
+                format!("[{}; {}]", s(e), s(&n.value))
+            }
+            ExprKind::Paren(ref e) => format!("(paren {})", s(e)),
+            ExprKind::Try(ref e) => format!("(? {})", s(e)),
+            ExprKind::Yield(ref e) => format!("(yield {})", opt(e)),
+            ExprKind::Err => "(err)".to_string(),
+        }
+    }
+
+    fn block(blk: &Block, src: &str, interner: &StringInterner) -> String {
+        let stmts: Vec<_> =
+            blk.stmts.iter().map(|st| stmt(st, src, interner)).collect();
+        format!("{{{}}}", stmts.join(" "))
+    }
+
+    fn stmt(st: &Stmt, src: &str, interner: &StringInterner) -> String {
+        match st.kind {
+            StmtKind::Local(ref local) => format!(
+                "(let {} {});",
+                &src[local.pat.span.lo..local.pat.span.hi],
+                local
+                    .init
+                    .as_ref()
+                    .map_or("_".to_string(), |e| sexp(e, src, interner))
+            ),
+            StmtKind::Item(ref item) => format!("(item {:?})", item.ident),
+            StmtKind::Expr(ref e) => sexp(e, src, interner),
+            StmtKind::Semi(ref e) => format!("{};", sexp(e, src, interner)),
+            StmtKind::Mac(ref mac) => {
+                let (ref mac, style, _) = **mac;
+                format!(
+                    "(mac {}){}",
+                    &src[mac.span.lo..mac.span.hi],
+                    if style == ast::MacStmtStyle::Semicolon {
+                        ";"
+                    } else {
+                        ""
+                    }
+                )
+            }
+        }
+    }
+
+    fn parse(src: &str) -> Result<String, ParseError> {
+        parse_with(
+            src,
+            |p| p.parse_expr(),
+            |e, interner| sexp(e, src, interner),
+        )
+    }
+
+    fn check(src: &str, expected: &str) {
+        assert_eq!(parse(src), Ok(expected.to_string()), "{}", src);
+    }
+
+    #[test]
+    fn test_precedence() {
+        check("1 + 2 * 3 - 4", "(- (+ 1 (* 2 3)) 4)");
+        check("a = b = c += d", "(= a (= b (+= c d)))");
+        check(
+            "a || b && c == d | e ^ f & g << h + i * j as u8",
+            "(|| a (&& b (== c (| d (^ e (& f \
+             (<< g (+ h (* i (as j u8))))))))))",
+        );
+        check("x as u8 as u16 + 1", "(+ (as (as x u8) u16) 1)");
+        check("a: T + b", "(+ (: a T) b)");
+        check("-a.b()? * !c", "(* (- (? (.b() a))) (! c))");
+        check("&&mut *a", "(& (&mut (* a)))");
+        check("box a + b", "(+ (box a) b)");
+        check("(a == b) < c", "(< (paren (== a b)) c)");
+        check_err(parse, "a < b > c", "comparison operators cannot be chained");
+        check_err(
+            parse,
+            "a == b == c",
+            "comparison operators cannot be chained",
+        );
+    }
+
+    #[test]
+    fn test_ranges() {
+        check("a..b", "(.. a b)");
+        check("..", "(.. _ _)");
+        check("a..", "(.. a _)");
+        check("..=b", "(..= _ b)");
+        check("a || b..c + d", "(.. (|| a b) (+ c d))");
+        check("x = ..a", "(= x (.. _ a))");
+        check("(a..)", "(paren (.. a _))");
+        check_err(parse, "a..=", "inclusive range with no end");
+        check_err(parse, "..=", "inclusive range with no end");
+    }
+
+    #[test]
+    fn test_postfix() {
+        check("f(a, b,)[0].c", "(. (index (call f a b) 0) c)");
+        check("t.0.1", "(. (. t 0) 1)");
+        check("x.f::<T, 'a>(1)", "(.f<T, 'a>() x 1)");
+        check("Vec::<u8>::new()", "(call Vec::<u8>::new)");
+        check("x.await?", "(? (await x))");
+        check("vec![1, 2].len()", "(.len() (mac vec![1, 2]))");
+        check_err(
+            parse,
+            "x.f::<T>",
+            "field expressions may not have generic arguments",
+        );
+        check_err(
+            parse,
+            "x.0u8",
+            "unexpected token: `0u8`, expected a field name",
+        );
+    }
+
+    #[test]
+    fn test_bottom() {
+        check("()", "(tuple )");
+        check("(1,)", "(tuple 1)");
+        check("(1, 2)", "(tuple 1 2)");
+        check("[]", "[]");
+        check("[1, 2,]", "[1 2]");
+        check("[0; N]", "[0; N]");
+        check("true", "true");
+        check("r#match", "r#match");
+        check("self.x", "(. self x)");
+        check("::std::f", "::std::f");
+        check("S { a, b: 1, ..c }", "(struct S a (b 1) ..c)");
+        check("S { 0: x }", "(struct S (0 x) .._)");
+        check("return", "(return _)");
+        check("break 'a 1", "(break 'a: 1)");
+        check("continue 'a", "(continue 'a: )");
+        check("yield x", "(yield x)");
+        check("unsafe { 1 }", "{1}");
+        check("async move { 1 }", "(async move {1})");
+        check("try { a? }", "(try {(? a)})");
+        check_err(parse, "fn", "expected expression, found keyword `fn`");
+        check_err(
+            parse,
+            "a b",
+            "expected one of `!`, `(`, `.`, `::`, `<eof>`, `?`, `[`, `{`, \
+             an operator, found `b`",
+        );
+    }
+
+    #[test]
+    fn test_closures() {
+        check("|x, y: u8| x + y", "(closure (x, y: u8) (+ x y))");
+        check("move || -> u8 { 1 }", "(move closure () {1})");
+        check("static move || yield", "(static move closure () (yield _))");
+        check("async |(a, b)| a", "(async closure ((a, b)) a)");
+        check("|_| || 1", "(closure (_) (closure () 1))");
+        check("|x| x = 1", "(closure (x) (= x 1))");
+        check_err(
+            parse,
+            "|| -> u8 1",
+            "expected one of `!`, `+`, `::`, `<`, `{`, found `1`",
+        );
+    }
+
+    #[test]
+    fn test_control_flow() {
+        check(
+            "if x == S {} else if let Some(y) = z && w {} else { 1 }",
+            "(if (== x S) {} (if (&& (let Some(y) z) w) {} {1}))",
+        );
+        check(
+            "while let Some(x) = it.next() { f(x); }",
+            "(while (let Some(x) (.next() it)) {(call f x);})",
+        );
+        check("for i in 0.. {}", "(for i (.. 0 _) {})");
+        check("for i in a..b { }", "(for i (.. a b) {})");
+        check("'a: loop { break 'a; }", "('a: loop {(break 'a: _);})");
+        check("'b: { 1 }", "'b: {1}");
+        check(
+            "match x { 1 => {} 2 => a, _ if b => c }",
+            "(match x (1 {}) (2 a) (_ if b c))",
+        );
+        check_err(
+            parse,
+            "match x { _ => { 1 } - 1 }",
+            "expected one of `=>`, `if`, `|`, found `}`",
+        );
+        check("match S { }", "(match S )");
+        check_err(
+            parse,
+            "match x { 1 => a 2 => b }",
+            "expected one of `!`, `(`, `,`, `.`, `::`, `?`, `[`, `{`, `}`, \
+             an operator, found `2`",
+        );
+        check_err(
+            parse,
+            "'a: x",
+            "expected `while`, `for`, `loop` or `{` after a label",
+        );
+    }
+
+    #[test]
+    fn test_statements() {
+        check("{ let x: u8 = 1; let y; x }", "{(let x 1); (let y _); x}");
+        check(
+            "{ if a {} - 1; { 2 } * 3 }",
+            "{(if a {} _) (- 1); {2} (* 3)}",
+        );
+        check(
+            "{ match x {}.f(); loop {}(1) }",
+            "{(.f() (match x )); (loop {}) (paren 1)}",
+        );
+        check("{ a = b; c }", "{(= a b); c}");
+        check("{ S { a }.a; S }", "{(. (struct S a .._) a); S}");
+        check("{ m!{} m!(); m![] }", "{(mac m!{}) (mac m!()); (mac m![])}");
+        check("{ m!().f() }", "{(.f() (mac m!()))}");
+        check("{ ;; x }", "{x}");
+        check("{ #[a] x; }", "{x;}");
+        check_err(
+            parse,
+            "{ a b }",
+            "expected one of `!`, `(`, `.`, `::`, `;`, `?`, `[`, `{`, `}`, \
+             an operator, found `b`",
+        );
+        check_err(
+            parse,
+            "{ let x = 1 }",
+            "expected one of `(`, `.`, `;`, `?`, `[`, an operator, found `}`",
+        );
+        check_err(
+            parse,
+            "{ #[a] }",
+            "expected statement after outer attribute",
+        );
+    }
+
+    #[test]
+    fn test_spans_and_attrs() {
+        let mut interner = StringInterner::empty();
+        let src = "#[a] x.f(1) + y";
+        let e = parse_expr_from_source_str(src, &mut interner).unwrap();
+        assert_eq!(e.span, Span::new(5, 15));
+        match e.kind {
+            ExprKind::Binary(op, ref lhs, _) => {
+                assert_eq!(op.span, Span::new(12, 13));
+                assert_eq!(lhs.attrs.len(), 1);
+                assert_eq!(lhs.span, Span::new(5, 11));
+            }
+            ref kind => panic!("{:?}", kind),
+        }
+        let src = "{ #![a] let (a, b) = c; }";
+        let e = parse_expr_from_source_str(src, &mut interner).unwrap();
+        assert_eq!(e.attrs.len(), 1);
+        match e.kind {
+            ExprKind::Block(ref blk, None) => {
+                assert_eq!(blk.stmts[0].span, Span::new(8, 23));
+                match blk.stmts[0].kind {
+                    StmtKind::Local(ref local) => {
+                        assert_eq!(local.span, Span::new(8, 22));
+                        match local.pat.kind {
+                            PatKind::Tuple(ref pats) => {
+                                assert_eq!(pats.len(), 2)
+                            }
+                            ref kind => panic!("{:?}", kind),
+                        }
+                    }
+                    ref kind => panic!("{:?}", kind),
+                }
+            }
+            ref kind => panic!("{:?}", kind),
+        }
+        let e =
+            parse_expr_from_source_str("f::<Vec<u8> >", &mut interner).unwrap();
+        match e.kind {
+            ExprKind::Path(None, ref path) => match path.segments[0].args {
+                Some(ref args) => match **args {
+                    GenericArgs::AngleBracketed(ref args) => {
+                        assert_eq!(args.span, Span::new(3, 13))
+                    }
+                    ref args => panic!("{:?}", args),
+                },
+                None => panic!("no args"),
+            },
+            ref kind => panic!("{:?}", kind),
+        }
+    }
+
+    const OPS: &[(&str, usize)] = &[
+        ("*", 5),
+        ("/", 5),
+        ("%", 5),
+        ("+", 4),
+        ("-", 4),
+        ("<<", 3),
+        (">>", 3),
+        ("&", 2),
+        ("^", 1),
+        ("|", 0),
+    ];
+
+    fn apply(op: &str, a: i64, b: i64) -> Option<i64> {
+        match op {
+            "*" => Some(a.wrapping_mul(b)),
+            "/" => a.checked_div(b),
+            "%" => a.checked_rem(b),
+            "+" => Some(a.wrapping_add(b)),
+            "-" => Some(a.wrapping_sub(b)),
+            "<<" => Some(a.wrapping_shl(b as u32)),
+            ">>" => Some(a.wrapping_shr(b as u32)),
+            "&" => Some(a & b),
+            "^" => Some(a ^ b),
+            "|" => Some(a | b),
+            _ => unreachable!(),
+        }
+    }
+
+    fn eval(e: &Expr, interner: &StringInterner) -> Option<i64> {
+        match e.kind {
+            ExprKind::Lit(Lit {
+                kind: LitKind::Token(token::Lit::Integer(n), None),
+                ..
+            }) => Some(interner.get(n).parse().unwrap()),
+            ExprKind::Binary(op, ref a, ref b) => apply(
+                op.node.to_string(),
+                eval(a, interner)?,
+                eval(b, interner)?,
+            ),
+            ref kind => panic!("{:?}", kind),
+        }
+    }
+
+    // evaluates `first op1 x1 op2 x2 ...` with a shunting-yard
+    fn shunting_yard(first: u8, rest: &[(usize, u8)]) -> Option<i64> {
+        let mut values = vec![i64::from(first)];
+        let mut ops: Vec<usize> = vec![];
+        let reduce = |values: &mut Vec<i64>, op: usize| -> Option<()> {
+            let b = values.pop().unwrap();
+            let a = values.pop().unwrap();
+            values.push(apply(OPS[op].0, a, b)?);
+            Some(())
+        };
+        for &(op, x) in rest {
+            while let Some(&top) = ops.last() {
+                if OPS[top].1 < OPS[op].1 {
+                    break;
+                }
+                ops.pop();
+                reduce(&mut values, top)?;
+            }
+            ops.push(op);
+            values.push(i64::from(x));
+        }
+        while let Some(top) = ops.pop() {
+            reduce(&mut values, top)?;
+        }
+        Some(values[0])
+    }
+
+    // left-associative binary operators parse as a shunting-yard groups them
+    #[quickcheck]
+    fn check_binary_precedence(first: u8, rest: Vec<(u8, u8)>) -> TestResult {
+        let rest: Vec<_> = rest
+            .into_iter()
+            .map(|(op, x)| (op as usize % OPS.len(), x))
+            .collect();
+        let mut src = first.to_string();
+        for &(op, x) in &rest {
+            src.push_str(&format!(" {} {}", OPS[op].0, x));
+        }
+        let mut interner = StringInterner::empty();
+        let e = parse_expr_from_source_str(&src, &mut interner).unwrap();
+        if e.span != Span::new(0, src.len()) {
+            return TestResult::failed();
+        }
+        TestResult::from_bool(
+            eval(&e, &interner) == shunting_yard(first, &rest),
+        )
+    }
+}
@@ -0,0 +1,604 @@
+use super::{PResult, Parser, PathStyle};
+use crate::ast::{
+    AnonConst, ExprKind, GenericBound, GenericBounds, GenericParam,
+    GenericParamKind, Generics, Path, PolyTraitRef, TraitBoundModifier,
+    TraitRef, WhereBoundPredicate, WhereClause, WhereEqPredicate,
+    WherePredicate, WhereRegionPredicate,
+};
+use lexer::token::{BinOpToken, Span, Token};
+
+impl<'a> Parser<'a> {
+    /// `<'a, T: Bound>`, or no generics. The where-clause is left empty,
+    /// for the item to parse where it belongs.
+    pub fn parse_generics(&mut self) -> PResult<Generics> {
+        let lo = self.token.sp;
+        if !self.eat_lt() {
+            let hi = self.prev_span.hi;
+            return Ok(Generics::empty(Span::new(hi, hi)));
+        }
+        let params = self.parse_generic_params()?;
+        self.expect_gt()?;
+        let span = lo.to(self.prev_span);
+        Ok(Generics {
+            params,
+            where_clause: WhereClause {
+                predicates: vec![],
+                span: Span::new(span.hi, span.hi),
+            },
+            span,
+        })
+    }
+
+    /// The params of `<...>`, up to the `>`.
+    pub fn parse_generic_params(&mut self) -> PResult<Vec<GenericParam>> {
+        let mut params = vec![];
+        loop {
+            let attrs = self.parse_outer_attributes()?;
+            let lo = self.token.sp;
+            if self.check_lifetime() {
+                let lifetime = self.expect_lifetime()?;
+                let bounds = if self.eat(&Token::Colon) {
+                    self.parse_lt_param_bounds()?
+                } else {
+                    vec![]
+                };
+                params.push(GenericParam {
+                    id: lifetime.id,
+                    ident: lifetime.ident,
+                    attrs,
+                    bounds,
+                    kind: GenericParamKind::Lifetime,
+                    span: lo.to(self.prev_span),
+                });
+            } else if self.eat_keyword("const") {
+                // `const N: usize = 1`
+                let ident = self.parse_ident()?;
+                self.expect(&Token::Colon)?;
+                let ty = self.parse_ty()?;
+                let default = if self.eat(&Token::Eq) {
+                    Some(self.parse_const_param_default()?)
+                } else {
+                    None
+                };
+                params.push(GenericParam {
+                    id: self.next_node_id(),
+                    ident,
+                    attrs,
+                    bounds: vec![],
+                    kind: GenericParamKind::Const { ty, default },
+                    span: lo.to(self.prev_span),
+                });
+            } else if self.check_ident() {
+                // `T: Bound = Default`
+                let ident = self.parse_ident()?;
+                let bounds = if self.eat(&Token::Colon) {
+                    self.parse_generic_bounds()?
+                } else {
+                    vec![]
+                };
+                let default = if self.eat(&Token::Eq) {
+                    Some(self.parse_ty()?)
+                } else {
+                    None
+                };
+                params.push(GenericParam {
+                    id: self.next_node_id(),
+                    ident,
+                    attrs,
+                    bounds,
+                    kind: GenericParamKind::Type { default },
+                    span: lo.to(self.prev_span),
+                });
+            } else {
+                if !attrs.is_empty() {
+                    let msg = "expected generic parameter after outer \
+                               attribute";
+                    return self.fatal(self.prev_span, msg.to_string());
+                }
+                break;
+            }
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+        }
+        Ok(params)
+    }
+
+    // the default of a const param: a block, a literal or a const's name
+    fn parse_const_param_default(&mut self) -> PResult<AnonConst> {
+        let value = if self.check_const_arg() {
+            self.parse_const_arg()?
+        } else {
+            let ident = self.parse_ident()?;
+            let kind = ExprKind::Path(None, Path::from_ident(ident));
+            self.mk_expr(ident.span, kind, vec![])
+        };
+        Ok(AnonConst {
+            id: self.next_node_id(),
+            value,
+        })
+    }
+
+    /// `for<'a, 'b>`, or nothing.
+    pub fn parse_late_bound_lifetime_defs(
+        &mut self,
+    ) -> PResult<Vec<GenericParam>> {
+        if !self.eat_keyword("for") {
+            return Ok(vec![]);
+        }
+        self.expect_lt()?;
+        let params = self.parse_generic_params()?;
+        self.expect_gt()?;
+        for param in &params {
+            if param.kind != GenericParamKind::Lifetime {
+                let msg = "only lifetime parameters can be used in this \
+                           context";
+                return self.fatal(param.span, msg.to_string());
+            }
+        }
+        Ok(params)
+    }
+
+    /// `'b + 'c`, the bounds of a lifetime.
+    pub fn parse_lt_param_bounds(&mut self) -> PResult<GenericBounds> {
+        let mut bounds = vec![];
+        while self.check_lifetime() {
+            bounds.push(GenericBound::Outlives(self.expect_lifetime()?));
+            if !self.eat(&Token::BinOp(BinOpToken::Plus)) {
+                break;
+            }
+        }
+        Ok(bounds)
+    }
+
+    /// `Trait + 'a + for<'b> Trait2<'b> + ?Sized + ~const Trait3`, possibly
+    /// empty.
+    pub fn parse_generic_bounds(&mut self) -> PResult<GenericBounds> {
+        let mut bounds = vec![];
+        loop {
+            let lo = self.token.sp;
+            let is_const = self.check(&Token::Tilde);
+            if is_const {
+                self.bump();
+                self.expect_keyword("const")?;
+            }
+            let is_maybe = self.eat(&Token::Question);
+            if self.check_lifetime() {
+                if is_const || is_maybe {
+                    let msg = "`?` and `~const` may only modify trait \
+                               bounds, not lifetime bounds";
+                    return self.fatal(lo.to(self.token.sp), msg.to_string());
+                }
+                bounds.push(GenericBound::Outlives(self.expect_lifetime()?));
+            } else if self.check_keyword("for") || self.check_path() {
+                let lifetime_defs = self.parse_late_bound_lifetime_defs()?;
+                let path = self.parse_path(PathStyle::Type)?;
+                let poly_trait_ref = PolyTraitRef {
+                    bound_generic_params: lifetime_defs,
+                    trait_ref: TraitRef {
+                        path,
+                        ref_id: self.next_node_id(),
+                    },
+                    span: lo.to(self.prev_span),
+                };
+                let modifier = match (is_const, is_maybe) {
+                    (false, false) => TraitBoundModifier::None,
+                    (false, true) => TraitBoundModifier::Maybe,
+                    (true, false) => TraitBoundModifier::MaybeConst,
+                    (true, true) => TraitBoundModifier::MaybeConstMaybe,
+                };
+                bounds.push(GenericBound::Trait(poly_trait_ref, modifier));
+            } else if is_const || is_maybe {
+                let msg = format!(
+                    "expected trait bound, found {}",
+                    self.token_descr()
+                );
+                return self.fatal(self.token.sp, msg);
+            } else {
+                break;
+            }
+            if !self.eat(&Token::BinOp(BinOpToken::Plus)) {
+                break;
+            }
+        }
+        Ok(bounds)
+    }
+
+    /// `where 'a: 'b, for<'c> T: Trait<'c>, T::A = U`, or nothing.
+    pub fn parse_where_clause(&mut self) -> PResult<WhereClause> {
+        let lo = self.token.sp;
+        if !self.eat_keyword("where") {
+            let hi = self.prev_span.hi;
+            return Ok(WhereClause {
+                predicates: vec![],
+                span: Span::new(hi, hi),
+            });
+        }
+        let mut predicates = vec![];
+        loop {
+            let pred_lo = self.token.sp;
+            if self.check_lifetime() {
+                let lifetime = self.expect_lifetime()?;
+                self.expect(&Token::Colon)?;
+                let bounds = self.parse_lt_param_bounds()?;
+                predicates.push(WherePredicate::RegionPredicate(
+                    WhereRegionPredicate {
+                        span: pred_lo.to(self.prev_span),
+                        lifetime,
+                        bounds,
+                    },
+                ));
+            } else if self.check_type() {
+                let lifetime_defs = self.parse_late_bound_lifetime_defs()?;
+                let ty = self.parse_ty()?;
+                if self.eat(&Token::Colon) {
+                    let bounds = self.parse_generic_bounds()?;
+                    predicates.push(WherePredicate::BoundPredicate(
+                        WhereBoundPredicate {
+                            span: pred_lo.to(self.prev_span),
+                            bound_generic_params: lifetime_defs,
+                            bounded_ty: ty,
+                            bounds,
+                        },
+                    ));
+                } else if self.eat(&Token::Eq) || self.eat(&Token::EqEq) {
+                    let rhs_ty = self.parse_ty()?;
+                    predicates.push(WherePredicate::EqPredicate(
+                        WhereEqPredicate {
+                            id: self.next_node_id(),
+                            span: pred_lo.to(self.prev_span),
+                            lhs_ty: ty,
+                            rhs_ty,
+                        },
+                    ));
+                } else {
+                    return self.unexpected();
+                }
+            } else {
+                break;
+            }
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+        }
+        Ok(WhereClause {
+            predicates,
+            span: lo.to(self.prev_span),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{check_err, parse_with};
+    use super::super::{parse_crate_from_source_str, ParseError};
+    use super::*;
+    use crate::ast::{
+        AssocTyConstraintKind, Crate, ForeignItemKind, GenericArgs, ItemKind,
+        TyKind,
+    };
+    use lexer::interner::StringInterner;
+
+    // prints `generics` as `<params> (where predicates)`, with types and
+    // paths as they are in `src`
+    fn sexp(g: &Generics, src: &str, interner: &StringInterner) -> String {
+        let text = |sp: Span| src[sp.lo..sp.hi].to_string();
+        let bounds = |bounds: &[GenericBound]| {
+            let bounds: Vec<_> = bounds
+                .iter()
+                .map(|bound| match *bound {
+                    GenericBound::Outlives(ref lt) => text(lt.ident.span),
+                    GenericBound::Trait(ref poly, modifier) => {
+                        let modifier = match modifier {
+                            TraitBoundModifier::None => "",
+                            TraitBoundModifier::Maybe => "?",
+                            TraitBoundModifier::MaybeConst => "~const ",
+                            TraitBoundModifier::MaybeConstMaybe => "~const ?",
+                        };
+                        let for_params = match poly.bound_generic_params.len() {
+                            0 => String::new(),
+                            n => format!("for{} ", n),
+                        };
+                        let path = text(poly.trait_ref.path.span);
+                        format!("{}{}{}", for_params, modifier, path)
+                    }
+                })
+                .collect();
+            bounds.join(" ")
+        };
+        let params: Vec<_> = g
+            .params
+            .iter()
+            .map(|param| {
+                let name = interner.get(param.ident.name);
+                let mut s = match param.kind {
+                    GenericParamKind::Const { ref ty, .. } => {
+                        format!("const {}: {}", name, text(ty.span))
+                    }
+                    _ => name.to_string(),
+                };
+                if !param.bounds.is_empty() {
+                    s = format!("{}: {}", s, bounds(&param.bounds));
+                }
+                match param.kind {
+                    GenericParamKind::Type {
+                        default: Some(ref ty),
+                    } => {
+                        s = format!("{} = {}", s, text(ty.span));
+                    }
+                    GenericParamKind::Const {
+                        default: Some(ref c),
+                        ..
+                    } => {
+                        s = format!("{} = {}", s, text(c.value.span));
+                    }
+                    _ => {}
+                }
+                if s == name {
+                    s
+                } else {
+                    format!("({})", s)
+                }
+            })
+            .collect();
+        let mut s = format!("<{}>", params.join(" "));
+        if !g.where_clause.span.is_empty() {
+            let predicates: Vec<_> = g
+                .where_clause
+                .predicates
+                .iter()
+                .map(|pred| match *pred {
+                    WherePredicate::BoundPredicate(ref p) => {
+                        let for_params = match p.bound_generic_params.len() {
+                            0 => String::new(),
+                            n => format!("for{} ", n),
+                        };
+                        format!(
+                            " ({}{}: {})",
+                            for_params,
+                            text(p.bounded_ty.span),
+                            bounds(&p.bounds)
+                        )
+                    }
+                    WherePredicate::RegionPredicate(ref p) => format!(
+                        " ({}: {})",
+                        text(p.lifetime.ident.span),
+                        bounds(&p.bounds)
+                    ),
+                    WherePredicate::EqPredicate(ref p) => format!(
+                        " ({} = {})",
+                        text(p.lhs_ty.span),
+                        text(p.rhs_ty.span)
+                    ),
+                })
+                .collect();
+            s = format!("{} (where{})", s, predicates.concat());
+        }
+        s
+    }
+
+    // the generics of the items of `krate`, and of the items inside them
+    fn print(
+        krate: &Crate,
+        src: &str,
+        interner: &StringInterner,
+    ) -> Vec<String> {
+        let mut generics = vec![];
+        for item in &krate.module.items {
+            match item.kind {
+                ItemKind::Fn(_, ref g, _)
+                | ItemKind::TyAlias(_, ref g)
+                | ItemKind::Enum(_, ref g)
+                | ItemKind::Struct(_, ref g)
+                | ItemKind::Union(_, ref g)
+                | ItemKind::TraitAlias(ref g, _) => generics.push(g),
+                ItemKind::Trait(_, _, ref g, _, ref items) => {
+                    generics.push(g);
+                    generics.extend(items.iter().map(|item| &item.generics));
+                }
+                ItemKind::Impl(_, _, _, ref g, _, _, ref items) => {
+                    generics.push(g);
+                    generics.extend(items.iter().map(|item| &item.generics));
+                }
+                ItemKind::ForeignMod(ref m) => {
+                    for item in &m.items {
+                        if let ForeignItemKind::Fn(_, ref g) = item.kind {
+                            generics.push(g);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        generics.iter().map(|g| sexp(g, src, interner)).collect()
+    }
+
+    fn parse(src: &str) -> Result<Vec<String>, ParseError> {
+        parse_with(
+            src,
+            |p| p.parse_crate_mod(),
+            |krate, interner| print(krate, src, interner),
+        )
+    }
+
+    fn check(src: &str, expected: &[&str]) {
+        assert_eq!(
+            parse(src),
+            Ok(expected.iter().map(|s| s.to_string()).collect()),
+            "{}",
+            src
+        );
+    }
+
+    #[test]
+    fn test_params() {
+        check("fn f() {}", &["<>"]);
+        check("fn f<>() {}", &["<>"]);
+        check(
+            "fn f<'a, 'b: 'a + 'c, T, U: Clone + 'a, V = u8, \
+             W: Tr<'a> = Box<W>,>() {}",
+            &["<'a ('b: 'a 'c) T (U: Clone 'a) (V = u8) \
+               (W: Tr<'a> = Box<W>)>"],
+        );
+        check(
+            "struct S<const N: usize, const M: u8 = 1, \
+             const K: bool = { N > 1 }, const L: i8 = -1, \
+             const J: usize = N>;",
+            &["<(const N: usize) (const M: u8 = 1) \
+               (const K: bool = { N > 1 }) (const L: i8 = -1) \
+               (const J: usize = N)>"],
+        );
+        check(
+            "fn f<#[a] 'a, #[b] T, #[c] const N: u8>() {}",
+            &["<'a T (const N: u8)>"],
+        );
+        check(
+            "fn f<T: Iterator<Item: Iterator<Item: Copy>>>() {}",
+            &["<(T: Iterator<Item: Iterator<Item: Copy>>)>"],
+        );
+        check_err(
+            parse,
+            "fn f<#[a]>() {}",
+            "expected generic parameter after outer attribute",
+        );
+        check_err(parse, "fn f<const N>() {}", "expected `:`, found `>`");
+        check_err(
+            parse,
+            "fn f<const N: u8 = x + 1>() {}",
+            "expected one of `,`, `>`, found `+`",
+        );
+        check_err(
+            parse,
+            "fn f<T, 1>() {}",
+            "expected one of `>`, `const`, identifier, lifetime, found `1`",
+        );
+    }
+
+    #[test]
+    fn test_bound_modifiers() {
+        check(
+            "fn f<T: ?Sized + ~const Tr + ~const ?Sized + \
+             for<'a> Fn(&'a u8) + 'static>() {}",
+            &["<(T: ?Sized ~const Tr ~const ?Sized for1 Fn(&'a u8) \
+               'static)>"],
+        );
+        check("trait Tr: ?Sized {}", &["<>"]);
+        check_err(
+            parse,
+            "fn f<T: ?'a>() {}",
+            "`?` and `~const` may only modify trait bounds, not lifetime \
+             bounds",
+        );
+        check_err(parse, "fn f<T: ?>() {}", "expected trait bound, found `>`");
+        check_err(parse, "fn f<T: ~Tr>() {}", "expected `const`, found `Tr`");
+    }
+
+    #[test]
+    fn test_where_clauses() {
+        check(
+            "fn f<T>(x: T) -> T where T: Clone + 'static, 'a: 'b + 'c, \
+             for<'x> &'x T: Tr<'x>, T::A = u8, Vec<T>: ?Sized, {}",
+            &["<T> (where (T: Clone 'static) ('a: 'b 'c) \
+               (for1 &'x T: Tr<'x>) (T::A = u8) (Vec<T>: ?Sized))"],
+        );
+        check("fn f() where {}", &["<> (where)"]);
+        check("fn f() where T: {}", &["<> (where (T: ))"]);
+        check(
+            "struct A<T> where T: X { a: T } \
+             struct B<T>(T) where T: X; \
+             struct C<T> where T: X; \
+             enum D<T> where T: X {} \
+             union E<T> where T: X { a: T } \
+             type F<T> where T: X = T; \
+             type G<T> = T where T: X; \
+             trait H<T>: Sup where T: X {} \
+             trait I<T> = Sup where T: X; \
+             impl<T> Tr for S<T> where T: X {} \
+             extern { fn f<T>() where T: X; }",
+            &["<T> (where (T: X))"; 11],
+        );
+        check_err(parse, "fn f() where 'a {}", "expected `:`, found `{`");
+        check_err(
+            parse,
+            "fn f() where T {}",
+            "expected one of `!`, `+`, `::`, `:`, `<`, `==`, `=`, found `{`",
+        );
+        check_err(
+            parse,
+            "struct S<T> where T: X [T];",
+            "expected one of `+`, `,`, `::`, `;`, `<`, `{`, found `[`",
+        );
+
+        let mut interner = StringInterner::empty();
+        let src = "fn f<T>() where T: X {}";
+        let krate = parse_crate_from_source_str(src, &mut interner).unwrap();
+        match krate.module.items[0].kind {
+            ItemKind::Fn(_, ref g, _) => {
+                assert_eq!(g.span, Span::new(4, 7));
+                assert_eq!(g.where_clause.span, Span::new(10, 20));
+            }
+            ref kind => panic!("{:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_generic_associated_types() {
+        check(
+            "trait Tr { \
+             type A<'a>: Debug where Self: 'a; \
+             type B<T> = Vec<T> where T: Clone; \
+             type C; \
+             fn f<T>() where T: X; \
+             }",
+            &[
+                "<>",
+                "<'a> (where (Self: 'a))",
+                "<T> (where (T: Clone))",
+                "<>",
+                "<T> (where (T: X))",
+            ],
+        );
+        check(
+            "impl Tr for S { \
+             type A<'a> where Self: 'a = &'a u8; \
+             type B<T> = Vec<T> where T: Clone; \
+             fn f<T>() where T: X {} \
+             }",
+            &[
+                "<>",
+                "<'a> (where (Self: 'a))",
+                "<T> (where (T: Clone))",
+                "<T> (where (T: X))",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_assoc_ty_bounds() {
+        let mut interner = StringInterner::empty();
+        let src = "Iterator<Item: Debug + 'a, Len = u8>";
+        let mut p = Parser::from_source_str(src, &mut interner).unwrap();
+        let ty = p.parse_ty().unwrap();
+        let args = match ty.kind {
+            TyKind::Path(None, ref path) => path.segments[0].args.clone(),
+            ref kind => panic!("{:?}", kind),
+        };
+        match *args.unwrap() {
+            GenericArgs::AngleBracketed(ref args) => {
+                assert!(args.args.is_empty());
+                match args.constraints[0].kind {
+                    AssocTyConstraintKind::Bound { ref bounds } => {
+                        assert_eq!(bounds.len(), 2)
+                    }
+                    ref kind => panic!("{:?}", kind),
+                }
+                assert_eq!(args.constraints[0].span, Span::new(9, 25));
+                match args.constraints[1].kind {
+                    AssocTyConstraintKind::Equality { .. } => {}
+                    ref kind => panic!("{:?}", kind),
+                }
+            }
+            ref args => panic!("{:?}", args),
+        }
+    }
+}
@@ -0,0 +1,1754 @@
+use super::{PResult, Parser, PathStyle};
+use crate::ast::{
+    respan, AnonConst, Attribute, BindingMode, Constness, Crate, CrateSugar,
+    Defaultness, EnumDef, Extern, FnDecl, FnHeader, FnSig, ForeignItem,
+    ForeignItemKind, ForeignMod, Generics, Ident, ImplItem, ImplItemKind,
+    ImplPolarity, IsAsync, IsAuto, Item, ItemKind, Lit, Mac, MacDelimiter,
+    MacroDef, Mod, MutTy, Mutability, Param, PatKind, Path, StructField,
+    TraitItem, TraitItemKind, TraitRef, TyKind, Unsafety, UseTree, UseTreeKind,
+    Variant, VariantData, Visibility, VisibilityKind, P,
+};
+use lexer::token::{self, BinOpToken, DelimToken, Span, Token};
+
+/// The name, kind and inner attributes of an item being parsed.
+type ItemInfo = (Ident, ItemKind, Option<Vec<Attribute>>);
+
+impl<'a> Parser<'a> {
+    /// The items of a file, up to the end of it.
+    pub fn parse_crate_mod(&mut self) -> PResult<Crate> {
+        let lo = self.token.sp;
+        let attrs = self.parse_inner_attributes()?;
+        let module = self.parse_mod_items(&Token::Eof, 0)?;
+        Ok(Crate {
+            module,
+            attrs,
+            span: lo.to(self.prev_span),
+        })
+    }
+
+    /// An item and its outer attributes, `None` if there is none.
+    pub fn parse_item(&mut self) -> PResult<Option<P<Item>>> {
+        let attrs = self.parse_outer_attributes()?;
+        self.parse_item_(attrs, true)
+    }
+
+    /// An item after its outer attributes, `None` if the tokens do not start
+    /// one. Macro invocations are items only if `macros_allowed`, in a block
+    /// they are statements.
+    pub(super) fn parse_item_(
+        &mut self,
+        attrs: Vec<Attribute>,
+        macros_allowed: bool,
+    ) -> PResult<Option<P<Item>>> {
+        let lo = self.token.sp;
+        let vis = self.parse_visibility()?;
+        let info = if self.eat_keyword("use") {
+            let tree = self.parse_use_tree()?;
+            self.expect(&Token::Semi)?;
+            (self.invalid_ident(lo), ItemKind::Use(P(tree)), None)
+        } else if self.is_keyword("extern")
+            && self.look_ahead(1, |t| self.token_is_keyword(t, "crate"))
+        {
+            self.bump();
+            self.bump();
+            self.parse_item_extern_crate()?
+        } else if self.check_fn_front_matter() {
+            let header = self.parse_fn_front_matter()?;
+            self.parse_item_fn(header)?
+        } else if self.eat_keyword("extern") {
+            let abi = self.parse_opt_abi()?;
+            self.parse_item_foreign_mod(lo, abi)?
+        } else if self.is_keyword("static")
+            && self.look_ahead(1, |t| {
+                self.token_is_ident(t) || self.token_is_keyword(t, "mut")
+            })
+        {
+            self.bump();
+            self.parse_item_static()?
+        } else if self.eat_keyword("const") {
+            self.parse_item_const()?
+        } else if self.check_keyword("trait")
+            || self.is_kw_at(0, "auto") && self.is_kw_at(1, "trait")
+            || self.is_kw_at(0, "unsafe")
+                && (self.is_kw_at(1, "trait")
+                    || self.is_kw_at(1, "auto") && self.is_kw_at(2, "trait"))
+        {
+            let unsafety = self.parse_unsafety();
+            let is_auto = if self.eat_keyword("auto") {
+                IsAuto::Yes
+            } else {
+                IsAuto::No
+            };
+            self.expect_keyword("trait")?;
+            self.parse_item_trait(is_auto, unsafety)?
+        } else if self.check_keyword("impl")
+            || self.is_kw_at(0, "unsafe") && self.is_kw_at(1, "impl")
+            || self.is_kw_at(0, "default")
+                && (self.is_kw_at(1, "impl")
+                    || self.is_kw_at(1, "unsafe") && self.is_kw_at(2, "impl"))
+        {
+            let defaultness = self.parse_defaultness();
+            let unsafety = self.parse_unsafety();
+            self.expect_keyword("impl")?;
+            self.parse_item_impl(lo, unsafety, defaultness)?
+        } else if self.eat_keyword("mod") {
+            self.parse_item_mod()?
+        } else if self.eat_keyword("type") {
+            self.parse_item_type()?
+        } else if self.eat_keyword("enum") {
+            self.parse_item_enum()?
+        } else if self.eat_keyword("struct") {
+            self.parse_item_struct()?
+        } else if self.is_kw_at(0, "union")
+            && self.look_ahead(1, |t| self.token_is_ident(t))
+        {
+            self.bump();
+            self.parse_item_union()?
+        } else if self.is_macro_rules_item()
+            || macros_allowed
+                && vis.node == VisibilityKind::Inherited
+                && self.check_path()
+                && self.look_ahead(1, |t| *t == Token::Not)
+        {
+            self.parse_item_mac(lo)?
+        } else if vis.node != VisibilityKind::Inherited {
+            let msg = format!(
+                "expected item after visibility, found {}",
+                self.token_descr()
+            );
+            return self.fatal(self.token.sp, msg);
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(self.mk_item(lo, vis, attrs, info)))
+    }
+
+    fn mk_item(
+        &mut self,
+        lo: Span,
+        vis: Visibility,
+        mut attrs: Vec<Attribute>,
+        (ident, kind, inner_attrs): ItemInfo,
+    ) -> P<Item> {
+        if let Some(inner_attrs) = inner_attrs {
+            attrs.extend(inner_attrs);
+        }
+        P(Item {
+            attrs,
+            id: self.next_node_id(),
+            span: lo.to(self.prev_span),
+            vis,
+            ident,
+            kind,
+        })
+    }
+
+    /// The empty name of items without one, like `impl` blocks, and of
+    /// unnamed params.
+    pub(super) fn invalid_ident(&mut self, lo: Span) -> Ident {
+        Ident::new(self.interner.insert(""), Span::new(lo.lo, lo.lo))
+    }
+
+    // whether the token `dist` ahead is the keyword, or contextual keyword,
+    // `kw`
+    fn is_kw_at(&self, dist: usize, kw: &str) -> bool {
+        self.look_ahead(dist, |t| self.token_is_keyword(t, kw))
+    }
+
+    /// `pub`, `pub(crate)`, `pub(self)`, `pub(super)`, `pub(in path)`,
+    /// `crate`, or nothing.
+    pub fn parse_visibility(&mut self) -> PResult<Visibility> {
+        let lo = self.token.sp;
+        if self.is_keyword("crate")
+            && !self.look_ahead(1, |t| *t == Token::ModSep)
+        {
+            self.bump();
+            let kind = VisibilityKind::Crate(CrateSugar::JustCrate);
+            return Ok(respan(lo, kind));
+        }
+        if !self.eat_keyword("pub") {
+            return Ok(respan(
+                Span::new(lo.lo, lo.lo),
+                VisibilityKind::Inherited,
+            ));
+        }
+        let close = Token::CloseDelim(DelimToken::Paren);
+        // not `pub (A, B)`, a public field of a tuple type
+        if self.check(&Token::OpenDelim(DelimToken::Paren)) {
+            if self.is_kw_at(1, "crate") && self.look_ahead(2, |t| *t == close)
+            {
+                self.bump();
+                self.bump();
+                self.bump();
+                let kind = VisibilityKind::Crate(CrateSugar::PubCrate);
+                return Ok(respan(lo.to(self.prev_span), kind));
+            }
+            let is_in = self.is_kw_at(1, "in");
+            if is_in
+                || (self.is_kw_at(1, "self") || self.is_kw_at(1, "super"))
+                    && self.look_ahead(2, |t| *t == close)
+            {
+                self.bump();
+                if is_in {
+                    self.bump();
+                }
+                let path = self.parse_path(PathStyle::Mod)?;
+                self.expect(&close)?;
+                let kind = VisibilityKind::Restricted {
+                    path: P(path),
+                    id: self.next_node_id(),
+                };
+                return Ok(respan(lo.to(self.prev_span), kind));
+            }
+        }
+        Ok(respan(lo, VisibilityKind::Public))
+    }
+
+    // `ident` or `_`
+    fn parse_ident_or_underscore(&mut self) -> PResult<Ident> {
+        match self.token.tok {
+            Token::Ident(name, false) if self.is_keyword("_") => {
+                let sp = self.token.sp;
+                self.bump();
+                Ok(Ident::new(name, sp))
+            }
+            _ => self.parse_ident(),
+        }
+    }
+
+    // `as name`, or nothing
+    fn parse_rename(&mut self) -> PResult<Option<Ident>> {
+        if self.eat_keyword("as") {
+            Ok(Some(self.parse_ident_or_underscore()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_unsafety(&mut self) -> Unsafety {
+        if self.eat_keyword("unsafe") {
+            Unsafety::Unsafe
+        } else {
+            Unsafety::Normal
+        }
+    }
+
+    // `default`, a contextual keyword before an impl or an impl item
+    fn parse_defaultness(&mut self) -> Defaultness {
+        let is_default = self.is_kw_at(0, "default")
+            && self.look_ahead(1, |t| {
+                ["impl", "const", "async", "fn", "unsafe", "extern", "type"]
+                    .iter()
+                    .any(|kw| self.token_is_keyword(t, kw))
+            });
+        if is_default {
+            self.bump();
+            Defaultness::Default
+        } else {
+            Defaultness::Final
+        }
+    }
+
+    // `a::b::{c, d as e, f::*}`
+    fn parse_use_tree(&mut self) -> PResult<UseTree> {
+        let lo = self.token.sp;
+        let mut prefix = Path {
+            span: Span::new(lo.lo, lo.lo),
+            segments: vec![],
+        };
+        let kind = if self.check(&Token::OpenDelim(DelimToken::Brace))
+            || self.check(&Token::BinOp(BinOpToken::Star))
+            || self.is_import_coupler()
+        {
+            // `{a, b}`, `::{a, b}` or `*`
+            if self.eat(&Token::ModSep) {
+                prefix.segments.push(self.mk_root_path_segment(lo));
+                prefix.span = lo;
+            }
+            self.parse_use_tree_glob_or_nested()?
+        } else {
+            prefix = self.parse_path(PathStyle::Mod)?;
+            if self.eat(&Token::ModSep) {
+                self.parse_use_tree_glob_or_nested()?
+            } else {
+                UseTreeKind::Simple(self.parse_rename()?)
+            }
+        };
+        Ok(UseTree {
+            prefix,
+            kind,
+            span: lo.to(self.prev_span),
+        })
+    }
+
+    fn parse_use_tree_glob_or_nested(&mut self) -> PResult<UseTreeKind> {
+        if self.eat(&Token::BinOp(BinOpToken::Star)) {
+            return Ok(UseTreeKind::Glob);
+        }
+        self.expect(&Token::OpenDelim(DelimToken::Brace))?;
+        let close = Token::CloseDelim(DelimToken::Brace);
+        let trees = self.parse_seq_to_end(&close, |p| p.parse_use_tree())?;
+        Ok(UseTreeKind::Nested(trees))
+    }
+
+    // `extern crate orig as name;`, after the `crate`
+    fn parse_item_extern_crate(&mut self) -> PResult<ItemInfo> {
+        let orig = match self.token.tok {
+            Token::Ident(name, false) if self.is_keyword("self") => {
+                let sp = self.token.sp;
+                self.bump();
+                Ident::new(name, sp)
+            }
+            _ => self.parse_ident()?,
+        };
+        let (orig_name, ident) = match self.parse_rename()? {
+            Some(rename) => (Some(orig.name), rename),
+            None => (None, orig),
+        };
+        self.expect(&Token::Semi)?;
+        Ok((ident, ItemKind::ExternCrate(orig_name), None))
+    }
+
+    /// Whether the tokens are the `const async unsafe extern "abi" fn` of a
+    /// function, each qualifier optional.
+    pub fn check_fn_front_matter(&mut self) -> bool {
+        self.expected_tokens.push(super::TokenType::Keyword("fn"));
+        let mut dist = 0;
+        for kw in &["const", "async", "unsafe"] {
+            if self.is_kw_at(dist, kw) {
+                dist += 1;
+            }
+        }
+        if self.is_kw_at(dist, "extern") {
+            dist += 1;
+            if self.look_ahead(dist, is_abi) {
+                dist += 1;
+            }
+        }
+        self.is_kw_at(dist, "fn")
+    }
+
+    /// The qualifiers of a function and its `fn`.
+    pub fn parse_fn_front_matter(&mut self) -> PResult<FnHeader> {
+        let constness = if self.eat_keyword("const") {
+            Constness::Const
+        } else {
+            Constness::NotConst
+        };
+        let asyncness = if self.eat_keyword("async") {
+            IsAsync::Async
+        } else {
+            IsAsync::NotAsync
+        };
+        let unsafety = self.parse_unsafety();
+        let ext = if self.eat_keyword("extern") {
+            match self.parse_opt_abi()? {
+                Some(abi) => Extern::Explicit(abi),
+                None => Extern::Implicit,
+            }
+        } else {
+            Extern::None
+        };
+        self.expect_keyword("fn")?;
+        Ok(FnHeader {
+            unsafety,
+            asyncness,
+            constness,
+            ext,
+        })
+    }
+
+    // the `"C"` of `extern "C"`
+    fn parse_opt_abi(&mut self) -> PResult<Option<Lit>> {
+        match self.token.tok {
+            Token::Literal(_, Some(_)) if is_abi(&self.token.tok) => {
+                let msg = "suffixes on an ABI spec are invalid";
+                self.fatal(self.token.sp, msg.to_string())
+            }
+            ref tok if is_abi(tok) => Ok(Some(self.parse_lit()?)),
+            _ => Ok(None),
+        }
+    }
+
+    // `name<T>(params) -> R { ... }`, after the `fn`
+    fn parse_item_fn(&mut self, header: FnHeader) -> PResult<ItemInfo> {
+        let ident = self.parse_ident()?;
+        let mut generics = self.parse_generics()?;
+        let decl = self.parse_fn_decl(false, false)?;
+        generics.where_clause = self.parse_where_clause()?;
+        let mut inner_attrs = vec![];
+        let body = self.parse_block_with_attrs(&mut inner_attrs)?;
+        let sig = FnSig { header, decl };
+        Ok((ident, ItemKind::Fn(sig, generics, body), Some(inner_attrs)))
+    }
+
+    /// `(params) -> R`. A method may take `self` first, and only a foreign
+    /// function may end with `...`.
+    pub fn parse_fn_decl(
+        &mut self,
+        allow_self: bool,
+        allow_c_variadic: bool,
+    ) -> PResult<P<FnDecl>> {
+        self.expect(&Token::OpenDelim(DelimToken::Paren))?;
+        let mut first = true;
+        let mut c_variadic = false;
+        let close = Token::CloseDelim(DelimToken::Paren);
+        let inputs = self.parse_seq_to_end(&close, |p| {
+            let lo = p.token.sp;
+            let attrs = p.parse_outer_attributes()?;
+            let is_first = first;
+            first = false;
+            if c_variadic {
+                let msg = "`...` must be the last parameter of a C-variadic \
+                           function";
+                return p.fatal(p.prev_span, msg.to_string());
+            }
+            if is_first && allow_self && p.is_self_param_start() {
+                return p.parse_self_param(lo, attrs);
+            }
+            let (pat, ty) = if p.check(&Token::DotDotDot) {
+                if !allow_c_variadic {
+                    let msg = "only foreign functions are allowed to be \
+                               C-variadic";
+                    return p.fatal(p.token.sp, msg.to_string());
+                }
+                p.bump();
+                c_variadic = true;
+                let pat = p.mk_pat(p.prev_span, PatKind::Wild);
+                (pat, p.mk_ty(p.prev_span, TyKind::CVarArgs))
+            } else {
+                let pat = p.parse_pat()?;
+                p.expect(&Token::Colon)?;
+                (pat, p.parse_ty()?)
+            };
+            Ok(Param {
+                attrs,
+                ty,
+                pat,
+                id: p.next_node_id(),
+                span: lo.to(p.prev_span),
+            })
+        })?;
+        let output = self.parse_ret_ty(true)?;
+        Ok(P(FnDecl {
+            inputs,
+            output,
+            c_variadic,
+        }))
+    }
+
+    // whether the token `dist` ahead is `self`, and not the start of a path
+    fn is_self_at(&self, dist: usize) -> bool {
+        self.is_kw_at(dist, "self")
+            && !self.look_ahead(dist + 1, |t| *t == Token::ModSep)
+    }
+
+    // `self`, `mut self`, `&'a mut self` or `self: T`
+    fn is_self_param_start(&self) -> bool {
+        let is_lifetime_at = |dist| {
+            self.look_ahead(dist, |t| match *t {
+                Token::Lifetime(_) => true,
+                _ => false,
+            })
+        };
+        if self.token.tok == Token::BinOp(BinOpToken::And) {
+            self.is_self_at(1)
+                || self.is_kw_at(1, "mut") && self.is_self_at(2)
+                || is_lifetime_at(1)
+                    && (self.is_self_at(2)
+                        || self.is_kw_at(2, "mut") && self.is_self_at(3))
+        } else if self.is_keyword("mut") {
+            self.is_self_at(1)
+        } else {
+            self.is_self_at(0)
+        }
+    }
+
+    fn parse_self_param(
+        &mut self,
+        lo: Span,
+        attrs: Vec<Attribute>,
+    ) -> PResult<Param> {
+        let (mutbl, ty) = if self.eat(&Token::BinOp(BinOpToken::And)) {
+            let lifetime = if self.check_lifetime() {
+                Some(self.expect_lifetime()?)
+            } else {
+                None
+            };
+            let mutbl = self.parse_mutability();
+            let self_ty = self.mk_ty(self.token.sp, TyKind::ImplicitSelf);
+            let ty = MutTy { ty: self_ty, mutbl };
+            let ty =
+                self.mk_ty(lo.to(self.token.sp), TyKind::Rptr(lifetime, ty));
+            (Mutability::Immutable, Some(ty))
+        } else {
+            (self.parse_mutability(), None)
+        };
+        let self_ident = match self.token.tok {
+            Token::Ident(name, false) => Ident::new(name, self.token.sp),
+            _ => unreachable!("not a self param"),
+        };
+        self.bump();
+        let pat_span = lo.to(self.prev_span);
+        let ty = match ty {
+            Some(ty) => ty,
+            None if self.eat(&Token::Colon) => self.parse_ty()?,
+            None => self.mk_ty(self.prev_span, TyKind::ImplicitSelf),
+        };
+        let kind =
+            PatKind::Ident(BindingMode::ByValue(mutbl), self_ident, None);
+        let pat = self.mk_pat(pat_span, kind);
+        Ok(Param {
+            attrs,
+            ty,
+            pat,
+            id: self.next_node_id(),
+            span: lo.to(self.prev_span),
+        })
+    }
+
+    // `{ ... }`, after `extern "abi"`
+    fn parse_item_foreign_mod(
+        &mut self,
+        lo: Span,
+        abi: Option<Lit>,
+    ) -> PResult<ItemInfo> {
+        self.expect(&Token::OpenDelim(DelimToken::Brace))?;
+        let inner_attrs = self.parse_inner_attributes()?;
+        let mut items = vec![];
+        while !self.eat(&Token::CloseDelim(DelimToken::Brace)) {
+            items.push(self.parse_foreign_item()?);
+        }
+        let kind = ItemKind::ForeignMod(ForeignMod { abi, items });
+        Ok((self.invalid_ident(lo), kind, Some(inner_attrs)))
+    }
+
+    /// A function, static, type or macro in an `extern` block.
+    pub fn parse_foreign_item(&mut self) -> PResult<ForeignItem> {
+        let attrs = self.parse_outer_attributes()?;
+        let lo = self.token.sp;
+        let vis = self.parse_visibility()?;
+        let (ident, kind) = if self.eat_keyword("fn") {
+            let ident = self.parse_ident()?;
+            let mut generics = self.parse_generics()?;
+            let decl = self.parse_fn_decl(false, true)?;
+            generics.where_clause = self.parse_where_clause()?;
+            self.expect(&Token::Semi)?;
+            (ident, ForeignItemKind::Fn(decl, generics))
+        } else if self.eat_keyword("static") {
+            let mutbl = self.parse_mutability();
+            let ident = self.parse_ident()?;
+            self.expect(&Token::Colon)?;
+            let ty = self.parse_ty()?;
+            self.expect(&Token::Semi)?;
+            (ident, ForeignItemKind::Static(ty, mutbl))
+        } else if self.eat_keyword("type") {
+            let ident = self.parse_ident()?;
+            self.expect(&Token::Semi)?;
+            (ident, ForeignItemKind::Ty)
+        } else if self.check_mac_item(&vis) {
+            let mac = self.parse_mac_item()?;
+            (self.invalid_ident(lo), ForeignItemKind::Macro(mac))
+        } else {
+            return self.unexpected();
+        };
+        Ok(ForeignItem {
+            ident,
+            attrs,
+            kind,
+            id: self.next_node_id(),
+            span: lo.to(self.prev_span),
+            vis,
+        })
+    }
+
+    // `mut NAME: T = e;`, after the `static`
+    fn parse_item_static(&mut self) -> PResult<ItemInfo> {
+        let mutbl = self.parse_mutability();
+        let ident = self.parse_ident()?;
+        self.expect(&Token::Colon)?;
+        let ty = self.parse_ty()?;
+        self.expect(&Token::Eq)?;
+        let e = self.parse_expr()?;
+        self.expect(&Token::Semi)?;
+        Ok((ident, ItemKind::Static(ty, mutbl, e), None))
+    }
+
+    // `NAME: T = e;` or `_: T = e;`, after the `const`
+    fn parse_item_const(&mut self) -> PResult<ItemInfo> {
+        let ident = self.parse_ident_or_underscore()?;
+        self.expect(&Token::Colon)?;
+        let ty = self.parse_ty()?;
+        self.expect(&Token::Eq)?;
+        let e = self.parse_expr()?;
+        self.expect(&Token::Semi)?;
+        Ok((ident, ItemKind::Const(ty, e), None))
+    }
+
+    // `T: Bounds { ... }` or `T = Bounds;`, after the `trait`
+    fn parse_item_trait(
+        &mut self,
+        is_auto: IsAuto,
+        unsafety: Unsafety,
+    ) -> PResult<ItemInfo> {
+        let ident = self.parse_ident()?;
+        let mut generics = self.parse_generics()?;
+        let bounds = if self.eat(&Token::Colon) {
+            self.parse_generic_bounds()?
+        } else {
+            vec![]
+        };
+        if self.eat(&Token::Eq) {
+            // a trait alias
+            let msg = if !bounds.is_empty() {
+                Some("bounds are not allowed on trait aliases")
+            } else if is_auto == IsAuto::Yes {
+                Some("trait aliases cannot be `auto`")
+            } else if unsafety == Unsafety::Unsafe {
+                Some("trait aliases cannot be `unsafe`")
+            } else {
+                None
+            };
+            if let Some(msg) = msg {
+                return self.fatal(ident.span, msg.to_string());
+            }
+            let bounds = self.parse_generic_bounds()?;
+            generics.where_clause = self.parse_where_clause()?;
+            self.expect(&Token::Semi)?;
+            return Ok((ident, ItemKind::TraitAlias(generics, bounds), None));
+        }
+        generics.where_clause = self.parse_where_clause()?;
+        self.expect(&Token::OpenDelim(DelimToken::Brace))?;
+        let inner_attrs = self.parse_inner_attributes()?;
+        let mut items = vec![];
+        while !self.eat(&Token::CloseDelim(DelimToken::Brace)) {
+            items.push(self.parse_trait_item()?);
+        }
+        let kind = ItemKind::Trait(is_auto, unsafety, generics, bounds, items);
+        Ok((ident, kind, Some(inner_attrs)))
+    }
+
+    /// A const, method, type or macro in a trait.
+    pub fn parse_trait_item(&mut self) -> PResult<TraitItem> {
+        let mut attrs = self.parse_outer_attributes()?;
+        let lo = self.token.sp;
+        let (ident, kind, generics) = if self.eat_keyword("type") {
+            // `type A<'a>: Bound where Self: 'a = Default;`
+            let ident = self.parse_ident()?;
+            let mut generics = self.parse_generics()?;
+            let bounds = if self.eat(&Token::Colon) {
+                self.parse_generic_bounds()?
+            } else {
+                vec![]
+            };
+            generics.where_clause = self.parse_where_clause()?;
+            let default = if self.eat(&Token::Eq) {
+                Some(self.parse_ty()?)
+            } else {
+                None
+            };
+            self.parse_trailing_where_clause(&mut generics)?;
+            self.expect(&Token::Semi)?;
+            (ident, TraitItemKind::Type(bounds, default), generics)
+        } else if self.check_fn_front_matter() {
+            let header = self.parse_fn_front_matter()?;
+            let ident = self.parse_ident()?;
+            let mut generics = self.parse_generics()?;
+            let decl = self.parse_fn_decl(true, false)?;
+            generics.where_clause = self.parse_where_clause()?;
+            let body = if self.eat(&Token::Semi) {
+                None
+            } else {
+                Some(self.parse_block_with_attrs(&mut attrs)?)
+            };
+            let sig = FnSig { header, decl };
+            (ident, TraitItemKind::Method(sig, body), generics)
+        } else if self.eat_keyword("const") {
+            let ident = self.parse_ident()?;
+            self.expect(&Token::Colon)?;
+            let ty = self.parse_ty()?;
+            let default = if self.eat(&Token::Eq) {
+                Some(self.parse_expr()?)
+            } else {
+                None
+            };
+            self.expect(&Token::Semi)?;
+            let generics = self.no_generics();
+            (ident, TraitItemKind::Const(ty, default), generics)
+        } else if self.check_mac_item(&respan(lo, VisibilityKind::Inherited)) {
+            let mac = self.parse_mac_item()?;
+            let generics = self.no_generics();
+            (self.invalid_ident(lo), TraitItemKind::Macro(mac), generics)
+        } else {
+            return self.unexpected();
+        };
+        Ok(TraitItem {
+            id: self.next_node_id(),
+            ident,
+            attrs,
+            generics,
+            kind,
+            span: lo.to(self.prev_span),
+        })
+    }
+
+    // the generics of an item that has none
+    fn no_generics(&self) -> Generics {
+        let hi = self.prev_span.hi;
+        Generics::empty(Span::new(hi, hi))
+    }
+
+    // the where-clause a type alias may also have after its type, as in
+    // `type A<'a> = &'a T where T: 'a;`, unless it had one before
+    fn parse_trailing_where_clause(
+        &mut self,
+        generics: &mut Generics,
+    ) -> PResult<()> {
+        if generics.where_clause.predicates.is_empty()
+            && self.is_keyword("where")
+        {
+            generics.where_clause = self.parse_where_clause()?;
+        }
+        Ok(())
+    }
+
+    // `<T> Trait for Ty { ... }` or `<T> Ty { ... }`, after the `impl`
+    fn parse_item_impl(
+        &mut self,
+        lo: Span,
+        unsafety: Unsafety,
+        defaultness: Defaultness,
+    ) -> PResult<ItemInfo> {
+        let mut generics = if self.choose_generics_over_qpath() {
+            self.parse_generics()?
+        } else {
+            self.no_generics()
+        };
+        let polarity = if self.check(&Token::Not)
+            && self.look_ahead(1, |t| self.token_can_begin_type(t))
+        {
+            self.bump();
+            ImplPolarity::Negative
+        } else {
+            ImplPolarity::Positive
+        };
+        let ty_first = self.parse_ty()?;
+        let (trait_ref, ty) = if self.eat_keyword("for") {
+            let ty_first = *ty_first;
+            let path = match ty_first.kind {
+                TyKind::Path(None, path) => path,
+                _ => {
+                    let msg = "expected a trait, found type";
+                    return self.fatal(ty_first.span, msg.to_string());
+                }
+            };
+            let trait_ref = TraitRef {
+                path,
+                ref_id: ty_first.id,
+            };
+            (Some(trait_ref), self.parse_ty()?)
+        } else {
+            if polarity == ImplPolarity::Negative {
+                let msg = "inherent impls cannot be negative";
+                return self.fatal(ty_first.span, msg.to_string());
+            }
+            (None, ty_first)
+        };
+        generics.where_clause = self.parse_where_clause()?;
+        self.expect(&Token::OpenDelim(DelimToken::Brace))?;
+        let inner_attrs = self.parse_inner_attributes()?;
+        let mut items = vec![];
+        while !self.eat(&Token::CloseDelim(DelimToken::Brace)) {
+            items.push(self.parse_impl_item()?);
+        }
+        let kind = ItemKind::Impl(
+            unsafety,
+            polarity,
+            defaultness,
+            generics,
+            trait_ref,
+            ty,
+            items,
+        );
+        Ok((self.invalid_ident(lo), kind, Some(inner_attrs)))
+    }
+
+    // whether the `<` after `impl` starts generics, as in `impl<T> S<T>`,
+    // rather than a qualified path, as in `impl <T as Trait>::Assoc`
+    fn choose_generics_over_qpath(&self) -> bool {
+        self.token.tok == Token::Lt
+            && (self.look_ahead(1, |t| match *t {
+                Token::Pound | Token::Gt | Token::Lifetime(_) => true,
+                _ => false,
+            }) || self.look_ahead(1, |t| self.token_is_ident(t))
+                && self.look_ahead(2, |t| match *t {
+                    Token::Gt | Token::Comma | Token::Colon | Token::Eq => true,
+                    _ => false,
+                })
+                || self.is_kw_at(1, "const"))
+    }
+
+    /// A const, method, type or macro in an impl.
+    pub fn parse_impl_item(&mut self) -> PResult<ImplItem> {
+        let mut attrs = self.parse_outer_attributes()?;
+        let lo = self.token.sp;
+        let vis = self.parse_visibility()?;
+        let defaultness = self.parse_defaultness();
+        let (ident, kind, generics) = if self.eat_keyword("type") {
+            // `type A<'a> where Self: 'a = T;`
+            let ident = self.parse_ident()?;
+            let mut generics = self.parse_generics()?;
+            generics.where_clause = self.parse_where_clause()?;
+            self.expect(&Token::Eq)?;
+            let ty = self.parse_ty()?;
+            self.parse_trailing_where_clause(&mut generics)?;
+            self.expect(&Token::Semi)?;
+            (ident, ImplItemKind::TyAlias(ty), generics)
+        } else if self.check_fn_front_matter() {
+            let header = self.parse_fn_front_matter()?;
+            let ident = self.parse_ident()?;
+            let mut generics = self.parse_generics()?;
+            let decl = self.parse_fn_decl(true, false)?;
+            generics.where_clause = self.parse_where_clause()?;
+            let body = self.parse_block_with_attrs(&mut attrs)?;
+            let sig = FnSig { header, decl };
+            (ident, ImplItemKind::Method(sig, body), generics)
+        } else if self.eat_keyword("const") {
+            let ident = self.parse_ident()?;
+            self.expect(&Token::Colon)?;
+            let ty = self.parse_ty()?;
+            self.expect(&Token::Eq)?;
+            let e = self.parse_expr()?;
+            self.expect(&Token::Semi)?;
+            (ident, ImplItemKind::Const(ty, e), self.no_generics())
+        } else if defaultness == Defaultness::Final && self.check_mac_item(&vis)
+        {
+            let mac = self.parse_mac_item()?;
+            let ident = self.invalid_ident(lo);
+            (ident, ImplItemKind::Macro(mac), self.no_generics())
+        } else {
+            return self.unexpected();
+        };
+        Ok(ImplItem {
+            id: self.next_node_id(),
+            ident,
+            vis,
+            defaultness,
+            attrs,
+            generics,
+            kind,
+            span: lo.to(self.prev_span),
+        })
+    }
+
+    // `m;` or `m { ... }`, after the `mod`
+    fn parse_item_mod(&mut self) -> PResult<ItemInfo> {
+        let ident = self.parse_ident()?;
+        if self.eat(&Token::Semi) {
+            let hi = self.prev_span.hi;
+            let module = Mod {
+                inner: Span::new(hi, hi),
+                items: vec![],
+                inline: false,
+            };
+            return Ok((ident, ItemKind::Mod(module), None));
+        }
+        self.expect(&Token::OpenDelim(DelimToken::Brace))?;
+        let inner_lo = self.prev_span.hi;
+        let inner_attrs = self.parse_inner_attributes()?;
+        let close = Token::CloseDelim(DelimToken::Brace);
+        let module = self.parse_mod_items(&close, inner_lo)?;
+        Ok((ident, ItemKind::Mod(module), Some(inner_attrs)))
+    }
+
+    // the items of a module up to `term`, which is eaten
+    fn parse_mod_items(
+        &mut self,
+        term: &Token,
+        inner_lo: usize,
+    ) -> PResult<Mod> {
+        let mut items = vec![];
+        loop {
+            let attrs = self.parse_outer_attributes()?;
+            let has_attrs = !attrs.is_empty();
+            if let Some(item) = self.parse_item_(attrs, true)? {
+                items.push(item);
+                continue;
+            }
+            if has_attrs {
+                let msg = "expected item after attributes";
+                return self.fatal(self.prev_span, msg.to_string());
+            }
+            if self.check(term) {
+                break;
+            }
+            let msg = format!("expected item, found {}", self.token_descr());
+            return self.fatal(self.token.sp, msg);
+        }
+        let inner = Span::new(inner_lo, self.token.sp.lo);
+        self.bump();
+        Ok(Mod {
+            inner,
+            items,
+            inline: true,
+        })
+    }
+
+    // `T<U> where U: Bound = V;`, after the `type`
+    fn parse_item_type(&mut self) -> PResult<ItemInfo> {
+        let ident = self.parse_ident()?;
+        let mut generics = self.parse_generics()?;
+        generics.where_clause = self.parse_where_clause()?;
+        self.expect(&Token::Eq)?;
+        let ty = self.parse_ty()?;
+        self.parse_trailing_where_clause(&mut generics)?;
+        self.expect(&Token::Semi)?;
+        Ok((ident, ItemKind::TyAlias(ty, generics), None))
+    }
+
+    // `E<T> { A, B(T), C { x: T }, D = 1 }`, after the `enum`
+    fn parse_item_enum(&mut self) -> PResult<ItemInfo> {
+        let ident = self.parse_ident()?;
+        let mut generics = self.parse_generics()?;
+        generics.where_clause = self.parse_where_clause()?;
+        self.expect(&Token::OpenDelim(DelimToken::Brace))?;
+        let close = Token::CloseDelim(DelimToken::Brace);
+        let variants =
+            self.parse_seq_to_end(&close, |p| p.parse_enum_variant())?;
+        let kind = ItemKind::Enum(EnumDef { variants }, generics);
+        Ok((ident, kind, None))
+    }
+
+    fn parse_enum_variant(&mut self) -> PResult<Variant> {
+        let attrs = self.parse_outer_attributes()?;
+        let lo = self.token.sp;
+        let ident = self.parse_ident()?;
+        let data = if self.check(&Token::OpenDelim(DelimToken::Brace)) {
+            VariantData::Struct(self.parse_record_struct_body()?)
+        } else if self.check(&Token::OpenDelim(DelimToken::Paren)) {
+            let fields = self.parse_tuple_struct_body()?;
+            VariantData::Tuple(fields, self.next_node_id())
+        } else {
+            VariantData::Unit(self.next_node_id())
+        };
+        let disr_expr = if self.eat(&Token::Eq) {
+            Some(AnonConst {
+                id: self.next_node_id(),
+                value: self.parse_expr()?,
+            })
+        } else {
+            None
+        };
+        Ok(Variant {
+            ident,
+            attrs,
+            id: self.next_node_id(),
+            data,
+            disr_expr,
+            span: lo.to(self.prev_span),
+        })
+    }
+
+    // `S<T>;`, `S<T>(T);` or `S<T> { x: T }`, after the `struct`, with a
+    // where-clause before the `;` or `{`, or after the `(T)`
+    fn parse_item_struct(&mut self) -> PResult<ItemInfo> {
+        let ident = self.parse_ident()?;
+        let mut generics = self.parse_generics()?;
+        let has_where = self.is_keyword("where");
+        generics.where_clause = self.parse_where_clause()?;
+        let data = if self.eat(&Token::Semi) {
+            VariantData::Unit(self.next_node_id())
+        } else if self.check(&Token::OpenDelim(DelimToken::Brace)) {
+            VariantData::Struct(self.parse_record_struct_body()?)
+        } else if !has_where && self.check(&Token::OpenDelim(DelimToken::Paren))
+        {
+            let fields = self.parse_tuple_struct_body()?;
+            generics.where_clause = self.parse_where_clause()?;
+            self.expect(&Token::Semi)?;
+            VariantData::Tuple(fields, self.next_node_id())
+        } else {
+            return self.unexpected();
+        };
+        Ok((ident, ItemKind::Struct(data, generics), None))
+    }
+
+    // `U<T> { x: T }`, after the `union`
+    fn parse_item_union(&mut self) -> PResult<ItemInfo> {
+        let ident = self.parse_ident()?;
+        let mut generics = self.parse_generics()?;
+        generics.where_clause = self.parse_where_clause()?;
+        let data = VariantData::Struct(self.parse_record_struct_body()?);
+        Ok((ident, ItemKind::Union(data, generics), None))
+    }
+
+    // `{ a: A, pub b: B }`
+    fn parse_record_struct_body(&mut self) -> PResult<Vec<StructField>> {
+        self.expect(&Token::OpenDelim(DelimToken::Brace))?;
+        let close = Token::CloseDelim(DelimToken::Brace);
+        self.parse_seq_to_end(&close, |p| {
+            let attrs = p.parse_outer_attributes()?;
+            let lo = p.token.sp;
+            let vis = p.parse_visibility()?;
+            let ident = p.parse_ident()?;
+            p.expect(&Token::Colon)?;
+            let ty = p.parse_ty()?;
+            Ok(StructField {
+                span: lo.to(p.prev_span),
+                ident: Some(ident),
+                vis,
+                id: p.next_node_id(),
+                ty,
+                attrs,
+            })
+        })
+    }
+
+    // `(A, pub B)`
+    fn parse_tuple_struct_body(&mut self) -> PResult<Vec<StructField>> {
+        self.expect(&Token::OpenDelim(DelimToken::Paren))?;
+        let close = Token::CloseDelim(DelimToken::Paren);
+        self.parse_seq_to_end(&close, |p| {
+            let attrs = p.parse_outer_attributes()?;
+            let lo = p.token.sp;
+            let vis = p.parse_visibility()?;
+            let ty = p.parse_ty()?;
+            Ok(StructField {
+                span: lo.to(p.prev_span),
+                ident: None,
+                vis,
+                id: p.next_node_id(),
+                ty,
+                attrs,
+            })
+        })
+    }
+
+    // `macro_rules! name { ... }`, which is an item even in a block
+    fn is_macro_rules_item(&self) -> bool {
+        self.is_kw_at(0, "macro_rules")
+            && self.look_ahead(1, |t| *t == Token::Not)
+            && self.look_ahead(2, |t| self.token_is_ident(t))
+    }
+
+    // whether a macro invocation starts here, in item position
+    fn check_mac_item(&mut self, vis: &Visibility) -> bool {
+        vis.node == VisibilityKind::Inherited
+            && self.check_path()
+            && self.look_ahead(1, |t| *t == Token::Not)
+    }
+
+    // `path!(...);` or `path! { ... }`
+    fn parse_mac_item(&mut self) -> PResult<Mac> {
+        let lo = self.token.sp;
+        let path = self.parse_path(PathStyle::Mod)?;
+        self.expect(&Token::Not)?;
+        let (delim, tts) = self.parse_delim_tts()?;
+        let span = lo.to(self.prev_span);
+        if delim != MacDelimiter::Brace {
+            self.expect(&Token::Semi)?;
+        }
+        Ok(Mac {
+            path,
+            delim,
+            tts,
+            span,
+        })
+    }
+
+    // a macro invocation or `macro_rules!` definition in item position
+    fn parse_item_mac(&mut self, lo: Span) -> PResult<ItemInfo> {
+        if self.is_macro_rules_item() {
+            self.bump();
+            self.bump();
+            let ident = self.parse_ident()?;
+            let (delim, tokens) = self.parse_delim_tts()?;
+            if delim != MacDelimiter::Brace {
+                self.expect(&Token::Semi)?;
+            }
+            let def = MacroDef {
+                tokens,
+                legacy: true,
+            };
+            return Ok((ident, ItemKind::MacroDef(def), None));
+        }
+        let mac = self.parse_mac_item()?;
+        Ok((self.invalid_ident(lo), ItemKind::Mac(mac), None))
+    }
+}
+
+// whether `tok` is a string literal, as the ABI of an `extern` is
+fn is_abi(tok: &Token) -> bool {
+    match *tok {
+        Token::Literal(token::Lit::Str_(_), _)
+        | Token::Literal(token::Lit::StrRaw(..), _) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{check_err, parse_with};
+    use super::super::{parse_crate_from_source_str, ParseError};
+    use super::*;
+    use crate::ast::{ExprKind, FunctionRetTy, GenericParamKind, StmtKind};
+    use lexer::interner::StringInterner;
+
+    fn parse(src: &str, interner: &mut StringInterner) -> Vec<P<Item>> {
+        match parse_crate_from_source_str(src, interner) {
+            Ok(krate) => krate.module.items,
+            Err(err) => panic!("{}: {}", src, err),
+        }
+    }
+
+    // the kind and name of each item
+    fn describe(src: &str) -> Result<Vec<String>, ParseError> {
+        let print = |krate: &Crate, interner: &StringInterner| {
+            let describe = |item: &P<Item>| {
+                let name = interner.get(item.ident.name);
+                let kind = item.kind.descriptive_variant();
+                format!("{} {}", kind, name).trim_end().to_string()
+            };
+            krate.module.items.iter().map(describe).collect()
+        };
+        parse_with(src, |p| p.parse_crate_mod(), print)
+    }
+
+    #[test]
+    fn test_item_kinds() {
+        let src = "
+            extern crate std as s;
+            use a::b;
+            static mut X: u8 = 1;
+            const _: () = ();
+            fn f() {}
+            mod m;
+            mod n { fn g() {} }
+            extern \"C\" {}
+            type T = u8;
+            enum E {}
+            struct S;
+            union U { a: u8 }
+            trait Tr {}
+            trait Al = Tr;
+            impl S {}
+            m!();
+            macro_rules! m { () => {} }
+        ";
+        assert_eq!(
+            describe(src).unwrap(),
+            vec![
+                "extern crate s",
+                "use",
+                "static item X",
+                "constant item _",
+                "function f",
+                "module m",
+                "module n",
+                "foreign module",
+                "type alias T",
+                "enum E",
+                "struct S",
+                "union U",
+                "trait Tr",
+                "trait alias Al",
+                "impl",
+                "item macro invocation",
+                "macro definition m",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_contextual_keywords() {
+        let src = "
+            unsafe auto trait A {}
+            auto trait B {}
+            default unsafe impl A for u8 {}
+            fn union() { union.x; default; auto }
+            macro_rules! macro_rules { () => {} }
+            m! { }
+        ";
+        assert_eq!(
+            describe(src).unwrap(),
+            vec![
+                "trait A",
+                "trait B",
+                "impl",
+                "function union",
+                "macro definition macro_rules",
+                "item macro invocation",
+            ]
+        );
+        let mut interner = StringInterner::empty();
+        let items = parse(src, &mut interner);
+        match items[0].kind {
+            ItemKind::Trait(IsAuto::Yes, Unsafety::Unsafe, ..) => {}
+            ref kind => panic!("{:?}", kind),
+        }
+        match items[2].kind {
+            ItemKind::Impl(
+                Unsafety::Unsafe,
+                ImplPolarity::Positive,
+                Defaultness::Default,
+                ..,
+            ) => {}
+            ref kind => panic!("{:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_visibility() {
+        let src = "
+            pub fn a() {}
+            pub(crate) fn b() {}
+            crate fn c() {}
+            pub(self) fn d() {}
+            pub(super) fn e() {}
+            pub(in a::b) fn f() {}
+            fn g() {}
+            struct S(pub (u8, u8), pub(crate) u8);
+        ";
+        let mut interner = StringInterner::empty();
+        let items = parse(src, &mut interner);
+        let restricted = |vis: &Visibility| match vis.node {
+            VisibilityKind::Restricted { ref path, .. } => path.segments.len(),
+            ref vis => panic!("{:?}", vis),
+        };
+        assert_eq!(items[0].vis.node, VisibilityKind::Public);
+        let at = |s: &str| src.find(s).unwrap();
+        assert_eq!(items[0].vis.span, Span::new(at("pub fn a"), at(" fn a")));
+        assert_eq!(
+            items[1].vis.node,
+            VisibilityKind::Crate(CrateSugar::PubCrate)
+        );
+        assert_eq!(items[1].vis.span, Span::new(at("pub(crate)"), at(" fn b")));
+        assert_eq!(
+            items[2].vis.node,
+            VisibilityKind::Crate(CrateSugar::JustCrate)
+        );
+        assert_eq!(restricted(&items[3].vis), 1);
+        assert_eq!(restricted(&items[4].vis), 1);
+        assert_eq!(restricted(&items[5].vis), 2);
+        assert_eq!(items[6].vis.node, VisibilityKind::Inherited);
+        match items[7].kind {
+            ItemKind::Struct(VariantData::Tuple(ref fields, _), _) => {
+                assert_eq!(fields[0].vis.node, VisibilityKind::Public);
+                match fields[0].ty.kind {
+                    TyKind::Tup(ref tys) => assert_eq!(tys.len(), 2),
+                    ref ty => panic!("{:?}", ty),
+                }
+                assert_eq!(
+                    fields[1].vis.node,
+                    VisibilityKind::Crate(CrateSugar::PubCrate)
+                );
+            }
+            ref kind => panic!("{:?}", kind),
+        }
+        check_err(
+            describe,
+            "pub let",
+            "expected item after visibility, found keyword `let`",
+        );
+    }
+
+    // `use` trees as they were written, spaces dropped
+    fn use_tree_to_string(tree: &UseTree, interner: &StringInterner) -> String {
+        let mut s: Vec<_> = tree
+            .prefix
+            .segments
+            .iter()
+            .map(|seg| interner.get(seg.ident.name).to_string())
+            .collect();
+        match tree.kind {
+            UseTreeKind::Simple(None) => {}
+            UseTreeKind::Simple(Some(rename)) => {
+                let last = s.pop().unwrap();
+                s.push(format!("{} as {}", last, interner.get(rename.name)));
+            }
+            UseTreeKind::Nested(ref trees) => {
+                let trees: Vec<_> = trees
+                    .iter()
+                    .map(|t| use_tree_to_string(t, interner))
+                    .collect();
+                s.push(format!("{{{}}}", trees.join(", ")));
+            }
+            UseTreeKind::Glob => s.push("*".to_string()),
+        }
+        s.join("::")
+    }
+
+    #[test]
+    fn test_use_trees() {
+        let srcs = [
+            ("use a;", "a"),
+            ("use a::b as c;", "a::b as c"),
+            ("use a::b as _;", "a::b as _"),
+            ("use ::a::*;", "{{root}}::a::*"),
+            (
+                "use a::{b, c::{self, d as e}, f::*,};",
+                "a::{b, c::{self, d as e}, f::*}",
+            ),
+            ("use {a, b};", "{a, b}"),
+            ("use ::{a};", "{{root}}::{a}"),
+            ("use *;", "*"),
+            ("use self::a::super::b;", "self::a::super::b"),
+            ("use crate::a;", "crate::a"),
+        ];
+        for &(src, expected) in &srcs {
+            let mut interner = StringInterner::empty();
+            let items = parse(src, &mut interner);
+            match items[0].kind {
+                ItemKind::Use(ref tree) => {
+                    assert_eq!(use_tree_to_string(tree, &interner), expected);
+                    assert_eq!(tree.span, Span::new(4, src.len() - 1));
+                }
+                ref kind => panic!("{:?}", kind),
+            }
+        }
+        check_err(describe, "use a::;", "expected identifier, found `;`");
+        check_err(describe, "use a::<T>;", "expected identifier, found `<`");
+    }
+
+    #[test]
+    fn test_fns() {
+        let src = "
+            const async unsafe extern \"C\"
+            fn a<'a, T: Clone + 'a>(x: T, (y, z): (u8, u8)) -> T {
+                #![inner]
+                x
+            }
+            extern fn b() {}
+            async fn c() {}
+        ";
+        let mut interner = StringInterner::empty();
+        let items = parse(src, &mut interner);
+        match items[0].kind {
+            ItemKind::Fn(ref sig, ref generics, ref body) => {
+                assert_eq!(sig.header.constness, Constness::Const);
+                assert_eq!(sig.header.asyncness, IsAsync::Async);
+                assert_eq!(sig.header.unsafety, Unsafety::Unsafe);
+                match sig.header.ext {
+                    Extern::Explicit(lit) => assert_eq!(lit.span.len(), 3),
+                    ext => panic!("{:?}", ext),
+                }
+                assert_eq!(sig.decl.inputs.len(), 2);
+                assert!(!sig.decl.c_variadic);
+                match sig.decl.output {
+                    FunctionRetTy::Ty(_) => {}
+                    ref ret => panic!("{:?}", ret),
+                }
+                assert_eq!(generics.params.len(), 2);
+                assert_eq!(generics.params[0].kind, GenericParamKind::Lifetime);
+                assert_eq!(generics.params[1].bounds.len(), 2);
+                assert_eq!(body.stmts.len(), 1);
+            }
+            ref kind => panic!("{:?}", kind),
+        }
+        assert_eq!(items[0].attrs.len(), 1);
+        match items[1].kind {
+            ItemKind::Fn(ref sig, ..) => {
+                assert_eq!(sig.header.ext, Extern::Implicit)
+            }
+            ref kind => panic!("{:?}", kind),
+        }
+        check_err(
+            describe,
+            "fn f(x) {}",
+            "expected one of `:`, `@`, found `)`",
+        );
+        check_err(
+            describe,
+            "fn f(a: u8, ...) {}",
+            "only foreign functions are allowed to be C-variadic",
+        );
+        check_err(
+            describe,
+            "fn f() -> u8;",
+            "expected one of `!`, `+`, `::`, `<`, `where`, `{`, found `;`",
+        );
+    }
+
+    // the self params of the methods of an impl, as `mutability type`
+    fn self_params(src: &str) -> Vec<String> {
+        let mut interner = StringInterner::empty();
+        let items = parse(src, &mut interner);
+        let impl_items = match items[0].kind {
+            ItemKind::Impl(.., ref items) => items,
+            ref kind => panic!("{:?}", kind),
+        };
+        impl_items
+            .iter()
+            .map(|item| match item.kind {
+                ImplItemKind::Method(ref sig, _) => {
+                    let param = &sig.decl.inputs[0];
+                    let mutbl = match param.pat.kind {
+                        PatKind::Ident(BindingMode::ByValue(m), ..) => m,
+                        PatKind::Path(..) => return "path".to_string(),
+                        ref pat => panic!("{:?}", pat),
+                    };
+                    let ty = match param.ty.kind {
+                        TyKind::ImplicitSelf => "Self".to_string(),
+                        TyKind::Rptr(lt, ref mt) => {
+                            assert_eq!(mt.ty.kind, TyKind::ImplicitSelf);
+                            format!(
+                                "&{}{:?}",
+                                lt.map_or("", |lt| interner.get(lt.ident.name)),
+                                mt.mutbl
+                            )
+                        }
+                        _ => {
+                            src[param.ty.span.lo..param.ty.span.hi].to_string()
+                        }
+                    };
+                    format!("{:?} {}", mutbl, ty)
+                }
+                ref kind => panic!("{:?}", kind),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_self_params() {
+        let src = "impl S {
+            fn a(self) {}
+            fn b(mut self) {}
+            fn c(&self) {}
+            fn d(&mut self, x: u8) {}
+            fn e(&'a self) {}
+            fn f(&'a mut self) {}
+            fn g(self: Box<Self>) {}
+            fn h(mut self: Self) {}
+            fn i(self::S: u8) {}
+        }";
+        assert_eq!(
+            self_params(src),
+            vec![
+                "Immutable Self",
+                "Mutable Self",
+                "Immutable &Immutable",
+                "Immutable &Mutable",
+                "Immutable &'aImmutable",
+                "Immutable &'aMutable",
+                "Immutable Box<Self>",
+                "Mutable Self",
+                "path",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_impls() {
+        let src = "
+            impl<'a, T: Tr> Tr for S<'a, T> { type A = u8; }
+            impl !Send for S {}
+            impl<T> S<T> {
+                pub const X: u8 = 1;
+                default fn f(&self) {}
+                pub(crate) type B = u8;
+                m!();
+            }
+        ";
+        let mut interner = StringInterner::empty();
+        let items = parse(src, &mut interner);
+        match items[0].kind {
+            ItemKind::Impl(
+                _,
+                ImplPolarity::Positive,
+                Defaultness::Final,
+                ref generics,
+                Some(ref trait_ref),
+                _,
+                ref items,
+            ) => {
+                assert_eq!(generics.params.len(), 2);
+                assert_eq!(trait_ref.path.segments.len(), 1);
+                assert_eq!(items.len(), 1);
+            }
+            ref kind => panic!("{:?}", kind),
+        }
+        match items[1].kind {
+            ItemKind::Impl(_, ImplPolarity::Negative, _, _, Some(_), ..) => {}
+            ref kind => panic!("{:?}", kind),
+        }
+        match items[2].kind {
+            ItemKind::Impl(.., ref items) => {
+                let kinds: Vec<_> = items
+                    .iter()
+                    .map(|item| match item.kind {
+                        ImplItemKind::Const(..) => "const",
+                        ImplItemKind::Method(..) => "method",
+                        ImplItemKind::TyAlias(..) => "type",
+                        ImplItemKind::Macro(..) => "macro",
+                    })
+                    .collect();
+                assert_eq!(kinds, vec!["const", "method", "type", "macro"]);
+                assert_eq!(items[0].vis.node, VisibilityKind::Public);
+                assert_eq!(items[1].defaultness, Defaultness::Default);
+            }
+            ref kind => panic!("{:?}", kind),
+        }
+        check_err(describe, "impl !S {}", "inherent impls cannot be negative");
+        check_err(describe, "impl &S for T {}", "expected a trait, found type");
+    }
+
+    #[test]
+    fn test_traits() {
+        let src = "
+            pub trait Tr<'a>: Sized + 'a {
+                const N: usize;
+                const M: usize = 1;
+                type A: Clone;
+                type B = u8;
+                fn f(&self) -> u8;
+                unsafe fn g() { #![a] }
+                m! {}
+            }
+        ";
+        let mut interner = StringInterner::empty();
+        let items = parse(src, &mut interner);
+        match items[0].kind {
+            ItemKind::Trait(
+                IsAuto::No,
+                Unsafety::Normal,
+                ref g,
+                ref b,
+                ref items,
+            ) => {
+                assert_eq!(g.params.len(), 1);
+                assert_eq!(b.len(), 2);
+                let kinds: Vec<_> = items
+                    .iter()
+                    .map(|item| match item.kind {
+                        TraitItemKind::Const(_, ref e) => {
+                            format!("const {}", e.is_some())
+                        }
+                        TraitItemKind::Method(_, ref b) => {
+                            format!("method {}", b.is_some())
+                        }
+                        TraitItemKind::Type(ref b, ref d) => {
+                            format!("type {} {}", b.len(), d.is_some())
+                        }
+                        TraitItemKind::Macro(..) => "macro".to_string(),
+                    })
+                    .collect();
+                assert_eq!(
+                    kinds,
+                    vec![
+                        "const false",
+                        "const true",
+                        "type 1 false",
+                        "type 0 true",
+                        "method false",
+                        "method true",
+                        "macro",
+                    ]
+                );
+                assert_eq!(items[5].attrs.len(), 1);
+            }
+            ref kind => panic!("{:?}", kind),
+        }
+        check_err(
+            describe,
+            "trait A: B = C;",
+            "bounds are not allowed on trait aliases",
+        );
+        check_err(
+            describe,
+            "unsafe trait A = C;",
+            "trait aliases cannot be `unsafe`",
+        );
+    }
+
+    #[test]
+    fn test_adts() {
+        let src = "
+            struct A;
+            struct B(u8, pub u16);
+            struct C<T> { pub a: T, #[attr] b: u8, }
+            enum D { X, Y(u8), Z { a: u8 } = 1, }
+            union E { a: u8, b: u16 }
+        ";
+        let mut interner = StringInterner::empty();
+        let items = parse(src, &mut interner);
+        let fields: Vec<_> = items
+            .iter()
+            .filter_map(|item| match item.kind {
+                ItemKind::Struct(ref data, _)
+                | ItemKind::Union(ref data, _) => Some(data.fields().len()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(fields, vec![0, 2, 2, 2]);
+        match items[2].kind {
+            ItemKind::Struct(VariantData::Struct(ref fields), ref generics) => {
+                assert_eq!(generics.params.len(), 1);
+                assert_eq!(fields[1].attrs.len(), 1);
+                assert_eq!(interner.get(fields[1].ident.unwrap().name), "b");
+            }
+            ref kind => panic!("{:?}", kind),
+        }
+        match items[3].kind {
+            ItemKind::Enum(ref def, _) => {
+                assert_eq!(def.variants.len(), 3);
+                match def.variants[0].data {
+                    VariantData::Unit(_) => {}
+                    ref data => panic!("{:?}", data),
+                }
+                assert_eq!(def.variants[1].data.fields().len(), 1);
+                assert!(def.variants[2].disr_expr.is_some());
+            }
+            ref kind => panic!("{:?}", kind),
+        }
+        check_err(
+            describe,
+            "struct S",
+            "expected one of `(`, `;`, `<`, `where`, `{`, found `<eof>`",
+        );
+        check_err(
+            describe,
+            "struct S(u8)",
+            "expected one of `;`, `where`, found `<eof>`",
+        );
+    }
+
+    #[test]
+    fn test_foreign_items() {
+        let src = "
+            extern \"C\" {
+                #![link(name = \"c\")]
+                pub fn printf(fmt: &u8, ...) -> i32;
+                static mut errno: i32;
+                type FILE;
+                m!();
+            }
+            extern {}
+        ";
+        let mut interner = StringInterner::empty();
+        let items = parse(src, &mut interner);
+        assert_eq!(items[0].attrs.len(), 1);
+        match items[0].kind {
+            ItemKind::ForeignMod(ref m) => {
+                assert!(m.abi.is_some());
+                match m.items[0].kind {
+                    ForeignItemKind::Fn(ref decl, _) => {
+                        assert!(decl.c_variadic);
+                        assert_eq!(decl.inputs[1].ty.kind, TyKind::CVarArgs);
+                    }
+                    ref kind => panic!("{:?}", kind),
+                }
+                assert_eq!(m.items[0].vis.node, VisibilityKind::Public);
+                match m.items[1].kind {
+                    ForeignItemKind::Static(_, Mutability::Mutable) => {}
+                    ref kind => panic!("{:?}", kind),
+                }
+                assert_eq!(m.items[2].kind, ForeignItemKind::Ty);
+                match m.items[3].kind {
+                    ForeignItemKind::Macro(ref mac) => {
+                        assert_eq!(mac.delim, MacDelimiter::Parenthesis)
+                    }
+                    ref kind => panic!("{:?}", kind),
+                }
+            }
+            ref kind => panic!("{:?}", kind),
+        }
+        match items[1].kind {
+            ItemKind::ForeignMod(ref m) => assert!(m.abi.is_none()),
+            ref kind => panic!("{:?}", kind),
+        }
+        check_err(
+            describe,
+            "extern { fn f(..., a: u8); }",
+            "`...` must be the last parameter of a C-variadic function",
+        );
+        check_err(
+            describe,
+            "extern \"C\"u8 {}",
+            "suffixes on an ABI spec are invalid",
+        );
+    }
+
+    #[test]
+    fn test_modules() {
+        let src = "#![a] mod m { #![b] mod n {} } #[c] mod o;";
+        let mut interner = StringInterner::empty();
+        let krate = parse_crate_from_source_str(src, &mut interner).unwrap();
+        assert_eq!(krate.attrs.len(), 1);
+        assert_eq!(krate.module.inner, Span::new(0, src.len()));
+        let items = &krate.module.items;
+        assert_eq!(items[0].attrs.len(), 1);
+        assert_eq!(items[0].span, Span::new(6, 30));
+        match items[0].kind {
+            ItemKind::Mod(ref m) => {
+                assert!(m.inline);
+                assert_eq!(m.inner, Span::new(13, 29));
+                assert_eq!(m.items.len(), 1);
+            }
+            ref kind => panic!("{:?}", kind),
+        }
+        match items[1].kind {
+            ItemKind::Mod(ref m) => assert!(!m.inline),
+            ref kind => panic!("{:?}", kind),
+        }
+        check_err(describe, "#[a]", "expected item after attributes");
+        check_err(describe, "mod m { 1 }", "expected item, found `1`");
+        check_err(describe, "}", "expected item, found `}`");
+    }
+
+    #[test]
+    fn test_items_in_blocks() {
+        let src = "fn f() {
+            use a;
+            fn g() {}
+            struct S;
+            macro_rules! m { () => {} }
+            m!();
+            unsafe { 1 };
+            static || 1;
+            let x = async move {};
+            extern crate c;
+            #[a] impl S {}
+            S
+        }";
+        let mut interner = StringInterner::empty();
+        let items = parse(src, &mut interner);
+        let body = match items[0].kind {
+            ItemKind::Fn(_, _, ref body) => body,
+            ref kind => panic!("{:?}", kind),
+        };
+        let kinds: Vec<_> = body
+            .stmts
+            .iter()
+            .map(|stmt| match stmt.kind {
+                StmtKind::Item(ref item) => item.kind.descriptive_variant(),
+                StmtKind::Mac(..) => "mac",
+                StmtKind::Semi(ref e) => match e.kind {
+                    ExprKind::Block(..) => "block",
+                    ExprKind::Closure(..) => "closure",
+                    _ => "expr",
+                },
+                StmtKind::Local(..) => "let",
+                StmtKind::Expr(..) => "expr",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "use",
+                "function",
+                "struct",
+                "macro definition",
+                "mac",
+                "block",
+                "closure",
+                "let",
+                "extern crate",
+                "impl",
+                "expr",
+            ]
+        );
+        match body.stmts[9].kind {
+            StmtKind::Item(ref item) => assert_eq!(item.attrs.len(), 1),
+            ref kind => panic!("{:?}", kind),
+        }
+    }
+}
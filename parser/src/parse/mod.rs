@@ -0,0 +1,614 @@
+//! A recursive descent parser producing the AST of `ast`, modelled after
+//! rustc's `syntax::parse::parser`.
+//!
+//! The parser works on the lexed tokens of a whole file, trivia dropped, and
+//! stops at the first error.
+
+use crate::ast::{self, Crate, Expr, ExprKind, Ident, Lifetime, NodeId, P};
+use lexer::interner::StringInterner;
+use lexer::str_to_token_slice;
+use lexer::token::{token_to_string, Span, Token, TokenAndSpan};
+use lexer::tokenstream::{build_token_trees, TokenTree, TokenTreeError};
+use lexer::LexError;
+use std::fmt;
+
+mod attr;
+mod classify;
+mod expr;
+mod generics;
+mod item;
+mod pat;
+mod path;
+mod stmt;
+mod ty;
+
+pub use self::classify::expr_requires_semi_to_be_stmt;
+pub use self::expr::{AssocOp, Fixity};
+pub use self::path::PathStyle;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParseError {
+    Lex(LexError),
+    /// What was wrong, and where.
+    Syntax(Span, String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::Lex(ref err) => match err.span() {
+                Some(sp) => write!(f, "{}..{}: {}", sp.lo, sp.hi, err),
+                None => write!(f, "{}", err),
+            },
+            ParseError::Syntax(sp, ref msg) => {
+                write!(f, "{}..{}: {}", sp.lo, sp.hi, msg)
+            }
+        }
+    }
+}
+
+impl From<LexError> for ParseError {
+    fn from(err: LexError) -> ParseError {
+        ParseError::Lex(err)
+    }
+}
+
+pub type PResult<T> = Result<T, ParseError>;
+
+/// Keywords and reserved identifiers of the 2018 edition, which are not
+/// identifiers unless written raw.
+const RESERVED: &[&str] = &[
+    "_", "abstract", "as", "async", "await", "become", "box", "break", "const",
+    "continue", "crate", "do", "dyn", "else", "enum", "extern", "false",
+    "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro", "match",
+    "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self",
+    "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// The keywords that may be path segments.
+const PATH_SEGMENT_KEYWORDS: &[&str] = &["self", "Self", "super", "crate"];
+
+pub fn is_reserved(name: &str) -> bool {
+    RESERVED.contains(&name)
+}
+
+/// Restrictions on what the expression being parsed may be.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Restrictions(u8);
+
+impl Restrictions {
+    const NONE: Restrictions = Restrictions(0);
+    /// The expression is a statement: one that needs no `;`, like a block
+    /// or an `if`, ends it, so `{ 1 } - 1` is two statements.
+    const STMT_EXPR: Restrictions = Restrictions(1);
+    /// A `{` is not a struct literal, as in `if x == S {}`.
+    const NO_STRUCT_LITERAL: Restrictions = Restrictions(2);
+
+    fn contains(self, other: Restrictions) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    fn without(self, other: Restrictions) -> Restrictions {
+        Restrictions(self.0 & !other.0)
+    }
+}
+
+/// What the parser would have accepted, for the message of an error.
+#[derive(Clone, PartialEq, Debug)]
+enum TokenType {
+    Token(Token),
+    Keyword(&'static str),
+    Operator,
+    Lifetime,
+    Ident,
+    Path,
+    Type,
+    Const,
+}
+
+impl TokenType {
+    fn to_string(&self, interner: &StringInterner) -> String {
+        match *self {
+            TokenType::Token(ref tok) => {
+                format!("`{}`", token_to_string(tok, interner))
+            }
+            TokenType::Keyword(kw) => format!("`{}`", kw),
+            TokenType::Operator => "an operator".to_string(),
+            TokenType::Lifetime => "lifetime".to_string(),
+            TokenType::Ident => "identifier".to_string(),
+            TokenType::Path => "path".to_string(),
+            TokenType::Type => "type".to_string(),
+            TokenType::Const => "const".to_string(),
+        }
+    }
+}
+
+pub struct Parser<'a> {
+    pub interner: &'a mut StringInterner,
+    // the tokens without trivia, ending with `Eof`
+    tokens: Vec<TokenAndSpan>,
+    // index in `tokens` of the token after `token`
+    pos: usize,
+    /// The current token. It may be what is left of a token the parser split,
+    /// like the second `>` of `>>`.
+    pub token: TokenAndSpan,
+    /// The span of the token before `token`.
+    pub prev_span: Span,
+    restrictions: Restrictions,
+    expected_tokens: Vec<TokenType>,
+    next_node_id: u32,
+}
+
+impl<'a> Parser<'a> {
+    /// A parser over lexed tokens. Trivia is skipped, and anything after an
+    /// `Eof` ignored.
+    pub fn new(
+        tokens: Vec<TokenAndSpan>,
+        interner: &'a mut StringInterner,
+    ) -> Parser<'a> {
+        let mut tokens: Vec<_> = tokens
+            .into_iter()
+            .take_while(|ts| ts.tok != Token::Eof)
+            .filter(|ts| !ts.tok.is_trivia())
+            .collect();
+        let end = tokens.last().map_or(0, |ts| ts.sp.hi);
+        tokens.push(TokenAndSpan {
+            tok: Token::Eof,
+            sp: Span::new(end, end),
+        });
+        let token = tokens[0];
+        Parser {
+            interner,
+            tokens,
+            pos: 1,
+            token,
+            prev_span: Span::new(token.sp.lo, token.sp.lo),
+            restrictions: Restrictions::NONE,
+            expected_tokens: vec![],
+            next_node_id: 1,
+        }
+    }
+
+    /// Lexes `src` and makes a parser over the tokens.
+    pub fn from_source_str(
+        src: &str,
+        interner: &'a mut StringInterner,
+    ) -> PResult<Parser<'a>> {
+        let tokens = str_to_token_slice(src.as_bytes(), interner)?;
+        Ok(Parser::new(tokens, interner))
+    }
+
+    pub fn next_node_id(&mut self) -> NodeId {
+        let id = NodeId(self.next_node_id);
+        self.next_node_id += 1;
+        id
+    }
+
+    /// Moves to the next token.
+    pub fn bump(&mut self) {
+        self.prev_span = self.token.sp;
+        if self.pos < self.tokens.len() {
+            self.token = self.tokens[self.pos];
+            self.pos += 1;
+        }
+        self.expected_tokens.clear();
+    }
+
+    /// The token `dist` tokens ahead, the current one for 0.
+    pub fn look_ahead<R, F>(&self, dist: usize, f: F) -> R
+    where
+        F: FnOnce(&Token) -> R,
+    {
+        if dist == 0 {
+            return f(&self.token.tok);
+        }
+        let last = self.tokens.len() - 1;
+        f(&self.tokens[(self.pos + dist - 1).min(last)].tok)
+    }
+
+    /// Whether the current token is `tok`, remembering it as expected.
+    pub fn check(&mut self, tok: &Token) -> bool {
+        let is_present = self.token.tok == *tok;
+        if !is_present {
+            self.expected_tokens.push(TokenType::Token(*tok));
+        }
+        is_present
+    }
+
+    pub fn eat(&mut self, tok: &Token) -> bool {
+        let is_present = self.check(tok);
+        if is_present {
+            self.bump();
+        }
+        is_present
+    }
+
+    pub fn expect(&mut self, tok: &Token) -> PResult<()> {
+        if self.eat(tok) {
+            Ok(())
+        } else {
+            self.unexpected()
+        }
+    }
+
+    /// Eats `tok`, or its start if the current token is longer and starts
+    /// with it, as `&` does `&&`, leaving the rest as the current token.
+    fn break_and_eat(&mut self, tok: &Token) -> bool {
+        if self.check(tok) {
+            self.bump();
+            return true;
+        }
+        match self.token.split(tok, self.interner) {
+            Some((first, rest)) => {
+                self.prev_span = first.sp;
+                self.token = rest;
+                self.expected_tokens.clear();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn token_is_keyword(&self, tok: &Token, kw: &str) -> bool {
+        match *tok {
+            Token::Ident(name, false) => self.interner.get(name) == kw,
+            _ => false,
+        }
+    }
+
+    pub fn is_keyword(&self, kw: &str) -> bool {
+        self.token_is_keyword(&self.token.tok, kw)
+    }
+
+    pub fn check_keyword(&mut self, kw: &'static str) -> bool {
+        self.expected_tokens.push(TokenType::Keyword(kw));
+        self.is_keyword(kw)
+    }
+
+    pub fn eat_keyword(&mut self, kw: &'static str) -> bool {
+        if self.check_keyword(kw) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn expect_keyword(&mut self, kw: &'static str) -> PResult<()> {
+        if self.eat_keyword(kw) {
+            Ok(())
+        } else {
+            self.unexpected()
+        }
+    }
+
+    /// Whether `tok` is an identifier that is not a keyword.
+    pub fn token_is_ident(&self, tok: &Token) -> bool {
+        match *tok {
+            Token::Ident(name, raw) => {
+                raw || !is_reserved(self.interner.get(name))
+            }
+            _ => false,
+        }
+    }
+
+    fn token_is_path_segment_keyword(&self, tok: &Token) -> bool {
+        match *tok {
+            Token::Ident(name, false) => {
+                PATH_SEGMENT_KEYWORDS.contains(&self.interner.get(name))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether `tok` may start a path, not counting the `<` of a qualified
+    /// one.
+    pub fn token_is_path_start(&self, tok: &Token) -> bool {
+        *tok == Token::ModSep
+            || self.token_is_ident(tok)
+            || self.token_is_path_segment_keyword(tok)
+    }
+
+    pub fn check_ident(&mut self) -> bool {
+        self.expected_tokens.push(TokenType::Ident);
+        self.token_is_ident(&self.token.tok)
+    }
+
+    pub fn check_path(&mut self) -> bool {
+        self.expected_tokens.push(TokenType::Path);
+        self.token_is_path_start(&self.token.tok)
+    }
+
+    pub fn check_lifetime(&mut self) -> bool {
+        self.expected_tokens.push(TokenType::Lifetime);
+        match self.token.tok {
+            Token::Lifetime(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn parse_ident(&mut self) -> PResult<Ident> {
+        if self.check_ident() {
+            if let Token::Ident(name, _) = self.token.tok {
+                let sp = self.token.sp;
+                self.bump();
+                return Ok(Ident::new(name, sp));
+            }
+        }
+        let msg = format!("expected identifier, found {}", self.token_descr());
+        self.fatal(self.token.sp, msg)
+    }
+
+    pub fn expect_lifetime(&mut self) -> PResult<Lifetime> {
+        if let Token::Lifetime(name) = self.token.tok {
+            let ident = Ident::new(name, self.token.sp);
+            self.bump();
+            return Ok(Lifetime {
+                id: self.next_node_id(),
+                ident,
+            });
+        }
+        let msg = format!("expected lifetime, found {}", self.token_descr());
+        self.fatal(self.token.sp, msg)
+    }
+
+    pub fn parse_mutability(&mut self) -> ast::Mutability {
+        if self.eat_keyword("mut") {
+            ast::Mutability::Mutable
+        } else {
+            ast::Mutability::Immutable
+        }
+    }
+
+    /// The current token as error messages refer to it.
+    fn token_descr(&self) -> String {
+        let text = token_to_string(&self.token.tok, self.interner);
+        match self.token.tok {
+            Token::Ident(name, false)
+                if is_reserved(self.interner.get(name)) =>
+            {
+                format!("keyword `{}`", text)
+            }
+            Token::DocComment(_) => "doc comment".to_string(),
+            _ => format!("`{}`", text),
+        }
+    }
+
+    pub fn fatal<T>(&self, sp: Span, msg: String) -> PResult<T> {
+        Err(ParseError::Syntax(sp, msg))
+    }
+
+    /// The error for the current token, listing what was expected instead.
+    pub fn unexpected<T>(&mut self) -> PResult<T> {
+        let mut expected: Vec<_> = self
+            .expected_tokens
+            .iter()
+            .map(|t| t.to_string(self.interner))
+            .collect();
+        expected.sort();
+        expected.dedup();
+        let found = self.token_descr();
+        let msg = match expected.len() {
+            0 => format!("unexpected token: {}", found),
+            1 => format!("expected {}, found {}", expected[0], found),
+            _ => format!(
+                "expected one of {}, found {}",
+                expected.join(", "),
+                found
+            ),
+        };
+        self.fatal(self.token.sp, msg)
+    }
+
+    fn with_res<F, T>(&mut self, r: Restrictions, f: F) -> T
+    where
+        F: FnOnce(&mut Self) -> T,
+    {
+        let old = self.restrictions;
+        self.restrictions = r;
+        let res = f(self);
+        self.restrictions = old;
+        res
+    }
+
+    pub fn mk_expr(
+        &mut self,
+        span: Span,
+        kind: ExprKind,
+        attrs: Vec<ast::Attribute>,
+    ) -> P<Expr> {
+        P(Expr {
+            id: self.next_node_id(),
+            kind,
+            span,
+            attrs,
+        })
+    }
+
+    /// Parses `f` separated by commas, allowing a trailing one, up to `ket`,
+    /// which is left as the current token.
+    fn parse_seq_to_before_end<T, F>(
+        &mut self,
+        ket: &Token,
+        mut f: F,
+    ) -> PResult<Vec<T>>
+    where
+        F: FnMut(&mut Self) -> PResult<T>,
+    {
+        let mut v = vec![];
+        while !self.check(ket) {
+            v.push(f(self)?);
+            if !self.eat(&Token::Comma) {
+                if !self.check(ket) {
+                    return self.unexpected();
+                }
+                break;
+            }
+        }
+        Ok(v)
+    }
+
+    /// `parse_seq_to_before_end`, then eats `ket`.
+    fn parse_seq_to_end<T, F>(&mut self, ket: &Token, f: F) -> PResult<Vec<T>>
+    where
+        F: FnMut(&mut Self) -> PResult<T>,
+    {
+        let v = self.parse_seq_to_before_end(ket, f)?;
+        self.bump();
+        Ok(v)
+    }
+
+    /// The token trees up to the close delimiter matching an open one
+    /// already eaten, which is left as the current token.
+    fn parse_tts_to_close(&mut self) -> PResult<Vec<TokenTree>> {
+        let mut tokens = vec![];
+        let mut depth = 0;
+        loop {
+            match self.token.tok {
+                Token::OpenDelim(_) => depth += 1,
+                Token::CloseDelim(_) if depth == 0 => break,
+                Token::CloseDelim(_) => depth -= 1,
+                Token::Eof => {
+                    let msg = "this file contains an unclosed delimiter";
+                    return self.fatal(self.token.sp, msg.to_string());
+                }
+                _ => {}
+            }
+            tokens.push(self.token);
+            self.bump();
+        }
+        build_token_trees(tokens).map_err(|err| {
+            let (sp, msg) = match err {
+                TokenTreeError::MismatchedCloseDelim { close, .. } => {
+                    (close.1, "mismatched closing delimiter")
+                }
+                TokenTreeError::UnexpectedCloseDelim(_, sp) => {
+                    (sp, "unexpected closing delimiter")
+                }
+                TokenTreeError::UnclosedDelim(_, sp) => {
+                    (sp, "unclosed delimiter")
+                }
+            };
+            ParseError::Syntax(sp, msg.to_string())
+        })
+    }
+
+    /// A delimited token tree, as the arguments of a macro.
+    fn parse_delim_tts(
+        &mut self,
+    ) -> PResult<(ast::MacDelimiter, Vec<TokenTree>)> {
+        let delim = match self.token.tok {
+            Token::OpenDelim(delim) => delim,
+            _ => {
+                let msg = format!(
+                    "expected open delimiter, found {}",
+                    self.token_descr()
+                );
+                return self.fatal(self.token.sp, msg);
+            }
+        };
+        let open_sp = self.token.sp;
+        self.bump();
+        let tts = self.parse_tts_to_close()?;
+        if self.token.tok != Token::CloseDelim(delim) {
+            let msg = "mismatched closing delimiter".to_string();
+            return self.fatal(self.token.sp.to(open_sp), msg);
+        }
+        self.bump();
+        let delim = ast::MacDelimiter::from_token(delim)
+            .expect("the lexer makes no invisible delimiters");
+        Ok((delim, tts))
+    }
+
+    fn expect_eof(&mut self) -> PResult<()> {
+        if self.check(&Token::Eof) {
+            Ok(())
+        } else {
+            self.unexpected()
+        }
+    }
+}
+
+/// Parses `src` as a single expression.
+pub fn parse_expr_from_source_str(
+    src: &str,
+    interner: &mut StringInterner,
+) -> PResult<P<Expr>> {
+    let mut parser = Parser::from_source_str(src, interner)?;
+    let expr = parser.parse_expr()?;
+    parser.expect_eof()?;
+    Ok(expr)
+}
+
+/// Parses `src` as the items of a file.
+pub fn parse_crate_from_source_str(
+    src: &str,
+    interner: &mut StringInterner,
+) -> PResult<Crate> {
+    let mut parser = Parser::from_source_str(src, interner)?;
+    parser.parse_crate_mod()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses all of `src` with `parse`, and prints what it makes with
+    /// `print`, for the tests of each part of the parser to compare.
+    pub(super) fn parse_with<T, R>(
+        src: &str,
+        parse: impl FnOnce(&mut Parser) -> PResult<T>,
+        print: impl FnOnce(&T, &StringInterner) -> R,
+    ) -> PResult<R> {
+        let mut interner = StringInterner::empty();
+        let mut p = Parser::from_source_str(src, &mut interner)?;
+        let node = parse(&mut p)?;
+        p.expect_eof()?;
+        Ok(print(&node, p.interner))
+    }
+
+    /// Asserts that `parse` fails on `src` with the syntax error `msg`.
+    pub(super) fn check_err<T: fmt::Debug>(
+        parse: impl FnOnce(&str) -> PResult<T>,
+        src: &str,
+        msg: &str,
+    ) {
+        match parse(src) {
+            Err(ParseError::Syntax(_, ref m)) if m == msg => {}
+            res => panic!("{}: {:?}", src, res),
+        }
+    }
+
+    #[test]
+    fn test_break_and_eat() {
+        let mut interner = StringInterner::empty();
+        let mut p = Parser::from_source_str("&& x", &mut interner).unwrap();
+        let and = Token::BinOp(lexer::token::BinOpToken::And);
+        assert!(p.break_and_eat(&and));
+        assert_eq!(p.prev_span, Span::new(0, 1));
+        assert_eq!(p.token.sp, Span::new(1, 2));
+        assert!(p.break_and_eat(&and));
+        assert!(!p.break_and_eat(&and));
+        assert!(p.check_ident());
+        p.bump();
+        assert_eq!(p.token.tok, Token::Eof);
+        assert_eq!(p.token.sp, Span::new(4, 4));
+    }
+
+    #[test]
+    fn test_unexpected() {
+        let mut interner = StringInterner::empty();
+        let mut p = Parser::from_source_str("fn", &mut interner).unwrap();
+        assert!(!p.check(&Token::Semi));
+        assert!(!p.check(&Token::Comma));
+        assert_eq!(
+            p.unexpected::<()>(),
+            Err(ParseError::Syntax(
+                Span::new(0, 2),
+                "expected one of `,`, `;`, found keyword `fn`".to_string()
+            ))
+        );
+        assert!(p.parse_ident().is_err());
+    }
+}
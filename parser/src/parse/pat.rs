@@ -0,0 +1,639 @@
+use super::{PResult, Parser, PathStyle};
+use crate::ast::{
+    self, respan, Attribute, BindingMode, Expr, ExprKind, FieldPat, Ident, Mac,
+    Mutability, Pat, PatKind, QSelf, RangeEnd, RangeSyntax, P,
+};
+use lexer::token::{self, BinOpToken, DelimToken, Span, Token};
+
+impl<'a> Parser<'a> {
+    /// A pattern where or-patterns may appear without parentheses, as in a
+    /// `let` or a `match` arm, which may also start with a `|`.
+    pub fn parse_top_pat(&mut self) -> PResult<P<Pat>> {
+        self.eat(&Token::BinOp(BinOpToken::Or));
+        self.parse_pat_with_or()
+    }
+
+    /// `a | b | c`, or a single pattern.
+    pub fn parse_pat_with_or(&mut self) -> PResult<P<Pat>> {
+        let lo = self.token.sp;
+        let first = self.parse_pat()?;
+        if !self.check(&Token::BinOp(BinOpToken::Or)) {
+            return Ok(first);
+        }
+        let mut pats = vec![first];
+        while self.eat(&Token::BinOp(BinOpToken::Or)) {
+            pats.push(self.parse_pat()?);
+        }
+        Ok(self.mk_pat(lo.to(self.prev_span), PatKind::Or(pats)))
+    }
+
+    /// A pattern without a top-level `|`, as closure params need.
+    pub fn parse_pat(&mut self) -> PResult<P<Pat>> {
+        self.parse_pat_with_range_pat(true)
+    }
+
+    // without `allow_range_pat`, a range is ambiguous, as in `&a..=b`
+    fn parse_pat_with_range_pat(
+        &mut self,
+        allow_range_pat: bool,
+    ) -> PResult<P<Pat>> {
+        let lo = self.token.sp;
+        let tok = self.token.tok;
+        let kind = match tok {
+            Token::BinOp(BinOpToken::And) | Token::AndAnd => {
+                // `&pat`, `&mut pat`, and `&&pat` as two of them
+                self.expect_and()?;
+                let mutbl = self.parse_mutability();
+                if let Token::Lifetime(..) = self.token.tok {
+                    let msg = format!(
+                        "unexpected lifetime {} in pattern",
+                        self.token_descr()
+                    );
+                    return self.fatal(self.token.sp, msg);
+                }
+                PatKind::Ref(self.parse_pat_with_range_pat(false)?, mutbl)
+            }
+            Token::OpenDelim(DelimToken::Paren) => {
+                // `(p)`, `(p,)` or `(a, .., b)`
+                self.bump();
+                let (mut fields, trailing_comma) =
+                    self.parse_pat_list(DelimToken::Paren)?;
+                if fields.len() == 1
+                    && !trailing_comma
+                    && fields[0].kind != PatKind::Rest
+                {
+                    PatKind::Paren(fields.pop().unwrap())
+                } else {
+                    PatKind::Tuple(fields)
+                }
+            }
+            Token::OpenDelim(DelimToken::Bracket) => {
+                // `[a, .., b]`
+                self.bump();
+                PatKind::Slice(self.parse_pat_list(DelimToken::Bracket)?.0)
+            }
+            Token::DotDotEq | Token::DotDotDot => {
+                // `..=b`
+                if tok == Token::DotDotDot {
+                    let msg = "range-to patterns with `...` are not allowed";
+                    return self.fatal(self.token.sp, msg.to_string());
+                }
+                self.bump();
+                let end = respan(
+                    self.prev_span,
+                    RangeEnd::Included(RangeSyntax::DotDotEq),
+                );
+                if !self.is_pat_range_end_start() {
+                    let msg = "inclusive range with no end";
+                    return self.fatal(end.span, msg.to_string());
+                }
+                PatKind::Range(None, Some(self.parse_pat_range_end()?), end)
+            }
+            Token::DotDot => {
+                // `..` in a tuple or slice, or `..b`
+                self.bump();
+                if self.is_pat_range_end_start() {
+                    let end = respan(self.prev_span, RangeEnd::Excluded);
+                    let hi = self.parse_pat_range_end()?;
+                    PatKind::Range(None, Some(hi), end)
+                } else {
+                    PatKind::Rest
+                }
+            }
+            Token::Literal(..) | Token::BinOp(BinOpToken::Minus) => {
+                let begin = self.parse_literal_maybe_minus()?;
+                self.parse_pat_range_or_lit(begin)?
+            }
+            _ if self.is_keyword("true") || self.is_keyword("false") => {
+                let begin = self.parse_literal_maybe_minus()?;
+                self.parse_pat_range_or_lit(begin)?
+            }
+            _ if self.eat_keyword("_") => PatKind::Wild,
+            _ if self.eat_keyword("ref") => {
+                let mutbl = self.parse_mutability();
+                self.parse_pat_ident(BindingMode::ByRef(mutbl))?
+            }
+            _ if self.eat_keyword("mut") => {
+                if self.is_keyword("ref") {
+                    let msg = "the order of `mut` and `ref` is incorrect";
+                    return self.fatal(
+                        self.prev_span.to(self.token.sp),
+                        msg.to_string(),
+                    );
+                }
+                self.parse_pat_ident(BindingMode::ByValue(Mutability::Mutable))?
+            }
+            _ if self.eat_keyword("box") => {
+                PatKind::Box(self.parse_pat_with_range_pat(false)?)
+            }
+            _ if self.can_be_ident_pat() => self
+                .parse_pat_ident(BindingMode::ByValue(Mutability::Immutable))?,
+            Token::Lt | Token::BinOp(BinOpToken::Shl) => {
+                // `<T as Trait>::C`, which may start a range
+                self.eat_lt();
+                let (qself, path) = self.parse_qpath(PathStyle::Expr)?;
+                match self.token.tok {
+                    Token::Not
+                    | Token::OpenDelim(DelimToken::Paren)
+                    | Token::OpenDelim(DelimToken::Brace) => {
+                        let msg = format!(
+                            "unexpected {} after qualified path",
+                            self.token_descr()
+                        );
+                        return self.fatal(self.token.sp, msg);
+                    }
+                    _ => {}
+                }
+                self.parse_pat_range_or_path(lo, Some(qself), path)?
+            }
+            _ if self.check_path() => {
+                let path = self.parse_path(PathStyle::Expr)?;
+                if self.eat(&Token::Not) {
+                    let (delim, tts) = self.parse_delim_tts()?;
+                    PatKind::Mac(Mac {
+                        path,
+                        delim,
+                        tts,
+                        span: lo.to(self.prev_span),
+                    })
+                } else if self.eat(&Token::OpenDelim(DelimToken::Paren)) {
+                    let fields = self.parse_pat_list(DelimToken::Paren)?.0;
+                    PatKind::TupleStruct(path, fields)
+                } else if self.eat(&Token::OpenDelim(DelimToken::Brace)) {
+                    let (fields, etc) = self.parse_pat_fields()?;
+                    PatKind::Struct(path, fields, etc)
+                } else {
+                    self.parse_pat_range_or_path(lo, None, path)?
+                }
+            }
+            _ => {
+                let msg =
+                    format!("expected pattern, found {}", self.token_descr());
+                return self.fatal(self.token.sp, msg);
+            }
+        };
+        if !allow_range_pat {
+            if let PatKind::Range(..) = kind {
+                let msg = "the range pattern here has ambiguous \
+                           interpretation";
+                return self.fatal(lo.to(self.prev_span), msg.to_string());
+            }
+        }
+        Ok(self.mk_pat(lo.to(self.prev_span), kind))
+    }
+
+    pub fn mk_pat(&mut self, span: Span, kind: PatKind) -> P<Pat> {
+        P(Pat {
+            id: self.next_node_id(),
+            kind,
+            span,
+        })
+    }
+
+    // an identifier that binds rather than starts a path, a struct, a
+    // range or a macro
+    fn can_be_ident_pat(&mut self) -> bool {
+        self.check_ident()
+            && !self.look_ahead(1, |t| match *t {
+                Token::OpenDelim(DelimToken::Paren)
+                | Token::OpenDelim(DelimToken::Brace)
+                | Token::DotDotDot
+                | Token::DotDotEq
+                | Token::DotDot
+                | Token::ModSep
+                | Token::Not => true,
+                _ => false,
+            })
+    }
+
+    // the patterns of a tuple, tuple struct or slice pattern, after the
+    // opening delimiter, and whether there is a trailing comma
+    fn parse_pat_list(
+        &mut self,
+        delim: DelimToken,
+    ) -> PResult<(Vec<P<Pat>>, bool)> {
+        let close = Token::CloseDelim(delim);
+        let mut fields = vec![];
+        let mut trailing_comma = false;
+        while !self.check(&close) {
+            fields.push(self.parse_pat_with_or()?);
+            trailing_comma = self.eat(&Token::Comma);
+            if !trailing_comma {
+                break;
+            }
+        }
+        self.expect(&close)?;
+        Ok((fields, trailing_comma))
+    }
+
+    // `name` or `name @ pat`, after its binding mode
+    fn parse_pat_ident(&mut self, mode: BindingMode) -> PResult<PatKind> {
+        let ident = self.parse_ident()?;
+        let sub = if self.eat(&Token::At) {
+            Some(self.parse_pat()?)
+        } else {
+            None
+        };
+        Ok(PatKind::Ident(mode, ident, sub))
+    }
+
+    // the fields of a struct pattern after the `{`, and whether they end
+    // with `..`
+    fn parse_pat_fields(&mut self) -> PResult<(Vec<FieldPat>, bool)> {
+        let close = Token::CloseDelim(DelimToken::Brace);
+        let mut fields = vec![];
+        let mut etc = false;
+        while !self.check(&close) {
+            let attrs = self.parse_outer_attributes()?;
+            if self.eat(&Token::DotDot) {
+                if !self.check(&close) {
+                    let msg = "`..` must be at the end and cannot have a \
+                               trailing comma";
+                    return self.fatal(self.prev_span, msg.to_string());
+                }
+                etc = true;
+                break;
+            }
+            fields.push(self.parse_pat_field(attrs)?);
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+        }
+        self.expect(&close)?;
+        Ok((fields, etc))
+    }
+
+    // `name: pat`, `0: pat`, or `box ref mut name`
+    fn parse_pat_field(&mut self, attrs: Vec<Attribute>) -> PResult<FieldPat> {
+        let lo = self.token.sp;
+        let (ident, pat, is_shorthand) =
+            if self.look_ahead(1, |t| *t == Token::Colon) {
+                let ident = match self.token.tok {
+                    Token::Literal(token::Lit::Integer(name), None) => {
+                        self.bump();
+                        Ident::new(name, self.prev_span)
+                    }
+                    _ => self.parse_ident()?,
+                };
+                self.bump();
+                (ident, self.parse_pat_with_or()?, false)
+            } else {
+                let is_box = self.eat_keyword("box");
+                let binding_lo = self.token.sp;
+                let mode = if self.eat_keyword("ref") {
+                    BindingMode::ByRef(self.parse_mutability())
+                } else {
+                    BindingMode::ByValue(self.parse_mutability())
+                };
+                let ident = self.parse_ident()?;
+                let kind = PatKind::Ident(mode, ident, None);
+                let mut pat = self.mk_pat(binding_lo.to(self.prev_span), kind);
+                if is_box {
+                    pat = self.mk_pat(lo.to(self.prev_span), PatKind::Box(pat));
+                }
+                (ident, pat, true)
+            };
+        Ok(FieldPat {
+            ident,
+            pat,
+            is_shorthand,
+            attrs,
+            id: self.next_node_id(),
+            span: lo.to(self.prev_span),
+        })
+    }
+
+    // a path pattern, or the range it starts
+    fn parse_pat_range_or_path(
+        &mut self,
+        lo: Span,
+        qself: Option<QSelf>,
+        path: ast::Path,
+    ) -> PResult<PatKind> {
+        if !self.is_pat_range_op() {
+            return Ok(PatKind::Path(qself, path));
+        }
+        let kind = ExprKind::Path(qself, path);
+        let begin = self.mk_expr(lo.to(self.prev_span), kind, vec![]);
+        self.parse_pat_range_or_lit(begin)
+    }
+
+    fn is_pat_range_op(&self) -> bool {
+        match self.token.tok {
+            Token::DotDot | Token::DotDotEq | Token::DotDotDot => true,
+            _ => false,
+        }
+    }
+
+    // `begin..=end`, `begin...end`, `begin..end` or `begin..` after
+    // `begin`, or the literal pattern `begin`
+    fn parse_pat_range_or_lit(&mut self, begin: P<Expr>) -> PResult<PatKind> {
+        let end = match self.token.tok {
+            Token::DotDotEq => RangeEnd::Included(RangeSyntax::DotDotEq),
+            Token::DotDotDot => RangeEnd::Included(RangeSyntax::DotDotDot),
+            Token::DotDot => RangeEnd::Excluded,
+            _ => return Ok(PatKind::Lit(begin)),
+        };
+        self.bump();
+        let end = respan(self.prev_span, end);
+        if self.is_pat_range_end_start() {
+            let hi = self.parse_pat_range_end()?;
+            Ok(PatKind::Range(Some(begin), Some(hi), end))
+        } else if end.node == RangeEnd::Excluded {
+            Ok(PatKind::Range(Some(begin), None, end))
+        } else {
+            let msg = "inclusive range with no end";
+            self.fatal(end.span, msg.to_string())
+        }
+    }
+
+    fn is_pat_range_end_start(&self) -> bool {
+        match self.token.tok {
+            Token::Literal(..)
+            | Token::BinOp(BinOpToken::Minus)
+            | Token::Lt
+            | Token::BinOp(BinOpToken::Shl) => true,
+            ref tok => self.token_is_path_start(tok),
+        }
+    }
+
+    // the end of a range pattern: a literal or a path
+    fn parse_pat_range_end(&mut self) -> PResult<P<Expr>> {
+        let lo = self.token.sp;
+        let (qself, path) = if self.eat_lt() {
+            let (qself, path) = self.parse_qpath(PathStyle::Expr)?;
+            (Some(qself), path)
+        } else if self.token_is_path_start(&self.token.tok) {
+            (None, self.parse_path(PathStyle::Expr)?)
+        } else {
+            return self.parse_literal_maybe_minus();
+        };
+        let kind = ExprKind::Path(qself, path);
+        Ok(self.mk_expr(lo.to(self.prev_span), kind, vec![]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{check_err, parse_with};
+    use super::super::{parse_expr_from_source_str, ParseError};
+    use super::*;
+    use lexer::interner::StringInterner;
+
+    // prints `pat` as an s-expression, with paths, literals and macros as
+    // they are in `src`
+    fn sexp(pat: &Pat, src: &str, interner: &StringInterner) -> String {
+        let text = |sp: Span| src[sp.lo..sp.hi].to_string();
+        let s = |pat: &Pat| sexp(pat, src, interner);
+        let list = |pats: &[P<Pat>]| {
+            pats.iter().map(|pat| s(pat)).collect::<Vec<_>>().join(" ")
+        };
+        let range_end = |e: &Option<P<Expr>>| match *e {
+            Some(ref e) => format!(" {}", text(e.span)),
+            None => String::new(),
+        };
+        match pat.kind {
+            PatKind::Wild => "_".to_string(),
+            PatKind::Ident(mode, ident, ref sub) => {
+                let mut words = match mode {
+                    BindingMode::ByRef(Mutability::Mutable) => vec!["ref mut"],
+                    BindingMode::ByRef(Mutability::Immutable) => vec!["ref"],
+                    BindingMode::ByValue(Mutability::Mutable) => vec!["mut"],
+                    BindingMode::ByValue(Mutability::Immutable) => vec![],
+                };
+                let name = interner.get(ident.name);
+                words.push(name);
+                let sub = sub.as_ref().map(|sub| s(sub));
+                if let Some(ref sub) = sub {
+                    words.push("@");
+                    words.push(sub);
+                }
+                if words.len() == 1 {
+                    name.to_string()
+                } else {
+                    format!("({})", words.join(" "))
+                }
+            }
+            PatKind::Struct(ref path, ref fields, etc) => {
+                let mut words = vec![text(path.span)];
+                for field in fields {
+                    if field.is_shorthand {
+                        words.push(s(&field.pat));
+                    } else {
+                        let name = interner.get(field.ident.name);
+                        words.push(format!("{}: {}", name, s(&field.pat)));
+                    }
+                }
+                if etc {
+                    words.push("..".to_string());
+                }
+                format!("(struct {})", words.join(" "))
+            }
+            PatKind::TupleStruct(ref path, ref pats) => {
+                format!("(tstruct {} {})", text(path.span), list(pats))
+            }
+            PatKind::Or(ref pats) => format!("(or {})", list(pats)),
+            PatKind::Path(None, ref path) => text(path.span),
+            PatKind::Path(Some(_), _) => format!("(qpath {})", text(pat.span)),
+            PatKind::Tuple(ref pats) => format!("(tuple {})", list(pats)),
+            PatKind::Box(ref pat) => format!("(box {})", s(pat)),
+            PatKind::Ref(ref pat, Mutability::Mutable) => {
+                format!("(&mut {})", s(pat))
+            }
+            PatKind::Ref(ref pat, Mutability::Immutable) => {
+                format!("(& {})", s(pat))
+            }
+            PatKind::Lit(ref e) => text(e.span),
+            PatKind::Range(ref lo, ref hi, end) => format!(
+                "(range{} {}{})",
+                range_end(lo),
+                text(end.span),
+                range_end(hi)
+            ),
+            PatKind::Slice(ref pats) => format!("(slice {})", list(pats)),
+            PatKind::Rest => "..".to_string(),
+            PatKind::Paren(ref pat) => format!("(paren {})", s(pat)),
+            PatKind::Mac(ref mac) => format!("(mac {})", text(mac.span)),
+        }
+    }
+
+    fn parse(src: &str) -> Result<String, ParseError> {
+        parse_with(
+            src,
+            |p| p.parse_top_pat(),
+            |pat, interner| sexp(pat, src, interner),
+        )
+    }
+
+    fn check(src: &str, expected: &str) {
+        assert_eq!(parse(src), Ok(expected.to_string()), "{}", src);
+    }
+
+    #[test]
+    fn test_bindings() {
+        check("x", "x");
+        check("r#match", "match");
+        check("ref x", "(ref x)");
+        check("ref mut x", "(ref mut x)");
+        check("mut x", "(mut x)");
+        check("x @ Some(_)", "(x @ (tstruct Some _))");
+        check("ref x @ 1..=5", "(ref x @ (range 1 ..= 5))");
+        check("x @ A | B", "(or (x @ A) B)");
+        check("x @ (A | B)", "(x @ (paren (or A B)))");
+        check_err(
+            parse,
+            "mut ref x",
+            "the order of `mut` and `ref` is incorrect",
+        );
+        check_err(parse, "ref 1", "expected identifier, found `1`");
+    }
+
+    #[test]
+    fn test_tuples_and_structs() {
+        check("()", "(tuple )");
+        check("(..)", "(tuple ..)");
+        check("(a,)", "(tuple a)");
+        check("(a)", "(paren a)");
+        check("(a, .., b)", "(tuple a .. b)");
+        check("S(a, .., b)", "(tstruct S a .. b)");
+        check("S::<T>()", "(tstruct S::<T> )");
+        check("S {}", "(struct S)");
+        check(
+            "S { a, ref mut b, box c, d: (x, y), 0: z, .. }",
+            "(struct S a (ref mut b) (box c) d: (tuple x y) 0: z ..)",
+        );
+        check("a::S { #[cfg(x)] a, .. }", "(struct a::S a ..)");
+        check_err(
+            parse,
+            "S { .., }",
+            "`..` must be at the end and cannot have a trailing comma",
+        );
+        check_err(parse, "S { a: }", "expected pattern, found `}`");
+        check_err(
+            parse,
+            "(a b)",
+            "expected one of `)`, `,`, `@`, `|`, found `b`",
+        );
+    }
+
+    #[test]
+    fn test_slices() {
+        check("[]", "(slice )");
+        check("[a, .., b]", "(slice a .. b)");
+        check("[first, rest @ ..]", "(slice first (rest @ ..))");
+        check("[[a, b], [c, ..]]", "(slice (slice a b) (slice c ..))");
+    }
+
+    #[test]
+    fn test_literals_and_ranges() {
+        check("-1", "-1");
+        check("b'a'", "b'a'");
+        check("true", "true");
+        check("\"s\"", "\"s\"");
+        check("1..=5", "(range 1 ..= 5)");
+        check("'a'...'z'", "(range 'a' ... 'z')");
+        check("-5..-1", "(range -5 .. -1)");
+        check("0..", "(range 0 ..)");
+        check("..=5", "(range ..= 5)");
+        check("..5", "(range .. 5)");
+        check("A::MIN..=0", "(range A::MIN ..= 0)");
+        check("0..=u8::MAX", "(range 0 ..= u8::MAX)");
+        check("<T as Tr>::C..=5", "(range <T as Tr>::C ..= 5)");
+        check("(0.., ..)", "(tuple (range 0 ..) ..)");
+        check("&(1..=5)", "(& (paren (range 1 ..= 5)))");
+        check_err(parse, "1..=", "inclusive range with no end");
+        check_err(
+            parse,
+            "...5",
+            "range-to patterns with `...` are not allowed",
+        );
+        check_err(
+            parse,
+            "&1..=5",
+            "the range pattern here has ambiguous interpretation",
+        );
+        check_err(
+            parse,
+            "box 0..",
+            "the range pattern here has ambiguous interpretation",
+        );
+    }
+
+    #[test]
+    fn test_or_patterns() {
+        check("A | B | C", "(or A B C)");
+        check("| A | B", "(or A B)");
+        check("| A", "A");
+        check("Some(A | B)", "(tstruct Some (or A B))");
+        check("S { a: 1 | 2 }", "(struct S a: (or 1 2))");
+        check("[A | B, ..]", "(slice (or A B) ..)");
+        check("(A | B, C)", "(tuple (or A B) C)");
+        check("&(A | B)", "(& (paren (or A B)))");
+        check("1..=2 | 5..", "(or (range 1 ..= 2) (range 5 ..))");
+        check_err(parse, "A |", "expected pattern, found `<eof>`");
+        check_err(
+            parse,
+            "A || B",
+            "expected one of `<eof>`, `@`, `|`, found `||`",
+        );
+    }
+
+    #[test]
+    fn test_box_ref_mac_and_paths() {
+        check("box x", "(box x)");
+        check("box (a, b)", "(box (tuple a b))");
+        check("&mut (a, b)", "(&mut (tuple a b))");
+        check("&&x", "(& (& x))");
+        check("&&mut x", "(& (&mut x))");
+        check("&mut x", "(&mut x)");
+        check("m!(x, y)", "(mac m!(x, y))");
+        check("a::m![]", "(mac a::m![])");
+        check("a::B", "a::B");
+        check("::a::B", "::a::B");
+        check("self::B", "self::B");
+        check("<T>::C", "(qpath <T>::C)");
+        check("<T as Tr>::C", "(qpath <T as Tr>::C)");
+        check("<<T as A>::B as C>::D", "(qpath <<T as A>::B as C>::D)");
+        check_err(
+            parse,
+            "<T as Tr>::C(x)",
+            "unexpected `(` after qualified path",
+        );
+        check_err(
+            parse,
+            "<T as Tr>::C { }",
+            "unexpected `{` after qualified path",
+        );
+        check_err(parse, "&'a x", "unexpected lifetime `'a` in pattern");
+        check_err(parse, "+", "expected pattern, found `+`");
+
+        let mut interner = StringInterner::empty();
+        let mut p = Parser::from_source_str("&&x", &mut interner).unwrap();
+        let pat = p.parse_pat().unwrap();
+        assert_eq!(pat.span, Span::new(0, 3));
+        match pat.kind {
+            PatKind::Ref(ref inner, _) => {
+                assert_eq!(inner.span, Span::new(1, 3))
+            }
+            ref kind => panic!("{:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_patterns_in_exprs() {
+        let mut interner = StringInterner::empty();
+        let srcs = [
+            "match x { | A | B if c => 1, S { a: 0..=9, .. } => 2 }",
+            "if let Some(x) | Ok(x) = y {}",
+            "while let [a, rest @ ..] = s {}",
+            "for (i, &x) in v {}",
+            "|&(a, b), [c, ..]| a",
+            "{ let A(x) | B(x) = y; }",
+        ];
+        for src in &srcs {
+            if let Err(err) = parse_expr_from_source_str(src, &mut interner) {
+                panic!("{}: {}", src, err);
+            }
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use super::{PResult, Parser, TokenType};
+use crate::ast::{
+    AngleBracketedArgs, AnonConst, AssocTyConstraint, AssocTyConstraintKind,
+    GenericArg, GenericArgs, Ident, ParenthesizedArgs, Path, PathSegment,
+    QSelf, P,
+};
+use lexer::token::{BinOpToken, DelimToken, Span, Token};
+
+/// Where a path is, which decides how its generic args are written.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PathStyle {
+    /// In an expression or pattern, generic args need a `::`, as in
+    /// `Vec::<u8>::new()`, since `a < b` is a comparison.
+    Expr,
+    /// In a type, `::` is optional, and `Fn(A) -> B` takes parenthesized
+    /// args.
+    Type,
+    /// In a `use`, a visibility or an attribute, no generic args.
+    Mod,
+}
+
+impl<'a> Parser<'a> {
+    pub fn parse_path(&mut self, style: PathStyle) -> PResult<Path> {
+        let lo = self.token.sp;
+        let mut segments = vec![];
+        if self.eat(&Token::ModSep) {
+            segments.push(self.mk_root_path_segment(lo));
+        }
+        self.parse_path_segments(&mut segments, style)?;
+        Ok(Path {
+            span: lo.to(self.prev_span),
+            segments,
+        })
+    }
+
+    /// The `{{root}}` segment a leading `::` at `sp` stands for.
+    pub(super) fn mk_root_path_segment(&mut self, sp: Span) -> PathSegment {
+        let root = self.interner.insert("{{root}}");
+        PathSegment {
+            ident: Ident::new(root, sp),
+            id: self.next_node_id(),
+            args: None,
+        }
+    }
+
+    /// Whether the current token is the `::` of `::{` or `::*`, which end
+    /// the path of a use tree.
+    pub(super) fn is_import_coupler(&mut self) -> bool {
+        self.check(&Token::ModSep)
+            && self.look_ahead(1, |t| {
+                *t == Token::OpenDelim(DelimToken::Brace)
+                    || *t == Token::BinOp(BinOpToken::Star)
+            })
+    }
+
+    /// `<T as Trait>::a::b` after the `<`, with `T as Trait` split off as the
+    /// `QSelf`, the path being `Trait::a::b`.
+    pub fn parse_qpath(&mut self, style: PathStyle) -> PResult<(QSelf, Path)> {
+        let lo = self.prev_span;
+        let ty = self.parse_ty()?;
+        let (mut segments, path_span) = if self.eat_keyword("as") {
+            let path = self.parse_path(PathStyle::Type)?;
+            (path.segments, path.span)
+        } else {
+            (vec![], self.token.sp)
+        };
+        let qself = QSelf {
+            ty,
+            path_span,
+            position: segments.len(),
+        };
+        self.expect_gt()?;
+        self.expect(&Token::ModSep)?;
+        self.parse_path_segments(&mut segments, style)?;
+        let path = Path {
+            span: lo.to(self.prev_span),
+            segments,
+        };
+        Ok((qself, path))
+    }
+
+    fn parse_path_segments(
+        &mut self,
+        segments: &mut Vec<PathSegment>,
+        style: PathStyle,
+    ) -> PResult<()> {
+        loop {
+            segments.push(self.parse_path_segment(style)?);
+            if self.is_import_coupler() || !self.eat(&Token::ModSep) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_path_segment_ident(&mut self) -> PResult<Ident> {
+        match self.token.tok {
+            Token::Ident(name, false)
+                if self.token_is_path_segment_keyword(&self.token.tok) =>
+            {
+                let sp = self.token.sp;
+                self.bump();
+                Ok(Ident::new(name, sp))
+            }
+            _ => self.parse_ident(),
+        }
+    }
+
+    fn is_args_start(tok: &Token) -> bool {
+        match *tok {
+            Token::Lt
+            | Token::BinOp(BinOpToken::Shl)
+            | Token::OpenDelim(DelimToken::Paren) => true,
+            _ => false,
+        }
+    }
+
+    pub fn parse_path_segment(
+        &mut self,
+        style: PathStyle,
+    ) -> PResult<PathSegment> {
+        let ident = self.parse_path_segment_ident()?;
+        let has_args = match style {
+            PathStyle::Type => {
+                self.expected_tokens.push(TokenType::Token(Token::Lt));
+                Parser::is_args_start(&self.token.tok)
+                    || self.check(&Token::ModSep)
+                        && self.look_ahead(1, Parser::is_args_start)
+            }
+            PathStyle::Expr => {
+                self.check(&Token::ModSep)
+                    && self.look_ahead(1, |t| {
+                        *t == Token::Lt || *t == Token::BinOp(BinOpToken::Shl)
+                    })
+            }
+            PathStyle::Mod => false,
+        };
+        let args = if has_args {
+            self.eat(&Token::ModSep);
+            Some(P(self.parse_generic_args_of_segment()?))
+        } else {
+            None
+        };
+        Ok(PathSegment {
+            ident,
+            id: self.next_node_id(),
+            args,
+        })
+    }
+
+    // `<...>` or `(...) -> R`
+    fn parse_generic_args_of_segment(&mut self) -> PResult<GenericArgs> {
+        let lo = self.token.sp;
+        if self.eat_lt() {
+            let (args, constraints) = self.parse_generic_args()?;
+            self.expect_gt()?;
+            Ok(GenericArgs::AngleBracketed(AngleBracketedArgs {
+                span: lo.to(self.prev_span),
+                args,
+                constraints,
+            }))
+        } else {
+            self.expect(&Token::OpenDelim(DelimToken::Paren))?;
+            let close = Token::CloseDelim(DelimToken::Paren);
+            let inputs = self.parse_seq_to_end(&close, |p| p.parse_ty())?;
+            let output = self.parse_ret_ty(false)?;
+            Ok(GenericArgs::Parenthesized(ParenthesizedArgs {
+                span: lo.to(self.prev_span),
+                inputs,
+                output,
+            }))
+        }
+    }
+
+    /// Eats a `<`, or the first one of `<<`.
+    pub fn eat_lt(&mut self) -> bool {
+        self.break_and_eat(&Token::Lt)
+    }
+
+    /// Eats a `<`, or fails.
+    pub fn expect_lt(&mut self) -> PResult<()> {
+        if self.eat_lt() {
+            Ok(())
+        } else {
+            self.unexpected()
+        }
+    }
+
+    /// Eats a `>`, or the first one of `>>`, `>=` or `>>=`, as the end of
+    /// `Vec<Vec<u8>>` is.
+    pub fn expect_gt(&mut self) -> PResult<()> {
+        if self.break_and_eat(&Token::Gt) {
+            Ok(())
+        } else {
+            self.unexpected()
+        }
+    }
+
+    /// The args and constraints of `<...>`, up to the `>`.
+    pub(super) fn parse_generic_args(
+        &mut self,
+    ) -> PResult<(Vec<GenericArg>, Vec<AssocTyConstraint>)> {
+        let mut args = vec![];
+        let mut constraints = vec![];
+        loop {
+            if self.check_ident()
+                && self.look_ahead(1, |t| *t == Token::Eq || *t == Token::Colon)
+            {
+                // `Item = T` or `Item: Bound`
+                let lo = self.token.sp;
+                let ident = self.parse_ident()?;
+                let kind = if self.eat(&Token::Eq) {
+                    AssocTyConstraintKind::Equality {
+                        ty: self.parse_ty()?,
+                    }
+                } else {
+                    self.bump();
+                    let bounds = self.parse_generic_bounds()?;
+                    AssocTyConstraintKind::Bound { bounds }
+                };
+                constraints.push(AssocTyConstraint {
+                    id: self.next_node_id(),
+                    ident,
+                    kind,
+                    span: lo.to(self.prev_span),
+                });
+            } else {
+                let arg = if self.check_lifetime() {
+                    GenericArg::Lifetime(self.expect_lifetime()?)
+                } else if self.check_const_arg() {
+                    GenericArg::Const(AnonConst {
+                        value: self.parse_const_arg()?,
+                        id: self.next_node_id(),
+                    })
+                } else if self.check_type() {
+                    GenericArg::Type(self.parse_ty()?)
+                } else {
+                    break;
+                };
+                if !constraints.is_empty() {
+                    let msg = "generic arguments must come before the first \
+                               constraint";
+                    return self.fatal(self.prev_span, msg.to_string());
+                }
+                args.push(arg);
+            }
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+        }
+        Ok((args, constraints))
+    }
+}
@@ -0,0 +1,169 @@
+use super::{
+    expr_requires_semi_to_be_stmt, PResult, Parser, PathStyle, Restrictions,
+};
+use crate::ast::{
+    Attribute, Block, BlockCheckMode, ExprKind, Local, Mac, MacDelimiter,
+    MacStmtStyle, Stmt, StmtKind, P,
+};
+use lexer::token::{DelimToken, Span, Token};
+
+impl<'a> Parser<'a> {
+    /// `{ stmts }`, without inner attributes.
+    pub fn parse_block(&mut self) -> PResult<P<Block>> {
+        let lo = self.token.sp;
+        self.expect(&Token::OpenDelim(DelimToken::Brace))?;
+        self.parse_block_tail(lo, BlockCheckMode::Default)
+    }
+
+    /// The statements of a block and its `}`, after the `{` and its inner
+    /// attributes.
+    pub fn parse_block_tail(
+        &mut self,
+        lo: Span,
+        rules: BlockCheckMode,
+    ) -> PResult<P<Block>> {
+        let mut stmts = vec![];
+        while !self.eat(&Token::CloseDelim(DelimToken::Brace)) {
+            if self.token.tok == Token::Eof {
+                return self.unexpected();
+            }
+            if let Some(stmt) = self.parse_full_stmt()? {
+                stmts.push(stmt);
+            }
+        }
+        Ok(P(Block {
+            stmts,
+            id: self.next_node_id(),
+            rules,
+            span: lo.to(self.prev_span),
+        }))
+    }
+
+    /// A statement and its trailing `;`, `None` for a stray `;`.
+    pub fn parse_full_stmt(&mut self) -> PResult<Option<Stmt>> {
+        if self.eat(&Token::Semi) {
+            return Ok(None);
+        }
+        let mut stmt = self.parse_stmt_without_recovery()?;
+        match stmt.kind {
+            StmtKind::Expr(ref expr) if self.token.tok != Token::Eof => {
+                // `a b` is not two statements, but `if a {} b` is
+                if expr_requires_semi_to_be_stmt(expr)
+                    && !self.check(&Token::Semi)
+                    && !self.check(&Token::CloseDelim(DelimToken::Brace))
+                {
+                    return self.unexpected();
+                }
+            }
+            StmtKind::Local(..) => {
+                self.expect(&Token::Semi)?;
+                stmt.span = stmt.span.to(self.prev_span);
+                return Ok(Some(stmt));
+            }
+            _ => {}
+        }
+        if self.eat(&Token::Semi) {
+            stmt = stmt.add_trailing_semicolon();
+            stmt.span = stmt.span.to(self.prev_span);
+        }
+        Ok(Some(stmt))
+    }
+
+    fn parse_stmt_without_recovery(&mut self) -> PResult<Stmt> {
+        let attrs = self.parse_outer_attributes()?;
+        let lo = self.token.sp;
+        let kind = if self.eat_keyword("let") {
+            StmtKind::Local(self.parse_local(attrs)?)
+        } else if let Some(item) = self.parse_item_(attrs.clone(), false)? {
+            StmtKind::Item(item)
+        } else if self.token_is_path_start(&self.token.tok) {
+            // a macro call, or an expression starting with a path
+            let path = self.parse_path(PathStyle::Expr)?;
+            if self.eat(&Token::Not) {
+                self.parse_stmt_mac(lo, path, attrs)?
+            } else {
+                let e = self.with_res(Restrictions::STMT_EXPR, |this| {
+                    this.parse_path_start_expr(lo, path, vec![])
+                })?;
+                StmtKind::Expr(self.parse_stmt_expr_rest(e, attrs)?)
+            }
+        } else {
+            if self.token.tok == Token::CloseDelim(DelimToken::Brace)
+                && !attrs.is_empty()
+            {
+                let msg = "expected statement after outer attribute";
+                return self.fatal(self.prev_span, msg.to_string());
+            }
+            let e =
+                self.parse_expr_res(Restrictions::STMT_EXPR, Some(attrs))?;
+            StmtKind::Expr(e)
+        };
+        Ok(Stmt {
+            id: self.next_node_id(),
+            kind,
+            span: lo.to(self.prev_span),
+        })
+    }
+
+    // the operators after the start of an expression statement
+    fn parse_stmt_expr_rest(
+        &mut self,
+        e: P<crate::ast::Expr>,
+        attrs: Vec<Attribute>,
+    ) -> PResult<P<crate::ast::Expr>> {
+        self.with_res(Restrictions::STMT_EXPR, |this| {
+            let e = this.parse_dot_or_call_expr_with(e, attrs)?;
+            this.parse_assoc_expr_with(0, super::expr::LhsExpr::Parsed(e))
+        })
+    }
+
+    // `path!(...)` as a statement, after the `!`
+    fn parse_stmt_mac(
+        &mut self,
+        lo: Span,
+        path: crate::ast::Path,
+        attrs: Vec<Attribute>,
+    ) -> PResult<StmtKind> {
+        let (delim, tts) = self.parse_delim_tts()?;
+        let span = lo.to(self.prev_span);
+        let mac = Mac {
+            path,
+            delim,
+            tts,
+            span,
+        };
+        // `m! {}` ends the statement, `m!().f()` is an expression
+        if delim == MacDelimiter::Brace {
+            return Ok(StmtKind::Mac(P((mac, MacStmtStyle::Braces, attrs))));
+        }
+        if self.token.tok == Token::Semi || self.token.tok == Token::Eof {
+            return Ok(StmtKind::Mac(P((mac, MacStmtStyle::NoBraces, attrs))));
+        }
+        let e = self.mk_expr(span, ExprKind::Mac(mac), vec![]);
+        Ok(StmtKind::Expr(self.parse_stmt_expr_rest(e, attrs)?))
+    }
+
+    // `let pat: ty = init`, after the `let`
+    fn parse_local(&mut self, attrs: Vec<Attribute>) -> PResult<P<Local>> {
+        let lo = self.prev_span;
+        let pat = self.parse_top_pat()?;
+        let ty = if self.eat(&Token::Colon) {
+            Some(self.parse_ty()?)
+        } else {
+            None
+        };
+        let init = if self.eat(&Token::Eq) {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        Ok(P(Local {
+            id: self.next_node_id(),
+            pat,
+            ty,
+            init,
+            span: lo.to(self.prev_span),
+            attrs,
+        }))
+    }
+}
@@ -0,0 +1,583 @@
+use super::{PResult, Parser, PathStyle, TokenType};
+use crate::ast::{
+    AnonConst, BareFnTy, BindingMode, Constness, FnDecl, FunctionRetTy,
+    GenericBound, GenericParam, IsAsync, Mac, MutTy, Mutability, Param,
+    PatKind, Path, PolyTraitRef, TraitBoundModifier, TraitObjectSyntax,
+    TraitRef, Ty, TyKind, P,
+};
+use lexer::token::{BinOpToken, DelimToken, Span, Token};
+
+/// The keywords that may start a type.
+const TYPE_KEYWORDS: &[&str] = &[
+    "_", "for", "impl", "fn", "unsafe", "extern", "typeof", "dyn", "self",
+    "Self", "super", "crate",
+];
+
+impl<'a> Parser<'a> {
+    pub fn token_can_begin_type(&self, tok: &Token) -> bool {
+        match *tok {
+            Token::Ident(name, raw) => {
+                let name = self.interner.get(name);
+                raw || !super::is_reserved(name)
+                    || TYPE_KEYWORDS.contains(&name)
+            }
+            Token::OpenDelim(DelimToken::Paren)
+            | Token::OpenDelim(DelimToken::Bracket)
+            | Token::Not
+            | Token::BinOp(BinOpToken::Star)
+            | Token::BinOp(BinOpToken::And)
+            | Token::AndAnd
+            | Token::Question
+            | Token::Lifetime(_)
+            | Token::Lt
+            | Token::BinOp(BinOpToken::Shl)
+            | Token::ModSep => true,
+            _ => false,
+        }
+    }
+
+    pub fn check_type(&mut self) -> bool {
+        self.expected_tokens.push(TokenType::Type);
+        self.token_can_begin_type(&self.token.tok)
+    }
+
+    pub fn parse_ty(&mut self) -> PResult<P<Ty>> {
+        self.parse_ty_common(true)
+    }
+
+    /// A type that does not take a following `+`, as after `as` or `&`, so
+    /// `&A + B` is an error rather than a reference to a trait object.
+    pub fn parse_ty_no_plus(&mut self) -> PResult<P<Ty>> {
+        self.parse_ty_common(false)
+    }
+
+    fn parse_ty_common(&mut self, allow_plus: bool) -> PResult<P<Ty>> {
+        let lo = self.token.sp;
+        let mut impl_dyn_multi = false;
+        let kind = if self.eat(&Token::OpenDelim(DelimToken::Paren)) {
+            // `(T)`, `(T,)` or `(A, B)`
+            let mut ts = vec![];
+            let mut last_comma = false;
+            while !self.check(&Token::CloseDelim(DelimToken::Paren)) {
+                ts.push(self.parse_ty()?);
+                last_comma = self.eat(&Token::Comma);
+                if !last_comma {
+                    break;
+                }
+            }
+            self.expect(&Token::CloseDelim(DelimToken::Paren))?;
+            if ts.len() == 1 && !last_comma {
+                TyKind::Paren(ts.pop().unwrap())
+            } else {
+                TyKind::Tup(ts)
+            }
+        } else if self.eat(&Token::Not) {
+            TyKind::Never
+        } else if self.eat(&Token::BinOp(BinOpToken::Star)) {
+            TyKind::Ptr(self.parse_ptr()?)
+        } else if self.eat(&Token::OpenDelim(DelimToken::Bracket)) {
+            // `[T]` or `[T; N]`
+            let elt_ty = self.parse_ty()?;
+            let kind = if self.eat(&Token::Semi) {
+                TyKind::Array(
+                    elt_ty,
+                    AnonConst {
+                        id: self.next_node_id(),
+                        value: self.parse_expr()?,
+                    },
+                )
+            } else {
+                TyKind::Slice(elt_ty)
+            };
+            self.expect(&Token::CloseDelim(DelimToken::Bracket))?;
+            kind
+        } else if self.check(&Token::BinOp(BinOpToken::And))
+            || self.check(&Token::AndAnd)
+        {
+            self.expect_and()?;
+            self.parse_borrowed_pointee()?
+        } else if self.eat_keyword("typeof") {
+            // `typeof(e)`, reserved
+            self.expect(&Token::OpenDelim(DelimToken::Paren))?;
+            let e = AnonConst {
+                id: self.next_node_id(),
+                value: self.parse_expr()?,
+            };
+            self.expect(&Token::CloseDelim(DelimToken::Paren))?;
+            TyKind::Typeof(e)
+        } else if self.eat_keyword("_") {
+            TyKind::Infer
+        } else if self.check_fn_front_matter() {
+            self.parse_ty_bare_fn(vec![])?
+        } else if self.check_keyword("for") {
+            // `for<'a> fn(&'a T)` or `for<'a> Trait<'a>`
+            let lo = self.token.sp;
+            let lifetime_defs = self.parse_late_bound_lifetime_defs()?;
+            if self.check_fn_front_matter() {
+                self.parse_ty_bare_fn(lifetime_defs)?
+            } else {
+                let path = self.parse_path(PathStyle::Type)?;
+                let parse_plus = allow_plus && self.check_plus();
+                self.parse_remaining_bounds(
+                    lifetime_defs,
+                    path,
+                    lo,
+                    parse_plus,
+                )?
+            }
+        } else if self.eat_keyword("impl") {
+            let bounds = self.parse_generic_bounds()?;
+            impl_dyn_multi = bounds.len() > 1;
+            TyKind::ImplTrait(self.next_node_id(), bounds)
+        } else if self.eat_keyword("dyn") {
+            let bounds = self.parse_generic_bounds()?;
+            impl_dyn_multi = bounds.len() > 1;
+            TyKind::TraitObject(bounds, TraitObjectSyntax::Dyn)
+        } else if self.check(&Token::Lt)
+            || self.check(&Token::BinOp(BinOpToken::Shl))
+        {
+            // `<T as Trait>::Assoc` or `<T>::Assoc`
+            self.eat_lt();
+            let (qself, path) = self.parse_qpath(PathStyle::Type)?;
+            TyKind::Path(Some(qself), path)
+        } else if self.check_path() {
+            let path = self.parse_path(PathStyle::Type)?;
+            if self.eat(&Token::Not) {
+                let (delim, tts) = self.parse_delim_tts()?;
+                TyKind::Mac(Mac {
+                    path,
+                    delim,
+                    tts,
+                    span: lo.to(self.prev_span),
+                })
+            } else if allow_plus && self.check_plus() {
+                // a trait object without `dyn`, as in `Box<Trait + Send>`
+                self.parse_remaining_bounds(vec![], path, lo, true)?
+            } else {
+                TyKind::Path(None, path)
+            }
+        } else {
+            let msg = format!("expected type, found {}", self.token_descr());
+            return self.fatal(self.token.sp, msg);
+        };
+        let span = lo.to(self.prev_span);
+        if impl_dyn_multi && !allow_plus {
+            let msg = "ambiguous `+` in a type";
+            return self.fatal(span, msg.to_string());
+        }
+        Ok(self.mk_ty(span, kind))
+    }
+
+    pub fn mk_ty(&mut self, span: Span, kind: TyKind) -> P<Ty> {
+        P(Ty {
+            id: self.next_node_id(),
+            kind,
+            span,
+        })
+    }
+
+    fn check_plus(&mut self) -> bool {
+        self.check(&Token::BinOp(BinOpToken::Plus))
+    }
+
+    // `Trait<'a> + Send + 'a` as a trait object, after the path of the first
+    // bound and the `for<'a>` before it
+    fn parse_remaining_bounds(
+        &mut self,
+        bound_generic_params: Vec<GenericParam>,
+        path: Path,
+        lo: Span,
+        parse_plus: bool,
+    ) -> PResult<TyKind> {
+        let poly_trait_ref = PolyTraitRef {
+            bound_generic_params,
+            trait_ref: TraitRef {
+                path,
+                ref_id: self.next_node_id(),
+            },
+            span: lo.to(self.prev_span),
+        };
+        let mut bounds = vec![GenericBound::Trait(
+            poly_trait_ref,
+            TraitBoundModifier::None,
+        )];
+        if parse_plus {
+            self.bump();
+            bounds.extend(self.parse_generic_bounds()?);
+        }
+        Ok(TyKind::TraitObject(bounds, TraitObjectSyntax::None))
+    }
+
+    // `mut T` or `const T`, after the `*`
+    fn parse_ptr(&mut self) -> PResult<MutTy> {
+        let mutbl = if self.eat_keyword("mut") {
+            Mutability::Mutable
+        } else if self.eat_keyword("const") {
+            Mutability::Immutable
+        } else {
+            let msg = "expected mut or const in raw pointer type";
+            return self.fatal(self.token.sp, msg.to_string());
+        };
+        let ty = self.parse_ty_no_plus()?;
+        Ok(MutTy { ty, mutbl })
+    }
+
+    // `'a mut T`, after the `&`
+    fn parse_borrowed_pointee(&mut self) -> PResult<TyKind> {
+        let lifetime = if self.check_lifetime() {
+            Some(self.expect_lifetime()?)
+        } else {
+            None
+        };
+        let mutbl = self.parse_mutability();
+        let ty = self.parse_ty_no_plus()?;
+        Ok(TyKind::Rptr(lifetime, MutTy { ty, mutbl }))
+    }
+
+    // `unsafe extern "C" fn(A, b: B, ...) -> R`, after the `for<'a>`
+    fn parse_ty_bare_fn(
+        &mut self,
+        generic_params: Vec<GenericParam>,
+    ) -> PResult<TyKind> {
+        let lo = self.token.sp;
+        let header = self.parse_fn_front_matter()?;
+        if header.constness == Constness::Const
+            || header.asyncness == IsAsync::Async
+        {
+            let msg = "function pointer types cannot be `const` or `async`";
+            return self.fatal(lo.to(self.prev_span), msg.to_string());
+        }
+        let decl = self.parse_bare_fn_decl()?;
+        Ok(TyKind::BareFn(P(BareFnTy {
+            unsafety: header.unsafety,
+            ext: header.ext,
+            generic_params,
+            decl,
+        })))
+    }
+
+    // `(A, b: B, ...) -> R`, whose params may have no names
+    fn parse_bare_fn_decl(&mut self) -> PResult<P<FnDecl>> {
+        self.expect(&Token::OpenDelim(DelimToken::Paren))?;
+        let mut c_variadic = false;
+        let close = Token::CloseDelim(DelimToken::Paren);
+        let inputs = self.parse_seq_to_end(&close, |p| {
+            let attrs = p.parse_outer_attributes()?;
+            let lo = p.token.sp;
+            if c_variadic {
+                let msg = "`...` must be the last parameter of a C-variadic \
+                           function";
+                return p.fatal(p.prev_span, msg.to_string());
+            }
+            let (pat, ty) = if p.eat(&Token::DotDotDot) {
+                c_variadic = true;
+                let pat = p.mk_pat(p.prev_span, PatKind::Wild);
+                (pat, p.mk_ty(p.prev_span, TyKind::CVarArgs))
+            } else if (p.token_is_ident(&p.token.tok) || p.is_keyword("_"))
+                && p.look_ahead(1, |t| *t == Token::Colon)
+            {
+                let pat = p.parse_pat()?;
+                p.bump();
+                (pat, p.parse_ty()?)
+            } else {
+                let ident = p.invalid_ident(lo);
+                let mode = BindingMode::ByValue(Mutability::Immutable);
+                let pat =
+                    p.mk_pat(ident.span, PatKind::Ident(mode, ident, None));
+                (pat, p.parse_ty()?)
+            };
+            Ok(Param {
+                attrs,
+                ty,
+                pat,
+                id: p.next_node_id(),
+                span: lo.to(p.prev_span),
+            })
+        })?;
+        let output = self.parse_ret_ty(false)?;
+        Ok(P(FnDecl {
+            inputs,
+            output,
+            c_variadic,
+        }))
+    }
+
+    /// Eats a `&`, or the first one of `&&`.
+    pub fn expect_and(&mut self) -> PResult<()> {
+        if self.break_and_eat(&Token::BinOp(BinOpToken::And)) {
+            Ok(())
+        } else {
+            self.unexpected()
+        }
+    }
+
+    /// `-> T`, or nothing. Without `allow_plus`, a `+` after `T` is not part
+    /// of it, as in `Box<dyn Fn() -> u8 + Send>`.
+    pub fn parse_ret_ty(&mut self, allow_plus: bool) -> PResult<FunctionRetTy> {
+        if self.eat(&Token::RArrow) {
+            Ok(FunctionRetTy::Ty(self.parse_ty_common(allow_plus)?))
+        } else {
+            let lo = self.token.sp.lo;
+            Ok(FunctionRetTy::Default(Span::new(lo, lo)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{check_err, parse_with};
+    use super::super::{
+        parse_crate_from_source_str, parse_expr_from_source_str, ParseError,
+    };
+    use super::*;
+    use crate::ast::{ExprKind, Extern, ItemKind, Unsafety};
+    use lexer::interner::StringInterner;
+
+    // prints `ty` as an s-expression, with paths, bounds and exprs as they
+    // are in `src`
+    fn sexp(ty: &Ty, src: &str, interner: &StringInterner) -> String {
+        let text = |sp: Span| src[sp.lo..sp.hi].to_string();
+        let s = |ty: &Ty| sexp(ty, src, interner);
+        let list = |tys: &[P<Ty>]| {
+            tys.iter().map(|ty| s(ty)).collect::<Vec<_>>().join(" ")
+        };
+        let bounds = |bounds: &[GenericBound]| {
+            let bounds: Vec<_> =
+                bounds.iter().map(|b| text(b.span())).collect();
+            bounds.join(" ")
+        };
+        let mutbl = |m: Mutability| match m {
+            Mutability::Mutable => "mut",
+            Mutability::Immutable => "const",
+        };
+        match ty.kind {
+            TyKind::Slice(ref ty) => format!("(slice {})", s(ty)),
+            TyKind::Array(ref ty, ref n) => {
+                format!("(array {} {})", s(ty), text(n.value.span))
+            }
+            TyKind::Ptr(ref mt) => {
+                format!("(*{} {})", mutbl(mt.mutbl), s(&mt.ty))
+            }
+            TyKind::Rptr(lt, ref mt) => format!(
+                "(&{}{} {})",
+                lt.map_or(String::new(), |lt| text(lt.ident.span) + " "),
+                mutbl(mt.mutbl),
+                s(&mt.ty)
+            ),
+            TyKind::BareFn(ref f) => {
+                let mut words = vec!["fn".to_string()];
+                if !f.generic_params.is_empty() {
+                    words.push(format!("for{}", f.generic_params.len()));
+                }
+                if f.unsafety == Unsafety::Unsafe {
+                    words.push("unsafe".to_string());
+                }
+                match f.ext {
+                    Extern::None => {}
+                    Extern::Implicit => words.push("extern".to_string()),
+                    Extern::Explicit(abi) => {
+                        words.push(format!("extern {}", text(abi.span)))
+                    }
+                }
+                let params: Vec<_> = f
+                    .decl
+                    .inputs
+                    .iter()
+                    .map(|p| {
+                        if p.pat.span.is_empty()
+                            || p.ty.kind == TyKind::CVarArgs
+                        {
+                            s(&p.ty)
+                        } else {
+                            format!("{}: {}", text(p.pat.span), s(&p.ty))
+                        }
+                    })
+                    .collect();
+                words.push(format!("({})", params.join(", ")));
+                if let FunctionRetTy::Ty(ref ret) = f.decl.output {
+                    words.push(format!("-> {}", s(ret)));
+                }
+                format!("({})", words.join(" "))
+            }
+            TyKind::Never => "!".to_string(),
+            TyKind::Tup(ref tys) => format!("(tuple {})", list(tys)),
+            TyKind::Path(None, ref path) => text(path.span),
+            TyKind::Path(Some(ref qself), ref path) => {
+                let segments: Vec<_> = path
+                    .segments
+                    .iter()
+                    .map(|seg| interner.get(seg.ident.name))
+                    .collect();
+                let (tr, rest) = segments.split_at(qself.position);
+                format!(
+                    "(qpath {} {} {})",
+                    s(&qself.ty),
+                    tr.join("::"),
+                    rest.join("::")
+                )
+            }
+            TyKind::TraitObject(ref b, TraitObjectSyntax::Dyn) => {
+                format!("(dyn {})", bounds(b))
+            }
+            TyKind::TraitObject(ref b, TraitObjectSyntax::None) => {
+                format!("(bare {})", bounds(b))
+            }
+            TyKind::ImplTrait(_, ref b) => format!("(impl {})", bounds(b)),
+            TyKind::Paren(ref ty) => format!("(paren {})", s(ty)),
+            TyKind::Typeof(ref e) => format!("(typeof {})", text(e.value.span)),
+            TyKind::Infer => "_".to_string(),
+            TyKind::ImplicitSelf => "self".to_string(),
+            TyKind::Mac(ref mac) => format!("(mac {})", text(mac.span)),
+            TyKind::CVarArgs => "...".to_string(),
+            TyKind::Err => "(err)".to_string(),
+        }
+    }
+
+    fn parse(src: &str) -> Result<String, ParseError> {
+        parse_with(
+            src,
+            |p| p.parse_ty(),
+            |ty, interner| sexp(ty, src, interner),
+        )
+    }
+
+    fn check(src: &str, expected: &str) {
+        assert_eq!(parse(src), Ok(expected.to_string()), "{}", src);
+    }
+
+    #[test]
+    fn test_pointers() {
+        check("&'a mut [u8; 4]", "(&'a mut (array u8 4))");
+        check("&&T", "(&const (&const T))");
+        check("&[T]", "(&const (slice T))");
+        check("*const *mut T", "(*const (*mut T))");
+        check("&dyn A", "(&const (dyn A))");
+        check("&(dyn A + 'a)", "(&const (paren (dyn A 'a)))");
+        check_err(parse, "*u8", "expected mut or const in raw pointer type");
+        check_err(parse, "&dyn A + B", "ambiguous `+` in a type");
+        check_err(parse, "&impl A + B", "ambiguous `+` in a type");
+        check_err(
+            parse,
+            "&A + B",
+            "expected one of `!`, `::`, `<`, `<eof>`, found `+`",
+        );
+    }
+
+    #[test]
+    fn test_simple_types() {
+        check("()", "(tuple )");
+        check("(u8,)", "(tuple u8)");
+        check("(u8, (), !)", "(tuple u8 (tuple ) !)");
+        check("(u8)", "(paren u8)");
+        check("_", "_");
+        check("[[u8; N]; { N + 1 }]", "(array (array u8 N) { N + 1 })");
+        check("typeof(1 + 1)", "(typeof 1 + 1)");
+        check("m!(u8)", "(mac m!(u8))");
+        check("::std::vec::Vec<u8>", "::std::vec::Vec<u8>");
+        check("Self::Assoc", "Self::Assoc");
+        check_err(parse, "", "expected type, found `<eof>`");
+        check_err(
+            parse,
+            "const fn()",
+            "function pointer types cannot be `const` or `async`",
+        );
+    }
+
+    #[test]
+    fn test_trait_objects() {
+        check(
+            "impl Iterator<Item = u8> + 'a",
+            "(impl Iterator<Item = u8> 'a)",
+        );
+        check(
+            "dyn for<'a> Fn(&'a u8) -> u8 + Send + 'static",
+            "(dyn for<'a> Fn(&'a u8) -> u8 Send 'static)",
+        );
+        check("Trait + Send", "(bare Trait Send)");
+        check("for<'a> Trait<'a>", "(bare for<'a> Trait<'a>)");
+        check("Box<Trait + Send>", "Box<Trait + Send>");
+        check("impl Fn() -> u8", "(impl Fn() -> u8)");
+        check_err(
+            parse,
+            "for<T> Trait",
+            "only lifetime parameters can be used in this context",
+        );
+    }
+
+    #[test]
+    fn test_bare_fns() {
+        check("fn()", "(fn ())");
+        check("for<'a> fn(&'a T)", "(fn for1 ((&'a const T)))");
+        check(
+            "unsafe extern \"C\" fn(u8, x: u16, _: u32, ...) -> !",
+            "(fn unsafe extern \"C\" (u8, x: u16, _: u32, ...) -> !)",
+        );
+        check("extern fn(&u8)", "(fn extern ((&const u8)))");
+        check(
+            "fn(Vec<u8>) -> fn() -> u8",
+            "(fn (Vec<u8>) -> (fn () -> u8))",
+        );
+        check_err(
+            parse,
+            "fn(..., u8)",
+            "`...` must be the last parameter of a C-variadic function",
+        );
+    }
+
+    #[test]
+    fn test_qualified_paths() {
+        check("<T as Trait>::Assoc", "(qpath T Trait Assoc)");
+        check("<T>::Assoc::B", "(qpath T  Assoc::B)");
+        check("<Vec<T> as a::Trait>::A", "(qpath Vec<T> a::Trait A)");
+        check("<<A as B>::C as D>::E", "(qpath (qpath A B C) D E)");
+        check_err(parse, "<T as Trait>", "expected `::`, found `<eof>`");
+        let mut interner = StringInterner::empty();
+        let src = "<T as Default>::default() + <Vec<u8>>::new()";
+        let e = parse_expr_from_source_str(src, &mut interner).unwrap();
+        match e.kind {
+            ExprKind::Binary(_, ref lhs, _) => match lhs.kind {
+                ExprKind::Call(ref f, _) => match f.kind {
+                    ExprKind::Path(Some(ref qself), ref path) => {
+                        assert_eq!(qself.position, 1);
+                        assert_eq!(qself.path_span, Span::new(6, 13));
+                        assert_eq!(path.segments.len(), 2);
+                        assert_eq!(path.span, Span::new(0, 23));
+                    }
+                    ref kind => panic!("{:?}", kind),
+                },
+                ref kind => panic!("{:?}", kind),
+            },
+            ref kind => panic!("{:?}", kind),
+        }
+        let src = "impl <T as Tr>::A {} impl<T> S<T> {}";
+        let krate = parse_crate_from_source_str(src, &mut interner).unwrap();
+        let generics: Vec<_> = krate
+            .module
+            .items
+            .iter()
+            .map(|item| match item.kind {
+                ItemKind::Impl(_, _, _, ref g, ..) => g.params.len(),
+                ref kind => panic!("{:?}", kind),
+            })
+            .collect();
+        assert_eq!(generics, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_split_gt() {
+        check("Vec<Vec<u8>>", "Vec<Vec<u8>>");
+        check("A<B<C<u8>>>", "A<B<C<u8>>>");
+        let mut interner = StringInterner::empty();
+        let srcs = [
+            "{ let x: Vec<u8>= v; }",
+            "{ let x: Vec<Vec<u8>>= v; }",
+            "{ f::<Vec<u8>>(); }",
+            "<<A as B>::C as D>::e",
+        ];
+        for src in &srcs {
+            if let Err(err) = parse_expr_from_source_str(src, &mut interner) {
+                panic!("{}: {}", src, err);
+            }
+        }
+    }
+}
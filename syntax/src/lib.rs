@@ -0,0 +1,21 @@
+//! The plain data types the lexer makes and the parser's AST holds: spans,
+//! tokens, token trees and attribute styles. Unlike the lexer, this crate
+//! uses no nightly features.
+
+#[cfg(test)]
+extern crate quickcheck;
+#[cfg(test)]
+#[macro_use(quickcheck)]
+extern crate quickcheck_macros;
+
+pub mod token;
+pub mod tokenstream;
+
+/// A key into the lexer's `StringInterner`.
+pub type IntKey = usize;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AttrStyle {
+    Outer,
+    Inner,
+}
@@ -0,0 +1,323 @@
+//! Token data types, modelled after rustc's `syntax::parse::token`.
+//!
+//! Names (identifiers, literal contents, doc comments) are stored as keys
+//! into the lexer's `StringInterner` instead of rustc's global `Symbol`
+//! table, `lexer::token` has what needs the interner.
+
+use crate::IntKey;
+
+/// A byte range `[lo, hi)` into the source being lexed.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Span {
+    pub lo: usize,
+    pub hi: usize,
+}
+
+pub const DUMMY_SP: Span = Span { lo: 0, hi: 0 };
+
+impl Span {
+    pub fn new(lo: usize, hi: usize) -> Span {
+        Span { lo, hi }
+    }
+
+    pub fn len(&self) -> usize {
+        self.hi - self.lo
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lo == self.hi
+    }
+
+    pub fn to(&self, end: Span) -> Span {
+        Span::new(self.lo, end.hi)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum BinOpToken {
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Caret,
+    And,
+    Or,
+    Shl,
+    Shr,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DelimToken {
+    /// A round parenthesis: `(` or `)`
+    Paren,
+    /// A square bracket: `[` or `]`
+    Bracket,
+    /// A curly brace: `{` or `}`
+    Brace,
+    /// An empty delimiter
+    NoDelim,
+}
+
+/// Literal contents, without quotes, prefixes or suffixes, and with escapes
+/// left unprocessed. Raw strings also record their number of `#`s.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Lit {
+    Byte(IntKey),
+    Char(IntKey),
+    Err(IntKey),
+    Integer(IntKey),
+    Float(IntKey),
+    Str_(IntKey),
+    StrRaw(IntKey, u16),
+    ByteStr(IntKey),
+    ByteStrRaw(IntKey, u16),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Token {
+    /* Expression-operator symbols. */
+    Eq,
+    Lt,
+    Le,
+    EqEq,
+    Ne,
+    Ge,
+    Gt,
+    AndAnd,
+    OrOr,
+    Not,
+    Tilde,
+    BinOp(BinOpToken),
+    BinOpEq(BinOpToken),
+
+    /* Structural symbols */
+    At,
+    Dot,
+    DotDot,
+    DotDotDot,
+    DotDotEq,
+    Comma,
+    Semi,
+    Colon,
+    ModSep,
+    RArrow,
+    LArrow,
+    FatArrow,
+    Pound,
+    Dollar,
+    Question,
+    /// Used by proc macros for representing lifetimes, not generated by lexer
+    /// right now.
+    SingleQuote,
+    /// An opening delimiter, eg. `{`
+    OpenDelim(DelimToken),
+    /// A closing delimiter, eg. `}`
+    CloseDelim(DelimToken),
+
+    /* Literals */
+    Literal(Lit, Option<IntKey>),
+
+    /* Name components */
+    /// An identifier, `true` when written as `r#ident`.
+    Ident(IntKey, bool),
+    /// A lifetime, the interned name includes the leading `'`.
+    Lifetime(IntKey),
+
+    /// Doc comment, the interned text is the whole comment including the
+    /// leading `///`, `//!`, `/**` or `/*!`.
+    DocComment(IntKey),
+
+    // Junk. These carry no data because we don't really care about the data
+    // they *would* carry, and don't really want to allocate a new ident for
+    // them. Instead, users could extract that from the associated span.
+    /// Whitespace
+    Whitespace,
+    /// Comment
+    Comment,
+    Shebang(IntKey),
+
+    Eof,
+}
+
+impl Token {
+    /// Returns `true` if the token is whitespace, a non-doc comment or a
+    /// shebang, ie. something rustc's parser never sees.
+    pub fn is_trivia(&self) -> bool {
+        match *self {
+            Token::Whitespace | Token::Comment | Token::Shebang(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Glues `self` and the token right after it into one operator, eg. `>`
+    /// and `>=` into `>>=`, as rustc's `Token::glue` does. Lifetimes are
+    /// glued by `TokenAndSpan::glue`.
+    pub fn glue(&self, joint: &Token) -> Option<Token> {
+        let mut text = op_to_str(self)?.to_string();
+        text.push_str(op_to_str(joint)?);
+        str_to_op(&text)
+    }
+
+    /// If the operator `self` starts with the operator `first`, the operator
+    /// left after it, eg. `>=` for `>>=` and `>`.
+    pub fn split(&self, first: &Token) -> Option<Token> {
+        let text = op_to_str(self)?;
+        let first = op_to_str(first)?;
+        if text.len() > first.len() && text.starts_with(first) {
+            str_to_op(&text[first.len()..])
+        } else {
+            None
+        }
+    }
+
+    /// The token of a single punctuation char, eg. `BinOp(Slash)` for `/`.
+    pub fn from_punct(c: char) -> Option<Token> {
+        if c == '\'' {
+            Some(Token::SingleQuote)
+        } else {
+            str_to_op(c.encode_utf8(&mut [0; 4]))
+        }
+    }
+}
+
+// the text of an operator token, the only tokens `glue` and `split` handle
+fn op_to_str(tok: &Token) -> Option<&'static str> {
+    let text = match *tok {
+        Token::Eq => "=",
+        Token::Lt => "<",
+        Token::Le => "<=",
+        Token::EqEq => "==",
+        Token::Ne => "!=",
+        Token::Ge => ">=",
+        Token::Gt => ">",
+        Token::AndAnd => "&&",
+        Token::OrOr => "||",
+        Token::Not => "!",
+        Token::Tilde => "~",
+        Token::BinOp(op) => binop_to_string(op),
+        Token::BinOpEq(op) => match op {
+            BinOpToken::Plus => "+=",
+            BinOpToken::Minus => "-=",
+            BinOpToken::Star => "*=",
+            BinOpToken::Slash => "/=",
+            BinOpToken::Percent => "%=",
+            BinOpToken::Caret => "^=",
+            BinOpToken::And => "&=",
+            BinOpToken::Or => "|=",
+            BinOpToken::Shl => "<<=",
+            BinOpToken::Shr => ">>=",
+        },
+        Token::At => "@",
+        Token::Dot => ".",
+        Token::DotDot => "..",
+        Token::DotDotDot => "...",
+        Token::DotDotEq => "..=",
+        Token::Comma => ",",
+        Token::Semi => ";",
+        Token::Colon => ":",
+        Token::ModSep => "::",
+        Token::RArrow => "->",
+        Token::LArrow => "<-",
+        Token::FatArrow => "=>",
+        Token::Pound => "#",
+        Token::Dollar => "$",
+        Token::Question => "?",
+        _ => return None,
+    };
+    Some(text)
+}
+
+fn str_to_op(text: &str) -> Option<Token> {
+    let tok = match text {
+        "=" => Token::Eq,
+        "<" => Token::Lt,
+        "<=" => Token::Le,
+        "==" => Token::EqEq,
+        "!=" => Token::Ne,
+        ">=" => Token::Ge,
+        ">" => Token::Gt,
+        "&&" => Token::AndAnd,
+        "||" => Token::OrOr,
+        "!" => Token::Not,
+        "~" => Token::Tilde,
+        "+" => Token::BinOp(BinOpToken::Plus),
+        "-" => Token::BinOp(BinOpToken::Minus),
+        "*" => Token::BinOp(BinOpToken::Star),
+        "/" => Token::BinOp(BinOpToken::Slash),
+        "%" => Token::BinOp(BinOpToken::Percent),
+        "^" => Token::BinOp(BinOpToken::Caret),
+        "&" => Token::BinOp(BinOpToken::And),
+        "|" => Token::BinOp(BinOpToken::Or),
+        "<<" => Token::BinOp(BinOpToken::Shl),
+        ">>" => Token::BinOp(BinOpToken::Shr),
+        "+=" => Token::BinOpEq(BinOpToken::Plus),
+        "-=" => Token::BinOpEq(BinOpToken::Minus),
+        "*=" => Token::BinOpEq(BinOpToken::Star),
+        "/=" => Token::BinOpEq(BinOpToken::Slash),
+        "%=" => Token::BinOpEq(BinOpToken::Percent),
+        "^=" => Token::BinOpEq(BinOpToken::Caret),
+        "&=" => Token::BinOpEq(BinOpToken::And),
+        "|=" => Token::BinOpEq(BinOpToken::Or),
+        "<<=" => Token::BinOpEq(BinOpToken::Shl),
+        ">>=" => Token::BinOpEq(BinOpToken::Shr),
+        "@" => Token::At,
+        "." => Token::Dot,
+        ".." => Token::DotDot,
+        "..." => Token::DotDotDot,
+        "..=" => Token::DotDotEq,
+        "," => Token::Comma,
+        ";" => Token::Semi,
+        ":" => Token::Colon,
+        "::" => Token::ModSep,
+        "->" => Token::RArrow,
+        "<-" => Token::LArrow,
+        "=>" => Token::FatArrow,
+        "#" => Token::Pound,
+        "$" => Token::Dollar,
+        "?" => Token::Question,
+        _ => return None,
+    };
+    Some(tok)
+}
+
+pub fn binop_to_string(op: BinOpToken) -> &'static str {
+    match op {
+        BinOpToken::Plus => "+",
+        BinOpToken::Minus => "-",
+        BinOpToken::Star => "*",
+        BinOpToken::Slash => "/",
+        BinOpToken::Percent => "%",
+        BinOpToken::Caret => "^",
+        BinOpToken::And => "&",
+        BinOpToken::Or => "|",
+        BinOpToken::Shl => "<<",
+        BinOpToken::Shr => ">>",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[quickcheck]
+    fn check_glue_split(a: usize, b: usize) -> bool {
+        // every operator, as the 1, 2 and 3 char strings of their chars
+        let chars = "=<>!&|~+-*/%^@.,;:#$?";
+        let mut ops = vec![];
+        for c in chars.chars() {
+            for d in chars.chars().map(Some).chain(Some(None)) {
+                for e in chars.chars().map(Some).chain(Some(None)) {
+                    let text: String =
+                        Some(c).into_iter().chain(d).chain(e).collect();
+                    ops.extend(str_to_op(&text));
+                }
+            }
+        }
+        let (a, b) = (ops[a % ops.len()], ops[b % ops.len()]);
+        match a.glue(&b) {
+            Some(glued) => glued.split(&a) == Some(b),
+            None => true,
+        }
+    }
+}
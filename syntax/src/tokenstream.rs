@@ -0,0 +1,36 @@
+//! Token trees, modelled after rustc's `syntax::tokenstream`.
+
+use crate::token::{DelimToken, Span, Token};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct DelimSpan {
+    pub open: Span,
+    pub close: Span,
+}
+
+impl DelimSpan {
+    pub fn entire(self) -> Span {
+        self.open.to(self.close)
+    }
+}
+
+/// When the main rust parser encounters a syntax-extension invocation, it
+/// parses the arguments to the invocation as a token-tree. This is a very
+/// loose structure, such that all sorts of different AST-fragments can
+/// be passed to syntax extensions using a uniform type.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum TokenTree {
+    /// A single token
+    Token(Span, Token),
+    /// A delimited sequence of token trees
+    Delimited(DelimSpan, DelimToken, Vec<TokenTree>),
+}
+
+impl TokenTree {
+    pub fn span(&self) -> Span {
+        match *self {
+            TokenTree::Token(sp, _) => sp,
+            TokenTree::Delimited(sp, ..) => sp.entire(),
+        }
+    }
+}